@@ -0,0 +1,31 @@
+#!/bin/bash
+# Capture full-page screenshots of test-pages/*.html
+# Usage: scripts/capture-test-pages.sh [output-dir] [browser-binary]
+
+set -e
+
+OUTPUT_DIR="${1:-target/screenshots}"
+BROWSER="${2:-target/release/asteroid-browser}"
+PAGES_DIR="$(cd "$(dirname "$0")/../test-pages" && pwd)"
+
+if [ ! -x "$BROWSER" ]; then
+    echo "Error: browser binary not found: $BROWSER"
+    echo "Build it first with: cargo build --release"
+    exit 1
+fi
+
+mkdir -p "$OUTPUT_DIR"
+
+failed=0
+for page in "$PAGES_DIR"/*.html; do
+    name="$(basename "$page" .html)"
+    output="$OUTPUT_DIR/$name.png"
+    if "$BROWSER" --screenshot "file://$page" "$output" --full-page; then
+        echo "Captured $name -> $output"
+    else
+        echo "Failed to capture $name"
+        failed=1
+    fi
+done
+
+exit $failed
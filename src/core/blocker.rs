@@ -3,12 +3,22 @@
 //! Provides built-in ad and tracker blocking using filter lists
 //! (EasyList, EasyPrivacy format). Blocks requests before they reach
 //! the network, saving bandwidth, RAM, and CPU.
+//!
+//! Element hiding rules (`##selector`, `example.com##selector` and
+//! `#@#` exceptions) are applied by a user script running in the
+//! `COSMETIC_WORLD` isolated world, which reports how many elements it
+//! hid.
 
+use crate::core::engine::{RequestDecision, RequestInfo, RequestInterceptor};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+
+/// Isolated world the cosmetic filter script runs in.
+pub const COSMETIC_WORLD: &str = "cosmetic-filters";
 
 /// Resource types that can be blocked.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ResourceType {
     Script,
     Image,
@@ -35,6 +45,21 @@ impl ResourceType {
             _ => Self::Other,
         }
     }
+
+    /// Filter-list name of this resource type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Script => "script",
+            Self::Image => "image",
+            Self::Stylesheet => "stylesheet",
+            Self::Font => "font",
+            Self::Media => "media",
+            Self::XmlHttpRequest => "xmlhttprequest",
+            Self::SubDocument => "subdocument",
+            Self::WebSocket => "websocket",
+            Self::Other => "other",
+        }
+    }
 }
 
 /// A single filter rule.
@@ -52,6 +77,57 @@ pub struct FilterRule {
     pub third_party_only: bool,
 }
 
+/// An element hiding rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CosmeticRule {
+    /// CSS selector of the elements to hide
+    pub selector: String,
+    /// Domains the rule applies to (empty = all)
+    pub domains: Vec<String>,
+    /// Domains the rule does not apply to (`~domain`)
+    pub excluded_domains: Vec<String>,
+    /// Whether this is an exception (`#@#`) rule
+    pub is_exception: bool,
+}
+
+impl CosmeticRule {
+    /// Parse a `domains##selector` or `domains#@#selector` rule.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (domains, selector, is_exception) = match line.split_once("#@#") {
+            Some((domains, selector)) => (domains, selector, true),
+            None => {
+                let (domains, selector) = line.split_once("##")?;
+                (domains, selector, false)
+            }
+        };
+        let selector = selector.trim();
+        if selector.is_empty() {
+            return None;
+        }
+        let mut rule = CosmeticRule {
+            selector: selector.to_string(),
+            domains: Vec::new(),
+            excluded_domains: Vec::new(),
+            is_exception,
+        };
+        for domain in domains.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match domain.strip_prefix('~') {
+                Some(excluded) => rule.excluded_domains.push(excluded.to_ascii_lowercase()),
+                None => rule.domains.push(domain.to_ascii_lowercase()),
+            }
+        }
+        Some(rule)
+    }
+
+    /// Whether the rule applies to pages on `host`.
+    pub fn applies_to(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        let on = |domain: &String| host == *domain || host.ends_with(&format!(".{}", domain));
+        (self.domains.is_empty() || self.domains.iter().any(on))
+            && !self.excluded_domains.iter().any(on)
+    }
+}
+
 /// Result of checking a URL against the filter engine.
 #[derive(Debug, Clone)]
 pub struct BlockResult {
@@ -74,6 +150,9 @@ pub struct BlockerStats {
     pub bytes_saved: u64,
     /// Number of loaded filter rules
     pub filter_count: usize,
+    /// Elements hidden by cosmetic rules
+    #[serde(default)]
+    pub elements_hidden: u64,
 }
 
 impl BlockerStats {
@@ -100,6 +179,8 @@ pub struct ContentBlocker {
     block_rules: Vec<FilterRule>,
     /// Exception (allow) rules
     exception_rules: Vec<FilterRule>,
+    /// Element hiding rules
+    cosmetic_rules: Vec<CosmeticRule>,
     /// Known ad/tracker domains for fast lookup
     domain_blocklist: HashSet<String>,
     /// Blocking statistics
@@ -114,6 +195,7 @@ impl ContentBlocker {
         let mut blocker = Self {
             block_rules: Vec::new(),
             exception_rules: Vec::new(),
+            cosmetic_rules: Vec::new(),
             domain_blocklist: HashSet::new(),
             stats: BlockerStats::default(),
             enabled: true,
@@ -187,6 +269,14 @@ impl ContentBlocker {
                 continue;
             }
 
+            // Parse element hiding rules (##, #@#)
+            if line.contains("##") || line.contains("#@#") {
+                if let Some(rule) = CosmeticRule::parse(line) {
+                    self.cosmetic_rules.push(rule);
+                }
+                continue;
+            }
+
             // Parse exception rules (@@)
             if line.starts_with("@@") {
                 if let Some(rule) = self.parse_rule(&line[2..], false) {
@@ -201,16 +291,60 @@ impl ContentBlocker {
             }
         }
 
-        self.stats.filter_count = self.block_rules.len() + self.exception_rules.len();
+        self.stats.filter_count =
+            self.block_rules.len() + self.exception_rules.len() + self.cosmetic_rules.len();
     }
 
-    /// Parse a single filter rule.
-    fn parse_rule(&self, pattern: &str, is_block: bool) -> Option<FilterRule> {
-        // Skip element hiding rules (##)
-        if pattern.contains("##") || pattern.contains("#@#") {
-            return None;
+    /// Selectors of the elements to hide on pages on `host`.
+    pub fn cosmetic_selectors(&self, host: &str) -> Vec<String> {
+        let excepted: HashSet<&str> = self
+            .cosmetic_rules
+            .iter()
+            .filter(|r| r.is_exception && r.applies_to(host))
+            .map(|r| r.selector.as_str())
+            .collect();
+        self.cosmetic_rules
+            .iter()
+            .filter(|r| !r.is_exception && r.applies_to(host))
+            .filter(|r| !excepted.contains(r.selector.as_str()))
+            .map(|r| r.selector.clone())
+            .collect()
+    }
+
+    /// Script applying the cosmetic rules, to be injected at document
+    /// start into the `COSMETIC_WORLD` world. It hides matching elements
+    /// with a style sheet and posts `{type: "hidden", count}` once the
+    /// document has loaded.
+    pub fn cosmetic_filter_js(&self) -> String {
+        let mut domains: BTreeMap<&str, Vec<&CosmeticRule>> = BTreeMap::new();
+        let mut generic = Vec::new();
+        for rule in &self.cosmetic_rules {
+            if rule.domains.is_empty() {
+                generic.push(rule);
+            }
+            for domain in &rule.domains {
+                domains.entry(domain).or_default().push(rule);
+            }
         }
+        let rules = serde_json::json!({
+            "generic": generic.iter().map(|r| rule_json(r)).collect::<Vec<_>>(),
+            "domains": domains
+                .iter()
+                .map(|(domain, rules)| {
+                    (domain.to_string(), rules.iter().map(|r| rule_json(r)).collect())
+                })
+                .collect::<serde_json::Map<_, _>>(),
+        });
+        COSMETIC_FILTER_JS.replace("__RULES__", &rules.to_string())
+    }
 
+    /// Count elements hidden by the cosmetic filter script.
+    pub fn record_hidden(&mut self, count: u64) {
+        self.stats.elements_hidden += count;
+    }
+
+    /// Parse a single filter rule.
+    fn parse_rule(&self, pattern: &str, is_block: bool) -> Option<FilterRule> {
         // Extract options after $
         let (pattern, options) = if let Some(idx) = pattern.rfind('$') {
             (&pattern[..idx], Some(&pattern[idx + 1..]))
@@ -376,6 +510,7 @@ impl ContentBlocker {
         self.stats.total_checked = 0;
         self.stats.total_blocked = 0;
         self.stats.bytes_saved = 0;
+        self.stats.elements_hidden = 0;
     }
 }
 
@@ -385,6 +520,87 @@ impl Default for ContentBlocker {
     }
 }
 
+/// Lets a shared blocker (`Arc<Mutex<ContentBlocker>>`) be installed
+/// directly as the engine's request interceptor.
+impl RequestInterceptor for Mutex<ContentBlocker> {
+    fn intercept(&self, request: &RequestInfo) -> RequestDecision {
+        let mut blocker = match self.lock() {
+            Ok(blocker) => blocker,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let result = blocker.should_block(
+            &request.url,
+            &request.initiator,
+            request.resource_type.as_str(),
+        );
+
+        if result.matched {
+            log::debug!(
+                "Blocked {} ({})",
+                request.url,
+                result.matching_rule.unwrap_or_default()
+            );
+            RequestDecision::Block
+        } else {
+            RequestDecision::Allow
+        }
+    }
+}
+
+fn rule_json(rule: &CosmeticRule) -> serde_json::Value {
+    serde_json::json!({
+        "selector": rule.selector,
+        "excluded": rule.excluded_domains,
+        "exception": rule.is_exception,
+    })
+}
+
+/// Cosmetic filter script; `__RULES__` is replaced with the generic and
+/// per-domain rules.
+const COSMETIC_FILTER_JS: &str = r#"
+(function() {
+    'use strict';
+
+    const RULES = __RULES__;
+    const host = location.hostname.toLowerCase();
+    const on = domain => host === domain || host.endsWith('.' + domain);
+
+    let rules = RULES.generic.slice();
+    const parts = host.split('.');
+    for (let i = 0; i < parts.length; i++) {
+        rules = rules.concat(RULES.domains[parts.slice(i).join('.')] || []);
+    }
+    rules = rules.filter(r => !r.excluded.some(on));
+    const excepted = new Set(rules.filter(r => r.exception).map(r => r.selector));
+    const selectors = [...new Set(
+        rules.filter(r => !r.exception && !excepted.has(r.selector)).map(r => r.selector)
+    )];
+    if (selectors.length === 0) return;
+
+    const style = document.createElement('style');
+    style.textContent = selectors.map(s => s + ' { display: none !important; }').join('
+');
+    (document.head || document.documentElement).appendChild(style);
+
+    function countHidden() {
+        let count = 0;
+        selectors.forEach(s => {
+            try { count += document.querySelectorAll(s).length; } catch (e) {}
+        });
+        if (count > 0) {
+            asteroid.postMessage({ type: 'hidden', count: count });
+        }
+    }
+
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', countHidden, { once: true });
+    } else {
+        countHidden();
+    }
+})();
+"#;
+
 /// Extract the domain from a URL.
 fn extract_domain(url: &str) -> Option<&str> {
     let url = url.trim_start_matches("https://")
@@ -486,6 +702,12 @@ pub const DEFAULT_FILTERS: &str = r#"[Adblock Plus 2.0]
 */banner/*$image
 */popup/*$subdocument
 */tracking/*$script,xmlhttprequest
+
+! --- Element Hiding ---
+##.adsbygoogle
+##.ad-banner
+##[id^="google_ads_iframe"]
+##.sponsored-content
 "#;
 
 #[cfg(test)]
@@ -558,11 +780,70 @@ mod tests {
         assert!(blocker.stats().block_rate() > 0.0);
     }
 
+    #[test]
+    fn test_blocker_as_interceptor() {
+        use crate::core::engine::ViewId;
+        use std::sync::Arc;
+
+        let blocker = Arc::new(Mutex::new(ContentBlocker::new()));
+        let interceptor: Arc<dyn RequestInterceptor> = blocker.clone();
+
+        let mut request = RequestInfo {
+            view_id: ViewId(1),
+            url: "https://doubleclick.net/pixel.gif".to_string(),
+            initiator: "https://example.com".to_string(),
+            resource_type: ResourceType::Image,
+        };
+        assert_eq!(interceptor.intercept(&request), RequestDecision::Block);
+
+        request.url = "https://example.com/logo.png".to_string();
+        assert_eq!(interceptor.intercept(&request), RequestDecision::Allow);
+
+        blocker.lock().unwrap().set_enabled(false);
+        request.url = "https://doubleclick.net/pixel.gif".to_string();
+        assert_eq!(interceptor.intercept(&request), RequestDecision::Allow);
+        assert_eq!(blocker.lock().unwrap().stats().total_checked, 3);
+    }
+
+    #[test]
+    fn test_cosmetic_rules() {
+        let mut blocker = ContentBlocker::new();
+        blocker.add_filter_list(
+            "##.ad-banner\n\
+             news.test,~blog.news.test##.promo\n\
+             shop.test#@#.ad-banner\n\
+             ||tracker.test^",
+        );
+        assert_eq!(blocker.stats().filter_count, 4);
+        assert_eq!(blocker.cosmetic_selectors("example.com"), vec![".ad-banner"]);
+        assert_eq!(
+            blocker.cosmetic_selectors("www.news.test"),
+            vec![".ad-banner", ".promo"]
+        );
+        assert_eq!(blocker.cosmetic_selectors("blog.news.test"), vec![".ad-banner"]);
+        assert!(blocker.cosmetic_selectors("shop.test").is_empty());
+
+        // Cosmetic rules never block requests
+        let result = blocker.should_block("https://news.test/.promo", "https://news.test", "image");
+        assert!(!result.matched);
+
+        let js = blocker.cosmetic_filter_js();
+        assert!(!js.contains("__RULES__"));
+        assert!(js.contains(r#""news.test":[{"exception":false,"excluded":["blog.news.test"],"selector":".promo"}]"#));
+        assert!(js.contains("asteroid.postMessage"));
+
+        blocker.record_hidden(3);
+        assert_eq!(blocker.stats().elements_hidden, 3);
+        blocker.reset_stats();
+        assert_eq!(blocker.stats().elements_hidden, 0);
+    }
+
     #[test]
     fn test_resource_type_parsing() {
         assert_eq!(ResourceType::from_str("script"), ResourceType::Script);
         assert_eq!(ResourceType::from_str("image"), ResourceType::Image);
         assert_eq!(ResourceType::from_str("xhr"), ResourceType::XmlHttpRequest);
         assert_eq!(ResourceType::from_str("unknown"), ResourceType::Other);
+        assert_eq!(ResourceType::from_str(ResourceType::Font.as_str()), ResourceType::Font);
     }
 }
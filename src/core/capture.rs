@@ -0,0 +1,190 @@
+//! View capture helpers for Asteroid Browser.
+//!
+//! Engines render views into `RgbaImage`s and encode them with
+//! `encode_png` for `BrowserEngine::capture_view`. Also parses the
+//! `--screenshot` command line used to capture pages from scripts.
+
+use crate::core::engine::{CaptureArea, CaptureOptions};
+use std::path::PathBuf;
+
+/// Raw 8-bit RGBA pixels, row-major.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Image filled with a single colour.
+    pub fn filled(width: u32, height: u32, rgba: [u8; 4]) -> Self {
+        let pixels = rgba
+            .iter()
+            .copied()
+            .cycle()
+            .take(width as usize * height as usize * 4)
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Scale down (nearest neighbour) so the width is at most `max_width`,
+    /// keeping the aspect ratio. Smaller images are returned unchanged.
+    pub fn scaled_to_width(self, max_width: u32) -> Self {
+        if max_width == 0 || self.width <= max_width {
+            return self;
+        }
+        let width = max_width;
+        let height = ((self.height as u64 * width as u64) / self.width as u64).max(1) as u32;
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let src_y = (y as u64 * self.height as u64 / height as u64) as usize;
+            for x in 0..width {
+                let src_x = (x as u64 * self.width as u64 / width as u64) as usize;
+                let offset = (src_y * self.width as usize + src_x) * 4;
+                pixels.extend_from_slice(&self.pixels[offset..offset + 4]);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Apply `CaptureOptions::max_width` and encode as PNG.
+    pub fn into_png(self, options: &CaptureOptions) -> Result<Vec<u8>, String> {
+        let image = match options.max_width {
+            Some(max_width) => self.scaled_to_width(max_width),
+            None => self,
+        };
+        encode_png(&image)
+    }
+}
+
+/// Encode an image as PNG.
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    if image.pixels.len() != image.width as usize * image.height as usize * 4 {
+        return Err(format!(
+            "Pixel buffer does not match {}x{} image",
+            image.width, image.height
+        ));
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer
+        .write_image_data(&image.pixels)
+        .map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Width and height from a PNG header, if `bytes` is a PNG.
+pub fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    if bytes.len() < 24 || bytes[..8] != SIGNATURE || &bytes[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// `--screenshot <url> <output.png> [--full-page]` arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenshotArgs {
+    pub url: String,
+    pub output: PathBuf,
+    pub options: CaptureOptions,
+}
+
+impl ScreenshotArgs {
+    /// Parse the program arguments (without the program name). Returns
+    /// `None` when `--screenshot` is not present, or an error message
+    /// when it is used incorrectly.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Option<Result<Self, String>> {
+        let args: Vec<String> = args.into_iter().collect();
+        let pos = args.iter().position(|a| a == "--screenshot")?;
+
+        let (Some(url), Some(output)) = (args.get(pos + 1), args.get(pos + 2)) else {
+            return Some(Err(
+                "usage: --screenshot <url> <output.png> [--full-page]".to_string()
+            ));
+        };
+        let area = if args.iter().any(|a| a == "--full-page") {
+            CaptureArea::FullPage
+        } else {
+            CaptureArea::Viewport
+        };
+
+        Some(Ok(Self {
+            url: url.clone(),
+            output: PathBuf::from(output),
+            options: CaptureOptions {
+                area,
+                max_width: None,
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let image = RgbaImage::filled(4, 3, [10, 20, 30, 255]);
+        let png = encode_png(&image).unwrap();
+        assert_eq!(png_dimensions(&png), Some((4, 3)));
+        assert_eq!(png_dimensions(b"not a png at all, really"), None);
+
+        let broken = RgbaImage {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 3],
+        };
+        assert!(encode_png(&broken).is_err());
+    }
+
+    #[test]
+    fn test_scaled_to_width() {
+        let image = RgbaImage::filled(1280, 800, [0, 0, 0, 255]);
+        let thumb = image.clone().scaled_to_width(320);
+        assert_eq!((thumb.width, thumb.height), (320, 200));
+        assert_eq!(thumb.pixels.len(), 320 * 200 * 4);
+        assert_eq!(image.clone().scaled_to_width(2000), image);
+    }
+
+    #[test]
+    fn test_screenshot_args() {
+        assert_eq!(ScreenshotArgs::parse(args(&["--new-window"])), None);
+
+        let parsed = ScreenshotArgs::parse(args(&[
+            "--screenshot",
+            "file:///tmp/simple.html",
+            "out.png",
+            "--full-page",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(parsed.url, "file:///tmp/simple.html");
+        assert_eq!(parsed.output, PathBuf::from("out.png"));
+        assert_eq!(parsed.options.area, CaptureArea::FullPage);
+
+        assert!(ScreenshotArgs::parse(args(&["--screenshot", "x"]))
+            .unwrap()
+            .is_err());
+    }
+}
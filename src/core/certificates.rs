@@ -0,0 +1,74 @@
+//! Certificate exceptions for Asteroid Browser.
+//!
+//! Engines keep the certificates the user chose to trust despite an error
+//! in a `CertificateExceptions` set. Exceptions are tied to the host and
+//! the server certificate's fingerprint, so a different certificate for
+//! the same host is rejected again. They only last for the session.
+
+use std::collections::HashSet;
+
+/// Temporary certificate exceptions, per host and fingerprint.
+#[derive(Debug, Default)]
+pub struct CertificateExceptions {
+    entries: HashSet<(String, String)>,
+}
+
+impl CertificateExceptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust a certificate for a host.
+    pub fn add(&mut self, host: &str, fingerprint: &str) {
+        self.entries
+            .insert((host.to_ascii_lowercase(), normalize(fingerprint)));
+    }
+
+    /// Whether a certificate was trusted for a host.
+    pub fn allows(&self, host: &str, fingerprint: &str) -> bool {
+        self.entries
+            .contains(&(host.to_ascii_lowercase(), normalize(fingerprint)))
+    }
+
+    /// Drop every exception.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Fingerprints compare without separators or case ("AB:CD" == "abcd").
+fn normalize(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceptions_per_host_and_fingerprint() {
+        let mut exceptions = CertificateExceptions::new();
+        assert!(!exceptions.allows("expired.test", "ab:cd"));
+
+        exceptions.add("Expired.test", "AB:CD");
+        assert!(exceptions.allows("expired.test", "abcd"));
+        // Another certificate, or another host, is not covered
+        assert!(!exceptions.allows("expired.test", "ab:ce"));
+        assert!(!exceptions.allows("other.test", "ab:cd"));
+
+        exceptions.clear();
+        assert!(exceptions.is_empty());
+    }
+}
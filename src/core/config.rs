@@ -3,8 +3,12 @@
 //! Handles loading, saving, and providing access to user preferences.
 //! Config file location: ~/.config/asteroid-browser/config.toml
 
+use crate::core::engine::EngineSettings;
+use crate::core::media_policy::{AutoplayPolicy, MediaPolicy, MediaSiteRule, PreloadPolicy};
+use crate::core::proxy::ProxyMode;
+use crate::core::user_agent::{resolve_user_agent, UserAgentRule};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Main configuration structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +22,10 @@ pub struct Config {
     #[serde(default)]
     pub engine: EngineConfig,
     #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub media: MediaConfig,
+    #[serde(default)]
     pub ui: UiConfig,
     #[serde(default)]
     pub keybindings: KeybindingConfig,
@@ -43,6 +51,13 @@ pub struct GeneralConfig {
     pub auto_update_check: bool,
 }
 
+impl GeneralConfig {
+    /// Download directory with a leading `~` expanded to the home directory.
+    pub fn download_path(&self) -> PathBuf {
+        expand_home(&self.download_dir)
+    }
+}
+
 impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
@@ -94,8 +109,18 @@ pub struct PrivacyConfig {
     pub send_dnt: bool,
     /// Clear cookies on browser close
     pub clear_cookies_on_close: bool,
+    /// Origins whose cookies survive `clear_cookies_on_close`
+    #[serde(default)]
+    pub cookie_allowlist: Vec<String>,
     /// HTTPS-only mode
     pub https_only: bool,
+    /// Block pop-ups opened without a click or key press
+    #[serde(default = "default_true")]
+    pub block_popups: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for PrivacyConfig {
@@ -105,7 +130,9 @@ impl Default for PrivacyConfig {
             block_trackers: true,
             send_dnt: false,
             clear_cookies_on_close: false,
+            cookie_allowlist: Vec::new(),
             https_only: true,
+            block_popups: true,
         }
     }
 }
@@ -114,14 +141,104 @@ impl Default for PrivacyConfig {
 pub struct EngineConfig {
     /// Current engine: "gecko" or "servo"
     pub current: String,
+    /// User agent preset ("desktop", "mobile", "minimal") or string;
+    /// empty for the engine's own
+    #[serde(default)]
+    pub user_agent: String,
+    /// User agents for particular sites, overriding `user_agent`
+    #[serde(default)]
+    pub user_agent_rules: Vec<UserAgentRule>,
 }
 
 impl Default for EngineConfig {
     fn default() -> Self {
         Self {
             current: "gecko".to_string(),
+            user_agent: String::new(),
+            user_agent_rules: Vec::new(),
+        }
+    }
+}
+
+/// Proxy settings, shared by the engine and the browser's own requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// "direct", "system", "manual" or "pac"
+    pub proxy_mode: ProxyMode,
+    /// HTTP proxy as host:port
+    pub http_proxy: String,
+    /// HTTPS proxy as host:port; empty to use `http_proxy`
+    pub https_proxy: String,
+    /// SOCKS5 proxy as host:port
+    pub socks_proxy: String,
+    /// Proxy auto-config script, for the "pac" mode
+    pub pac_url: String,
+    /// Hosts, domains (".corp.example") and networks reached directly
+    pub bypass: Vec<String>,
+    /// Resolve host names on the SOCKS proxy rather than locally
+    pub proxy_dns: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy_mode: ProxyMode::Direct,
+            http_proxy: String::new(),
+            https_proxy: String::new(),
+            socks_proxy: String::new(),
+            pac_url: String::new(),
+            bypass: vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()],
+            proxy_dns: true,
+        }
+    }
+}
+
+/// Autoplay and preload policies, with overrides for particular sites.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaConfig {
+    /// "allow", "block_audible" or "block_all"
+    pub autoplay: AutoplayPolicy,
+    /// "none", "metadata" or "auto"
+    pub preload: PreloadPolicy,
+    /// Policies for particular sites, overriding the two above
+    pub site_rules: Vec<MediaSiteRule>,
+}
+
+impl MediaConfig {
+    /// The global policy.
+    pub fn policy(&self) -> MediaPolicy {
+        MediaPolicy {
+            autoplay: self.autoplay,
+            preload: self.preload,
+        }
+    }
+
+    /// Set the policy of a site; `None` parts follow the global policy.
+    /// The rule is dropped once it overrides nothing.
+    pub fn set_site_rule(
+        &mut self,
+        site: &str,
+        autoplay: Option<AutoplayPolicy>,
+        preload: Option<PreloadPolicy>,
+    ) {
+        self.site_rules.retain(|rule| !rule.site.eq_ignore_ascii_case(site));
+        if autoplay.is_some() || preload.is_some() {
+            self.site_rules.push(MediaSiteRule {
+                site: site.to_string(),
+                autoplay,
+                preload,
+            });
         }
     }
+
+    /// Rule for exactly `site`, if any.
+    pub fn site_rule(&self, site: &str) -> Option<&MediaSiteRule> {
+        self.site_rules
+            .iter()
+            .find(|rule| rule.site.eq_ignore_ascii_case(site))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -192,6 +309,8 @@ impl Default for Config {
             performance: PerformanceConfig::default(),
             privacy: PrivacyConfig::default(),
             engine: EngineConfig::default(),
+            network: NetworkConfig::default(),
+            media: MediaConfig::default(),
             ui: UiConfig::default(),
             keybindings: KeybindingConfig::default(),
         }
@@ -206,6 +325,11 @@ impl Config {
         config_dir.join("asteroid-browser").join("config.toml")
     }
 
+    /// Path of a data file stored next to config.toml.
+    pub fn data_path(file_name: &str) -> PathBuf {
+        Self::config_path().with_file_name(file_name)
+    }
+
     /// Load configuration from disk, or return defaults.
     pub fn load() -> Self {
         let path = Self::config_path();
@@ -233,19 +357,123 @@ impl Config {
 
     /// Save configuration to disk.
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let path = Self::config_path();
+        self.save_to(&Self::config_path())
+    }
 
+    /// Save configuration to `path`.
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
         let content = toml::to_string_pretty(self)?;
-        std::fs::write(&path, content)?;
+        std::fs::write(path, content)?;
 
         log::info!("Saved config to {}", path.display());
         Ok(())
     }
+
+    /// Change one setting by key, e.g. `privacy.block_ads`, as the
+    /// settings page does. Values of the wrong type or out of range are
+    /// rejected and leave the configuration unchanged.
+    pub fn set(&mut self, key: &str, value: &serde_json::Value) -> Result<(), String> {
+        let flag = || {
+            value
+                .as_bool()
+                .ok_or_else(|| format!("{} must be true or false", key))
+        };
+        let number = |min: u64, max: u64| {
+            value
+                .as_u64()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| format!("{} must be between {} and {}", key, min, max))
+        };
+        let choice = |choices: &[&str]| {
+            value
+                .as_str()
+                .filter(|v| choices.contains(v))
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} must be one of {}", key, choices.join(", ")))
+        };
+
+        match key {
+            "general.tab_suspension_enabled" => self.general.tab_suspension_enabled = flag()?,
+            "general.tab_suspension_delay" => {
+                self.general.tab_suspension_delay = number(30, 86_400)?
+            }
+            "general.vertical_tabs" => self.general.vertical_tabs = flag()?,
+            "general.vim_hints" => self.general.vim_hints = flag()?,
+            "general.auto_update_check" => self.general.auto_update_check = flag()?,
+            "performance.hardware_acceleration" => {
+                self.performance.hardware_acceleration = flag()?
+            }
+            "performance.memory_trim_level" => {
+                self.performance.memory_trim_level = choice(&["off", "moderate", "aggressive"])?
+            }
+            "performance.cache_size_mb" => self.performance.cache_size_mb = number(10, 500)?,
+            // One switch covers both lists
+            "privacy.block_ads" => {
+                let enabled = flag()?;
+                self.privacy.block_ads = enabled;
+                self.privacy.block_trackers = enabled;
+            }
+            "privacy.send_dnt" => self.privacy.send_dnt = flag()?,
+            "privacy.clear_cookies_on_close" => self.privacy.clear_cookies_on_close = flag()?,
+            "privacy.https_only" => self.privacy.https_only = flag()?,
+            "privacy.block_popups" => self.privacy.block_popups = flag()?,
+            "engine.current" => {
+                self.engine.current = value
+                    .as_str()
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| format!("{} must be an engine name", key))?
+                    .to_string()
+            }
+            "engine.user_agent" => {
+                let agent = value
+                    .as_str()
+                    .ok_or_else(|| format!("{} must be a preset or user agent", key))?;
+                resolve_user_agent(agent)?;
+                self.engine.user_agent = agent.trim().to_string();
+            }
+            "media.autoplay" => {
+                let names = AutoplayPolicy::ALL.map(|policy| policy.name());
+                self.media.autoplay = AutoplayPolicy::from_name(&choice(&names)?)
+                    .unwrap_or_default()
+            }
+            "media.preload" => {
+                let names = PreloadPolicy::ALL.map(|policy| policy.name());
+                self.media.preload = PreloadPolicy::from_name(&choice(&names)?)
+                    .unwrap_or_default()
+            }
+            "ui.developer_tools" => self.ui.developer_tools = flag()?,
+            _ => return Err(format!("Unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Settings handed to the engine through `apply_settings`.
+    pub fn engine_settings(&self) -> EngineSettings {
+        EngineSettings {
+            send_dnt: self.privacy.send_dnt,
+            tracking_protection: self.privacy.block_trackers,
+            cache_size_mb: self.performance.cache_size_mb,
+            memory_cache_mb: self.performance.memory_cache_mb,
+            network: self.network.clone(),
+            media: self.media.policy(),
+        }
+    }
+}
+
+/// Expand a leading `~` in a user-supplied path.
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match dirs::home_dir() {
+            Some(home) => home.join(rest.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    }
 }
 
 #[cfg(test)]
@@ -271,10 +499,133 @@ mod tests {
         assert_eq!(config.engine.current, deserialized.engine.current);
     }
 
+    #[test]
+    fn test_user_agent_rules() {
+        let config: Config = toml::from_str(
+            r#"
+[engine]
+current = "gecko"
+user_agent = "desktop"
+
+[[engine.user_agent_rules]]
+site = "news.example"
+user_agent = "mobile"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.engine.user_agent, "desktop");
+        assert_eq!(config.engine.user_agent_rules[0].site, "news.example");
+
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.engine.user_agent_rules, config.engine.user_agent_rules);
+    }
+
+    #[test]
+    fn test_network_section() {
+        let config: Config = toml::from_str(
+            r#"
+[network]
+proxy_mode = "manual"
+http_proxy = "proxy.corp:3128"
+bypass = [".corp.example"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.network.proxy_mode, ProxyMode::Manual);
+        assert_eq!(config.network.http_proxy, "proxy.corp:3128");
+        assert!(config.network.proxy_dns);
+        assert_eq!(config.engine_settings().network, config.network);
+
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.network, config.network);
+        assert_eq!(Config::default().network.proxy_mode, ProxyMode::Direct);
+    }
+
+    #[test]
+    fn test_media_site_rules() {
+        let mut config: Config = toml::from_str(
+            r#"
+[media]
+autoplay = "block_audible"
+
+[[media.site_rules]]
+site = "video.test"
+autoplay = "allow"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.media.autoplay, AutoplayPolicy::BlockAudible);
+        assert_eq!(config.media.preload, PreloadPolicy::Metadata);
+        assert_eq!(config.media.site_rule("Video.test").unwrap().preload, None);
+        assert_eq!(config.engine_settings().media, config.media.policy());
+
+        config
+            .media
+            .set_site_rule("video.test", None, Some(PreloadPolicy::None));
+        config.media.set_site_rule("radio.test", None, None);
+        assert_eq!(config.media.site_rules.len(), 1);
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.media, config.media);
+
+        config.media.set_site_rule("video.test", None, None);
+        assert!(config.media.site_rules.is_empty());
+    }
+
+    #[test]
+    fn test_set_validates_values() {
+        use serde_json::json;
+
+        let mut config = Config::default();
+        config.set("privacy.block_ads", &json!(false)).unwrap();
+        assert!(!config.privacy.block_ads && !config.privacy.block_trackers);
+        config.set("general.tab_suspension_delay", &json!(600)).unwrap();
+        assert_eq!(config.general.tab_suspension_delay, 600);
+        config.set("performance.memory_trim_level", &json!("off")).unwrap();
+        assert_eq!(config.performance.memory_trim_level, "off");
+
+        assert!(config.set("general.tab_suspension_delay", &json!(5)).is_err());
+        assert!(config.set("general.tab_suspension_delay", &json!("600")).is_err());
+        assert!(config.set("performance.memory_trim_level", &json!("max")).is_err());
+        assert!(config.set("privacy.https_only", &json!(1)).is_err());
+        assert!(config.set("general.home_page", &json!("x")).is_err());
+        assert_eq!(config.general.tab_suspension_delay, 600);
+
+        config.set("engine.user_agent", &json!("mobile")).unwrap();
+        assert!(config.set("engine.user_agent", &json!("a\r\nb")).is_err());
+        assert!(config.set("engine.user_agent", &json!(true)).is_err());
+        assert_eq!(config.engine.user_agent, "mobile");
+
+        config.set("media.autoplay", &json!("block_audible")).unwrap();
+        config.set("media.preload", &json!("none")).unwrap();
+        assert!(config.set("media.preload", &json!("everything")).is_err());
+        assert_eq!(config.media.policy().preload, PreloadPolicy::None);
+        assert_eq!(config.media.autoplay, AutoplayPolicy::BlockAudible);
+
+        let dir = std::env::temp_dir().join(format!("asteroid-config-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        config.save_to(&path).unwrap();
+        let saved: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.general.tab_suspension_delay, 600);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_config_path() {
         let path = Config::config_path();
         assert!(path.to_string_lossy().contains("asteroid-browser"));
         assert!(path.to_string_lossy().contains("config.toml"));
     }
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~/Downloads"), home.join("Downloads"));
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("/tmp/dl"), PathBuf::from("/tmp/dl"));
+        assert_eq!(expand_home("~other/dl"), PathBuf::from("~other/dl"));
+        assert!(Config::data_path("downloads.json").ends_with("asteroid-browser/downloads.json"));
+    }
 }
@@ -0,0 +1,238 @@
+//! Crash recovery for Asteroid Browser.
+//!
+//! When the content process of a view dies (`EngineEvent::ViewCrashed`),
+//! its tab is marked `TabState::Error` and shows the crash page, and the
+//! page is reloaded automatically. Pages that keep crashing wait twice as
+//! long before each reload, and are left on the crash page after
+//! `MAX_AUTO_RELOADS` crashes within `CRASH_WINDOW`.
+
+use crate::core::engine::{EngineEvent, ViewId};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Wait before reloading a page after its first crash.
+pub const FIRST_RELOAD_DELAY: Duration = Duration::from_secs(1);
+
+/// Crashes after which a page is no longer reloaded automatically.
+pub const MAX_AUTO_RELOADS: usize = 4;
+
+/// How long a crash counts against a page.
+pub const CRASH_WINDOW: Duration = Duration::from_secs(600);
+
+/// The latest crash of a view.
+#[derive(Debug, Clone)]
+pub struct ViewCrash {
+    /// Page that crashed
+    pub url: String,
+    pub reason: String,
+    /// Recent crashes of the page, oldest first
+    crashes: Vec<Instant>,
+    /// When the page is reloaded, unless the user got there first
+    reload_at: Option<Instant>,
+    /// The crash page is being loaded into the view
+    showing_page: bool,
+}
+
+impl ViewCrash {
+    /// Crashes of the page within `CRASH_WINDOW`.
+    pub fn count(&self) -> usize {
+        self.crashes.len()
+    }
+
+    /// Time left before the automatic reload, if there is one.
+    pub fn reload_in(&self, now: Instant) -> Option<Duration> {
+        self.reload_at.map(|at| at.saturating_duration_since(now))
+    }
+}
+
+/// Crashed views and their pending reloads.
+#[derive(Debug)]
+pub struct CrashRecovery {
+    first_delay: Duration,
+    views: HashMap<ViewId, ViewCrash>,
+}
+
+impl Default for CrashRecovery {
+    fn default() -> Self {
+        Self::new(FIRST_RELOAD_DELAY)
+    }
+}
+
+impl CrashRecovery {
+    pub fn new(first_delay: Duration) -> Self {
+        Self {
+            first_delay,
+            views: HashMap::new(),
+        }
+    }
+
+    /// Wait before the automatic reload following the `count`-th recent
+    /// crash; `None` once the page has crashed too often.
+    pub fn reload_delay(&self, count: usize) -> Option<Duration> {
+        if count == 0 || count > MAX_AUTO_RELOADS {
+            return None;
+        }
+        Some(self.first_delay * 2u32.pow(count as u32 - 1))
+    }
+
+    /// Record a crash of the page at `url` and schedule its reload.
+    /// Returns the wait before the reload, if the page is reloaded.
+    pub fn crashed(
+        &mut self,
+        view_id: ViewId,
+        url: &str,
+        reason: &str,
+        now: Instant,
+    ) -> Option<Duration> {
+        let crash = self.views.entry(view_id).or_insert_with(|| ViewCrash {
+            url: url.to_string(),
+            reason: String::new(),
+            crashes: Vec::new(),
+            reload_at: None,
+            showing_page: false,
+        });
+        // Crashes of another page do not count against this one
+        if crash.url != url {
+            crash.url = url.to_string();
+            crash.crashes.clear();
+        }
+        crash.reason = reason.to_string();
+        crash
+            .crashes
+            .retain(|at| now.duration_since(*at) < CRASH_WINDOW);
+        crash.crashes.push(now);
+
+        let count = crash.crashes.len();
+        let delay = self.reload_delay(count);
+        if let Some(crash) = self.views.get_mut(&view_id) {
+            crash.reload_at = delay.map(|delay| now + delay);
+        }
+        log::warn!(
+            "{} crashed ({} times) on {}: {}",
+            view_id,
+            count,
+            url,
+            reason
+        );
+        delay
+    }
+
+    pub fn get(&self, view_id: ViewId) -> Option<&ViewCrash> {
+        self.views.get(&view_id)
+    }
+
+    /// The crash page is about to be loaded into the view.
+    pub fn showing_page(&mut self, view_id: ViewId) {
+        if let Some(crash) = self.views.get_mut(&view_id) {
+            crash.showing_page = true;
+        }
+    }
+
+    /// Whether an event belongs to the loading of a crash page, which must
+    /// not change the tab: it stays errored, at the URL that crashed.
+    pub fn is_page_event(&mut self, event: &EngineEvent) -> bool {
+        let Some(crash) = self.views.get_mut(&event.view_id()) else {
+            return false;
+        };
+        if !crash.showing_page {
+            return false;
+        }
+        if matches!(
+            event,
+            EngineEvent::LoadFinished(_) | EngineEvent::LoadFailed(..)
+        ) {
+            crash.showing_page = false;
+        }
+        true
+    }
+
+    /// The view is being navigated by someone else; drop its pending reload.
+    pub fn navigated(&mut self, view_id: ViewId) {
+        if let Some(crash) = self.views.get_mut(&view_id) {
+            crash.reload_at = None;
+        }
+    }
+
+    /// Views whose reload is due, with the URL to load.
+    pub fn due(&mut self, now: Instant) -> Vec<(ViewId, String)> {
+        let mut due: Vec<(ViewId, String)> = self
+            .views
+            .iter_mut()
+            .filter(|(_, crash)| crash.reload_at.is_some_and(|at| at <= now))
+            .map(|(id, crash)| {
+                crash.reload_at = None;
+                (*id, crash.url.clone())
+            })
+            .collect();
+        due.sort_by_key(|(id, _)| id.0);
+        due
+    }
+
+    /// Forget a closed view.
+    pub fn forget(&mut self, view_id: ViewId) {
+        self.views.remove(&view_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reloads_back_off() {
+        let mut recovery = CrashRecovery::new(Duration::from_secs(1));
+        let view = ViewId(1);
+        let url = "https://crash.test/";
+        let start = Instant::now();
+
+        let delays: Vec<Option<u64>> = (0..5)
+            .map(|i| {
+                recovery
+                    .crashed(view, url, "oom", start + Duration::from_secs(i))
+                    .map(|d| d.as_secs())
+            })
+            .collect();
+        assert_eq!(delays, vec![Some(1), Some(2), Some(4), Some(8), None]);
+        assert_eq!(recovery.get(view).unwrap().count(), 5);
+
+        // Old crashes stop counting, and so do crashes of other pages
+        let later = start + CRASH_WINDOW + Duration::from_secs(10);
+        assert_eq!(
+            recovery.crashed(view, url, "oom", later),
+            Some(Duration::from_secs(1))
+        );
+        recovery.crashed(view, url, "oom", later);
+        assert_eq!(
+            recovery.crashed(view, "https://other.test/", "oom", later),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_due_reloads() {
+        let mut recovery = CrashRecovery::new(Duration::from_secs(1));
+        let start = Instant::now();
+        recovery.crashed(ViewId(1), "https://a.test/", "killed", start);
+        recovery.crashed(ViewId(2), "https://b.test/", "killed", start);
+        recovery.navigated(ViewId(2));
+
+        assert!(recovery.due(start).is_empty());
+        let at = start + Duration::from_secs(1);
+        assert_eq!(
+            recovery.due(at),
+            vec![(ViewId(1), "https://a.test/".to_string())]
+        );
+        assert!(recovery.due(at).is_empty());
+        assert_eq!(recovery.get(ViewId(1)).unwrap().reload_in(at), None);
+
+        // Only the crash page's own load is hidden from the tab
+        recovery.showing_page(ViewId(1));
+        assert!(recovery.is_page_event(&EngineEvent::LoadStarted(ViewId(1))));
+        assert!(!recovery.is_page_event(&EngineEvent::LoadStarted(ViewId(2))));
+        assert!(recovery.is_page_event(&EngineEvent::LoadFinished(ViewId(1))));
+        assert!(!recovery.is_page_event(&EngineEvent::LoadStarted(ViewId(1))));
+
+        recovery.forget(ViewId(1));
+        assert!(recovery.get(ViewId(1)).is_none());
+    }
+}
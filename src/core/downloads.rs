@@ -0,0 +1,789 @@
+//! Download manager for Asteroid Browser.
+//!
+//! Fetches files handed over by the engine (`EngineEvent::DownloadRequested`)
+//! into the configured download directory. Transfers can be paused, resumed
+//! with an HTTP Range request, or cancelled, and the download list is
+//! persisted to downloads.json next to the config file. Transfers go
+//! through the proxy of the `[network]` settings.
+
+use crate::core::config::NetworkConfig;
+use crate::core::proxy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinHandle;
+
+/// Identifier of a download.
+pub type DownloadId = u64;
+
+/// Suffix of files that are still being transferred.
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Lifecycle state of a download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    InProgress,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl DownloadState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::InProgress => "Downloading",
+            Self::Paused => "Paused",
+            Self::Completed => "Completed",
+            Self::Failed => "Failed",
+            Self::Cancelled => "Cancelled",
+        }
+    }
+}
+
+/// A single download.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Download {
+    pub id: DownloadId,
+    pub url: String,
+    /// Final location of the file
+    pub path: PathBuf,
+    pub state: DownloadState,
+    pub received_bytes: u64,
+    /// Size reported by the server, if known
+    pub total_bytes: Option<u64>,
+    /// Reason of the last failure
+    pub error: Option<String>,
+    /// Unix timestamp when the download was started
+    pub started_at: u64,
+}
+
+impl Download {
+    /// File name shown to the user.
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Progress in the 0.0 - 1.0 range, if the size is known.
+    pub fn progress(&self) -> Option<f64> {
+        match self.total_bytes {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.received_bytes as f64 / total as f64).min(1.0)),
+            None => None,
+        }
+    }
+
+    /// Location of the file while it is being transferred.
+    fn partial_path(&self) -> PathBuf {
+        partial_path(&self.path)
+    }
+}
+
+/// Errors that can occur during download operations.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// Download not found
+    NotFound(DownloadId),
+    /// Operation not possible in the current state
+    InvalidState(DownloadId, DownloadState),
+    /// HTTP request failed
+    Http(String),
+    /// File system error
+    Io(String),
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "Download not found: {}", id),
+            Self::InvalidState(id, state) => {
+                write!(f, "Download {} is {}", id, state.label().to_lowercase())
+            }
+            Self::Http(msg) => write!(f, "HTTP error: {}", msg),
+            Self::Io(msg) => write!(f, "I/O error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+/// Download list shared with the transfer tasks.
+struct Shared {
+    downloads: Mutex<Vec<Download>>,
+    store_path: Option<PathBuf>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Vec<Download>> {
+        self.downloads.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update<F: FnOnce(&mut Download)>(&self, id: DownloadId, f: F) {
+        if let Some(download) = self.lock().iter_mut().find(|d| d.id == id) {
+            f(download);
+        }
+    }
+
+    /// Write the download list to disk.
+    fn persist(&self) {
+        let Some(ref path) = self.store_path else {
+            return;
+        };
+        let content = match serde_json::to_string_pretty(&*self.lock()) {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("Failed to serialize downloads: {}", e);
+                return;
+            }
+        };
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                log::error!("Failed to create {}: {}", parent.display(), e);
+                return;
+            }
+        }
+        if let Err(e) = std::fs::write(path, content) {
+            log::error!("Failed to save downloads to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Tracks and drives all downloads.
+pub struct DownloadManager {
+    download_dir: PathBuf,
+    shared: Arc<Shared>,
+    tasks: HashMap<DownloadId, JoinHandle<()>>,
+    /// Client for transfers, or why it could not be set up
+    client: Result<reqwest::Client, String>,
+    next_id: DownloadId,
+}
+
+impl DownloadManager {
+    /// Create a manager saving into `download_dir`.
+    ///
+    /// When `store_path` is set, previous downloads are loaded from it and
+    /// the list is saved back on every change. Transfers that were running
+    /// when the browser exited come back paused. Invalid proxy settings
+    /// fail every transfer rather than bypassing the proxy.
+    pub fn new(
+        download_dir: PathBuf,
+        store_path: Option<PathBuf>,
+        network: &NetworkConfig,
+    ) -> Self {
+        let mut downloads: Vec<Download> = store_path
+            .as_deref()
+            .and_then(load_downloads)
+            .unwrap_or_default();
+        for download in downloads.iter_mut() {
+            if download.state == DownloadState::InProgress {
+                download.state = DownloadState::Paused;
+            }
+        }
+        let next_id = downloads.iter().map(|d| d.id).max().unwrap_or(0) + 1;
+
+        let client = proxy::client_builder(network).and_then(|builder| {
+            builder
+                .user_agent(concat!("asteroid-browser/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .map_err(|e| e.to_string())
+        });
+        if let Err(ref e) = client {
+            log::error!("Downloads are unavailable: {}", e);
+        }
+
+        Self {
+            download_dir,
+            shared: Arc::new(Shared {
+                downloads: Mutex::new(downloads),
+                store_path,
+            }),
+            tasks: HashMap::new(),
+            client,
+            next_id,
+        }
+    }
+
+    /// Start downloading `url`. Must be called within a tokio runtime.
+    pub fn start(&mut self, url: &str, suggested_filename: Option<&str>) -> DownloadId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let name = suggested_filename
+            .map(sanitize_filename)
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| filename_from_url(url));
+        let path = {
+            let downloads = self.shared.lock();
+            let reserved: Vec<&Path> = downloads
+                .iter()
+                .filter(|d| matches!(d.state, DownloadState::InProgress | DownloadState::Paused))
+                .map(|d| d.path.as_path())
+                .collect();
+            unique_path(&self.download_dir, &name, &reserved)
+        };
+
+        log::info!("Downloading {} to {}", url, path.display());
+        self.shared.lock().push(Download {
+            id,
+            url: url.to_string(),
+            path,
+            state: DownloadState::InProgress,
+            received_bytes: 0,
+            total_bytes: None,
+            error: None,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        });
+        self.shared.persist();
+        self.spawn_transfer(id);
+        id
+    }
+
+    /// Pause a running download, keeping the partial file.
+    pub async fn pause(&mut self, id: DownloadId) -> Result<(), DownloadError> {
+        self.expect_state(id, &[DownloadState::InProgress])?;
+        self.stop_transfer(id).await;
+        self.shared.update(id, |d| {
+            d.state = DownloadState::Paused;
+            d.received_bytes = std::fs::metadata(d.partial_path())
+                .map(|m| m.len())
+                .unwrap_or(0);
+        });
+        self.shared.persist();
+        Ok(())
+    }
+
+    /// Resume a paused or failed download from where it stopped.
+    pub fn resume(&mut self, id: DownloadId) -> Result<(), DownloadError> {
+        self.expect_state(id, &[DownloadState::Paused, DownloadState::Failed])?;
+        self.shared.update(id, |d| {
+            d.state = DownloadState::InProgress;
+            d.error = None;
+        });
+        self.shared.persist();
+        self.spawn_transfer(id);
+        Ok(())
+    }
+
+    /// Cancel a download and delete its partial file.
+    pub async fn cancel(&mut self, id: DownloadId) -> Result<(), DownloadError> {
+        self.expect_state(
+            id,
+            &[
+                DownloadState::InProgress,
+                DownloadState::Paused,
+                DownloadState::Failed,
+            ],
+        )?;
+        self.stop_transfer(id).await;
+        let mut partial = None;
+        self.shared.update(id, |d| {
+            d.state = DownloadState::Cancelled;
+            partial = Some(d.partial_path());
+        });
+        if let Some(partial) = partial {
+            if let Err(e) = std::fs::remove_file(&partial) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to remove {}: {}", partial.display(), e);
+                }
+            }
+        }
+        self.shared.persist();
+        Ok(())
+    }
+
+    /// Remove finished, failed and cancelled downloads from the list.
+    pub fn clear_finished(&mut self) {
+        self.shared
+            .lock()
+            .retain(|d| matches!(d.state, DownloadState::InProgress | DownloadState::Paused));
+        self.shared.persist();
+    }
+
+    pub fn get(&self, id: DownloadId) -> Option<Download> {
+        self.shared.lock().iter().find(|d| d.id == id).cloned()
+    }
+
+    /// All downloads, oldest first.
+    pub fn list(&self) -> Vec<Download> {
+        self.shared.lock().clone()
+    }
+
+    pub fn download_dir(&self) -> &Path {
+        &self.download_dir
+    }
+
+    fn expect_state(&self, id: DownloadId, allowed: &[DownloadState]) -> Result<(), DownloadError> {
+        let download = self.get(id).ok_or(DownloadError::NotFound(id))?;
+        if allowed.contains(&download.state) {
+            Ok(())
+        } else {
+            Err(DownloadError::InvalidState(id, download.state))
+        }
+    }
+
+    fn spawn_transfer(&mut self, id: DownloadId) {
+        let client = self.client.clone();
+        let shared = self.shared.clone();
+        let task = tokio::spawn(async move {
+            let result = match client {
+                Ok(client) => transfer(&client, &shared, id).await,
+                Err(e) => Err(DownloadError::Http(e)),
+            };
+            shared.update(id, |d| match result {
+                Ok(()) => {
+                    log::info!("Download finished: {}", d.path.display());
+                    d.state = DownloadState::Completed;
+                }
+                Err(ref e) => {
+                    log::warn!("Download of {} failed: {}", d.url, e);
+                    d.state = DownloadState::Failed;
+                    d.error = Some(e.to_string());
+                }
+            });
+            shared.persist();
+        });
+        self.tasks.insert(id, task);
+    }
+
+    /// Abort a transfer task and wait until it no longer touches the file.
+    async fn stop_transfer(&mut self, id: DownloadId) {
+        if let Some(task) = self.tasks.remove(&id) {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for DownloadManager {
+    fn drop(&mut self) {
+        // Partial files stay on disk and are resumed after a restart
+        for task in self.tasks.values() {
+            task.abort();
+        }
+    }
+}
+
+/// Fetch a download into its partial file, then move it into place.
+async fn transfer(
+    client: &reqwest::Client,
+    shared: &Shared,
+    id: DownloadId,
+) -> Result<(), DownloadError> {
+    let download = shared
+        .lock()
+        .iter()
+        .find(|d| d.id == id)
+        .cloned()
+        .ok_or(DownloadError::NotFound(id))?;
+    let partial = download.partial_path();
+
+    if let Some(parent) = partial.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    // Continue from whatever already made it to disk
+    let offset = tokio::fs::metadata(&partial)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let mut request = client.get(&download.url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+
+    let mut response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(DownloadError::Http(format!("server responded {}", status)));
+    }
+
+    // Servers that ignore Range send the whole file again
+    let resumed = offset > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut received = if resumed { offset } else { 0 };
+    let total = response.content_length().map(|len| len + received);
+    shared.update(id, |d| {
+        d.received_bytes = received;
+        d.total_bytes = total;
+    });
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&partial)
+        .await?;
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+        shared.update(id, |d| d.received_bytes = received);
+    }
+    file.flush().await?;
+    drop(file);
+
+    tokio::fs::rename(&partial, &download.path).await?;
+    Ok(())
+}
+
+fn load_downloads(path: &Path) -> Option<Vec<Download>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(downloads) => Some(downloads),
+        Err(e) => {
+            log::error!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Derive a file name from the last path segment of a URL.
+pub fn filename_from_url(url: &str) -> String {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    let after_scheme = without_query
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(without_query);
+    let name = match after_scheme.split_once('/') {
+        Some((_, path)) => path.rsplit('/').next().unwrap_or(""),
+        None => "",
+    };
+    let name = sanitize_filename(name);
+    if name.is_empty() {
+        "download".to_string()
+    } else {
+        name
+    }
+}
+
+/// Strip path separators and leading dots so a name stays inside the
+/// download directory.
+pub fn sanitize_filename(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c == '/' || c == '\\' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    name.trim().trim_start_matches('.').to_string()
+}
+
+/// Pick a path in `dir` that does not clash with an existing file (or a
+/// download in progress), appending " (1)", " (2)", ... to the stem.
+pub fn unique_path(dir: &Path, name: &str, reserved: &[&Path]) -> PathBuf {
+    let taken =
+        |path: &Path| path.exists() || partial_path(path).exists() || reserved.contains(&path);
+
+    let candidate = dir.join(name);
+    if !taken(&candidate) {
+        return candidate;
+    }
+
+    let (stem, ext) = match name.rfind('.') {
+        Some(i) if i > 0 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !taken(p))
+        .unwrap_or(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "asteroid-downloads-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn test_body() -> Vec<u8> {
+        (0..64 * 1024).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Serve `body` over HTTP, honouring `Range: bytes=N-`. Each request's
+    /// range start is recorded. With `slow` set the body trickles out.
+    async fn serve(body: Vec<u8>, slow: bool) -> (String, Arc<Mutex<Vec<Option<u64>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let body = body.clone();
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request).to_lowercase();
+                    let start = request
+                        .lines()
+                        .find_map(|l| l.strip_prefix("range: bytes="))
+                        .and_then(|r| r.trim_end_matches('-').parse::<u64>().ok());
+                    seen.lock().unwrap().push(start);
+
+                    let offset = start.unwrap_or(0) as usize;
+                    let header = match start {
+                        Some(_) => format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                            body.len() - offset,
+                            offset,
+                            body.len() - 1,
+                            body.len()
+                        ),
+                        None => format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        ),
+                    };
+                    if socket.write_all(header.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    for chunk in body[offset..].chunks(4096) {
+                        if socket.write_all(chunk).await.is_err() {
+                            return;
+                        }
+                        if slow {
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                        }
+                    }
+                });
+            }
+        });
+
+        (format!("http://{}", addr), ranges)
+    }
+
+    async fn wait_until<F: Fn(&Download) -> bool>(
+        manager: &DownloadManager,
+        id: DownloadId,
+        condition: F,
+    ) -> Download {
+        for _ in 0..500 {
+            let download = manager.get(id).unwrap();
+            if condition(&download) {
+                return download;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out waiting on download {:?}", manager.get(id));
+    }
+
+    #[test]
+    fn test_filename_from_url() {
+        assert_eq!(
+            filename_from_url("https://example.com/files/a.zip?x=1"),
+            "a.zip"
+        );
+        assert_eq!(filename_from_url("https://example.com/"), "download");
+        assert_eq!(filename_from_url("https://example.com"), "download");
+        assert_eq!(sanitize_filename("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(sanitize_filename(".hidden"), "hidden");
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let dir = temp_dir("unique");
+        assert_eq!(unique_path(&dir, "file.txt", &[]), dir.join("file.txt"));
+
+        std::fs::write(dir.join("file.txt"), b"x").unwrap();
+        std::fs::write(dir.join("file (1).txt.part"), b"x").unwrap();
+        let reserved = dir.join("file (2).txt");
+        assert_eq!(
+            unique_path(&dir, "file.txt", &[reserved.as_path()]),
+            dir.join("file (3).txt")
+        );
+
+        std::fs::write(dir.join("README"), b"x").unwrap();
+        assert_eq!(unique_path(&dir, "README", &[]), dir.join("README (1)"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_download_completes() {
+        let dir = temp_dir("complete");
+        let (base, _) = serve(test_body(), false).await;
+        let mut manager = DownloadManager::new(dir.clone(), None, &NetworkConfig::default());
+
+        std::fs::write(dir.join("data.bin"), b"existing").unwrap();
+        let id = manager.start(&format!("{}/files/data.bin", base), None);
+        let download = wait_until(&manager, id, |d| d.state != DownloadState::InProgress).await;
+
+        assert_eq!(download.state, DownloadState::Completed);
+        assert_eq!(download.path, dir.join("data (1).bin"));
+        assert_eq!(download.received_bytes, 64 * 1024);
+        assert_eq!(download.progress(), Some(1.0));
+        assert_eq!(std::fs::read(&download.path).unwrap(), test_body());
+        assert!(!download.partial_path().exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_download_through_proxy() {
+        use crate::core::proxy::ProxyMode;
+
+        let dir = temp_dir("proxy");
+        // The test server answers any request, so it stands in for the proxy
+        let (proxy_url, ranges) = serve(test_body(), false).await;
+        let network = NetworkConfig {
+            proxy_mode: ProxyMode::Manual,
+            http_proxy: proxy_url.clone(),
+            ..NetworkConfig::default()
+        };
+        let mut manager = DownloadManager::new(dir.clone(), None, &network);
+
+        let id = manager.start("http://files.invalid/data.bin", None);
+        let download = wait_until(&manager, id, |d| d.state != DownloadState::InProgress).await;
+        assert_eq!(download.state, DownloadState::Completed);
+        assert_eq!(std::fs::read(&download.path).unwrap(), test_body());
+        assert_eq!(ranges.lock().unwrap().len(), 1);
+
+        // A broken proxy setting fails the download instead of going direct
+        let network = NetworkConfig {
+            http_proxy: "proxy.invalid".to_string(),
+            ..network
+        };
+        let mut manager = DownloadManager::new(dir.clone(), None, &network);
+        let id = manager.start(&format!("{}/data.bin", proxy_url), None);
+        let download = wait_until(&manager, id, |d| d.state != DownloadState::InProgress).await;
+        assert_eq!(download.state, DownloadState::Failed);
+        assert_eq!(ranges.lock().unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_with_range() {
+        let dir = temp_dir("resume");
+        let (base, ranges) = serve(test_body(), true).await;
+        let mut manager = DownloadManager::new(dir.clone(), None, &NetworkConfig::default());
+
+        let id = manager.start(&format!("{}/big.bin", base), Some("big.bin"));
+        wait_until(&manager, id, |d| d.received_bytes > 0).await;
+        manager.pause(id).await.unwrap();
+
+        let paused = manager.get(id).unwrap();
+        assert_eq!(paused.state, DownloadState::Paused);
+        assert!(paused.received_bytes > 0 && paused.received_bytes < 64 * 1024);
+        assert!(matches!(
+            manager.pause(id).await,
+            Err(DownloadError::InvalidState(_, DownloadState::Paused))
+        ));
+
+        manager.resume(id).unwrap();
+        let download = wait_until(&manager, id, |d| d.state != DownloadState::InProgress).await;
+        assert_eq!(download.state, DownloadState::Completed);
+        assert_eq!(std::fs::read(&download.path).unwrap(), test_body());
+
+        let ranges = ranges.lock().unwrap().clone();
+        assert_eq!(ranges, vec![None, Some(paused.received_bytes)]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_removes_partial_file() {
+        let dir = temp_dir("cancel");
+        let (base, _) = serve(test_body(), true).await;
+        let mut manager = DownloadManager::new(dir.clone(), None, &NetworkConfig::default());
+
+        let id = manager.start(&format!("{}/big.bin", base), None);
+        let download = wait_until(&manager, id, |d| d.received_bytes > 0).await;
+        manager.cancel(id).await.unwrap();
+
+        assert_eq!(manager.get(id).unwrap().state, DownloadState::Cancelled);
+        assert!(!download.partial_path().exists());
+        assert!(!download.path.exists());
+        assert!(manager.resume(id).is_err());
+        assert!(matches!(
+            manager.resume(99),
+            Err(DownloadError::NotFound(99))
+        ));
+
+        manager.clear_finished();
+        assert!(manager.list().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_downloads_persist() {
+        let dir = temp_dir("persist");
+        let store = dir.join("downloads.json");
+        let (base, _) = serve(test_body(), true).await;
+
+        let (done, interrupted) = {
+            let mut manager =
+                DownloadManager::new(dir.clone(), Some(store.clone()), &NetworkConfig::default());
+            let done = manager.start(&format!("{}/a.bin", base), None);
+            wait_until(&manager, done, |d| d.state == DownloadState::Completed).await;
+            let interrupted = manager.start(&format!("{}/b.bin", base), None);
+            wait_until(&manager, interrupted, |d| d.received_bytes > 0).await;
+            (done, interrupted)
+        };
+
+        let mut manager =
+            DownloadManager::new(dir.clone(), Some(store), &NetworkConfig::default());
+        assert_eq!(manager.get(done).unwrap().state, DownloadState::Completed);
+        assert_eq!(
+            manager.get(interrupted).unwrap().state,
+            DownloadState::Paused
+        );
+
+        // New ids continue after the restored ones
+        let id = manager.start(&format!("{}/c.bin", base), None);
+        assert!(id > interrupted);
+        manager.cancel(id).await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -3,9 +3,14 @@
 //! Provides a stable, engine-agnostic interface that allows swapping
 //! between rendering engines (Gecko, Servo) without changing the UI layer.
 
+use crate::core::blocker::ResourceType;
+use crate::core::config::NetworkConfig;
+use crate::core::media_policy::MediaPolicy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
 
 /// Unique identifier for a browser view/tab.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -28,6 +33,51 @@ pub enum VideoDecoder {
     Software,
 }
 
+/// Optional features of an engine. The UI hides or disables what the
+/// active engine does not support.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineCapabilities {
+    /// `find_in_page`
+    pub find: bool,
+    /// `suspend_view` / `resume_view`, needed for tab suspension
+    pub suspend: bool,
+    /// Decoders accepted by `set_video_decoder`
+    pub video_decoders: Vec<VideoDecoder>,
+    /// `execute_script` and user scripts
+    pub script_execution: bool,
+    /// `print_to_pdf`
+    pub printing: bool,
+    /// `capture_view`
+    pub capture: bool,
+    /// `set_request_interceptor` and `set_navigation_interceptor`, needed
+    /// for content blocking and HTTPS-only mode
+    pub request_interception: bool,
+}
+
+impl EngineCapabilities {
+    /// Every feature, with all video decoders.
+    pub fn all() -> Self {
+        Self {
+            find: true,
+            suspend: true,
+            video_decoders: vec![
+                VideoDecoder::VAAPI,
+                VideoDecoder::FFmpegOptimized,
+                VideoDecoder::Software,
+            ],
+            script_execution: true,
+            printing: true,
+            capture: true,
+            request_interception: true,
+        }
+    }
+
+    /// Whether hardware video decoding can be used.
+    pub fn hardware_video(&self) -> bool {
+        self.video_decoders.contains(&VideoDecoder::VAAPI)
+    }
+}
+
 /// Memory trim aggressiveness levels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrimLevel {
@@ -74,6 +124,24 @@ pub struct NavigationState {
     pub progress: f64,
 }
 
+/// Media playback state of a view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MediaState {
+    /// Some media element is playing
+    pub playing: bool,
+    /// The playing media has sound, whether or not the view is muted
+    pub audible: bool,
+    /// The view's sound is muted with `BrowserEngine::set_muted`
+    pub muted: bool,
+}
+
+impl MediaState {
+    /// Whether the view plays sound, or would if it were not muted.
+    pub fn is_playing_audio(&self) -> bool {
+        self.playing && self.audible
+    }
+}
+
 /// Events emitted by the engine to the UI layer.
 #[derive(Debug, Clone)]
 pub enum EngineEvent {
@@ -93,8 +161,692 @@ pub enum EngineEvent {
     NavigationStateChanged(ViewId, NavigationState),
     /// Console message from page
     ConsoleMessage(ViewId, String),
-    /// Certificate error
-    CertificateError(ViewId, String),
+    /// A navigation was stopped by a certificate problem; it can be
+    /// retried with `BrowserEngine::accept_certificate_exception`
+    CertificateError(ViewId, CertificateError),
+    /// A navigation failed with a network error
+    LoadFailed(ViewId, LoadError),
+    /// A subresource request was blocked by the request interceptor
+    RequestBlocked(ViewId, String),
+    /// The navigation interceptor replaced the URL of a top-level
+    /// navigation (view, original URL, URL loaded instead)
+    NavigationRedirected(ViewId, String, String),
+    /// A navigation or link resolved to a file that should be downloaded
+    DownloadRequested(ViewId, DownloadRequest),
+    /// A page asked for a permission (view, origin, kind); answer with
+    /// `BrowserEngine::respond_permission`
+    PermissionRequested(ViewId, String, PermissionKind),
+    /// Zoom level or mode of a view changed
+    ZoomChanged(ViewId, Zoom),
+    /// A page asked to open a new view (opener, request), through
+    /// `window.open` or a `target=_blank` link
+    NewViewRequested(ViewId, NewViewRequest),
+    /// A view navigated to an `asteroid://` URL. Engines never load these
+    /// themselves; the browser serves them with `load_html`
+    InternalPageRequested(ViewId, String),
+    /// A privileged internal page posted a message through its bridge
+    InternalMessage(ViewId, serde_json::Value),
+    /// A user script in an isolated world (view, world name) posted a
+    /// message with `asteroid.postMessage`
+    UserScriptMessage(ViewId, String, serde_json::Value),
+    /// Outcome of `find_in_page` (view, current match, total matches);
+    /// the current match counts from 1 and is 0 when nothing matched
+    FindResult(ViewId, usize, usize),
+    /// The content process of a view died (view, reason); the view shows
+    /// nothing until it is navigated again
+    ViewCrashed(ViewId, String),
+    /// A view did not answer `ping_view` in time; sent by the engine
+    /// host's watchdog, not by engines
+    ViewUnresponsive(ViewId),
+    /// A view answered `ping_view`
+    ViewResponsive(ViewId),
+    /// The tab of a view was closed; sent by the engine host, not by
+    /// engines
+    ViewClosed(ViewId),
+    /// Media in a view started or stopped playing, became audible or
+    /// silent, or was muted or unmuted
+    MediaStateChanged(ViewId, MediaState),
+}
+
+impl EngineEvent {
+    /// The view the event is about.
+    pub fn view_id(&self) -> ViewId {
+        match self {
+            Self::TitleChanged(id, _)
+            | Self::UrlChanged(id, _)
+            | Self::LoadProgress(id, _)
+            | Self::LoadFinished(id)
+            | Self::LoadStarted(id)
+            | Self::FaviconReady(id, _)
+            | Self::NavigationStateChanged(id, _)
+            | Self::ConsoleMessage(id, _)
+            | Self::CertificateError(id, _)
+            | Self::LoadFailed(id, _)
+            | Self::RequestBlocked(id, _)
+            | Self::NavigationRedirected(id, _, _)
+            | Self::DownloadRequested(id, _)
+            | Self::PermissionRequested(id, _, _)
+            | Self::ZoomChanged(id, _)
+            | Self::NewViewRequested(id, _)
+            | Self::InternalPageRequested(id, _)
+            | Self::InternalMessage(id, _)
+            | Self::UserScriptMessage(id, _, _)
+            | Self::FindResult(id, _, _)
+            | Self::ViewCrashed(id, _)
+            | Self::ViewUnresponsive(id)
+            | Self::ViewClosed(id)
+            | Self::ViewResponsive(id)
+            | Self::MediaStateChanged(id, _) => *id,
+        }
+    }
+}
+
+/// Capabilities a page has to ask the user for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionKind {
+    Geolocation,
+    Notifications,
+    Camera,
+    Microphone,
+    Clipboard,
+    PersistentStorage,
+    /// Opening new windows without a user gesture
+    Popups,
+}
+
+impl PermissionKind {
+    pub const ALL: [PermissionKind; 7] = [
+        Self::Geolocation,
+        Self::Notifications,
+        Self::Camera,
+        Self::Microphone,
+        Self::Clipboard,
+        Self::PersistentStorage,
+        Self::Popups,
+    ];
+
+    /// Name used in storage and engine preferences.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Geolocation => "geolocation",
+            Self::Notifications => "notifications",
+            Self::Camera => "camera",
+            Self::Microphone => "microphone",
+            Self::Clipboard => "clipboard",
+            Self::PersistentStorage => "persistent_storage",
+            Self::Popups => "popups",
+        }
+    }
+
+    /// Human readable name.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Geolocation => "Location",
+            Self::Notifications => "Notifications",
+            Self::Camera => "Camera",
+            Self::Microphone => "Microphone",
+            Self::Clipboard => "Clipboard",
+            Self::PersistentStorage => "Persistent storage",
+            Self::Popups => "Pop-ups",
+        }
+    }
+}
+
+/// A download handed over by the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadRequest {
+    /// URL of the file
+    pub url: String,
+    /// File name suggested by the page or Content-Disposition header
+    pub suggested_filename: Option<String>,
+    /// MIME type reported by the server
+    pub mime_type: Option<String>,
+}
+
+/// Where a page wants a new view shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowDisposition {
+    /// Focused tab, e.g. a `target=_blank` link
+    #[default]
+    ForegroundTab,
+    /// Tab opened behind the opener, e.g. a middle click
+    BackgroundTab,
+    /// Separate window, e.g. `window.open` with size features
+    Popup,
+}
+
+/// A new view requested by a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewViewRequest {
+    pub url: String,
+    /// Whether the request came from a click or key press
+    pub user_gesture: bool,
+    pub disposition: WindowDisposition,
+}
+
+/// Why a certificate was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CertificateErrorKind {
+    Expired,
+    NotYetValid,
+    /// Issued by an authority that is not trusted
+    UntrustedIssuer,
+    SelfSigned,
+    /// Issued for a different host name
+    HostMismatch,
+    Revoked,
+    /// Signed with a broken algorithm
+    WeakSignature,
+    Other,
+}
+
+impl CertificateErrorKind {
+    /// Explanation shown on the interstitial.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Expired => "The certificate has expired.",
+            Self::NotYetValid => "The certificate is not valid yet.",
+            Self::UntrustedIssuer => "The certificate was issued by an authority that is not trusted.",
+            Self::SelfSigned => "The certificate is self-signed.",
+            Self::HostMismatch => "The certificate belongs to a different site.",
+            Self::Revoked => "The certificate has been revoked by its issuer.",
+            Self::WeakSignature => "The certificate uses a weak signature algorithm.",
+            Self::Other => "The certificate could not be verified.",
+        }
+    }
+}
+
+/// One certificate of a chain, as presented by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// Validity period as Unix timestamps
+    pub not_before: u64,
+    pub not_after: u64,
+    /// SHA-256 fingerprint, hex encoded
+    pub fingerprint: String,
+}
+
+/// A rejected server certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateError {
+    /// URL of the navigation that was stopped
+    pub url: String,
+    pub host: String,
+    pub kind: CertificateErrorKind,
+    /// Server certificate first, then its issuers
+    pub chain: Vec<CertificateInfo>,
+}
+
+impl CertificateError {
+    /// Fingerprint of the server certificate, which exceptions are tied to.
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.chain.first().map(|cert| cert.fingerprint.as_str())
+    }
+}
+
+/// Why a navigation failed before a page could be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadErrorKind {
+    /// The host name could not be resolved
+    DnsFailure,
+    ConnectionRefused,
+    ConnectionReset,
+    TimedOut,
+    /// No secure connection could be set up (no TLS on the port, ...)
+    SecureConnectionFailed,
+    Other,
+}
+
+impl LoadErrorKind {
+    /// Explanation shown on error pages.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::DnsFailure => "The server could not be found.",
+            Self::ConnectionRefused => "The server refused the connection.",
+            Self::ConnectionReset => "The connection was reset.",
+            Self::TimedOut => "The server took too long to respond.",
+            Self::SecureConnectionFailed => "A secure connection could not be established.",
+            Self::Other => "The page could not be loaded.",
+        }
+    }
+}
+
+/// A failed navigation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    /// URL that could not be loaded
+    pub url: String,
+    pub kind: LoadErrorKind,
+}
+
+/// A cookie held in the engine's cookie jar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Domain the cookie is scoped to (leading `.` for domain cookies)
+    pub domain: String,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Unix timestamp of expiry, `None` for session cookies
+    pub expires: Option<u64>,
+}
+
+impl Cookie {
+    /// Whether the cookie is sent to `host`.
+    pub fn matches_host(&self, host: &str) -> bool {
+        let domain = self.domain.trim_start_matches('.');
+        host.eq_ignore_ascii_case(domain)
+            || (self.domain.starts_with('.')
+                && host.to_ascii_lowercase().ends_with(&format!(".{}", domain.to_ascii_lowercase())))
+    }
+}
+
+/// Kinds of data a site can store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SiteDataKind {
+    Cookies,
+    LocalStorage,
+    IndexedDb,
+    Cache,
+}
+
+impl SiteDataKind {
+    pub const ALL: [SiteDataKind; 4] = [
+        Self::Cookies,
+        Self::LocalStorage,
+        Self::IndexedDb,
+        Self::Cache,
+    ];
+}
+
+/// Storage used by a single origin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SiteDataUsage {
+    pub origin: String,
+    pub cookies: usize,
+    pub local_storage_bytes: u64,
+    pub indexed_db_bytes: u64,
+    pub cache_bytes: u64,
+}
+
+impl SiteDataUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.local_storage_bytes + self.indexed_db_bytes + self.cache_bytes
+    }
+}
+
+/// Part of a page to capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureArea {
+    /// What is currently visible in the view
+    #[default]
+    Viewport,
+    /// The whole scrollable document
+    FullPage,
+}
+
+/// Options for `BrowserEngine::capture_view`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CaptureOptions {
+    pub area: CaptureArea,
+    /// Scale the image down to at most this width (for thumbnails)
+    pub max_width: Option<u32>,
+}
+
+impl CaptureOptions {
+    /// Small viewport capture for tab thumbnails.
+    pub fn thumbnail() -> Self {
+        Self {
+            area: CaptureArea::Viewport,
+            max_width: Some(320),
+        }
+    }
+}
+
+/// Paper size for PDF output.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    #[default]
+    A4,
+    Letter,
+    Legal,
+    /// Any size, in millimetres
+    Custom { width_mm: f64, height_mm: f64 },
+}
+
+impl PageSize {
+    /// Portrait width and height in millimetres.
+    pub fn dimensions_mm(&self) -> (f64, f64) {
+        match *self {
+            Self::A4 => (210.0, 297.0),
+            Self::Letter => (215.9, 279.4),
+            Self::Legal => (215.9, 355.6),
+            Self::Custom {
+                width_mm,
+                height_mm,
+            } => (width_mm, height_mm),
+        }
+    }
+}
+
+/// Page margins in millimetres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageMargins {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl PageMargins {
+    pub fn uniform(mm: f64) -> Self {
+        Self {
+            top: mm,
+            right: mm,
+            bottom: mm,
+            left: mm,
+        }
+    }
+}
+
+/// Options for `BrowserEngine::print_to_pdf`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    pub landscape: bool,
+    pub margins: PageMargins,
+    /// Print background colours and images
+    pub print_background: bool,
+    /// Print the title and URL as a header and page numbers as a footer
+    pub header_footer: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            landscape: false,
+            margins: PageMargins::uniform(10.0),
+            print_background: false,
+            header_footer: true,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Paper width and height in millimetres, after orientation.
+    pub fn paper_size_mm(&self) -> (f64, f64) {
+        let (width, height) = self.page_size.dimensions_mm();
+        if self.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+}
+
+/// When a user script runs in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionTime {
+    /// Before any page script runs, once the document element exists
+    Start,
+    /// Once the document is parsed (DOMContentLoaded)
+    End,
+    /// Once the page and its subresources have loaded
+    Idle,
+}
+
+/// JavaScript world a user script runs in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ScriptWorld {
+    /// The page's own globals; page scripts can see and replace anything
+    /// the user script defines
+    Page,
+    /// Separate globals sharing only the DOM with the page. Scripts in the
+    /// same named world share state and may post messages back to the
+    /// browser
+    Isolated(String),
+}
+
+impl ScriptWorld {
+    pub fn isolated(name: &str) -> Self {
+        Self::Isolated(name.to_string())
+    }
+}
+
+/// Identifier of a registered user script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UserScriptId(pub u64);
+
+impl fmt::Display for UserScriptId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserScript({})", self.0)
+    }
+}
+
+/// A script injected into every matching document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserScript {
+    pub source: String,
+    pub injection_time: InjectionTime,
+    pub world: ScriptWorld,
+    /// URL patterns where `*` matches anything; empty matches every page
+    pub url_matches: Vec<String>,
+}
+
+/// How `find_in_page` matches the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    pub case_sensitive: bool,
+    /// Only match whole words
+    pub whole_word: bool,
+    /// Treat the query as a regular expression
+    pub regex: bool,
+    /// Highlight every match, not only the current one
+    pub highlight_all: bool,
+    /// Only match link text (quick find)
+    pub links_only: bool,
+}
+
+/// User settings an engine maps onto its own preferences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineSettings {
+    /// Send the Do Not Track header
+    pub send_dnt: bool,
+    /// Use the engine's own tracking protection as well
+    pub tracking_protection: bool,
+    /// Disk cache size in megabytes
+    pub cache_size_mb: u64,
+    /// Memory cache size in megabytes
+    pub memory_cache_mb: u64,
+    /// Proxy settings
+    pub network: NetworkConfig,
+    /// Autoplay and preload policy of views without one of their own
+    pub media: MediaPolicy,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            send_dnt: false,
+            tracking_protection: true,
+            cache_size_mb: 100,
+            memory_cache_mb: 50,
+            network: NetworkConfig::default(),
+            media: MediaPolicy::default(),
+        }
+    }
+}
+
+/// How zooming scales a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoomMode {
+    /// Scale the whole page: text, images and layout
+    #[default]
+    FullPage,
+    /// Scale text only
+    TextOnly,
+}
+
+/// Zoom applied to a view.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Zoom {
+    /// Scale factor, 1.0 is 100%
+    pub level: f64,
+    #[serde(default)]
+    pub mode: ZoomMode,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            mode: ZoomMode::FullPage,
+        }
+    }
+}
+
+impl Zoom {
+    pub const MIN_LEVEL: f64 = 0.3;
+    pub const MAX_LEVEL: f64 = 5.0;
+
+    /// Zoom with the level clamped to the supported range.
+    pub fn new(level: f64, mode: ZoomMode) -> Self {
+        let level = if level.is_finite() { level } else { 1.0 };
+        Self {
+            level: level.clamp(Self::MIN_LEVEL, Self::MAX_LEVEL),
+            mode,
+        }
+    }
+
+    /// Level as a whole percentage.
+    pub fn percent(&self) -> u32 {
+        (self.level * 100.0).round() as u32
+    }
+
+    /// Whether this is the 100% full-page default.
+    pub fn is_default(&self) -> bool {
+        self.percent() == 100 && self.mode == ZoomMode::FullPage
+    }
+}
+
+/// Description of a subresource request about to be issued by the engine.
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    /// View that issued the request
+    pub view_id: ViewId,
+    /// URL being requested
+    pub url: String,
+    /// URL of the top-level document that initiated the request
+    pub initiator: String,
+    /// Type of resource being requested
+    pub resource_type: ResourceType,
+}
+
+/// Decision returned by a request interceptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestDecision {
+    /// Let the request proceed unchanged
+    Allow,
+    /// Cancel the request
+    Block,
+    /// Load the given URL instead
+    Redirect(String),
+}
+
+/// Hook consulted by the engine before every subresource load.
+///
+/// Interceptors are shared between the engine and the rest of the browser,
+/// so they must be thread-safe and use interior mutability for any state.
+pub trait RequestInterceptor: Send + Sync {
+    /// Decide what to do with a request.
+    fn intercept(&self, request: &RequestInfo) -> RequestDecision;
+}
+
+/// Top-level navigation a view is about to make, whoever started it: the
+/// browser, a link or form in the page, a redirect or a history step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Navigation {
+    pub view_id: ViewId,
+    /// URL about to be loaded
+    pub url: String,
+    /// Agent the document is requested with, starting as the view's
+    /// current one; `None` for the engine's own
+    pub user_agent: Option<String>,
+}
+
+/// Hook consulted by the engine before every top-level navigation.
+///
+/// Shared like request interceptors, so the same rules apply.
+pub trait NavigationInterceptor: Send + Sync {
+    /// Adjust a navigation before it is made; the engine loads the URL
+    /// left in it with the agent left in it, which the view keeps for
+    /// later navigations.
+    fn intercept_navigation(&self, navigation: &mut Navigation);
+}
+
+/// Runs several navigation interceptors in order, each seeing the changes
+/// of the ones before it.
+pub struct NavigationChain {
+    interceptors: Vec<Arc<dyn NavigationInterceptor>>,
+}
+
+impl NavigationChain {
+    pub fn new(interceptors: Vec<Arc<dyn NavigationInterceptor>>) -> Self {
+        Self { interceptors }
+    }
+}
+
+impl NavigationInterceptor for NavigationChain {
+    fn intercept_navigation(&self, navigation: &mut Navigation) {
+        for interceptor in &self.interceptors {
+            interceptor.intercept_navigation(navigation);
+        }
+    }
+}
+
+/// Runs several interceptors in order. A block ends the chain, a redirect
+/// is passed on to the next interceptor as the URL being requested.
+pub struct InterceptorChain {
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+}
+
+impl InterceptorChain {
+    pub fn new(interceptors: Vec<Arc<dyn RequestInterceptor>>) -> Self {
+        Self { interceptors }
+    }
+}
+
+impl RequestInterceptor for InterceptorChain {
+    fn intercept(&self, request: &RequestInfo) -> RequestDecision {
+        let mut request = request.clone();
+        let mut redirected = false;
+        for interceptor in &self.interceptors {
+            match interceptor.intercept(&request) {
+                RequestDecision::Allow => {}
+                RequestDecision::Block => return RequestDecision::Block,
+                RequestDecision::Redirect(url) => {
+                    request.url = url;
+                    redirected = true;
+                }
+            }
+        }
+        if redirected {
+            RequestDecision::Redirect(request.url)
+        } else {
+            RequestDecision::Allow
+        }
+    }
 }
 
 /// Result type for engine operations.
@@ -168,9 +920,51 @@ pub trait BrowserEngine: Send {
     /// Stop loading the current page.
     fn stop(&mut self, view_id: ViewId) -> EngineResult<()>;
 
+    /// Send `user_agent` from the view's next navigation on; `None` goes
+    /// back to the engine's own agent.
+    fn set_user_agent(&mut self, view_id: ViewId, user_agent: Option<&str>) -> EngineResult<()>;
+
+    /// Mute or unmute all sound of a view.
+    fn set_muted(&mut self, view_id: ViewId, muted: bool) -> EngineResult<()>;
+
+    /// Pause the media playing in a view, or resume the media it paused
+    /// last if nothing plays.
+    fn media_play_pause(&mut self, view_id: ViewId) -> EngineResult<()>;
+
+    /// Give a view its own autoplay and preload policy instead of the one
+    /// in `EngineSettings`. Applies to media the page starts loading or
+    /// playing from then on.
+    fn set_media_policy(&mut self, view_id: ViewId, policy: MediaPolicy) -> EngineResult<()>;
+
     /// Execute JavaScript in the specified view and return the result.
     fn execute_script(&mut self, view_id: ViewId, script: &str) -> EngineResult<serde_json::Value>;
 
+    /// Inject `script` into every document whose URL matches one of
+    /// `url_matches` (every document when empty) at `time`, in `world`.
+    fn add_user_script(
+        &mut self,
+        script: &str,
+        time: InjectionTime,
+        world: ScriptWorld,
+        url_matches: &[&str],
+    ) -> EngineResult<UserScriptId>;
+
+    /// Stop injecting a user script. Documents it already ran in keep it.
+    fn remove_user_script(&mut self, id: UserScriptId) -> EngineResult<()>;
+
+    /// Execute JavaScript in an isolated world of a view's document.
+    fn execute_script_in_world(
+        &mut self,
+        view_id: ViewId,
+        world: &str,
+        script: &str,
+    ) -> EngineResult<serde_json::Value>;
+
+    /// Ask a view's content process to answer with `ViewResponsive` once
+    /// its event loop gets to it. The host's watchdog uses this to spot
+    /// hung pages.
+    fn ping_view(&mut self, view_id: ViewId) -> EngineResult<()>;
+
     /// Suspend a view to save memory (serialize state, release resources).
     fn suspend_view(&mut self, view_id: ViewId) -> EngineResult<()>;
 
@@ -195,15 +989,94 @@ pub trait BrowserEngine: Send {
     /// Get navigation state for a view.
     fn get_navigation_state(&self, view_id: ViewId) -> EngineResult<NavigationState>;
 
-    /// Find text in the current page.
-    fn find_in_page(&mut self, view_id: ViewId, query: &str, forward: bool) -> EngineResult<()>;
+    /// Find text in the current page, moving to the next (or previous)
+    /// match when the query is repeated. Emits `FindResult`; queries that
+    /// are not valid patterns are an error.
+    fn find_in_page(
+        &mut self,
+        view_id: ViewId,
+        query: &str,
+        options: &FindOptions,
+        forward: bool,
+    ) -> EngineResult<()>;
 
     /// Clear the find-in-page highlight.
     fn clear_find(&mut self, view_id: ViewId) -> EngineResult<()>;
 
+    /// Apply user settings. Called at startup and whenever they change.
+    fn apply_settings(&mut self, settings: &EngineSettings) -> EngineResult<()>;
+
+    /// Install (or remove, with `None`) the hook consulted for every subresource load.
+    fn set_request_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn RequestInterceptor>>,
+    ) -> EngineResult<()>;
+
+    /// Install (or remove, with `None`) the hook consulted before every
+    /// top-level navigation. A changed URL is reported with
+    /// `EngineEvent::NavigationRedirected`.
+    fn set_navigation_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    ) -> EngineResult<()>;
+
+    /// Answer a pending `PermissionRequested` event.
+    fn respond_permission(
+        &mut self,
+        view_id: ViewId,
+        origin: &str,
+        kind: PermissionKind,
+        granted: bool,
+    ) -> EngineResult<()>;
+
+    /// List cookies, optionally only those sent to `origin`.
+    fn list_cookies(&self, origin: Option<&str>) -> EngineResult<Vec<Cookie>>;
+
+    /// Delete a single cookie (matched by domain, path and name).
+    fn delete_cookie(&mut self, cookie: &Cookie) -> EngineResult<()>;
+
+    /// Delete every cookie.
+    fn clear_cookies(&mut self) -> EngineResult<()>;
+
+    /// Clear stored data of the given kinds for one origin, or for all with `None`.
+    fn clear_site_data(&mut self, origin: Option<&str>, kinds: &[SiteDataKind]) -> EngineResult<()>;
+
+    /// Storage used per origin.
+    fn site_data_usage(&self) -> EngineResult<Vec<SiteDataUsage>>;
+
+    /// Render a view to PNG-encoded bytes.
+    fn capture_view(&self, view_id: ViewId, options: CaptureOptions) -> EngineResult<Vec<u8>>;
+
+    /// Set the zoom of a view. Levels outside `Zoom::MIN_LEVEL` and
+    /// `Zoom::MAX_LEVEL` are clamped; emits `ZoomChanged` when the zoom changes.
+    fn set_zoom(&mut self, view_id: ViewId, zoom: Zoom) -> EngineResult<()>;
+
+    /// Current zoom of a view.
+    fn get_zoom(&self, view_id: ViewId) -> EngineResult<Zoom>;
+
+    /// Trust the certificate with `fingerprint` for `host` for the rest of
+    /// the session, and retry the navigation it stopped in the view.
+    fn accept_certificate_exception(
+        &mut self,
+        view_id: ViewId,
+        host: &str,
+        fingerprint: &str,
+    ) -> EngineResult<()>;
+
+    /// Raw response body of the view's current document, if the engine
+    /// still has it. `None` means the caller has to refetch the URL.
+    fn page_source(&self, view_id: ViewId) -> EngineResult<Option<String>>;
+
+    /// Render a view to a PDF file at `path`.
+    fn print_to_pdf(&mut self, view_id: ViewId, options: &PdfOptions, path: &Path) -> EngineResult<()>;
+
     /// Get the engine name and version.
     fn engine_info(&self) -> (String, String);
 
+    /// Optional features this engine supports. Available before
+    /// `initialize`.
+    fn capabilities(&self) -> EngineCapabilities;
+
     /// Poll for pending events from the engine.
     fn poll_events(&mut self) -> Vec<EngineEvent>;
 }
@@ -211,6 +1084,17 @@ pub trait BrowserEngine: Send {
 /// Factory function type for creating engine instances.
 pub type EngineFactory = fn() -> Box<dyn BrowserEngine>;
 
+/// A registered engine, as listed in settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineInfo {
+    /// Registry name, as stored in `engine.current`
+    pub name: String,
+    /// Name reported by the engine, e.g. "Gecko"
+    pub display_name: String,
+    pub version: String,
+    pub capabilities: EngineCapabilities,
+}
+
 /// Registry of available engines.
 pub struct EngineRegistry {
     engines: HashMap<String, EngineFactory>,
@@ -240,6 +1124,11 @@ impl EngineRegistry {
         }
     }
 
+    /// Name of the default engine; empty while none is registered.
+    pub fn default_name(&self) -> &str {
+        &self.default_engine
+    }
+
     /// Create an instance of the default engine.
     pub fn create_default(&self) -> Option<Box<dyn BrowserEngine>> {
         self.engines.get(&self.default_engine).map(|f| f())
@@ -254,6 +1143,32 @@ impl EngineRegistry {
     pub fn available_engines(&self) -> Vec<&str> {
         self.engines.keys().map(|s| s.as_str()).collect()
     }
+
+    /// Describe every registered engine, sorted by name. Engines are
+    /// created to ask, but not initialized.
+    pub fn engine_infos(&self) -> Vec<EngineInfo> {
+        let mut infos: Vec<EngineInfo> = self
+            .engines
+            .iter()
+            .map(|(name, factory)| {
+                let engine = factory();
+                let (display_name, version) = engine.engine_info();
+                EngineInfo {
+                    name: name.clone(),
+                    display_name,
+                    version,
+                    capabilities: engine.capabilities(),
+                }
+            })
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+
+    /// Capabilities of a named engine.
+    pub fn capabilities(&self, name: &str) -> Option<EngineCapabilities> {
+        self.engines.get(name).map(|f| f().capabilities())
+    }
 }
 
 impl Default for EngineRegistry {
@@ -281,10 +1196,64 @@ mod tests {
         assert!((stats.total_mb() - 100.0).abs() < 0.01);
     }
 
+    struct BlockAll;
+
+    impl RequestInterceptor for BlockAll {
+        fn intercept(&self, _request: &RequestInfo) -> RequestDecision {
+            RequestDecision::Block
+        }
+    }
+
+    #[test]
+    fn test_request_interceptor_object() {
+        let interceptor: Arc<dyn RequestInterceptor> = Arc::new(BlockAll);
+        let request = RequestInfo {
+            view_id: ViewId(1),
+            url: "https://example.com/script.js".to_string(),
+            initiator: "https://example.com".to_string(),
+            resource_type: ResourceType::Script,
+        };
+        assert_eq!(interceptor.intercept(&request), RequestDecision::Block);
+    }
+
+    struct Upgrade;
+
+    impl RequestInterceptor for Upgrade {
+        fn intercept(&self, request: &RequestInfo) -> RequestDecision {
+            match request.url.strip_prefix("http://") {
+                Some(rest) => RequestDecision::Redirect(format!("https://{}", rest)),
+                None => RequestDecision::Allow,
+            }
+        }
+    }
+
+    #[test]
+    fn test_interceptor_chain() {
+        let request = RequestInfo {
+            view_id: ViewId(1),
+            url: "http://example.com/app.js".to_string(),
+            initiator: "https://example.com".to_string(),
+            resource_type: ResourceType::Script,
+        };
+        let chain = InterceptorChain::new(vec![Arc::new(Upgrade), Arc::new(Upgrade)]);
+        assert_eq!(
+            chain.intercept(&request),
+            RequestDecision::Redirect("https://example.com/app.js".to_string())
+        );
+        let chain = InterceptorChain::new(vec![Arc::new(Upgrade), Arc::new(BlockAll)]);
+        assert_eq!(chain.intercept(&request), RequestDecision::Block);
+        assert_eq!(
+            InterceptorChain::new(Vec::new()).intercept(&request),
+            RequestDecision::Allow
+        );
+    }
+
     #[test]
     fn test_engine_registry() {
         let registry = EngineRegistry::new();
         assert!(registry.available_engines().is_empty());
+        assert!(registry.engine_infos().is_empty());
         assert!(registry.create_default().is_none());
+        assert_eq!(registry.default_name(), "");
     }
 }
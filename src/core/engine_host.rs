@@ -0,0 +1,986 @@
+//! Engine host for Asteroid Browser.
+//!
+//! Owns the browser engine and the tab manager on a dedicated thread.
+//! The GTK loop, the memory monitor and the updater drive them through a
+//! cloneable `EngineHandle`: commands go over a channel and are answered
+//! asynchronously, and engine events are pushed to subscribers instead of
+//! being polled by the UI. The host also watches views for hangs,
+//! reloads pages whose content process crashed and picks the user agent
+//! of each navigation.
+
+use crate::core::crash::CrashRecovery;
+use crate::core::engine::{BrowserEngine, EngineError, EngineEvent, EngineResult, MemoryStats, ViewId};
+use crate::core::engine_set::EngineSet;
+use crate::core::history::History;
+use crate::core::https_only::HttpsOnly;
+use crate::core::media_policy::MediaPolicies;
+use crate::core::memory::{handle_memory_pressure, MemoryPressure};
+use crate::core::tab::{TabManager, TabState};
+use crate::core::user_agent::UserAgents;
+use crate::core::watchdog::Watchdog;
+use crate::core::zoom::ZoomStore;
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
+
+/// How long the host waits for a command before polling engine events.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How often inactive tabs are checked for suspension.
+const SUSPENSION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of events buffered per subscriber before the oldest are dropped.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Reply channel for a command.
+type Reply<T> = oneshot::Sender<EngineResult<T>>;
+
+/// Closure run on the engine thread with exclusive access to its state.
+type HostFn = Box<dyn FnOnce(&mut HostState) + Send>;
+
+/// State owned by the engine thread.
+pub struct HostState {
+    /// Every loaded engine, routing each view to the one showing it
+    pub engine: EngineSet,
+    pub tabs: TabManager,
+    /// Per-site zoom, restored when a view changes site
+    pub zoom: ZoomStore,
+    /// Upgrades navigations to HTTPS and tracks the upgrades that failed
+    pub https_only: HttpsOnly,
+    /// Pages visited this session, for asteroid://history
+    pub history: History,
+    /// Pings live views and reports the ones that hang
+    pub watchdog: Watchdog,
+    /// Crashed views and their automatic reloads
+    pub crashes: CrashRecovery,
+    /// User agent of each tab, per site and per tab
+    pub user_agents: Arc<UserAgents>,
+    /// Autoplay and preload policy of each tab, per site
+    pub media_policies: MediaPolicies,
+}
+
+/// Commands understood by the engine host.
+pub enum EngineCommand {
+    /// Open a new background tab
+    CreateTab(Reply<ViewId>),
+    /// Open a new background tab in a named engine
+    CreateTabIn(String, Reply<ViewId>),
+    /// Show a tab in another engine
+    ReopenInEngine(ViewId, String, Reply<()>),
+    /// Close a tab
+    CloseTab(ViewId, Reply<()>),
+    /// Make a tab the active one
+    SwitchToTab(ViewId, Reply<()>),
+    /// Navigate a tab to a URL
+    LoadUrl(ViewId, String, Reply<()>),
+    /// Pick a user agent for a tab, or follow the site rules again with
+    /// `None`; the tab is reloaded if its agent changed
+    SetUserAgent(ViewId, Option<String>, Reply<()>),
+    /// Mute or unmute a tab
+    SetMuted(ViewId, bool, Reply<()>),
+    /// Pause or resume the media of a tab
+    MediaPlayPause(ViewId, Reply<()>),
+    /// Navigate back in history
+    GoBack(ViewId, Reply<()>),
+    /// Navigate forward in history
+    GoForward(ViewId, Reply<()>),
+    /// Reload the current page
+    Reload(ViewId, Reply<()>),
+    /// Stop loading
+    Stop(ViewId, Reply<()>),
+    /// Execute JavaScript in a tab
+    ExecuteScript(ViewId, String, Reply<serde_json::Value>),
+    /// React to system memory pressure
+    MemoryPressure(MemoryPressure, Reply<()>),
+    /// Query engine memory usage
+    GetMemoryUsage(Reply<MemoryStats>),
+    /// Run an arbitrary closure against the host state
+    Call(HostFn),
+    /// Shut the engine down and stop the host thread
+    Shutdown(Reply<()>),
+}
+
+impl HostState {
+    /// Execute a single command.
+    fn execute(&mut self, command: EngineCommand, events: &broadcast::Sender<EngineEvent>) {
+        let engine = &mut self.engine;
+        // A dropped reply receiver just means the caller stopped waiting
+        match command {
+            EngineCommand::CreateTab(reply) => {
+                let _ = reply.send(self.tabs.create_tab(engine));
+            }
+            EngineCommand::CreateTabIn(name, reply) => {
+                let _ = reply.send(self.tabs.create_tab_in(&name, engine));
+            }
+            EngineCommand::ReopenInEngine(view_id, name, reply) => {
+                let _ = reply.send(self.tabs.reopen_in_engine(view_id, &name, engine));
+            }
+            EngineCommand::CloseTab(view_id, reply) => {
+                self.watchdog.forget(view_id);
+                self.crashes.forget(view_id);
+                self.user_agents.forget(view_id);
+                self.media_policies.forget(view_id);
+                let result = self.tabs.close_tab(view_id, engine);
+                if result.is_ok() {
+                    let _ = events.send(EngineEvent::ViewClosed(view_id));
+                }
+                let _ = reply.send(result);
+            }
+            EngineCommand::SwitchToTab(view_id, reply) => {
+                let _ = reply.send(self.tabs.switch_to_tab(view_id, engine));
+            }
+            EngineCommand::LoadUrl(view_id, url, reply) => {
+                if let Err(e) = self.user_agents.apply(engine, view_id, &url) {
+                    log::warn!("Failed to set user agent of {}: {}", view_id, e);
+                }
+                if let Err(e) = self.media_policies.apply(engine, view_id, &url) {
+                    log::warn!("Failed to set media policy of {}: {}", view_id, e);
+                }
+                let _ = reply.send(self.https_only.load_url(engine, view_id, &url));
+            }
+            EngineCommand::SetUserAgent(view_id, user_agent, reply) => {
+                let _ = reply.send(self.set_tab_user_agent(view_id, user_agent.as_deref()));
+            }
+            EngineCommand::SetMuted(view_id, muted, reply) => {
+                let _ = reply.send(engine.set_muted(view_id, muted));
+            }
+            EngineCommand::MediaPlayPause(view_id, reply) => {
+                let _ = reply.send(engine.media_play_pause(view_id));
+            }
+            EngineCommand::GoBack(view_id, reply) => {
+                let _ = reply.send(engine.go_back(view_id));
+            }
+            EngineCommand::GoForward(view_id, reply) => {
+                let _ = reply.send(engine.go_forward(view_id));
+            }
+            EngineCommand::Reload(view_id, reply) => {
+                let _ = reply.send(engine.reload(view_id));
+            }
+            EngineCommand::Stop(view_id, reply) => {
+                let _ = reply.send(engine.stop(view_id));
+            }
+            EngineCommand::ExecuteScript(view_id, script, reply) => {
+                let _ = reply.send(engine.execute_script(view_id, &script));
+            }
+            EngineCommand::MemoryPressure(pressure, reply) => {
+                handle_memory_pressure(pressure, &mut self.tabs, engine);
+                let _ = reply.send(Ok(()));
+            }
+            EngineCommand::GetMemoryUsage(reply) => {
+                let _ = reply.send(Ok(engine.get_memory_usage()));
+            }
+            EngineCommand::Call(f) => f(self),
+            EngineCommand::Shutdown(reply) => {
+                let _ = reply.send(engine.shutdown());
+            }
+        }
+    }
+
+    /// Pick a tab's user agent, reloading the tab if it changed.
+    fn set_tab_user_agent(&mut self, view_id: ViewId, user_agent: Option<&str>) -> EngineResult<()> {
+        let url = match self.tabs.get_tab(view_id) {
+            Some(tab) => tab.url.clone(),
+            None => return Err(EngineError::ViewNotFound(view_id)),
+        };
+        self.user_agents
+            .set_tab(view_id, user_agent)
+            .map_err(EngineError::Other)?;
+        if self.user_agents.apply(&mut self.engine, view_id, &url)? {
+            self.engine.reload(view_id)?;
+        }
+        Ok(())
+    }
+
+    /// Give every tab the media policy of its site again, after the
+    /// policies changed.
+    pub fn apply_media_policies(&mut self) {
+        let tabs: Vec<(ViewId, String)> = self
+            .tabs
+            .tabs_in_order()
+            .iter()
+            .map(|tab| (tab.view_id, tab.url.clone()))
+            .collect();
+        for (view_id, url) in tabs {
+            if let Err(e) = self.media_policies.apply(&mut self.engine, view_id, &url) {
+                log::warn!("Failed to set media policy of {}: {}", view_id, e);
+            }
+        }
+    }
+
+    /// Apply pending engine events to the tab manager and publish them.
+    fn dispatch_events(&mut self, events: &broadcast::Sender<EngineEvent>) {
+        for event in self.engine.poll_events() {
+            // The crash page leaves the tab at the URL that crashed
+            if self.crashes.is_page_event(&event) {
+                continue;
+            }
+            let previous_url = match event {
+                EngineEvent::UrlChanged(view_id, _) => {
+                    self.tabs.get_tab(view_id).map(|tab| tab.url.clone())
+                }
+                _ => None,
+            };
+            self.tabs.handle_engine_event(&event);
+            self.https_only.handle_event(&event);
+            match event {
+                EngineEvent::LoadStarted(view_id) => self.crashes.navigated(view_id),
+                EngineEvent::LoadFinished(view_id) => {
+                    self.tabs.update_thumbnail(view_id, &self.engine);
+                }
+                EngineEvent::UrlChanged(view_id, ref url) => {
+                    self.history.record(url);
+                    // Later navigations on the new site send its agent
+                    if let Err(e) = self.user_agents.apply(&mut self.engine, view_id, url) {
+                        log::warn!("Failed to set user agent of {}: {}", view_id, e);
+                    }
+                    if let Err(e) = self.media_policies.apply(&mut self.engine, view_id, url) {
+                        log::warn!("Failed to set media policy of {}: {}", view_id, e);
+                    }
+                    let restored = self.zoom.restore(
+                        &mut self.engine,
+                        view_id,
+                        previous_url.as_deref(),
+                        url,
+                    );
+                    if let Err(e) = restored {
+                        log::warn!("Failed to restore zoom of {}: {}", view_id, e);
+                    }
+                }
+                EngineEvent::TitleChanged(view_id, ref title) => {
+                    if let Some(tab) = self.tabs.get_tab(view_id) {
+                        self.history.set_title(&tab.url, title);
+                    }
+                }
+                EngineEvent::ZoomChanged(view_id, zoom) => {
+                    if let Some(tab) = self.tabs.get_tab(view_id) {
+                        self.zoom.set(&tab.url, zoom);
+                    }
+                }
+                EngineEvent::ViewCrashed(view_id, ref reason) => {
+                    self.watchdog.forget(view_id);
+                    if let Some(tab) = self.tabs.get_tab(view_id) {
+                        self.crashes
+                            .crashed(view_id, &tab.url, reason, Instant::now());
+                    }
+                }
+                // Answers to pings only matter after a reported hang
+                EngineEvent::ViewResponsive(view_id) if !self.watchdog.answered(view_id) => {
+                    continue;
+                }
+                _ => {}
+            }
+            // Sending only fails when nobody is subscribed
+            let _ = events.send(event);
+        }
+    }
+
+    /// Reload crashed pages whose backoff ran out, ping live views and
+    /// report the ones that stopped answering.
+    fn watch_views(&mut self, events: &broadcast::Sender<EngineEvent>) {
+        let now = Instant::now();
+        for (view_id, url) in self.crashes.due(now) {
+            if self.tabs.get_tab(view_id).is_none() {
+                self.crashes.forget(view_id);
+                continue;
+            }
+            log::info!("Reloading crashed {}: {}", view_id, url);
+            if let Err(e) = self.engine.load_url(view_id, &url) {
+                log::error!("Failed to reload crashed {}: {}", view_id, e);
+            }
+        }
+
+        let live: Vec<ViewId> = self
+            .tabs
+            .tabs_in_order()
+            .into_iter()
+            .filter(|tab| !matches!(tab.state, TabState::Suspended | TabState::Error))
+            .map(|tab| tab.view_id)
+            .collect();
+        for view_id in self.watchdog.round(&live, now) {
+            if let Err(e) = self.engine.ping_view(view_id) {
+                log::debug!("Cannot ping {}: {}", view_id, e);
+                self.watchdog.forget(view_id);
+            }
+        }
+        for view_id in self.watchdog.overdue(now) {
+            log::warn!("{} is not responding", view_id);
+            let _ = events.send(EngineEvent::ViewUnresponsive(view_id));
+        }
+    }
+}
+
+/// Cloneable handle used to drive the engine thread.
+#[derive(Clone)]
+pub struct EngineHandle {
+    commands: mpsc::Sender<EngineCommand>,
+    events: broadcast::Sender<EngineEvent>,
+}
+
+impl EngineHandle {
+    /// Subscribe to engine events published after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        self.events.subscribe()
+    }
+
+    /// Send a command built around a fresh reply channel and await the answer.
+    async fn request<T>(&self, command: impl FnOnce(Reply<T>) -> EngineCommand) -> EngineResult<T> {
+        let (tx, rx) = oneshot::channel();
+        self.commands.send(command(tx)).map_err(|_| host_gone())?;
+        rx.await.map_err(|_| host_gone())?
+    }
+
+    pub async fn create_tab(&self) -> EngineResult<ViewId> {
+        self.request(EngineCommand::CreateTab).await
+    }
+
+    pub async fn create_tab_in(&self, engine: &str) -> EngineResult<ViewId> {
+        let engine = engine.to_string();
+        self.request(|r| EngineCommand::CreateTabIn(engine, r))
+            .await
+    }
+
+    pub async fn reopen_in_engine(&self, view_id: ViewId, engine: &str) -> EngineResult<()> {
+        let engine = engine.to_string();
+        self.request(|r| EngineCommand::ReopenInEngine(view_id, engine, r))
+            .await
+    }
+
+    pub async fn close_tab(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::CloseTab(view_id, r)).await
+    }
+
+    pub async fn switch_to_tab(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::SwitchToTab(view_id, r))
+            .await
+    }
+
+    pub async fn load_url(&self, view_id: ViewId, url: &str) -> EngineResult<()> {
+        let url = url.to_string();
+        self.request(|r| EngineCommand::LoadUrl(view_id, url, r))
+            .await
+    }
+
+    /// Pick a user agent (preset name or string) for a tab; `None` goes
+    /// back to the site rules.
+    pub async fn set_user_agent(&self, view_id: ViewId, user_agent: Option<&str>) -> EngineResult<()> {
+        let user_agent = user_agent.map(str::to_string);
+        self.request(|r| EngineCommand::SetUserAgent(view_id, user_agent, r))
+            .await
+    }
+
+    pub async fn set_muted(&self, view_id: ViewId, muted: bool) -> EngineResult<()> {
+        self.request(|r| EngineCommand::SetMuted(view_id, muted, r)).await
+    }
+
+    pub async fn media_play_pause(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::MediaPlayPause(view_id, r)).await
+    }
+
+    pub async fn go_back(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::GoBack(view_id, r)).await
+    }
+
+    pub async fn go_forward(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::GoForward(view_id, r)).await
+    }
+
+    pub async fn reload(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::Reload(view_id, r)).await
+    }
+
+    pub async fn stop(&self, view_id: ViewId) -> EngineResult<()> {
+        self.request(|r| EngineCommand::Stop(view_id, r)).await
+    }
+
+    pub async fn execute_script(
+        &self,
+        view_id: ViewId,
+        script: &str,
+    ) -> EngineResult<serde_json::Value> {
+        let script = script.to_string();
+        self.request(|r| EngineCommand::ExecuteScript(view_id, script, r))
+            .await
+    }
+
+    pub async fn memory_pressure(&self, pressure: MemoryPressure) -> EngineResult<()> {
+        self.request(|r| EngineCommand::MemoryPressure(pressure, r))
+            .await
+    }
+
+    pub async fn memory_usage(&self) -> EngineResult<MemoryStats> {
+        self.request(EngineCommand::GetMemoryUsage).await
+    }
+
+    /// Run a closure on the engine thread and await its result.
+    pub async fn call<T, F>(&self, f: F) -> EngineResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut HostState) -> T + Send + 'static,
+    {
+        self.request(|reply| {
+            EngineCommand::Call(Box::new(move |state| {
+                let _ = reply.send(Ok(f(state)));
+            }))
+        })
+        .await
+    }
+
+    /// Run a closure on the engine thread, blocking until it returns.
+    ///
+    /// For callers outside the async runtime, such as GTK signal handlers.
+    /// Must not be called from within a tokio task.
+    pub fn call_blocking<T, F>(&self, f: F) -> EngineResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut HostState) -> T + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(EngineCommand::Call(Box::new(move |state| {
+                let _ = tx.send(f(state));
+            })))
+            .map_err(|_| host_gone())?;
+        rx.blocking_recv().map_err(|_| host_gone())
+    }
+}
+
+fn host_gone() -> EngineError {
+    EngineError::Other("Engine host is not running".to_string())
+}
+
+/// The engine thread and the handle used to reach it.
+pub struct EngineHost {
+    handle: EngineHandle,
+    thread: JoinHandle<()>,
+}
+
+impl EngineHost {
+    /// Move the engines and tab manager onto a new thread.
+    pub fn spawn(engine: EngineSet, tabs: TabManager) -> std::io::Result<Self> {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (event_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        let events = event_tx.clone();
+        let thread = std::thread::Builder::new()
+            .name("asteroid-engine".to_string())
+            .spawn(move || {
+                let state = HostState {
+                    engine,
+                    tabs,
+                    zoom: ZoomStore::new(None),
+                    https_only: HttpsOnly::default(),
+                    history: History::default(),
+                    watchdog: Watchdog::default(),
+                    crashes: CrashRecovery::default(),
+                    user_agents: Arc::default(),
+                    media_policies: MediaPolicies::default(),
+                };
+                run(state, command_rx, events)
+            })?;
+
+        Ok(Self {
+            handle: EngineHandle {
+                commands: command_tx,
+                events: event_tx,
+            },
+            thread,
+        })
+    }
+
+    pub fn handle(&self) -> EngineHandle {
+        self.handle.clone()
+    }
+
+    /// Shut the engine down and wait for the thread to exit.
+    pub fn shutdown(self) -> EngineResult<()> {
+        let (tx, rx) = oneshot::channel();
+        let result = match self.handle.commands.send(EngineCommand::Shutdown(tx)) {
+            Ok(()) => rx.blocking_recv().unwrap_or_else(|_| Err(host_gone())),
+            Err(_) => Err(host_gone()),
+        };
+        if self.thread.join().is_err() {
+            log::error!("Engine thread panicked");
+        }
+        result
+    }
+}
+
+/// Engine thread main loop.
+fn run(
+    mut state: HostState,
+    commands: mpsc::Receiver<EngineCommand>,
+    events: broadcast::Sender<EngineEvent>,
+) {
+    log::info!("Engine host started");
+    let mut last_suspension_check = Instant::now();
+
+    loop {
+        match commands.recv_timeout(EVENT_POLL_INTERVAL) {
+            Ok(EngineCommand::Shutdown(reply)) => {
+                state.dispatch_events(&events);
+                state.execute(EngineCommand::Shutdown(reply), &events);
+                break;
+            }
+            Ok(command) => state.execute(command, &events),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                if let Err(e) = state.engine.shutdown() {
+                    log::error!("Engine shutdown error: {}", e);
+                }
+                break;
+            }
+        }
+
+        state.dispatch_events(&events);
+        state.watch_views(&events);
+
+        if last_suspension_check.elapsed() >= SUSPENSION_CHECK_INTERVAL {
+            state.tabs.check_suspensions(&mut state.engine);
+            last_suspension_check = Instant::now();
+        }
+    }
+
+    log::info!("Engine host stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::{MediaState, Zoom, ZoomMode};
+    use crate::core::tab::{SuspensionConfig, TabState};
+    use crate::engines::mock::MockEngine;
+
+    fn spawn_mock() -> (EngineHost, crate::engines::mock::MockHandle) {
+        let engine = MockEngine::new();
+        let mock = engine.handle();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        (EngineHost::spawn(engine.into_engine_set(), tabs).unwrap(), mock)
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_commands_and_events() {
+        let (host, _mock) = spawn_mock();
+        let handle = host.handle();
+        let mut events = handle.subscribe();
+
+        runtime().block_on(async {
+            let id = handle.create_tab().await.unwrap();
+            handle.switch_to_tab(id).await.unwrap();
+            handle.load_url(id, "https://example.com/").await.unwrap();
+
+            loop {
+                match events.recv().await.unwrap() {
+                    EngineEvent::LoadFinished(v) if v == id => break,
+                    _ => {}
+                }
+            }
+
+            // The host applied the events to its tab manager before publishing
+            let (title, state) = handle
+                .call(move |host| {
+                    let tab = host.tabs.get_tab(id).unwrap();
+                    (tab.title.clone(), tab.state.clone())
+                })
+                .await
+                .unwrap();
+            assert_eq!(title, "Example Domain");
+            assert_eq!(state, TabState::Active);
+
+            assert!(matches!(
+                handle.load_url(ViewId(999), "https://example.com/").await,
+                Err(EngineError::ViewNotFound(_))
+            ));
+
+            handle.close_tab(id).await.unwrap();
+            loop {
+                if let EngineEvent::ViewClosed(v) = events.recv().await.unwrap() {
+                    assert_eq!(v, id);
+                    break;
+                }
+            }
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_memory_pressure_reaches_tabs() {
+        let (host, _mock) = spawn_mock();
+        let handle = host.handle();
+
+        runtime().block_on(async {
+            let first = handle.create_tab().await.unwrap();
+            let second = handle.create_tab().await.unwrap();
+            handle.switch_to_tab(second).await.unwrap();
+
+            let before = handle.memory_usage().await.unwrap().total_bytes;
+            handle
+                .memory_pressure(MemoryPressure::Critical)
+                .await
+                .unwrap();
+            assert!(handle.memory_usage().await.unwrap().total_bytes < before);
+
+            let state = handle
+                .call(move |host| host.tabs.get_tab(first).unwrap().state.clone())
+                .await
+                .unwrap();
+            assert_eq!(state, TabState::Suspended);
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_zoom_follows_site() {
+        let (host, _mock) = spawn_mock();
+        let handle = host.handle();
+
+        runtime().block_on(async {
+            let id = handle.create_tab().await.unwrap();
+            handle.load_url(id, "https://example.com/").await.unwrap();
+            let zoom = Zoom::new(1.5, ZoomMode::FullPage);
+            handle
+                .call(move |host| host.engine.set_zoom(id, zoom))
+                .await
+                .unwrap()
+                .unwrap();
+
+            handle.load_url(id, "https://other.test/").await.unwrap();
+            let get_zoom = move |host: &mut HostState| host.engine.get_zoom(id).unwrap();
+            assert_eq!(handle.call(get_zoom).await.unwrap(), Zoom::default());
+
+            handle.load_url(id, "https://example.com/again").await.unwrap();
+            assert_eq!(handle.call(get_zoom).await.unwrap(), zoom);
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_https_only_navigation() {
+        use crate::core::engine::LoadErrorKind;
+        use crate::core::https_only::HttpsOnlyPolicy;
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+
+        let fixture = MockFixture::test_pages().page(
+            "https://plain.test/",
+            MockPage::new("Plain").event(MockEvent::LoadError {
+                kind: LoadErrorKind::ConnectionRefused,
+            }),
+        );
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(MockEngine::with_fixture(fixture).into_engine_set(), tabs).unwrap();
+        let handle = host.handle();
+        let mut events = handle.subscribe();
+
+        runtime().block_on(async {
+            let policy = Arc::new(HttpsOnlyPolicy::new(true, None));
+            handle
+                .call(move |host| host.https_only = HttpsOnly::new(policy))
+                .await
+                .unwrap();
+            let id = handle.create_tab().await.unwrap();
+
+            handle.load_url(id, "http://example.com/").await.unwrap();
+            let url = handle
+                .call(move |host| host.engine.get_navigation_state(id).unwrap().url)
+                .await
+                .unwrap();
+            assert_eq!(url, "https://example.com/");
+
+            handle.load_url(id, "http://plain.test/").await.unwrap();
+            loop {
+                if let EngineEvent::LoadFailed(_, error) = events.recv().await.unwrap() {
+                    assert_eq!(error.url, "https://plain.test/");
+                    break;
+                }
+            }
+            let fallback = handle
+                .call(move |host| host.https_only.take_fallback(id))
+                .await
+                .unwrap();
+            assert_eq!(fallback.as_deref(), Some("http://plain.test/"));
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_tabs_in_other_engines() {
+        let (host, mock) = spawn_mock();
+        let handle = host.handle();
+
+        runtime().block_on(async {
+            let id = handle.create_tab_in("other").await.unwrap();
+            handle.load_url(id, "https://example.com/").await.unwrap();
+            assert!(mock.calls_to("load_url").is_empty());
+            assert!(handle.create_tab_in("servo").await.is_err());
+
+            handle.reopen_in_engine(id, "mock").await.unwrap();
+            let (engine, tab_engine) = handle
+                .call(move |host| {
+                    let tab = host.tabs.get_tab(id).unwrap();
+                    (host.engine.engine_of(id).to_string(), tab.engine.clone())
+                })
+                .await
+                .unwrap();
+            assert_eq!(engine, "mock");
+            assert_eq!(tab_engine, None);
+            assert_eq!(mock.calls_to("load_url")[0].args, vec!["https://example.com/"]);
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    fn spawn_fixture(fixture: crate::engines::mock::MockFixture) -> (EngineHost, crate::engines::mock::MockHandle) {
+        let engine = MockEngine::with_fixture(fixture);
+        let mock = engine.handle();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        (EngineHost::spawn(engine.into_engine_set(), tabs).unwrap(), mock)
+    }
+
+    #[test]
+    fn test_crashing_page_is_reloaded_with_backoff() {
+        use crate::core::crash::MAX_AUTO_RELOADS;
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+
+        let fixture = MockFixture::test_pages().page(
+            "https://crash.test/",
+            MockPage::new("Crash").event(MockEvent::Crash {
+                reason: "oom".to_string(),
+            }),
+        );
+        let (host, mock) = spawn_fixture(fixture);
+        let handle = host.handle();
+        let mut events = handle.subscribe();
+
+        runtime().block_on(async {
+            handle
+                .call(|host| host.crashes = CrashRecovery::new(Duration::from_millis(10)))
+                .await
+                .unwrap();
+            let id = handle.create_tab().await.unwrap();
+            handle.load_url(id, "https://crash.test/").await.unwrap();
+
+            let mut crashes = 0;
+            while crashes <= MAX_AUTO_RELOADS {
+                if let EngineEvent::ViewCrashed(..) = events.recv().await.unwrap() {
+                    crashes += 1;
+                }
+            }
+            // The last crash is left alone
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let (state, url) = handle
+                .call(move |host| {
+                    let tab = host.tabs.get_tab(id).unwrap();
+                    (tab.state.clone(), tab.url.clone())
+                })
+                .await
+                .unwrap();
+            assert_eq!(state, TabState::Error);
+            assert_eq!(url, "https://crash.test/");
+            assert_eq!(mock.calls_to("load_url").len(), MAX_AUTO_RELOADS + 1);
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_hung_view_is_reported() {
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+
+        let fixture = MockFixture::test_pages()
+            .page("https://hang.test/", MockPage::new("Hang").event(MockEvent::Hang));
+        let (host, _mock) = spawn_fixture(fixture);
+        let handle = host.handle();
+        let mut events = handle.subscribe();
+
+        runtime().block_on(async {
+            handle
+                .call(|host| {
+                    host.watchdog = Watchdog::new(Duration::from_millis(10), Duration::from_millis(30))
+                })
+                .await
+                .unwrap();
+            let id = handle.create_tab().await.unwrap();
+            handle.load_url(id, "https://hang.test/").await.unwrap();
+            loop {
+                match events.recv().await.unwrap() {
+                    EngineEvent::ViewResponsive(_) => panic!("Answer published before a hang"),
+                    EngineEvent::ViewUnresponsive(v) if v == id => break,
+                    _ => {}
+                }
+            }
+
+            handle.load_url(id, "https://example.com/").await.unwrap();
+            loop {
+                if let EngineEvent::ViewResponsive(v) = events.recv().await.unwrap() {
+                    assert_eq!(v, id);
+                    break;
+                }
+            }
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    async fn next_media(events: &mut broadcast::Receiver<EngineEvent>) -> MediaState {
+        loop {
+            if let EngineEvent::MediaStateChanged(_, state) = events.recv().await.unwrap() {
+                return state;
+            }
+        }
+    }
+
+    #[test]
+    fn test_mute_and_pause_tab_media() {
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+
+        let fixture = MockFixture::test_pages().page(
+            "https://radio.test/",
+            MockPage::new("Radio").event(MockEvent::Media { audible: true }),
+        );
+        let (host, _mock) = spawn_fixture(fixture);
+        let handle = host.handle();
+        let mut events = handle.subscribe();
+
+        runtime().block_on(async {
+            let id = handle.create_tab().await.unwrap();
+            handle.load_url(id, "https://radio.test/").await.unwrap();
+            assert!(next_media(&mut events).await.is_playing_audio());
+
+            handle.set_muted(id, true).await.unwrap();
+            assert!(next_media(&mut events).await.muted);
+            handle.media_play_pause(id).await.unwrap();
+            assert!(!next_media(&mut events).await.playing);
+
+            let media = handle
+                .call(move |host| host.tabs.get_tab(id).map(|tab| tab.media))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(media.muted && !media.playing);
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_media_policy_per_site() {
+        use crate::core::config::MediaConfig;
+        use crate::core::media_policy::{AutoplayPolicy, MediaSiteRule};
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+
+        let fixture = MockFixture::test_pages()
+            .page(
+                "https://radio.test/",
+                MockPage::new("Radio").event(MockEvent::Media { audible: true }),
+            )
+            .page(
+                "https://quiet.test/",
+                MockPage::new("Quiet").event(MockEvent::Media { audible: true }),
+            );
+        let (host, mock) = spawn_fixture(fixture);
+        let handle = host.handle();
+        let mut events = handle.subscribe();
+
+        runtime().block_on(async {
+            let config = MediaConfig {
+                autoplay: AutoplayPolicy::Allow,
+                site_rules: vec![MediaSiteRule {
+                    site: "quiet.test".to_string(),
+                    autoplay: Some(AutoplayPolicy::BlockAll),
+                    preload: None,
+                }],
+                ..MediaConfig::default()
+            };
+            handle
+                .call(move |host| host.media_policies = MediaPolicies::new(&config))
+                .await
+                .unwrap();
+            let id = handle.create_tab().await.unwrap();
+            handle.load_url(id, "https://radio.test/").await.unwrap();
+            assert!(next_media(&mut events).await.playing);
+            assert!(mock.calls_to("set_media_policy").is_empty());
+
+            handle.load_url(id, "https://quiet.test/").await.unwrap();
+            assert!(!next_media(&mut events).await.playing);
+            // Nothing started by itself before the tab was muted
+            handle.set_muted(id, true).await.unwrap();
+            assert!(!next_media(&mut events).await.playing);
+            handle.media_play_pause(id).await.unwrap();
+            assert!(next_media(&mut events).await.playing);
+            assert_eq!(
+                mock.calls_to("set_media_policy")[0].args,
+                vec!["block_all", "metadata"]
+            );
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_user_agent_per_site_and_tab() {
+        use crate::core::user_agent::{UserAgentPreset, UserAgentRule};
+
+        let (host, mock) = spawn_mock();
+        let handle = host.handle();
+
+        runtime().block_on(async {
+            let rules = vec![UserAgentRule {
+                site: "light.test".to_string(),
+                user_agent: "mobile".to_string(),
+            }];
+            handle
+                .call(move |host| host.user_agents = Arc::new(UserAgents::new("", &rules)))
+                .await
+                .unwrap();
+            let id = handle.create_tab().await.unwrap();
+            handle.load_url(id, "https://example.com/").await.unwrap();
+            assert!(mock.calls_to("set_user_agent").is_empty());
+
+            // Set before the navigation reaches the engine
+            handle.load_url(id, "https://light.test/").await.unwrap();
+            let methods: Vec<&str> = mock.calls().iter().map(|c| c.method).collect();
+            let load = methods.iter().rposition(|m| *m == "load_url").unwrap();
+            assert_eq!(methods[load - 1], "set_user_agent");
+            let agents = mock.calls_to("set_user_agent");
+            assert_eq!(agents[0].args, vec![UserAgentPreset::Mobile.user_agent()]);
+
+            handle.set_user_agent(id, Some("minimal")).await.unwrap();
+            assert_eq!(mock.calls_to("set_user_agent")[1].args, vec!["Mozilla/5.0"]);
+            assert_eq!(mock.calls_to("reload").len(), 1);
+            assert!(handle.set_user_agent(id, Some("a\nb")).await.is_err());
+            assert!(handle.set_user_agent(ViewId(999), None).await.is_err());
+        });
+
+        host.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_blocking_calls_and_shutdown() {
+        let (host, mock) = spawn_mock();
+        let handle = host.handle();
+
+        let count = handle
+            .call_blocking(|host| {
+                let id = host.tabs.create_tab(&mut host.engine).unwrap();
+                host.engine.load_url(id, "https://example.com/").unwrap();
+                host.tabs.tab_count()
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+
+        host.shutdown().unwrap();
+        assert_eq!(mock.calls_to("shutdown").len(), 1);
+        assert!(handle.call_blocking(|_| ()).is_err());
+    }
+}
@@ -0,0 +1,692 @@
+//! Engine set for Asteroid Browser.
+//!
+//! Lets tabs be shown by different engines, e.g. trying a page in Servo
+//! while the rest of the browser runs on Gecko. `EngineSet` is itself a
+//! `BrowserEngine`: calls about a view go to the engine that owns it, and
+//! everything else goes to every engine loaded so far. Engines other than
+//! the default are created from the registry the first time a tab asks
+//! for them, and are brought up to date with the settings, interceptor
+//! and user scripts the default engine already has. A view moved to
+//! another engine keeps its user agent and media policy, and stays muted.
+
+use crate::core::engine::{
+    BrowserEngine, CaptureOptions, Cookie, EngineCapabilities, EngineError, EngineEvent,
+    EngineRegistry, EngineResult, EngineSettings, FindOptions, InjectionTime, MemoryStats,
+    NavigationInterceptor, NavigationState, PdfOptions, PermissionKind, RequestInterceptor,
+    ScriptWorld,
+    SiteDataKind, SiteDataUsage, TrimLevel, UserScript, UserScriptId, VideoDecoder, ViewId,
+    Zoom,
+};
+use crate::core::media_policy::MediaPolicy;
+use crate::core::user_scripts::UserScripts;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The engines of the browser and the views each of them shows.
+pub struct EngineSet {
+    registry: EngineRegistry,
+    default_name: String,
+    /// Loaded engines by registry name
+    engines: BTreeMap<String, Box<dyn BrowserEngine>>,
+    /// Engine name of every view; views not listed belong to the default
+    owners: HashMap<ViewId, String>,
+    initialized: bool,
+    // Replayed on engines loaded later
+    settings: Option<EngineSettings>,
+    interceptor: Option<Arc<dyn RequestInterceptor>>,
+    navigation_interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    video_decoder: Option<VideoDecoder>,
+    hardware_acceleration: Option<bool>,
+    scripts: UserScripts,
+    /// Id each engine gave to a script of `scripts`
+    script_ids: HashMap<UserScriptId, Vec<(String, UserScriptId)>>,
+    /// Agent set for each view, carried over by `move_view`
+    user_agents: HashMap<ViewId, String>,
+    /// Muted views, carried over by `move_view`
+    muted: HashSet<ViewId>,
+    /// Policy set for each view, carried over by `move_view`
+    media_policies: HashMap<ViewId, MediaPolicy>,
+}
+
+impl EngineSet {
+    /// A set around the default engine; other engines come from `registry`.
+    pub fn new(name: &str, engine: Box<dyn BrowserEngine>, registry: EngineRegistry) -> Self {
+        let mut engines = BTreeMap::new();
+        engines.insert(name.to_string(), engine);
+        Self {
+            registry,
+            default_name: name.to_string(),
+            engines,
+            owners: HashMap::new(),
+            initialized: false,
+            settings: None,
+            interceptor: None,
+            navigation_interceptor: None,
+            video_decoder: None,
+            hardware_acceleration: None,
+            scripts: UserScripts::new(),
+            script_ids: HashMap::new(),
+            user_agents: HashMap::new(),
+            muted: HashSet::new(),
+            media_policies: HashMap::new(),
+        }
+    }
+
+    /// Registry name of the default engine.
+    pub fn default_name(&self) -> &str {
+        &self.default_name
+    }
+
+    /// Names of the engines tabs can be opened in, sorted.
+    pub fn available_engines(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .registry
+            .available_engines()
+            .into_iter()
+            .map(str::to_string)
+            .chain(std::iter::once(self.default_name.clone()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Names of the engines created so far.
+    pub fn loaded_engines(&self) -> Vec<&str> {
+        self.engines.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Registry name of the engine showing a view.
+    pub fn engine_of(&self, view_id: ViewId) -> &str {
+        self.owners.get(&view_id).unwrap_or(&self.default_name)
+    }
+
+    /// Capabilities of the engine showing a view.
+    pub fn view_capabilities(&self, view_id: ViewId) -> EngineCapabilities {
+        self.engines
+            .get(self.engine_of(view_id))
+            .map(|engine| engine.capabilities())
+            .unwrap_or_default()
+    }
+
+    /// Create a view in the named engine, loading it first if needed.
+    pub fn create_view_in(&mut self, name: &str, view_id: ViewId) -> EngineResult<()> {
+        self.load(name)?.create_view(view_id)?;
+        self.owners.insert(view_id, name.to_string());
+        Ok(())
+    }
+
+    /// Recreate a view in another engine. The new view is empty; the old
+    /// one is destroyed once the new one exists.
+    pub fn move_view(&mut self, view_id: ViewId, name: &str) -> EngineResult<()> {
+        let previous = self.engine_of(view_id).to_string();
+        if previous == name {
+            return Ok(());
+        }
+        let agent = self.user_agents.get(&view_id).cloned();
+        let muted = self.muted.contains(&view_id);
+        let media_policy = self.media_policies.get(&view_id).copied();
+        let engine = self.load(name)?;
+        engine.create_view(view_id)?;
+        if let Some(agent) = agent {
+            if let Err(e) = engine.set_user_agent(view_id, Some(&agent)) {
+                log::warn!("{} moved to {} without its user agent: {}", view_id, name, e);
+            }
+        }
+        if muted {
+            if let Err(e) = engine.set_muted(view_id, true) {
+                log::warn!("{} moved to {} unmuted: {}", view_id, name, e);
+            }
+        }
+        if let Some(policy) = media_policy {
+            if let Err(e) = engine.set_media_policy(view_id, policy) {
+                log::warn!("{} moved to {} without its media policy: {}", view_id, name, e);
+            }
+        }
+        self.owners.insert(view_id, name.to_string());
+        if let Some(engine) = self.engines.get_mut(&previous) {
+            if let Err(e) = engine.destroy_view(view_id) {
+                log::warn!("Failed to destroy {} in {}: {}", view_id, previous, e);
+            }
+        }
+        log::info!("Moved {} from {} to {}", view_id, previous, name);
+        Ok(())
+    }
+
+    /// The named engine, created and set up like the default one on first use.
+    fn load(&mut self, name: &str) -> EngineResult<&mut dyn BrowserEngine> {
+        if !self.engines.contains_key(name) {
+            let mut engine = self
+                .registry
+                .create(name)
+                .ok_or_else(|| EngineError::Other(format!("Unknown engine: {}", name)))?;
+            if self.initialized {
+                engine.initialize()?;
+            }
+            self.set_up(name, engine.as_mut());
+            log::info!("Loaded engine {}", name);
+            self.engines.insert(name.to_string(), engine);
+        }
+        self.engine(name)
+    }
+
+    /// Give a newly loaded engine what the others were given. Features it
+    /// lacks are left off.
+    fn set_up(&mut self, name: &str, engine: &mut dyn BrowserEngine) {
+        let mut results = Vec::new();
+        if let Some(settings) = &self.settings {
+            results.push(engine.apply_settings(settings));
+        }
+        if let Some(interceptor) = &self.interceptor {
+            results.push(engine.set_request_interceptor(Some(Arc::clone(interceptor))));
+        }
+        if let Some(interceptor) = &self.navigation_interceptor {
+            results.push(engine.set_navigation_interceptor(Some(Arc::clone(interceptor))));
+        }
+        if let Some(decoder) = &self.video_decoder {
+            results.push(engine.set_video_decoder(decoder.clone()));
+        }
+        if let Some(enabled) = self.hardware_acceleration {
+            results.push(engine.enable_hardware_acceleration(enabled));
+        }
+        for (id, script) in self.scripts.iter() {
+            match install_script(engine, script) {
+                Ok(engine_id) => self
+                    .script_ids
+                    .entry(id)
+                    .or_default()
+                    .push((name.to_string(), engine_id)),
+                Err(e) => results.push(Err(e)),
+            }
+        }
+        for result in results {
+            if let Err(e) = result {
+                log::warn!("Engine {} set up without a feature: {}", name, e);
+            }
+        }
+    }
+
+    fn engine(&mut self, name: &str) -> EngineResult<&mut dyn BrowserEngine> {
+        match self.engines.get_mut(name) {
+            Some(engine) => Ok(engine.as_mut()),
+            None => Err(EngineError::Other(format!("Engine {} is not loaded", name))),
+        }
+    }
+
+    fn view(&self, view_id: ViewId) -> EngineResult<&dyn BrowserEngine> {
+        match self.engines.get(self.engine_of(view_id)) {
+            Some(engine) => Ok(engine.as_ref()),
+            None => Err(EngineError::ViewNotFound(view_id)),
+        }
+    }
+
+    fn view_mut(&mut self, view_id: ViewId) -> EngineResult<&mut dyn BrowserEngine> {
+        let name = self.owners.get(&view_id).unwrap_or(&self.default_name);
+        match self.engines.get_mut(name) {
+            Some(engine) => Ok(engine.as_mut()),
+            None => Err(EngineError::ViewNotFound(view_id)),
+        }
+    }
+
+    /// Run `f` on every loaded engine. The default engine's result is
+    /// returned; failures of the others are logged.
+    fn each(
+        &mut self,
+        what: &str,
+        mut f: impl FnMut(&mut dyn BrowserEngine) -> EngineResult<()>,
+    ) -> EngineResult<()> {
+        let mut result = Ok(());
+        for (name, engine) in &mut self.engines {
+            match f(engine.as_mut()) {
+                Err(e) if *name == self.default_name => result = Err(e),
+                Err(e) => log::warn!("Engine {} failed to {}: {}", name, what, e),
+                Ok(()) => {}
+            }
+        }
+        result
+    }
+
+    /// Collect `f` from every loaded engine, like `each`.
+    fn collect_all<T>(
+        &self,
+        what: &str,
+        f: impl Fn(&dyn BrowserEngine) -> EngineResult<Vec<T>>,
+    ) -> EngineResult<Vec<T>> {
+        let mut all = Vec::new();
+        for (name, engine) in &self.engines {
+            match f(engine.as_ref()) {
+                Ok(items) => all.extend(items),
+                Err(e) if *name == self.default_name => return Err(e),
+                Err(e) => log::warn!("Engine {} failed to {}: {}", name, what, e),
+            }
+        }
+        Ok(all)
+    }
+}
+
+fn install_script(engine: &mut dyn BrowserEngine, script: &UserScript) -> EngineResult<UserScriptId> {
+    let url_matches: Vec<&str> = script.url_matches.iter().map(|p| p.as_str()).collect();
+    engine.add_user_script(
+        &script.source,
+        script.injection_time,
+        script.world.clone(),
+        &url_matches,
+    )
+}
+
+impl BrowserEngine for EngineSet {
+    fn initialize(&mut self) -> EngineResult<()> {
+        self.each("initialize", |engine| engine.initialize())?;
+        self.initialized = true;
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> EngineResult<()> {
+        self.initialized = false;
+        self.owners.clear();
+        self.each("shut down", |engine| engine.shutdown())
+    }
+
+    fn create_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        let name = self.default_name.clone();
+        self.create_view_in(&name, view_id)
+    }
+
+    fn load_url(&mut self, view_id: ViewId, url: &str) -> EngineResult<()> {
+        self.view_mut(view_id)?.load_url(view_id, url)
+    }
+
+    fn load_html(&mut self, view_id: ViewId, html: &str, base_url: &str) -> EngineResult<()> {
+        self.view_mut(view_id)?.load_html(view_id, html, base_url)
+    }
+
+    fn go_back(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.go_back(view_id)
+    }
+
+    fn go_forward(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.go_forward(view_id)
+    }
+
+    fn reload(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.reload(view_id)
+    }
+
+    fn stop(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.stop(view_id)
+    }
+
+    fn set_user_agent(&mut self, view_id: ViewId, user_agent: Option<&str>) -> EngineResult<()> {
+        self.view_mut(view_id)?.set_user_agent(view_id, user_agent)?;
+        match user_agent {
+            Some(agent) => self.user_agents.insert(view_id, agent.to_string()),
+            None => self.user_agents.remove(&view_id),
+        };
+        Ok(())
+    }
+
+    fn set_muted(&mut self, view_id: ViewId, muted: bool) -> EngineResult<()> {
+        self.view_mut(view_id)?.set_muted(view_id, muted)?;
+        if muted {
+            self.muted.insert(view_id);
+        } else {
+            self.muted.remove(&view_id);
+        }
+        Ok(())
+    }
+
+    fn media_play_pause(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.media_play_pause(view_id)
+    }
+
+    fn set_media_policy(&mut self, view_id: ViewId, policy: MediaPolicy) -> EngineResult<()> {
+        self.view_mut(view_id)?.set_media_policy(view_id, policy)?;
+        self.media_policies.insert(view_id, policy);
+        Ok(())
+    }
+
+    fn execute_script(&mut self, view_id: ViewId, script: &str) -> EngineResult<serde_json::Value> {
+        self.view_mut(view_id)?.execute_script(view_id, script)
+    }
+
+    fn add_user_script(
+        &mut self,
+        script: &str,
+        time: InjectionTime,
+        world: ScriptWorld,
+        url_matches: &[&str],
+    ) -> EngineResult<UserScriptId> {
+        let script = UserScript {
+            source: script.to_string(),
+            injection_time: time,
+            world,
+            url_matches: url_matches.iter().map(|p| p.to_string()).collect(),
+        };
+        // Only the default engine has to take it
+        let default_name = self.default_name.clone();
+        let mut installed = vec![(
+            default_name.clone(),
+            install_script(self.engine(&default_name)?, &script)?,
+        )];
+        for (name, engine) in &mut self.engines {
+            if *name == default_name {
+                continue;
+            }
+            match install_script(engine.as_mut(), &script) {
+                Ok(engine_id) => installed.push((name.clone(), engine_id)),
+                Err(e) => log::warn!("Engine {} failed to add a user script: {}", name, e),
+            }
+        }
+        let id = self
+            .scripts
+            .add(&script.source, time, script.world, url_matches);
+        self.script_ids.insert(id, installed);
+        Ok(id)
+    }
+
+    fn remove_user_script(&mut self, id: UserScriptId) -> EngineResult<()> {
+        if !self.scripts.remove(id) {
+            return Err(EngineError::Other(format!("Unknown user script: {}", id)));
+        }
+        for (name, engine_id) in self.script_ids.remove(&id).unwrap_or_default() {
+            if let Some(engine) = self.engines.get_mut(&name) {
+                if let Err(e) = engine.remove_user_script(engine_id) {
+                    log::warn!("Engine {} failed to remove {}: {}", name, id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_script_in_world(
+        &mut self,
+        view_id: ViewId,
+        world: &str,
+        script: &str,
+    ) -> EngineResult<serde_json::Value> {
+        self.view_mut(view_id)?
+            .execute_script_in_world(view_id, world, script)
+    }
+
+    fn ping_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.ping_view(view_id)
+    }
+
+    fn suspend_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.suspend_view(view_id)
+    }
+
+    fn resume_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.resume_view(view_id)
+    }
+
+    fn destroy_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        let result = self.view_mut(view_id)?.destroy_view(view_id);
+        self.owners.remove(&view_id);
+        self.user_agents.remove(&view_id);
+        self.muted.remove(&view_id);
+        self.media_policies.remove(&view_id);
+        result
+    }
+
+    fn set_video_decoder(&mut self, decoder: VideoDecoder) -> EngineResult<()> {
+        self.each("set the video decoder", |engine| {
+            engine.set_video_decoder(decoder.clone())
+        })?;
+        self.video_decoder = Some(decoder);
+        Ok(())
+    }
+
+    fn enable_hardware_acceleration(&mut self, enabled: bool) -> EngineResult<()> {
+        self.each("set hardware acceleration", |engine| {
+            engine.enable_hardware_acceleration(enabled)
+        })?;
+        self.hardware_acceleration = Some(enabled);
+        Ok(())
+    }
+
+    fn get_memory_usage(&self) -> MemoryStats {
+        self.engines
+            .values()
+            .map(|engine| engine.get_memory_usage())
+            .fold(MemoryStats::default(), |total, stats| MemoryStats {
+                total_bytes: total.total_bytes + stats.total_bytes,
+                js_heap_bytes: total.js_heap_bytes + stats.js_heap_bytes,
+                image_cache_bytes: total.image_cache_bytes + stats.image_cache_bytes,
+                dom_bytes: total.dom_bytes + stats.dom_bytes,
+                layout_bytes: total.layout_bytes + stats.layout_bytes,
+                network_cache_bytes: total.network_cache_bytes + stats.network_cache_bytes,
+            })
+    }
+
+    fn trim_memory(&mut self, level: TrimLevel) -> EngineResult<()> {
+        self.each("trim memory", |engine| engine.trim_memory(level))
+    }
+
+    fn get_navigation_state(&self, view_id: ViewId) -> EngineResult<NavigationState> {
+        self.view(view_id)?.get_navigation_state(view_id)
+    }
+
+    fn find_in_page(
+        &mut self,
+        view_id: ViewId,
+        query: &str,
+        options: &FindOptions,
+        forward: bool,
+    ) -> EngineResult<()> {
+        self.view_mut(view_id)?
+            .find_in_page(view_id, query, options, forward)
+    }
+
+    fn clear_find(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.view_mut(view_id)?.clear_find(view_id)
+    }
+
+    fn apply_settings(&mut self, settings: &EngineSettings) -> EngineResult<()> {
+        self.settings = Some(settings.clone());
+        self.each("apply settings", |engine| engine.apply_settings(settings))
+    }
+
+    fn set_request_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn RequestInterceptor>>,
+    ) -> EngineResult<()> {
+        self.each("set the request interceptor", |engine| {
+            engine.set_request_interceptor(interceptor.clone())
+        })?;
+        self.interceptor = interceptor;
+        Ok(())
+    }
+
+    fn set_navigation_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    ) -> EngineResult<()> {
+        self.each("set the navigation interceptor", |engine| {
+            engine.set_navigation_interceptor(interceptor.clone())
+        })?;
+        self.navigation_interceptor = interceptor;
+        Ok(())
+    }
+
+    fn respond_permission(
+        &mut self,
+        view_id: ViewId,
+        origin: &str,
+        kind: PermissionKind,
+        granted: bool,
+    ) -> EngineResult<()> {
+        self.view_mut(view_id)?
+            .respond_permission(view_id, origin, kind, granted)
+    }
+
+    fn list_cookies(&self, origin: Option<&str>) -> EngineResult<Vec<Cookie>> {
+        self.collect_all("list cookies", |engine| engine.list_cookies(origin))
+    }
+
+    fn delete_cookie(&mut self, cookie: &Cookie) -> EngineResult<()> {
+        self.each("delete a cookie", |engine| engine.delete_cookie(cookie))
+    }
+
+    fn clear_cookies(&mut self) -> EngineResult<()> {
+        self.each("clear cookies", |engine| engine.clear_cookies())
+    }
+
+    fn clear_site_data(&mut self, origin: Option<&str>, kinds: &[SiteDataKind]) -> EngineResult<()> {
+        self.each("clear site data", |engine| engine.clear_site_data(origin, kinds))
+    }
+
+    fn site_data_usage(&self) -> EngineResult<Vec<SiteDataUsage>> {
+        self.collect_all("report site data", |engine| engine.site_data_usage())
+    }
+
+    fn capture_view(&self, view_id: ViewId, options: CaptureOptions) -> EngineResult<Vec<u8>> {
+        self.view(view_id)?.capture_view(view_id, options)
+    }
+
+    fn set_zoom(&mut self, view_id: ViewId, zoom: Zoom) -> EngineResult<()> {
+        self.view_mut(view_id)?.set_zoom(view_id, zoom)
+    }
+
+    fn get_zoom(&self, view_id: ViewId) -> EngineResult<Zoom> {
+        self.view(view_id)?.get_zoom(view_id)
+    }
+
+    fn accept_certificate_exception(
+        &mut self,
+        view_id: ViewId,
+        host: &str,
+        fingerprint: &str,
+    ) -> EngineResult<()> {
+        self.view_mut(view_id)?
+            .accept_certificate_exception(view_id, host, fingerprint)
+    }
+
+    fn page_source(&self, view_id: ViewId) -> EngineResult<Option<String>> {
+        self.view(view_id)?.page_source(view_id)
+    }
+
+    fn print_to_pdf(&mut self, view_id: ViewId, options: &PdfOptions, path: &Path) -> EngineResult<()> {
+        self.view_mut(view_id)?.print_to_pdf(view_id, options, path)
+    }
+
+    fn engine_info(&self) -> (String, String) {
+        self.engines
+            .get(&self.default_name)
+            .map(|engine| engine.engine_info())
+            .unwrap_or_default()
+    }
+
+    fn capabilities(&self) -> EngineCapabilities {
+        self.engines
+            .get(&self.default_name)
+            .map(|engine| engine.capabilities())
+            .unwrap_or_default()
+    }
+
+    fn poll_events(&mut self) -> Vec<EngineEvent> {
+        self.engines
+            .values_mut()
+            .flat_map(|engine| engine.poll_events())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::mock::MockEngine;
+
+    fn engine_set() -> (EngineSet, crate::engines::mock::MockHandle) {
+        let engine = MockEngine::new();
+        let mock = engine.handle();
+        (engine.into_engine_set(), mock)
+    }
+
+    #[test]
+    fn test_views_are_routed_to_their_engine() {
+        let (mut engines, mock) = engine_set();
+        engines.initialize().unwrap();
+        assert_eq!(engines.available_engines(), vec!["mock", "other"]);
+        assert_eq!(engines.loaded_engines(), vec!["mock"]);
+
+        engines.create_view(ViewId(1)).unwrap();
+        engines.create_view_in("other", ViewId(2)).unwrap();
+        assert_eq!(engines.loaded_engines(), vec!["mock", "other"]);
+        assert_eq!(engines.engine_of(ViewId(2)), "other");
+        assert!(engines.create_view_in("servo", ViewId(3)).is_err());
+
+        engines.load_url(ViewId(2), "https://example.com/").unwrap();
+        assert!(mock.calls_to("load_url").is_empty());
+        let url = engines.get_navigation_state(ViewId(2)).unwrap().url;
+        assert_eq!(url, "https://example.com/");
+        let events = engines.poll_events();
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, EngineEvent::LoadFinished(ViewId(2)))));
+
+        // Unknown views are the default engine's to report
+        assert!(matches!(
+            engines.load_url(ViewId(9), "https://example.com/"),
+            Err(EngineError::ViewNotFound(_))
+        ));
+        assert_eq!(mock.calls_to("load_url").len(), 1);
+    }
+
+    #[test]
+    fn test_loaded_engines_get_existing_scripts() {
+        let (mut engines, mock) = engine_set();
+        engines.initialize().unwrap();
+        let id = engines
+            .add_user_script(
+                "hints()",
+                InjectionTime::End,
+                ScriptWorld::isolated("hints"),
+                &[],
+            )
+            .unwrap();
+
+        engines.create_view_in("other", ViewId(1)).unwrap();
+        engines.load_url(ViewId(1), "https://example.com/").unwrap();
+        assert!(engines
+            .execute_script_in_world(ViewId(1), "hints", "1")
+            .is_ok());
+
+        engines.remove_user_script(id).unwrap();
+        assert_eq!(mock.calls_to("remove_user_script").len(), 1);
+        engines.load_url(ViewId(1), "https://example.com/").unwrap();
+        assert!(engines
+            .execute_script_in_world(ViewId(1), "hints", "1")
+            .is_err());
+        assert!(engines.remove_user_script(id).is_err());
+    }
+
+    #[test]
+    fn test_move_view() {
+        let (mut engines, mock) = engine_set();
+        engines.create_view(ViewId(1)).unwrap();
+        engines.load_url(ViewId(1), "https://example.com/").unwrap();
+
+        engines.move_view(ViewId(1), "other").unwrap();
+        assert_eq!(engines.engine_of(ViewId(1)), "other");
+        assert_eq!(mock.calls_to("destroy_view").len(), 1);
+        // The view starts over in its new engine
+        let url = engines.get_navigation_state(ViewId(1)).unwrap().url;
+        assert_ne!(url, "https://example.com/");
+
+        // and keeps its user agent and mute
+        engines.set_user_agent(ViewId(1), Some("Mozilla/5.0")).unwrap();
+        engines.set_muted(ViewId(1), true).unwrap();
+        engines
+            .set_media_policy(ViewId(1), MediaPolicy::default())
+            .unwrap();
+        engines.move_view(ViewId(1), "mock").unwrap();
+        assert_eq!(mock.calls_to("set_user_agent")[0].args, vec!["Mozilla/5.0"]);
+        assert_eq!(mock.calls_to("set_muted")[0].args, vec!["true"]);
+        assert_eq!(mock.calls_to("set_media_policy").len(), 1);
+
+        engines.destroy_view(ViewId(1)).unwrap();
+        assert_eq!(engines.engine_of(ViewId(1)), "mock");
+    }
+}
@@ -0,0 +1,124 @@
+//! Find in page for Asteroid Browser.
+//!
+//! Queries are compiled to a regular expression following the
+//! `FindOptions` (literal or regex, case sensitivity, whole words), so
+//! every engine matches them the same way. `FindSession` keeps which
+//! match is current while the user steps through them.
+
+use crate::core::engine::FindOptions;
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+/// Compile a query; `Err` describes an invalid regular expression.
+pub fn find_pattern(query: &str, options: &FindOptions) -> Result<Regex, String> {
+    let pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let pattern = if options.whole_word {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+/// Byte ranges of the non-empty matches of `pattern` in `text`.
+pub fn find_matches(pattern: &Regex, text: &str) -> Vec<Range<usize>> {
+    pattern
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| m.range())
+        .collect()
+}
+
+/// Current match of an ongoing search in one view.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindSession {
+    query: String,
+    options: FindOptions,
+    /// Index of the current match (0-based)
+    current: usize,
+    total: usize,
+}
+
+impl FindSession {
+    /// Step to the next (or previous) match for `query`, starting over at
+    /// the first match when the query or options changed. Returns the
+    /// 1-based current match and the total, (0, 0) without matches.
+    pub fn step(
+        &mut self,
+        query: &str,
+        options: &FindOptions,
+        total: usize,
+        forward: bool,
+    ) -> (usize, usize) {
+        let same = self.query == query && self.options == *options && self.total == total;
+        self.current = match (same, total) {
+            (_, 0) => 0,
+            (false, _) => 0,
+            (true, _) if forward => (self.current + 1) % total,
+            (true, _) => (self.current + total - 1) % total,
+        };
+        self.query = query.to_string();
+        self.options = options.clone();
+        self.total = total;
+        if total == 0 {
+            (0, 0)
+        } else {
+            (self.current + 1, total)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_options() {
+        let text = "Cat catalog CAT. cat? a.t";
+        let count = |query: &str, options: FindOptions| {
+            find_matches(&find_pattern(query, &options).unwrap(), text).len()
+        };
+
+        assert_eq!(count("cat", FindOptions::default()), 4);
+        let case = FindOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(count("cat", case.clone()), 2);
+        let word = FindOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(count("cat", word), 3);
+        // Literal queries are not patterns
+        assert_eq!(count("a.t", FindOptions::default()), 1);
+        let regex = FindOptions {
+            regex: true,
+            ..case
+        };
+        assert_eq!(count(r"[Cc]at\b", regex.clone()), 2);
+        assert!(find_pattern("(", &regex).is_err());
+    }
+
+    #[test]
+    fn test_session_steps_through_matches() {
+        let mut session = FindSession::default();
+        let options = FindOptions::default();
+        assert_eq!(session.step("cat", &options, 3, true), (1, 3));
+        assert_eq!(session.step("cat", &options, 3, true), (2, 3));
+        assert_eq!(session.step("cat", &options, 3, true), (3, 3));
+        assert_eq!(session.step("cat", &options, 3, true), (1, 3));
+        assert_eq!(session.step("cat", &options, 3, false), (3, 3));
+
+        // A new query starts over
+        assert_eq!(session.step("dog", &options, 2, false), (1, 2));
+        assert_eq!(session.step("dogs", &options, 0, true), (0, 0));
+    }
+}
@@ -0,0 +1,114 @@
+//! Browsing history for Asteroid Browser.
+//!
+//! Pages visited during the session, newest first, for the
+//! asteroid://history page. Internal pages and about: URLs are not
+//! recorded, and only the most recent `HISTORY_LIMIT` visits are kept.
+
+use crate::core::internal_pages::is_internal_url;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of visits kept.
+pub const HISTORY_LIMIT: usize = 1000;
+
+/// One visit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: String,
+    /// Unix timestamp of the visit
+    pub visited_at: u64,
+}
+
+/// Visited pages, newest first.
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(HISTORY_LIMIT)
+    }
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            limit,
+        }
+    }
+
+    /// Record a visit to `url`.
+    pub fn record(&mut self, url: &str) {
+        if !url.contains("://") || is_internal_url(url) {
+            return;
+        }
+        let visited_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // Reloads and same-page navigations refresh the newest visit
+        if let Some(newest) = self.entries.front_mut().filter(|e| e.url == url) {
+            newest.visited_at = visited_at;
+            return;
+        }
+        self.entries.push_front(HistoryEntry {
+            url: url.to_string(),
+            title: String::new(),
+            visited_at,
+        });
+        self.entries.truncate(self.limit);
+    }
+
+    /// Title of the latest visit to `url`.
+    pub fn set_title(&mut self, url: &str, title: &str) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.url == url) {
+            entry.title = title.to_string();
+        }
+    }
+
+    /// Visits, newest first.
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_visits() {
+        let mut history = History::new(2);
+        history.record("https://a.test/");
+        history.set_title("https://a.test/", "A");
+        history.record("https://a.test/");
+        history.record("asteroid://history");
+        history.record("about:blank");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.entries().next().unwrap().title, "A");
+
+        history.record("https://b.test/");
+        history.record("https://c.test/");
+        let urls: Vec<_> = history.entries().map(|e| e.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://c.test/", "https://b.test/"]);
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+}
@@ -0,0 +1,439 @@
+//! HTTPS-only mode for Asteroid Browser.
+//!
+//! While `privacy.https_only` is on, http:// navigations are rewritten to
+//! https:// before they are made: the browser's own by `HttpsOnly`, and
+//! links, redirects and history steps by `HttpsOnlyPolicy` as the
+//! engine's navigation interceptor. The policy also upgrades subresources
+//! as a request interceptor. Local hosts (localhost, private
+//! and link-local addresses) are never upgraded. When an upgraded
+//! navigation fails the user may continue over HTTP, which exempts the
+//! site; exemptions are saved to https_exemptions.json.
+
+use crate::core::engine::{
+    BrowserEngine, EngineEvent, EngineResult, Navigation, NavigationInterceptor, RequestDecision,
+    RequestInfo, RequestInterceptor, ViewId,
+};
+use crate::core::site_data::host_of;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Whether a host is only reachable locally: localhost, private,
+/// loopback and link-local addresses, and `.local` names.
+pub fn is_local_host(host: &str) -> bool {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local") {
+        return true;
+    }
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        Ok(IpAddr::V6(ip)) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+        Err(_) => false,
+    }
+}
+
+/// https:// form of an http:// URL; the default port is dropped.
+pub fn upgrade_url(url: &str) -> Option<String> {
+    let rest = strip_scheme(url, "http://")?;
+    let (authority, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let authority = authority.strip_suffix(":80").unwrap_or(authority);
+    Some(format!("https://{}{}", authority, path))
+}
+
+fn strip_scheme<'a>(url: &'a str, scheme: &str) -> Option<&'a str> {
+    let prefix = url.get(..scheme.len())?;
+    prefix
+        .eq_ignore_ascii_case(scheme)
+        .then(|| &url[scheme.len()..])
+}
+
+/// Upgrade switch and per-site exemptions, shared by the engine thread and
+/// the request interceptor.
+#[derive(Debug, Default)]
+pub struct HttpsOnlyPolicy {
+    enabled: AtomicBool,
+    /// Where exemptions are saved, if anywhere
+    path: Option<PathBuf>,
+    /// Hosts loaded over plain HTTP at the user's request
+    exemptions: RwLock<BTreeSet<String>>,
+}
+
+impl HttpsOnlyPolicy {
+    /// Create a policy, loading previous exemptions from `path` if given.
+    pub fn new(enabled: bool, path: Option<PathBuf>) -> Self {
+        let exemptions = path
+            .as_deref()
+            .and_then(load_exemptions)
+            .unwrap_or_default();
+        Self {
+            enabled: AtomicBool::new(enabled),
+            path,
+            exemptions: RwLock::new(exemptions),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether a host is loaded as the page asks, without upgrading.
+    pub fn is_exempt(&self, host: &str) -> bool {
+        is_local_host(host)
+            || self
+                .exemptions
+                .read()
+                .is_ok_and(|e| e.contains(&host.to_ascii_lowercase()))
+    }
+
+    /// Allow plain HTTP for a host from now on.
+    pub fn exempt(&self, host: &str) {
+        let host = host.to_ascii_lowercase();
+        if host.is_empty() {
+            return;
+        }
+        let added = self.exemptions.write().is_ok_and(|mut e| e.insert(host));
+        if added {
+            self.save();
+        }
+    }
+
+    /// Upgrade the host again.
+    pub fn remove_exemption(&self, host: &str) {
+        let removed = self
+            .exemptions
+            .write()
+            .is_ok_and(|mut e| e.remove(&host.to_ascii_lowercase()));
+        if removed {
+            self.save();
+        }
+    }
+
+    /// Hosts exempted by the user, sorted.
+    pub fn exemptions(&self) -> Vec<String> {
+        self.exemptions
+            .read()
+            .map(|e| e.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// URL to load instead of `url`, or `None` to load it as is.
+    pub fn upgrade(&self, url: &str) -> Option<String> {
+        if !self.is_enabled() || self.is_exempt(host_of(url)) {
+            return None;
+        }
+        upgrade_url(url)
+    }
+
+    fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(&self.exemptions())
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!(
+                "Failed to save HTTPS-only exemptions to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+fn load_exemptions(path: &Path) -> Option<BTreeSet<String>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(exemptions) => Some(exemptions),
+        Err(e) => {
+            log::error!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Subresources of exempted pages are left alone, everything else is
+/// upgraded.
+impl RequestInterceptor for HttpsOnlyPolicy {
+    fn intercept(&self, request: &RequestInfo) -> RequestDecision {
+        if self.is_exempt(host_of(&request.initiator)) {
+            return RequestDecision::Allow;
+        }
+        match self.upgrade(&request.url) {
+            Some(url) => RequestDecision::Redirect(url),
+            None => RequestDecision::Allow,
+        }
+    }
+}
+
+/// Top-level navigations the browser doesn't start itself are upgraded in
+/// the engine; `HttpsOnly` learns of them from `NavigationRedirected`.
+impl NavigationInterceptor for HttpsOnlyPolicy {
+    fn intercept_navigation(&self, navigation: &mut Navigation) {
+        if let Some(url) = self.upgrade(&navigation.url) {
+            navigation.url = url;
+        }
+    }
+}
+
+/// Navigation side of HTTPS-only mode, owned by the engine thread.
+#[derive(Debug, Default)]
+pub struct HttpsOnly {
+    policy: Arc<HttpsOnlyPolicy>,
+    /// Original http:// URL of upgraded navigations still loading
+    upgraded: HashMap<ViewId, String>,
+    /// Original http:// URL of upgraded navigations that failed
+    failed: HashMap<ViewId, String>,
+}
+
+impl HttpsOnly {
+    pub fn new(policy: Arc<HttpsOnlyPolicy>) -> Self {
+        Self {
+            policy,
+            upgraded: HashMap::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> &Arc<HttpsOnlyPolicy> {
+        &self.policy
+    }
+
+    /// Navigate a view, upgrading the URL if the policy asks for it.
+    pub fn load_url(
+        &mut self,
+        engine: &mut dyn BrowserEngine,
+        view_id: ViewId,
+        url: &str,
+    ) -> EngineResult<()> {
+        self.failed.remove(&view_id);
+        match self.policy.upgrade(url) {
+            Some(upgraded) => {
+                log::debug!("Upgrading {} to HTTPS", url);
+                self.upgraded.insert(view_id, url.to_string());
+                engine.load_url(view_id, &upgraded)
+            }
+            None => {
+                self.upgraded.remove(&view_id);
+                engine.load_url(view_id, url)
+            }
+        }
+    }
+
+    /// Track the outcome of upgraded navigations.
+    pub fn handle_event(&mut self, event: &EngineEvent) {
+        match event {
+            // Upgraded by the engine: a link, redirect or history step
+            EngineEvent::NavigationRedirected(view_id, original, url)
+                if upgrade_url(original).as_deref() == Some(url.as_str()) =>
+            {
+                self.failed.remove(view_id);
+                self.upgraded.insert(*view_id, original.clone());
+            }
+            EngineEvent::LoadFailed(view_id, error) => {
+                if let Some(original) = self.upgraded.remove(view_id) {
+                    if upgrade_url(&original).as_deref() == Some(error.url.as_str()) {
+                        self.failed.insert(*view_id, original);
+                    }
+                }
+            }
+            EngineEvent::LoadFinished(view_id) => {
+                self.upgraded.remove(view_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Original http:// URL of an upgraded navigation that failed in the
+    /// view, to offer loading it without HTTPS.
+    pub fn take_fallback(&mut self, view_id: ViewId) -> Option<String> {
+        self.failed.remove(&view_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blocker::ResourceType;
+    use crate::core::engine::{LoadError, LoadErrorKind};
+    use crate::engines::mock::MockEngine;
+
+    #[test]
+    fn test_local_hosts() {
+        for host in [
+            "localhost",
+            "app.localhost",
+            "printer.local",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.0.5",
+            "[::1]",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(is_local_host(host), "{}", host);
+        }
+        for host in ["example.com", "8.8.8.8", "172.32.0.1", "2001:db8::1"] {
+            assert!(!is_local_host(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_upgrade() {
+        assert_eq!(
+            upgrade_url("http://example.com:80/a?b").as_deref(),
+            Some("https://example.com/a?b")
+        );
+        assert_eq!(
+            upgrade_url("HTTP://example.com:8080").as_deref(),
+            Some("https://example.com:8080")
+        );
+        assert_eq!(upgrade_url("https://example.com/"), None);
+
+        let policy = HttpsOnlyPolicy::new(true, None);
+        assert!(policy.upgrade("http://localhost:3000/").is_none());
+        assert!(policy.upgrade("http://192.168.0.10/").is_none());
+        policy.exempt("Legacy.test");
+        assert!(policy.upgrade("http://legacy.test/").is_none());
+        assert_eq!(policy.exemptions(), vec!["legacy.test"]);
+
+        let request = RequestInfo {
+            view_id: ViewId(1),
+            url: "http://cdn.test/app.js".to_string(),
+            initiator: "https://news.test/".to_string(),
+            resource_type: ResourceType::Script,
+        };
+        assert_eq!(
+            policy.intercept(&request),
+            RequestDecision::Redirect("https://cdn.test/app.js".to_string())
+        );
+        let request = RequestInfo {
+            initiator: "http://legacy.test/".to_string(),
+            ..request
+        };
+        assert_eq!(policy.intercept(&request), RequestDecision::Allow);
+
+        policy.set_enabled(false);
+        assert!(policy.upgrade("http://example.com/").is_none());
+    }
+
+    #[test]
+    fn test_failed_upgrade_offers_fallback() {
+        let mut engine = MockEngine::new();
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let mut https_only = HttpsOnly::new(Arc::new(HttpsOnlyPolicy::new(true, None)));
+
+        https_only
+            .load_url(&mut engine, view, "http://example.com/")
+            .unwrap();
+        assert_eq!(
+            engine.get_navigation_state(view).unwrap().url,
+            "https://example.com/"
+        );
+
+        let failed = EngineEvent::LoadFailed(
+            view,
+            LoadError {
+                url: "https://example.com/".to_string(),
+                kind: LoadErrorKind::SecureConnectionFailed,
+            },
+        );
+        https_only.handle_event(&failed);
+        assert_eq!(
+            https_only.take_fallback(view).as_deref(),
+            Some("http://example.com/")
+        );
+        // Only reported once, and not for loads that were not upgraded
+        assert_eq!(https_only.take_fallback(view), None);
+        https_only
+            .load_url(&mut engine, view, "https://example.com/")
+            .unwrap();
+        https_only.handle_event(&failed);
+        assert_eq!(https_only.take_fallback(view), None);
+    }
+
+    #[test]
+    fn test_links_in_pages_are_upgraded() {
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+
+        let mut links = MockPage::new("Links");
+        links.links = vec![
+            "http://secure.test/".to_string(),
+            "http://down.test/".to_string(),
+        ];
+        let fixture = MockFixture::test_pages()
+            .page("https://links.test/", links)
+            .page("https://secure.test/", MockPage::new("Secure"))
+            .page(
+                "https://down.test/",
+                MockPage::new("Down").event(MockEvent::LoadError {
+                    kind: LoadErrorKind::SecureConnectionFailed,
+                }),
+            );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let policy = Arc::new(HttpsOnlyPolicy::new(true, None));
+        engine.set_navigation_interceptor(Some(policy.clone())).unwrap();
+        let mut https_only = HttpsOnly::new(policy);
+
+        https_only
+            .load_url(&mut engine, view, "https://links.test/")
+            .unwrap();
+        engine.follow_link(view, 0).unwrap();
+        assert_eq!(
+            engine.get_navigation_state(view).unwrap().url,
+            "https://secure.test/"
+        );
+
+        engine.go_back(view).unwrap();
+        engine.follow_link(view, 1).unwrap();
+        for event in engine.poll_events() {
+            https_only.handle_event(&event);
+        }
+        // The failed upgrade still offers the page over HTTP
+        assert_eq!(
+            https_only.take_fallback(view).as_deref(),
+            Some("http://down.test/")
+        );
+    }
+
+    #[test]
+    fn test_exemptions_persist() {
+        let dir = std::env::temp_dir().join(format!("asteroid-https-{}", std::process::id()));
+        let path = dir.join("https_exemptions.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let policy = HttpsOnlyPolicy::new(true, Some(path.clone()));
+        policy.exempt("legacy.test");
+        policy.exempt("old.test");
+        policy.remove_exemption("old.test");
+
+        let policy = HttpsOnlyPolicy::new(true, Some(path));
+        assert_eq!(policy.exemptions(), vec!["legacy.test"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,246 @@
+//! asteroid:// internal page router for Asteroid Browser.
+//!
+//! Engines never load `asteroid://` URLs themselves: navigating to one
+//! emits `EngineEvent::InternalPageRequested`, and the browser answers
+//! with the HTML produced by the handler registered for the page, shown
+//! through `BrowserEngine::load_html`.
+//!
+//! Documents loaded that way are privileged. They get a
+//! `window.asteroid.postMessage(message)` bridge whose messages arrive as
+//! `EngineEvent::InternalMessage` and are handed to the message handler of
+//! the page the view shows. Engines only expose the bridge to documents
+//! the browser loaded under an `asteroid://` URL, and the router checks
+//! the sender again, so regular web content cannot reach it. For the same
+//! reason command links below a page (`asteroid://downloads/pause/3`) are
+//! only followed from that page itself.
+
+use crate::core::engine::{EngineError, EngineResult, ViewId};
+use std::collections::BTreeMap;
+
+/// Scheme prefix of internal pages.
+pub const INTERNAL_SCHEME: &str = "asteroid://";
+
+/// Whether a URL points at an internal page.
+pub fn is_internal_url(url: &str) -> bool {
+    url.get(..INTERNAL_SCHEME.len())
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case(INTERNAL_SCHEME))
+}
+
+/// Page name of an internal URL (`asteroid://downloads/pause/3` is
+/// "downloads").
+pub fn page_name(url: &str) -> Option<&str> {
+    if !is_internal_url(url) {
+        return None;
+    }
+    let rest = &url[INTERNAL_SCHEME.len()..];
+    let name = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// A navigation to an internal page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalRequest<'a> {
+    pub view_id: ViewId,
+    /// Full requested URL
+    pub url: &'a str,
+    /// Page name, e.g. "downloads"
+    pub page: &'a str,
+    /// Command path below the page, e.g. "pause/3" (empty for the page)
+    pub path: &'a str,
+}
+
+/// What a page handler produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InternalResponse {
+    /// Show `html` in the view under `url`
+    Page { url: String, html: String },
+    /// The request was carried out and the view was left alone, or
+    /// navigated elsewhere by the handler
+    Handled,
+}
+
+type PageHandler<C> = Box<dyn Fn(&C, &InternalRequest) -> EngineResult<InternalResponse>>;
+type MessageHandler<C> = Box<dyn Fn(&C, ViewId, &serde_json::Value) -> EngineResult<()>>;
+
+/// Handlers for internal pages, called with a browser context `C`.
+pub struct InternalPages<C> {
+    pages: BTreeMap<&'static str, PageHandler<C>>,
+    messages: BTreeMap<&'static str, MessageHandler<C>>,
+}
+
+impl<C> Default for InternalPages<C> {
+    fn default() -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            messages: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C> InternalPages<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `asteroid://<page>` and the command links below it.
+    pub fn register(
+        &mut self,
+        page: &'static str,
+        handler: impl Fn(&C, &InternalRequest) -> EngineResult<InternalResponse> + 'static,
+    ) {
+        self.pages.insert(page, Box::new(handler));
+    }
+
+    /// Receive the messages `asteroid://<page>` posts through the bridge.
+    pub fn on_message(
+        &mut self,
+        page: &'static str,
+        handler: impl Fn(&C, ViewId, &serde_json::Value) -> EngineResult<()> + 'static,
+    ) {
+        self.messages.insert(page, Box::new(handler));
+    }
+
+    /// Registered page names, sorted.
+    pub fn pages(&self) -> Vec<&'static str> {
+        self.pages.keys().copied().collect()
+    }
+
+    /// Answer a navigation to `url` in a view currently showing
+    /// `current_url`.
+    pub fn route(
+        &self,
+        ctx: &C,
+        view_id: ViewId,
+        url: &str,
+        current_url: &str,
+    ) -> EngineResult<InternalResponse> {
+        let page = page_name(url)
+            .ok_or_else(|| EngineError::NavigationError(format!("Not an internal page: {}", url)))?;
+        let handler = self
+            .pages
+            .get(page)
+            .ok_or_else(|| EngineError::NavigationError(format!("Unknown page: {}", url)))?;
+
+        let rest = &url[INTERNAL_SCHEME.len() + page.len()..];
+        let path = rest
+            .strip_prefix('/')
+            .map(|p| p.split(['?', '#']).next().unwrap_or(p))
+            .unwrap_or("");
+        if !path.is_empty() && page_name(current_url) != Some(page) {
+            log::warn!("Refused {} requested from {}", url, current_url);
+            return Err(EngineError::NavigationError(format!(
+                "{} may only be opened from its own page",
+                url
+            )));
+        }
+
+        handler(
+            ctx,
+            &InternalRequest {
+                view_id,
+                url,
+                page,
+                path,
+            },
+        )
+    }
+
+    /// Hand a bridge message to the page the sending view shows.
+    pub fn dispatch_message(
+        &self,
+        ctx: &C,
+        view_id: ViewId,
+        current_url: &str,
+        message: &serde_json::Value,
+    ) -> EngineResult<()> {
+        let handler = page_name(current_url).and_then(|page| self.messages.get(page));
+        match handler {
+            Some(handler) => handler(ctx, view_id, message),
+            None => {
+                log::warn!("Dropped internal message from {}", current_url);
+                Err(EngineError::Other(format!(
+                    "{} cannot post internal messages",
+                    current_url
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn pages() -> InternalPages<RefCell<Vec<String>>> {
+        let mut pages = InternalPages::new();
+        pages.register("downloads", |log: &RefCell<Vec<String>>, request| {
+            log.borrow_mut().push(format!("page {}", request.path));
+            Ok(InternalResponse::Page {
+                url: "asteroid://downloads".to_string(),
+                html: "<h1>Downloads</h1>".to_string(),
+            })
+        });
+        pages.on_message("downloads", |log: &RefCell<Vec<String>>, _, message| {
+            log.borrow_mut().push(format!("message {}", message["type"]));
+            Ok(())
+        });
+        pages
+    }
+
+    #[test]
+    fn test_page_name() {
+        assert!(is_internal_url("Asteroid://settings"));
+        assert_eq!(page_name("asteroid://downloads/pause/3"), Some("downloads"));
+        assert_eq!(page_name("asteroid://source?url=x"), Some("source"));
+        assert_eq!(page_name("asteroid://"), None);
+        assert_eq!(page_name("https://downloads/"), None);
+    }
+
+    #[test]
+    fn test_route() {
+        let pages = pages();
+        let log = RefCell::new(Vec::new());
+        let view = ViewId(1);
+        assert_eq!(pages.pages(), vec!["downloads"]);
+
+        let response = pages
+            .route(&log, view, "asteroid://downloads", "https://example.com/")
+            .unwrap();
+        assert!(matches!(response, InternalResponse::Page { .. }));
+        assert!(pages
+            .route(&log, view, "asteroid://nothing", "about:blank")
+            .is_err());
+
+        // Command links only work from their own page
+        assert!(pages
+            .route(&log, view, "asteroid://downloads/clear", "https://evil.test/")
+            .is_err());
+        pages
+            .route(&log, view, "asteroid://downloads/clear?x#y", "asteroid://downloads")
+            .unwrap();
+        assert_eq!(*log.borrow(), vec!["page ", "page clear"]);
+    }
+
+    #[test]
+    fn test_messages_only_from_internal_pages() {
+        let pages = pages();
+        let log = RefCell::new(Vec::new());
+        let message = serde_json::json!({ "type": "clear" });
+
+        pages
+            .dispatch_message(&log, ViewId(1), "asteroid://downloads", &message)
+            .unwrap();
+        assert!(pages
+            .dispatch_message(&log, ViewId(1), "https://evil.test/", &message)
+            .is_err());
+        assert!(pages
+            .dispatch_message(&log, ViewId(1), "asteroid://about", &message)
+            .is_err());
+        assert_eq!(*log.borrow(), vec![r#"message "clear""#]);
+    }
+}
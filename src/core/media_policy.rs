@@ -0,0 +1,261 @@
+//! Autoplay and media preload policies for Asteroid Browser.
+//!
+//! Videos that start by themselves and media that is downloaded before it
+//! is played cost CPU, memory and bandwidth that low-end machines lack.
+//! The `[media]` section sets how much of either pages get, and its
+//! `[[media.site_rules]]` change that for particular sites. The global
+//! policy reaches the engine with its settings; views on a site with
+//! other rules get theirs through `BrowserEngine::set_media_policy`.
+
+use crate::core::config::MediaConfig;
+use crate::core::engine::{BrowserEngine, EngineResult, ViewId};
+use crate::core::site_data::{host_in_site, host_of};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Which media may start playing without a click or key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoplayPolicy {
+    Allow,
+    /// Muted media may start; media with sound waits for the user
+    BlockAudible,
+    #[default]
+    BlockAll,
+}
+
+impl AutoplayPolicy {
+    pub const ALL: [AutoplayPolicy; 3] = [Self::Allow, Self::BlockAudible, Self::BlockAll];
+
+    /// Name used in the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::BlockAudible => "block_audible",
+            Self::BlockAll => "block_all",
+        }
+    }
+
+    /// Name shown in settings.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Allow => "Allow",
+            Self::BlockAudible => "Block audible",
+            Self::BlockAll => "Block all",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name() == name)
+    }
+
+    /// Whether media may start by itself.
+    pub fn allows(&self, audible: bool) -> bool {
+        match self {
+            Self::Allow => true,
+            Self::BlockAudible => !audible,
+            Self::BlockAll => false,
+        }
+    }
+}
+
+/// How much of a media file is fetched before it is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreloadPolicy {
+    /// Nothing until playback starts
+    None,
+    /// Only the size and duration
+    #[default]
+    Metadata,
+    /// As much as the page asks for
+    Auto,
+}
+
+impl PreloadPolicy {
+    pub const ALL: [PreloadPolicy; 3] = [Self::None, Self::Metadata, Self::Auto];
+
+    /// Name used in the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Metadata => "metadata",
+            Self::Auto => "auto",
+        }
+    }
+
+    /// Name shown in settings.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Metadata => "Metadata only",
+            Self::Auto => "Automatic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|policy| policy.name() == name)
+    }
+}
+
+/// Autoplay and preload policy of a view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MediaPolicy {
+    pub autoplay: AutoplayPolicy,
+    pub preload: PreloadPolicy,
+}
+
+/// Media policy for one site, from `[[media.site_rules]]`. Parts left out
+/// follow the global policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaSiteRule {
+    /// Host the rule applies to, along with its subdomains
+    pub site: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autoplay: Option<AutoplayPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preload: Option<PreloadPolicy>,
+}
+
+/// Site whose rule a URL is edited under: its host, lowercase.
+pub fn media_site(url: &str) -> String {
+    host_of(url).to_ascii_lowercase()
+}
+
+/// Media policy of each view, owned by the engine thread.
+#[derive(Debug, Default)]
+pub struct MediaPolicies {
+    /// `[media]` policy, which the engine has from its settings
+    global: MediaPolicy,
+    rules: Vec<MediaSiteRule>,
+    /// Policy last handed to the engine for each view
+    applied: HashMap<ViewId, MediaPolicy>,
+}
+
+impl MediaPolicies {
+    pub fn new(config: &MediaConfig) -> Self {
+        let mut policies = Self::default();
+        policies.configure(config);
+        policies
+    }
+
+    /// Replace the global policy and the site rules. Views pick the change
+    /// up the next time they are applied.
+    pub fn configure(&mut self, config: &MediaConfig) {
+        self.global = config.policy();
+        self.rules = config
+            .site_rules
+            .iter()
+            .map(|rule| MediaSiteRule {
+                site: rule.site.trim().trim_start_matches('.').to_ascii_lowercase(),
+                ..rule.clone()
+            })
+            .filter(|rule| !rule.site.is_empty())
+            .collect();
+    }
+
+    /// Policy for a page at `url`; the most specific site rule wins.
+    pub fn policy_for(&self, url: &str) -> MediaPolicy {
+        let host = host_of(url);
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| host_in_site(host, &rule.site))
+            .max_by_key(|rule| rule.site.len());
+        MediaPolicy {
+            autoplay: rule.and_then(|r| r.autoplay).unwrap_or(self.global.autoplay),
+            preload: rule.and_then(|r| r.preload).unwrap_or(self.global.preload),
+        }
+    }
+
+    /// Hand the policy for `url` to the engine, unless the view already
+    /// has it. Views without a call follow the global policy. Returns
+    /// whether the policy changed.
+    pub fn apply(
+        &mut self,
+        engine: &mut dyn BrowserEngine,
+        view_id: ViewId,
+        url: &str,
+    ) -> EngineResult<bool> {
+        let policy = self.policy_for(url);
+        let current = self.applied.get(&view_id).copied().unwrap_or(self.global);
+        if policy == current {
+            return Ok(false);
+        }
+        engine.set_media_policy(view_id, policy)?;
+        self.applied.insert(view_id, policy);
+        Ok(true)
+    }
+
+    /// Forget a closed view.
+    pub fn forget(&mut self, view_id: ViewId) {
+        self.applied.remove(&view_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::mock::MockEngine;
+
+    fn config() -> MediaConfig {
+        MediaConfig {
+            autoplay: AutoplayPolicy::BlockAll,
+            preload: PreloadPolicy::Metadata,
+            site_rules: vec![
+                MediaSiteRule {
+                    site: "video.test".to_string(),
+                    autoplay: Some(AutoplayPolicy::Allow),
+                    preload: Some(PreloadPolicy::Auto),
+                },
+                MediaSiteRule {
+                    site: "news.video.test".to_string(),
+                    autoplay: None,
+                    preload: Some(PreloadPolicy::None),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_site_rules_override_global_policy() {
+        let policies = MediaPolicies::new(&config());
+        assert_eq!(policies.policy_for("https://example.com/"), MediaPolicy::default());
+        assert_eq!(
+            policies.policy_for("https://www.Video.test/watch"),
+            MediaPolicy {
+                autoplay: AutoplayPolicy::Allow,
+                preload: PreloadPolicy::Auto,
+            }
+        );
+        // Parts a rule leaves out come from the global policy
+        assert_eq!(
+            policies.policy_for("https://news.video.test/"),
+            MediaPolicy {
+                autoplay: AutoplayPolicy::BlockAll,
+                preload: PreloadPolicy::None,
+            }
+        );
+        assert!(AutoplayPolicy::BlockAudible.allows(false));
+        assert!(!AutoplayPolicy::BlockAudible.allows(true));
+    }
+
+    #[test]
+    fn test_apply_only_where_policy_differs() {
+        let mut engine = MockEngine::new();
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let handle = engine.handle();
+        let mut policies = MediaPolicies::new(&config());
+
+        assert!(!policies.apply(&mut engine, view, "https://example.com/").unwrap());
+        assert!(policies.apply(&mut engine, view, "https://video.test/a").unwrap());
+        assert!(!policies.apply(&mut engine, view, "https://video.test/b").unwrap());
+        assert!(policies.apply(&mut engine, view, "https://example.com/").unwrap());
+
+        let calls = handle.calls_to("set_media_policy");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].args, vec!["allow", "auto"]);
+        assert_eq!(calls[1].args, vec!["block_all", "metadata"]);
+    }
+}
@@ -197,6 +197,8 @@ impl Default for CacheConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::tab::{SuspensionConfig, TabState};
+    use crate::engines::mock::MockEngine;
 
     #[test]
     fn test_system_memory_info() {
@@ -225,4 +227,55 @@ mod tests {
         assert_eq!(config.disk_cache_max_bytes, 100 * 1024 * 1024);
         assert_eq!(config.memory_cache_max_bytes, 50 * 1024 * 1024);
     }
+
+    fn tabs_with_pages(engine: &mut MockEngine, count: usize) -> TabManager {
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        for _ in 0..count {
+            let id = manager.create_tab(engine).unwrap();
+            engine.load_url(id, "https://example.com/video.html").unwrap();
+        }
+        for event in engine.poll_events() {
+            manager.handle_engine_event(&event);
+        }
+        manager
+    }
+
+    #[test]
+    fn test_critical_pressure_suspends_and_trims() {
+        let mut engine = MockEngine::new();
+        let handle = engine.handle();
+        let mut manager = tabs_with_pages(&mut engine, 5);
+        let before = engine.get_memory_usage().total_bytes;
+
+        handle_memory_pressure(MemoryPressure::Critical, &mut manager, &mut engine);
+
+        assert_eq!(manager.suspended_count(), 4);
+        assert_eq!(manager.active_tab().unwrap().state, TabState::Active);
+        assert_eq!(handle.calls_to("trim_memory")[0].args, vec!["Aggressive".to_string()]);
+        assert!(engine.get_memory_usage().total_bytes < before);
+    }
+
+    #[test]
+    fn test_low_pressure_suspends_three_oldest() {
+        let mut engine = MockEngine::new();
+        let handle = engine.handle();
+        let mut manager = tabs_with_pages(&mut engine, 6);
+
+        handle_memory_pressure(MemoryPressure::Low, &mut manager, &mut engine);
+
+        assert_eq!(manager.suspended_count(), 3);
+        assert_eq!(handle.calls_to("trim_memory")[0].args, vec!["Moderate".to_string()]);
+    }
+
+    #[test]
+    fn test_normal_pressure_only_checks_timeouts() {
+        let mut engine = MockEngine::new();
+        let handle = engine.handle();
+        let mut manager = tabs_with_pages(&mut engine, 3);
+
+        handle_memory_pressure(MemoryPressure::Normal, &mut manager, &mut engine);
+
+        assert_eq!(manager.suspended_count(), 0);
+        assert!(handle.calls_to("trim_memory").is_empty());
+    }
 }
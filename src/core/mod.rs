@@ -1,6 +1,26 @@
 pub mod engine;
+pub mod engine_host;
+pub mod engine_set;
+pub mod capture;
+pub mod certificates;
+pub mod crash;
+pub mod downloads;
+pub mod find;
+pub mod history;
+pub mod https_only;
+pub mod media_policy;
+pub mod internal_pages;
+pub mod permissions;
+pub mod popups;
+pub mod print;
+pub mod proxy;
+pub mod site_data;
 pub mod tab;
 pub mod memory;
 pub mod updater;
+pub mod user_agent;
+pub mod user_scripts;
+pub mod watchdog;
+pub mod zoom;
 pub mod blocker;
 pub mod config;
@@ -0,0 +1,204 @@
+//! Site permission store for Asteroid Browser.
+//!
+//! Remembers allow/deny decisions per origin for the capabilities pages
+//! request through `EngineEvent::PermissionRequested`. Anything not stored
+//! falls back to asking the user. Decisions are persisted to
+//! permissions.json next to the config file.
+
+use crate::core::engine::PermissionKind;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What to do when an origin requests a permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+    Ask,
+}
+
+impl PermissionDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Ask => "ask",
+        }
+    }
+}
+
+/// Per-origin permission decisions.
+#[derive(Debug, Default)]
+pub struct PermissionStore {
+    /// Where decisions are saved, if anywhere
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, BTreeMap<PermissionKind, PermissionDecision>>,
+}
+
+impl PermissionStore {
+    /// Create a store, loading previous decisions from `path` if given.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let entries = path.as_deref().and_then(load_entries).unwrap_or_default();
+        Self { path, entries }
+    }
+
+    /// Decision for an origin, `Ask` if none was stored.
+    pub fn decision(&self, origin: &str, kind: PermissionKind) -> PermissionDecision {
+        self.entries
+            .get(origin)
+            .and_then(|perms| perms.get(&kind))
+            .copied()
+            .unwrap_or(PermissionDecision::Ask)
+    }
+
+    /// Store a decision. Setting `Ask` forgets the stored one.
+    pub fn set(&mut self, origin: &str, kind: PermissionKind, decision: PermissionDecision) {
+        if decision == PermissionDecision::Ask {
+            if let Some(perms) = self.entries.get_mut(origin) {
+                perms.remove(&kind);
+                if perms.is_empty() {
+                    self.entries.remove(origin);
+                }
+            }
+        } else {
+            self.entries
+                .entry(origin.to_string())
+                .or_default()
+                .insert(kind, decision);
+        }
+        self.save();
+    }
+
+    /// Forget every decision for an origin.
+    pub fn clear_origin(&mut self, origin: &str) {
+        if self.entries.remove(origin).is_some() {
+            self.save();
+        }
+    }
+
+    /// All stored decisions, ordered by origin.
+    pub fn entries(&self) -> Vec<(&str, PermissionKind, PermissionDecision)> {
+        self.entries
+            .iter()
+            .flat_map(|(origin, perms)| {
+                perms
+                    .iter()
+                    .map(move |(kind, decision)| (origin.as_str(), *kind, *decision))
+            })
+            .collect()
+    }
+
+    fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(&self.entries)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("Failed to save permissions to {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn load_entries(
+    path: &Path,
+) -> Option<BTreeMap<String, BTreeMap<PermissionKind, PermissionDecision>>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+            log::error!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Origin (`scheme://host[:port]`) of a URL, if it has one.
+pub fn origin_of(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    // Drop any user:password@ prefix
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    if scheme.is_empty() || host.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}://{}",
+        scheme.to_ascii_lowercase(),
+        host.to_ascii_lowercase()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_of() {
+        assert_eq!(
+            origin_of("https://Example.com:8443/path?q=1").as_deref(),
+            Some("https://example.com:8443")
+        );
+        assert_eq!(
+            origin_of("http://user:pw@host.test#x").as_deref(),
+            Some("http://host.test")
+        );
+        assert_eq!(origin_of("about:blank"), None);
+    }
+
+    #[test]
+    fn test_decisions() {
+        let mut store = PermissionStore::new(None);
+        let origin = "https://maps.test";
+        assert_eq!(
+            store.decision(origin, PermissionKind::Geolocation),
+            PermissionDecision::Ask
+        );
+
+        store.set(
+            origin,
+            PermissionKind::Geolocation,
+            PermissionDecision::Allow,
+        );
+        store.set(origin, PermissionKind::Camera, PermissionDecision::Deny);
+        assert_eq!(
+            store.decision(origin, PermissionKind::Geolocation),
+            PermissionDecision::Allow
+        );
+        assert_eq!(store.entries().len(), 2);
+
+        store.set(origin, PermissionKind::Camera, PermissionDecision::Ask);
+        assert_eq!(store.entries().len(), 1);
+        store.clear_origin(origin);
+        assert!(store.entries().is_empty());
+    }
+
+    #[test]
+    fn test_permissions_persist() {
+        let dir = std::env::temp_dir().join(format!("asteroid-permissions-{}", std::process::id()));
+        let path = dir.join("permissions.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut store = PermissionStore::new(Some(path.clone()));
+        store.set(
+            "https://chat.test",
+            PermissionKind::Notifications,
+            PermissionDecision::Deny,
+        );
+
+        let store = PermissionStore::new(Some(path));
+        assert_eq!(
+            store.decision("https://chat.test", PermissionKind::Notifications),
+            PermissionDecision::Deny
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
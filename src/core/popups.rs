@@ -0,0 +1,165 @@
+//! Popup blocker for Asteroid Browser.
+//!
+//! Decides whether a `NewViewRequested` event may open a tab. Requests
+//! made without a user gesture are blocked while `privacy.block_popups`
+//! is on, unless the opener's origin has a stored `PermissionKind::Popups`
+//! decision. Blocked requests are kept per opener so the notice can offer
+//! to open them.
+
+use crate::core::engine::{NewViewRequest, PermissionKind, ViewId};
+use crate::core::permissions::{origin_of, PermissionDecision, PermissionStore};
+use std::collections::HashMap;
+
+/// Popups blocked on one page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedPopups {
+    /// Origin of the page that tried to open them
+    pub origin: String,
+    pub requests: Vec<NewViewRequest>,
+}
+
+/// Popup policy and the popups blocked so far.
+#[derive(Debug, Default)]
+pub struct PopupBlocker {
+    /// Block requests without a user gesture by default
+    enabled: bool,
+    blocked: HashMap<ViewId, BlockedPopups>,
+}
+
+impl PopupBlocker {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            blocked: HashMap::new(),
+        }
+    }
+
+    /// Block popups opened without a user gesture.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Whether a request from the page at `opener_url` may open. Blocked
+    /// requests are remembered for `take_blocked`.
+    pub fn allow(
+        &mut self,
+        store: &PermissionStore,
+        opener: ViewId,
+        opener_url: &str,
+        request: &NewViewRequest,
+    ) -> bool {
+        let origin = origin_of(opener_url);
+        let decision = origin.as_deref().map_or(PermissionDecision::Ask, |o| {
+            store.decision(o, PermissionKind::Popups)
+        });
+        let allowed = match decision {
+            PermissionDecision::Allow => true,
+            PermissionDecision::Deny => false,
+            PermissionDecision::Ask => !self.enabled || request.user_gesture,
+        };
+
+        if !allowed {
+            log::info!("Blocked popup from {} to {}", opener_url, request.url);
+            self.blocked
+                .entry(opener)
+                .or_insert_with(|| BlockedPopups {
+                    origin: origin.unwrap_or_default(),
+                    requests: Vec::new(),
+                })
+                .requests
+                .push(request.clone());
+        }
+        allowed
+    }
+
+    /// Number of popups blocked on the opener's current page.
+    pub fn blocked_count(&self, opener: ViewId) -> usize {
+        self.blocked.get(&opener).map_or(0, |b| b.requests.len())
+    }
+
+    /// Remove and return the popups blocked for an opener.
+    pub fn take_blocked(&mut self, opener: ViewId) -> Option<BlockedPopups> {
+        self.blocked.remove(&opener)
+    }
+
+    /// Forget popups blocked for an opener that navigated away or closed.
+    pub fn clear(&mut self, opener: ViewId) {
+        self.blocked.remove(&opener);
+    }
+}
+
+/// Text of the blocked popups notice.
+pub fn blocked_notice(count: usize) -> String {
+    if count == 1 {
+        "1 pop-up blocked".to_string()
+    } else {
+        format!("{} pop-ups blocked", count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::WindowDisposition;
+
+    fn popup(url: &str, user_gesture: bool) -> NewViewRequest {
+        NewViewRequest {
+            url: url.to_string(),
+            user_gesture,
+            disposition: WindowDisposition::Popup,
+        }
+    }
+
+    #[test]
+    fn test_blocks_without_gesture() {
+        let store = PermissionStore::new(None);
+        let mut blocker = PopupBlocker::new(true);
+        let page = "https://news.test/article";
+
+        assert!(blocker.allow(&store, ViewId(1), page, &popup("https://ads.test/1", true)));
+        assert!(!blocker.allow(&store, ViewId(1), page, &popup("https://ads.test/2", false)));
+        assert!(!blocker.allow(&store, ViewId(1), page, &popup("https://ads.test/3", false)));
+        assert_eq!(blocker.blocked_count(ViewId(1)), 2);
+        assert_eq!(blocked_notice(2), "2 pop-ups blocked");
+
+        let blocked = blocker.take_blocked(ViewId(1)).unwrap();
+        assert_eq!(blocked.origin, "https://news.test");
+        assert_eq!(blocked.requests[0].url, "https://ads.test/2");
+        assert_eq!(blocker.blocked_count(ViewId(1)), 0);
+
+        // Disabled blocker lets everything through
+        let mut blocker = PopupBlocker::new(false);
+        assert!(blocker.allow(&store, ViewId(1), page, &popup("https://ads.test/4", false)));
+    }
+
+    #[test]
+    fn test_per_site_override() {
+        let mut store = PermissionStore::new(None);
+        store.set(
+            "https://mail.test",
+            PermissionKind::Popups,
+            PermissionDecision::Allow,
+        );
+        store.set(
+            "https://spam.test",
+            PermissionKind::Popups,
+            PermissionDecision::Deny,
+        );
+        let mut blocker = PopupBlocker::new(true);
+
+        assert!(blocker.allow(
+            &store,
+            ViewId(1),
+            "https://mail.test/inbox",
+            &popup("https://mail.test/compose", false)
+        ));
+        assert!(!blocker.allow(
+            &store,
+            ViewId(2),
+            "https://spam.test/",
+            &popup("https://spam.test/win", true)
+        ));
+        blocker.clear(ViewId(2));
+        assert_eq!(blocker.blocked_count(ViewId(2)), 0);
+    }
+}
@@ -0,0 +1,191 @@
+//! Printing and PDF export for Asteroid Browser.
+//!
+//! `save_pdf` exports a view through `BrowserEngine::print_to_pdf` into
+//! the download directory, so saving as PDF needs no print server. Also
+//! provides the minimal PDF writer used by engines without a native
+//! print backend.
+
+use crate::core::downloads::{sanitize_filename, unique_path};
+use crate::core::engine::{BrowserEngine, EngineError, EngineResult, PdfOptions, ViewId};
+use std::path::{Path, PathBuf};
+
+/// Points per millimetre (PDF user space is 1/72 inch).
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// Export a view as PDF into `dir`, named after the page title.
+/// Returns the path written.
+pub fn save_pdf(
+    engine: &mut dyn BrowserEngine,
+    view_id: ViewId,
+    options: &PdfOptions,
+    dir: &Path,
+    title: &str,
+) -> EngineResult<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| EngineError::Other(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+    let path = unique_path(dir, &pdf_file_name(title), &[]);
+    engine.print_to_pdf(view_id, options, &path)?;
+    log::info!("Saved {} as PDF to {}", view_id, path.display());
+    Ok(path)
+}
+
+/// File name for a PDF of a page with the given title.
+pub fn pdf_file_name(title: &str) -> String {
+    let name = sanitize_filename(title);
+    if name.is_empty() {
+        "page.pdf".to_string()
+    } else {
+        format!("{}.pdf", name)
+    }
+}
+
+/// Escape text for a PDF string literal.
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            // Standard fonts only cover Latin-1
+            c if c.is_ascii() && !c.is_ascii_control() => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// Build a PDF of `pages` empty pages laid out per `options`, with the
+/// header (title) and page-number footer when enabled.
+pub fn blank_pdf(options: &PdfOptions, title: &str, pages: usize) -> Vec<u8> {
+    let pages = pages.max(1);
+    let (width_mm, height_mm) = options.paper_size_mm();
+    let (width, height) = (width_mm * POINTS_PER_MM, height_mm * POINTS_PER_MM);
+    let left = options.margins.left * POINTS_PER_MM;
+    let top = height - options.margins.top * POINTS_PER_MM;
+    let bottom = options.margins.bottom * POINTS_PER_MM;
+
+    // Objects: 1 catalog, 2 page tree, 3 font, then a page and its
+    // content stream per page
+    let mut objects: Vec<String> = Vec::new();
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 4 + i * 2)).collect();
+    objects.push(format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages
+    ));
+    objects.push("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string());
+
+    for page in 0..pages {
+        let mut content = String::new();
+        if options.header_footer {
+            content.push_str(&format!(
+                "BT /F1 9 Tf {:.2} {:.2} Td ({}) Tj ET\n",
+                left,
+                top - 9.0,
+                pdf_string(title)
+            ));
+            content.push_str(&format!(
+                "BT /F1 9 Tf {:.2} {:.2} Td (Page {} of {}) Tj ET\n",
+                left,
+                bottom,
+                page + 1,
+                pages
+            ));
+        }
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            width,
+            height,
+            5 + page * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::PageSize;
+
+    #[test]
+    fn test_blank_pdf_layout() {
+        let options = PdfOptions {
+            page_size: PageSize::Letter,
+            landscape: true,
+            ..Default::default()
+        };
+        let pdf = String::from_utf8(blank_pdf(&options, "Report (draft)", 2)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        // Letter, landscape: 11 x 8.5 inches
+        assert!(pdf.contains("/MediaBox [0 0 792.00 612.00]"));
+        assert!(pdf.contains("(Report \\(draft\\)) Tj"));
+        assert!(pdf.contains("(Page 2 of 2) Tj"));
+
+        // xref offsets point at the objects
+        let xref = pdf.rfind("\nxref\n").unwrap() + 1;
+        let first = pdf[xref..].lines().nth(3).unwrap();
+        let offset: usize = first[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with("1 0 obj"));
+
+        let plain = PdfOptions {
+            header_footer: false,
+            ..Default::default()
+        };
+        assert!(!String::from_utf8(blank_pdf(&plain, "x", 1))
+            .unwrap()
+            .contains("Tj"));
+    }
+
+    #[test]
+    fn test_save_pdf_to_dir() {
+        use crate::engines::mock::MockEngine;
+
+        let dir = std::env::temp_dir().join(format!("asteroid-print-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut engine = MockEngine::new();
+        engine.create_view(ViewId(1)).unwrap();
+        engine.load_url(ViewId(1), "https://example.com/").unwrap();
+
+        let options = PdfOptions::default();
+        let first = save_pdf(&mut engine, ViewId(1), &options, &dir, "Example/Domain").unwrap();
+        assert_eq!(first, dir.join("Example_Domain.pdf"));
+        assert!(std::fs::read(&first).unwrap().starts_with(b"%PDF"));
+
+        let second = save_pdf(&mut engine, ViewId(1), &options, &dir, "Example/Domain").unwrap();
+        assert_eq!(second, dir.join("Example_Domain (1).pdf"));
+        assert!(save_pdf(&mut engine, ViewId(9), &options, &dir, "x").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,195 @@
+//! Proxy settings for Asteroid Browser.
+//!
+//! The `[network]` section picks how connections leave the browser:
+//! directly, through the system proxy, through the proxies it lists, or
+//! as a PAC script decides. The engine gets the settings as preferences;
+//! the browser's own requests (update checks, downloads, page source
+//! fetches) use `client_builder`, so both go through the same proxy.
+
+use crate::core::config::NetworkConfig;
+use serde::{Deserialize, Serialize};
+
+/// How connections find their proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    /// Connect directly
+    #[default]
+    Direct,
+    /// Use the desktop's proxy settings (and `*_proxy` variables)
+    System,
+    /// Use `http_proxy`, `https_proxy` and `socks_proxy`
+    Manual,
+    /// Let the script at `pac_url` choose
+    Pac,
+}
+
+/// A proxy host and port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyServer {
+    /// Host name or address, without brackets
+    pub host: String,
+    pub port: u16,
+}
+
+impl ProxyServer {
+    /// Parse `host:port`, optionally with a scheme such as `http://`.
+    /// `None` for an empty value.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        let address = value
+            .split_once("://")
+            .map_or(value, |(_, rest)| rest)
+            .trim_end_matches('/');
+        let (host, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Proxy {} needs a port", value))?;
+        let port = port
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| format!("Proxy {} has an invalid port", value))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() || host.contains(['/', '@', ' ']) {
+            return Err(format!("Proxy {} has an invalid host", value));
+        }
+        Ok(Some(Self {
+            host: host.to_string(),
+            port,
+        }))
+    }
+
+    /// URL of the proxy for `scheme`.
+    pub fn url(&self, scheme: &str) -> String {
+        if self.host.contains(':') {
+            format!("{}://[{}]:{}", scheme, self.host, self.port)
+        } else {
+            format!("{}://{}:{}", scheme, self.host, self.port)
+        }
+    }
+}
+
+/// The manual proxies of a `[network]` section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyServers {
+    pub http: Option<ProxyServer>,
+    /// Falls back to the HTTP proxy, as most corporate proxies do both
+    pub https: Option<ProxyServer>,
+    /// SOCKS5, for everything the others don't carry
+    pub socks: Option<ProxyServer>,
+}
+
+impl ProxyServers {
+    pub fn from_config(network: &NetworkConfig) -> Result<Self, String> {
+        let http = ProxyServer::parse(&network.http_proxy)?;
+        let https = match ProxyServer::parse(&network.https_proxy)? {
+            Some(server) => Some(server),
+            None => http.clone(),
+        };
+        Ok(Self {
+            http,
+            https,
+            socks: ProxyServer::parse(&network.socks_proxy)?,
+        })
+    }
+}
+
+/// Check a `[network]` section, as the engine would read it.
+pub fn validate(network: &NetworkConfig) -> Result<(), String> {
+    ProxyServers::from_config(network)?;
+    if network.proxy_mode == ProxyMode::Pac && network.pac_url.trim().is_empty() {
+        return Err("PAC mode needs a pac_url".to_string());
+    }
+    Ok(())
+}
+
+/// HTTP client builder whose requests take the configured proxy.
+///
+/// reqwest cannot run PAC scripts, so in PAC mode the manual proxies are
+/// used if any are set, and connections are direct otherwise.
+pub fn client_builder(network: &NetworkConfig) -> Result<reqwest::ClientBuilder, String> {
+    let builder = reqwest::Client::builder();
+    match network.proxy_mode {
+        // reqwest reads the `*_proxy` variables itself
+        ProxyMode::System => return Ok(builder),
+        ProxyMode::Direct => return Ok(builder.no_proxy()),
+        ProxyMode::Manual | ProxyMode::Pac => {}
+    }
+
+    let servers = ProxyServers::from_config(network)?;
+    let bypass = reqwest::NoProxy::from_string(&network.bypass.join(","));
+    let mut proxies = Vec::new();
+    if let Some(server) = &servers.http {
+        proxies.push(reqwest::Proxy::http(server.url("http")));
+    }
+    if let Some(server) = &servers.https {
+        proxies.push(reqwest::Proxy::https(server.url("http")));
+    }
+    if let Some(server) = &servers.socks {
+        // socks5h resolves names on the proxy. reqwest looks up the SOCKS
+        // proxy's own address here, so an unknown host fails the build.
+        let scheme = if network.proxy_dns { "socks5h" } else { "socks5" };
+        proxies.push(reqwest::Proxy::all(server.url(scheme)));
+    }
+
+    // Earlier proxies win, so SOCKS only carries what the others don't
+    let mut builder = builder.no_proxy();
+    for proxy in proxies {
+        let proxy = proxy.map_err(|e| e.to_string())?;
+        builder = builder.proxy(proxy.no_proxy(bypass.clone()));
+    }
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(host: &str, port: u16) -> Option<ProxyServer> {
+        Some(ProxyServer {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    #[test]
+    fn test_parse_proxy_server() {
+        assert_eq!(ProxyServer::parse(" "), Ok(None));
+        assert_eq!(ProxyServer::parse("proxy.corp:3128"), Ok(server("proxy.corp", 3128)));
+        assert_eq!(ProxyServer::parse("http://proxy.corp:8080/"), Ok(server("proxy.corp", 8080)));
+        assert_eq!(ProxyServer::parse("socks5://[::1]:1080"), Ok(server("::1", 1080)));
+        assert_eq!(server("::1", 1080).unwrap().url("socks5h"), "socks5h://[::1]:1080");
+        assert!(ProxyServer::parse("proxy.corp").is_err());
+        assert!(ProxyServer::parse("proxy.corp:0").is_err());
+        assert!(ProxyServer::parse("user@proxy.corp:3128").is_err());
+    }
+
+    #[test]
+    fn test_https_falls_back_to_http_proxy() {
+        let mut network = NetworkConfig {
+            proxy_mode: ProxyMode::Manual,
+            http_proxy: "proxy.corp:3128".to_string(),
+            socks_proxy: "10.0.0.2:1080".to_string(),
+            ..NetworkConfig::default()
+        };
+        let servers = ProxyServers::from_config(&network).unwrap();
+        assert_eq!(servers.https, server("proxy.corp", 3128));
+        assert_eq!(servers.socks, server("10.0.0.2", 1080));
+        assert!(client_builder(&network).unwrap().build().is_ok());
+
+        network.https_proxy = "tls.corp:443".to_string();
+        let servers = ProxyServers::from_config(&network).unwrap();
+        assert_eq!(servers.https, server("tls.corp", 443));
+
+        network.proxy_mode = ProxyMode::Pac;
+        assert!(validate(&network).is_err());
+        network.pac_url = "http://wpad.corp/proxy.pac".to_string();
+        assert!(validate(&network).is_ok());
+        network.http_proxy = "proxy.corp".to_string();
+        assert!(validate(&network).is_err());
+        assert!(client_builder(&network).is_err());
+    }
+}
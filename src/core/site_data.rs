@@ -0,0 +1,326 @@
+//! Cookie and site storage management for Asteroid Browser.
+//!
+//! Holds the in-memory cookie jar and per-origin storage accounting used
+//! by in-process engines, and the policy applied at shutdown when
+//! `PrivacyConfig::clear_cookies_on_close` is set.
+
+use crate::core::engine::{BrowserEngine, Cookie, EngineResult, SiteDataKind, SiteDataUsage};
+use std::collections::BTreeMap;
+
+/// Host part of an origin or URL (no scheme, port or path).
+pub fn host_of(origin: &str) -> &str {
+    let rest = origin.split_once("://").map(|(_, r)| r).unwrap_or(origin);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+/// Whether `host` is `site` or one of its subdomains. `site` must be
+/// lowercase.
+pub fn host_in_site(host: &str, site: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    host == site
+        || host
+            .strip_suffix(site)
+            .is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Cookie jar and storage usage, keyed by origin.
+#[derive(Debug, Clone, Default)]
+pub struct SiteDataJar {
+    cookies: Vec<Cookie>,
+    usage: BTreeMap<String, SiteDataUsage>,
+}
+
+impl SiteDataJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a cookie set by `origin`, replacing one with the same
+    /// domain, path and name.
+    pub fn set_cookie(&mut self, origin: &str, cookie: Cookie) {
+        self.entry(origin);
+        self.cookies.retain(|c| !same_cookie(c, &cookie));
+        self.cookies.push(cookie);
+    }
+
+    /// Cookies sent to `origin`, or all cookies.
+    pub fn cookies(&self, origin: Option<&str>) -> Vec<Cookie> {
+        self.cookies
+            .iter()
+            .filter(|c| origin.is_none_or(|o| c.matches_host(host_of(o))))
+            .cloned()
+            .collect()
+    }
+
+    /// Delete a cookie. Returns whether it existed.
+    pub fn delete_cookie(&mut self, cookie: &Cookie) -> bool {
+        let before = self.cookies.len();
+        self.cookies.retain(|c| !same_cookie(c, cookie));
+        self.prune();
+        self.cookies.len() != before
+    }
+
+    /// Add what an engine's own stores hold, including data kept from
+    /// earlier sessions. Cookies already in the jar are kept as they are;
+    /// for storage the larger figure wins, as the stores may not have
+    /// counted the latest writes yet.
+    pub fn merge_stored(&mut self, cookies: Vec<Cookie>, usage: Vec<SiteDataUsage>) {
+        for cookie in cookies {
+            if self.cookies.iter().any(|c| same_cookie(c, &cookie)) {
+                continue;
+            }
+            if !self.usage.keys().any(|o| cookie.matches_host(host_of(o))) {
+                let scheme = if cookie.secure { "https" } else { "http" };
+                self.entry(&format!("{}://{}", scheme, cookie.domain.trim_start_matches('.')));
+            }
+            self.cookies.push(cookie);
+        }
+        for stored in usage {
+            let usage = self.entry(&stored.origin);
+            usage.local_storage_bytes = usage.local_storage_bytes.max(stored.local_storage_bytes);
+            usage.indexed_db_bytes = usage.indexed_db_bytes.max(stored.indexed_db_bytes);
+            usage.cache_bytes = usage.cache_bytes.max(stored.cache_bytes);
+        }
+        self.prune();
+    }
+
+    /// Account storage written by an origin.
+    pub fn add_usage(&mut self, origin: &str, kind: SiteDataKind, bytes: u64) {
+        let usage = self.entry(origin);
+        match kind {
+            SiteDataKind::Cookies => {}
+            SiteDataKind::LocalStorage => usage.local_storage_bytes += bytes,
+            SiteDataKind::IndexedDb => usage.indexed_db_bytes += bytes,
+            SiteDataKind::Cache => usage.cache_bytes += bytes,
+        }
+    }
+
+    /// Clear data of the given kinds for one origin, or for every origin.
+    pub fn clear(&mut self, origin: Option<&str>, kinds: &[SiteDataKind]) {
+        if kinds.contains(&SiteDataKind::Cookies) {
+            match origin {
+                Some(origin) => {
+                    let host = host_of(origin);
+                    self.cookies.retain(|c| !c.matches_host(host));
+                }
+                None => self.cookies.clear(),
+            }
+        }
+
+        for (key, usage) in self.usage.iter_mut() {
+            if origin.is_some_and(|o| o != key) {
+                continue;
+            }
+            for kind in kinds {
+                match kind {
+                    SiteDataKind::Cookies => {}
+                    SiteDataKind::LocalStorage => usage.local_storage_bytes = 0,
+                    SiteDataKind::IndexedDb => usage.indexed_db_bytes = 0,
+                    SiteDataKind::Cache => usage.cache_bytes = 0,
+                }
+            }
+        }
+        self.prune();
+    }
+
+    /// Usage per origin, ordered by origin.
+    pub fn usage(&self) -> Vec<SiteDataUsage> {
+        self.usage
+            .values()
+            .map(|u| SiteDataUsage {
+                cookies: self
+                    .cookies
+                    .iter()
+                    .filter(|c| c.matches_host(host_of(&u.origin)))
+                    .count(),
+                ..u.clone()
+            })
+            .collect()
+    }
+
+    fn entry(&mut self, origin: &str) -> &mut SiteDataUsage {
+        self.usage
+            .entry(origin.to_string())
+            .or_insert_with(|| SiteDataUsage {
+                origin: origin.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Forget origins that no longer store anything.
+    fn prune(&mut self) {
+        let cookies = &self.cookies;
+        self.usage.retain(|origin, usage| {
+            usage.total_bytes() > 0 || cookies.iter().any(|c| c.matches_host(host_of(origin)))
+        });
+    }
+}
+
+fn same_cookie(a: &Cookie, b: &Cookie) -> bool {
+    a.name == b.name && a.path == b.path && a.domain.eq_ignore_ascii_case(&b.domain)
+}
+
+/// Whether a cookie belongs to a site on the allowlist.
+///
+/// Allowlist entries are origins or bare hosts; a site keeps its own
+/// cookies, its subdomains' cookies and the parent-domain cookies it is
+/// sent.
+pub fn is_allowlisted(cookie: &Cookie, allowlist: &[String]) -> bool {
+    let domain = cookie.domain.trim_start_matches('.').to_ascii_lowercase();
+    allowlist.iter().any(|entry| {
+        let host = host_of(entry).to_ascii_lowercase();
+        cookie.matches_host(&host) || domain.ends_with(&format!(".{}", host))
+    })
+}
+
+/// Delete every cookie not covered by `allowlist`. Returns how many were deleted.
+pub fn clear_cookies_on_close(
+    engine: &mut dyn BrowserEngine,
+    allowlist: &[String],
+) -> EngineResult<usize> {
+    if allowlist.is_empty() {
+        let count = engine.list_cookies(None)?.len();
+        engine.clear_cookies()?;
+        return Ok(count);
+    }
+
+    let mut deleted = 0;
+    for cookie in engine.list_cookies(None)? {
+        if !is_allowlisted(&cookie, allowlist) {
+            engine.delete_cookie(&cookie)?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(domain: &str, name: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: "1".to_string(),
+            domain: domain.to_string(),
+            path: "/".to_string(),
+            secure: true,
+            http_only: false,
+            expires: None,
+        }
+    }
+
+    #[test]
+    fn test_host_of() {
+        assert_eq!(host_of("https://example.com:8443/x"), "example.com");
+        assert_eq!(host_of("https://example.com"), "example.com");
+        assert_eq!(host_of("mail.example.com"), "mail.example.com");
+    }
+
+    #[test]
+    fn test_jar_cookies_and_usage() {
+        let mut jar = SiteDataJar::new();
+        jar.set_cookie("https://example.com", cookie(".example.com", "sid"));
+        jar.set_cookie("https://example.com", cookie(".example.com", "sid"));
+        jar.set_cookie("https://news.test", cookie("news.test", "pref"));
+        jar.add_usage("https://example.com", SiteDataKind::LocalStorage, 2048);
+        jar.add_usage("https://example.com", SiteDataKind::Cache, 4096);
+
+        assert_eq!(jar.cookies(None).len(), 2);
+        assert_eq!(jar.cookies(Some("https://mail.example.com")).len(), 1);
+
+        let usage = jar.usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].origin, "https://example.com");
+        assert_eq!(usage[0].cookies, 1);
+        assert_eq!(usage[0].total_bytes(), 6144);
+
+        jar.clear(Some("https://example.com"), &[SiteDataKind::Cache]);
+        assert_eq!(jar.usage()[0].total_bytes(), 2048);
+
+        jar.clear(Some("https://news.test"), &SiteDataKind::ALL);
+        assert_eq!(jar.usage().len(), 1);
+        assert!(jar.delete_cookie(&cookie(".example.com", "sid")));
+        jar.clear(None, &SiteDataKind::ALL);
+        assert!(jar.usage().is_empty());
+    }
+
+    #[test]
+    fn test_merge_stored_data() {
+        let mut jar = SiteDataJar::new();
+        jar.set_cookie("https://example.com", cookie(".example.com", "sid"));
+        jar.add_usage("https://example.com", SiteDataKind::LocalStorage, 4096);
+
+        let mut old = cookie("old.test", "uid");
+        old.secure = false;
+        jar.merge_stored(
+            vec![cookie(".example.com", "sid"), old],
+            vec![
+                SiteDataUsage {
+                    origin: "https://example.com".to_string(),
+                    local_storage_bytes: 1024,
+                    indexed_db_bytes: 8192,
+                    ..Default::default()
+                },
+                SiteDataUsage {
+                    origin: "https://empty.test".to_string(),
+                    ..Default::default()
+                },
+            ],
+        );
+
+        assert_eq!(jar.cookies(None).len(), 2);
+        let usage = jar.usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].origin, "http://old.test");
+        assert_eq!(usage[0].cookies, 1);
+        assert_eq!(usage[1].local_storage_bytes, 4096);
+        assert_eq!(usage[1].indexed_db_bytes, 8192);
+    }
+
+    #[test]
+    fn test_allowlist_matching() {
+        let allowlist = vec!["https://example.com".to_string(), "bank.test".to_string()];
+        assert!(is_allowlisted(&cookie(".example.com", "a"), &allowlist));
+        assert!(is_allowlisted(&cookie("mail.example.com", "a"), &allowlist));
+        assert!(is_allowlisted(&cookie("bank.test", "a"), &allowlist));
+        assert!(!is_allowlisted(&cookie("tracker.test", "a"), &allowlist));
+        assert!(!is_allowlisted(&cookie("notexample.com", "a"), &allowlist));
+    }
+
+    #[test]
+    fn test_clear_cookies_on_close_keeps_allowlist() {
+        use crate::core::engine::ViewId;
+        use crate::engines::mock::{MockEngine, MockFixture, MockPage};
+
+        let fixture = MockFixture::new()
+            .page(
+                "https://example.com/",
+                MockPage::new("Example").cookie("sid", "abc"),
+            )
+            .page(
+                "https://tracker.test/",
+                MockPage::new("Tracker").cookie("uid", "xyz"),
+            );
+        let mut engine = MockEngine::with_fixture(fixture);
+        engine.create_view(ViewId(1)).unwrap();
+        engine.load_url(ViewId(1), "https://example.com/").unwrap();
+        engine.load_url(ViewId(1), "https://tracker.test/").unwrap();
+        assert_eq!(engine.list_cookies(None).unwrap().len(), 2);
+
+        let deleted =
+            clear_cookies_on_close(&mut engine, &["https://example.com".to_string()]).unwrap();
+        assert_eq!(deleted, 1);
+        let left = engine.list_cookies(None).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].name, "sid");
+
+        assert_eq!(clear_cookies_on_close(&mut engine, &[]).unwrap(), 1);
+        assert!(engine.list_cookies(None).unwrap().is_empty());
+    }
+}
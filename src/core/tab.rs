@@ -1,9 +1,14 @@
 //! Tab management system for Asteroid Browser.
 //!
 //! Handles tab lifecycle including creation, suspension after inactivity,
-//! restoration, and memory-pressure-driven unloading.
-
-use crate::core::engine::{BrowserEngine, EngineResult, ViewId};
+//! restoration, and memory-pressure-driven unloading. Tabs playing audio
+//! are never suspended.
+
+use crate::core::engine::{
+    BrowserEngine, CaptureOptions, EngineEvent, EngineResult, MediaState, NewViewRequest,
+    ViewId, WindowDisposition,
+};
+use crate::core::engine_set::EngineSet;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -21,6 +26,9 @@ pub struct SuspendedState {
     pub suspended_at: u64,
     /// Favicon data (optional, compressed)
     pub favicon: Option<Vec<u8>>,
+    /// Last-seen thumbnail of the page (PNG)
+    #[serde(default)]
+    pub snapshot: Option<Vec<u8>>,
 }
 
 /// Current state of a tab.
@@ -59,6 +67,14 @@ pub struct Tab {
     pub pinned: bool,
     /// Favicon data
     pub favicon: Option<Vec<u8>>,
+    /// Thumbnail of the page as last rendered (PNG)
+    pub thumbnail: Option<Vec<u8>>,
+    /// Tab whose page opened this one
+    pub opener: Option<ViewId>,
+    /// Registry name of the engine showing the tab; `None` for the default
+    pub engine: Option<String>,
+    /// Media playing in the tab, and whether it is muted
+    pub media: MediaState,
 }
 
 impl Tab {
@@ -74,6 +90,10 @@ impl Tab {
             suspended_data: None,
             pinned: false,
             favicon: None,
+            thumbnail: None,
+            opener: None,
+            engine: None,
+            media: MediaState::default(),
         }
     }
 
@@ -96,6 +116,21 @@ impl Tab {
             self.state = TabState::Background;
         }
     }
+
+    /// What is kept of the tab while it has no live view.
+    fn suspended_state(&self) -> SuspendedState {
+        SuspendedState {
+            url: self.url.clone(),
+            title: self.title.clone(),
+            scroll_position: (0.0, 0.0),
+            suspended_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            favicon: self.favicon.clone(),
+            snapshot: self.thumbnail.clone(),
+        }
+    }
 }
 
 /// Configuration for tab suspension behavior.
@@ -166,6 +201,106 @@ impl TabManager {
         Ok(view_id)
     }
 
+    /// Create a new tab shown by a named engine of `engines`.
+    pub fn create_tab_in(&mut self, name: &str, engines: &mut EngineSet) -> EngineResult<ViewId> {
+        let view_id = ViewId(self.next_id);
+        self.next_id += 1;
+
+        engines.create_view_in(name, view_id)?;
+
+        let mut tab = Tab::new(view_id);
+        tab.engine = (name != engines.default_name()).then(|| name.to_string());
+        self.tabs.insert(view_id, tab);
+        self.tab_order.push(view_id);
+
+        if self.active_tab.is_none() {
+            self.active_tab = Some(view_id);
+        }
+
+        Ok(view_id)
+    }
+
+    /// Show a tab in another engine. The page is reloaded from its URL; a
+    /// suspended tab stays suspended, with its saved state, if the new
+    /// engine can suspend views.
+    pub fn reopen_in_engine(
+        &mut self,
+        view_id: ViewId,
+        name: &str,
+        engines: &mut EngineSet,
+    ) -> EngineResult<()> {
+        let tab = self
+            .tabs
+            .get_mut(&view_id)
+            .ok_or(crate::core::engine::EngineError::ViewNotFound(view_id))?;
+        if engines.engine_of(view_id) == name {
+            return Ok(());
+        }
+
+        let state = match tab.suspended_data.take() {
+            Some(state) => state,
+            None => tab.suspended_state(),
+        };
+        let was_suspended = tab.state == TabState::Suspended;
+        if let Err(e) = engines.move_view(view_id, name) {
+            if was_suspended {
+                tab.suspended_data = Some(state);
+            }
+            return Err(e);
+        }
+        tab.engine = (name != engines.default_name()).then(|| name.to_string());
+
+        if was_suspended && engines.view_capabilities(view_id).suspend {
+            engines.suspend_view(view_id)?;
+            tab.suspended_data = Some(state);
+        } else {
+            tab.title = state.title;
+            tab.favicon = state.favicon;
+            tab.thumbnail = state.snapshot;
+            tab.state = TabState::Loading;
+            engines.load_url(view_id, &state.url)?;
+        }
+
+        log::info!("Reopened tab {} in {}", view_id, name);
+        Ok(())
+    }
+
+    /// Open a tab a page asked for, placed after its opener and the tabs
+    /// the opener already opened.
+    ///
+    /// There is a single window, so popups open as focused tabs.
+    pub fn open_from(
+        &mut self,
+        opener: ViewId,
+        request: &NewViewRequest,
+        engine: &mut dyn BrowserEngine,
+    ) -> EngineResult<ViewId> {
+        if !self.tabs.contains_key(&opener) {
+            return Err(crate::core::engine::EngineError::ViewNotFound(opener));
+        }
+        let view_id = self.create_tab(engine)?;
+        if let Some(tab) = self.tabs.get_mut(&view_id) {
+            tab.opener = Some(opener);
+        }
+
+        let position = self
+            .tab_order
+            .iter()
+            .rposition(|id| {
+                *id == opener
+                    || (*id != view_id
+                        && self.tabs.get(id).is_some_and(|t| t.opener == Some(opener)))
+            })
+            .map_or(self.tab_order.len(), |i| i + 1);
+        self.move_tab(view_id, position);
+
+        if request.disposition != WindowDisposition::BackgroundTab {
+            self.switch_to_tab(view_id, engine)?;
+        }
+        engine.load_url(view_id, &request.url)?;
+        Ok(view_id)
+    }
+
     /// Close a tab and release its resources.
     pub fn close_tab(
         &mut self,
@@ -178,12 +313,19 @@ impl TabManager {
             }
         }
 
-        self.tabs.remove(&view_id);
+        let opener = self.tabs.remove(&view_id).and_then(|tab| tab.opener);
         self.tab_order.retain(|&id| id != view_id);
+        for tab in self.tabs.values_mut() {
+            if tab.opener == Some(view_id) {
+                tab.opener = None;
+            }
+        }
 
-        // If we closed the active tab, activate the nearest tab
+        // If we closed the active tab, go back to its opener or the nearest tab
         if self.active_tab == Some(view_id) {
-            self.active_tab = self.tab_order.last().copied();
+            self.active_tab = opener
+                .filter(|id| self.tabs.contains_key(id))
+                .or_else(|| self.tab_order.last().copied());
         }
 
         Ok(())
@@ -218,6 +360,20 @@ impl TabManager {
         Ok(())
     }
 
+    /// Refresh a tab's thumbnail from what the engine currently renders.
+    pub fn update_thumbnail(&mut self, view_id: ViewId, engine: &dyn BrowserEngine) {
+        let Some(tab) = self.tabs.get_mut(&view_id) else {
+            return;
+        };
+        if tab.state == TabState::Suspended {
+            return;
+        }
+        match engine.capture_view(view_id, CaptureOptions::thumbnail()) {
+            Ok(png) => tab.thumbnail = Some(png),
+            Err(e) => log::debug!("Failed to capture thumbnail for {}: {}", view_id, e),
+        }
+    }
+
     /// Suspend a tab to save memory.
     pub fn suspend_tab(
         &mut self,
@@ -239,17 +395,19 @@ impl TabManager {
             return Ok(());
         }
 
+        // Suspending would cut off what the user is listening to
+        if tab.media.is_playing_audio() {
+            return Ok(());
+        }
+
+        // Keep a last-seen image while the view is gone
+        match engine.capture_view(view_id, CaptureOptions::thumbnail()) {
+            Ok(png) => tab.thumbnail = Some(png),
+            Err(e) => log::debug!("No thumbnail for {}: {}", view_id, e),
+        }
+
         // Save state
-        let suspended_state = SuspendedState {
-            url: tab.url.clone(),
-            title: tab.title.clone(),
-            scroll_position: (0.0, 0.0),
-            suspended_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            favicon: tab.favicon.clone(),
-        };
+        let suspended_state = tab.suspended_state();
 
         // Release engine resources
         engine.suspend_view(view_id)?;
@@ -312,6 +470,7 @@ impl TabManager {
                     && tab.inactive_duration() > threshold
                     && active_tab != Some(**id)
                     && (!tab.pinned || self.suspension_config.suspend_pinned)
+                    && !tab.media.is_playing_audio()
             })
             .map(|(id, _)| *id)
             .collect();
@@ -351,7 +510,9 @@ impl TabManager {
             .tabs
             .iter()
             .filter(|(id, tab)| {
-                tab.state == TabState::Background && active_tab != Some(**id)
+                tab.state == TabState::Background
+                    && active_tab != Some(**id)
+                    && !tab.media.is_playing_audio()
             })
             .map(|(id, tab)| (*id, tab.last_active))
             .collect();
@@ -445,6 +606,13 @@ impl TabManager {
         }
     }
 
+    /// Update the media state of a tab.
+    pub fn update_tab_media(&mut self, view_id: ViewId, media: MediaState) {
+        if let Some(tab) = self.tabs.get_mut(&view_id) {
+            tab.media = media;
+        }
+    }
+
     /// Mark a tab as finished loading.
     pub fn mark_loaded(&mut self, view_id: ViewId) {
         if let Some(tab) = self.tabs.get_mut(&view_id) {
@@ -457,11 +625,59 @@ impl TabManager {
             }
         }
     }
+
+    /// Mark a tab as loading (unless it is suspended).
+    pub fn mark_loading(&mut self, view_id: ViewId) {
+        if let Some(tab) = self.tabs.get_mut(&view_id) {
+            if tab.state != TabState::Suspended {
+                tab.state = TabState::Loading;
+            }
+        }
+    }
+
+    /// Mark a tab whose content process died (unless it is suspended).
+    pub fn mark_crashed(&mut self, view_id: ViewId) {
+        if let Some(tab) = self.tabs.get_mut(&view_id) {
+            if tab.state != TabState::Suspended {
+                tab.state = TabState::Error;
+            }
+        }
+    }
+
+    /// Update tab metadata and state from an engine event.
+    pub fn handle_engine_event(&mut self, event: &EngineEvent) {
+        match event {
+            EngineEvent::LoadStarted(id) => self.mark_loading(*id),
+            EngineEvent::LoadFinished(id) | EngineEvent::LoadFailed(id, _) => self.mark_loaded(*id),
+            EngineEvent::UrlChanged(id, url) => self.update_tab_url(*id, url.clone()),
+            EngineEvent::TitleChanged(id, title) => self.update_tab_title(*id, title.clone()),
+            EngineEvent::FaviconReady(id, data) => self.update_tab_favicon(*id, data.clone()),
+            EngineEvent::ViewCrashed(id, _) => self.mark_crashed(*id),
+            EngineEvent::MediaStateChanged(id, media) => self.update_tab_media(*id, *media),
+            _ => {}
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engines::mock::MockEngine;
+
+    /// Open `count` tabs on example.com and feed the engine events back.
+    fn open_tabs(manager: &mut TabManager, engine: &mut MockEngine, count: usize) -> Vec<ViewId> {
+        let ids: Vec<ViewId> = (0..count)
+            .map(|_| {
+                let id = manager.create_tab(engine).unwrap();
+                engine.load_url(id, "https://example.com/").unwrap();
+                id
+            })
+            .collect();
+        for event in engine.poll_events() {
+            manager.handle_engine_event(&event);
+        }
+        ids
+    }
 
     #[test]
     fn test_tab_creation() {
@@ -490,4 +706,224 @@ mod tests {
         assert_eq!(config.max_active_tabs, 10);
         assert!(!config.suspend_pinned);
     }
+
+    #[test]
+    fn test_tab_manager_tracks_engine_events() {
+        let mut engine = MockEngine::new();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 2);
+
+        let first = manager.get_tab(ids[0]).unwrap();
+        assert_eq!(first.state, TabState::Active);
+        assert_eq!(first.title, "Example Domain");
+        assert_eq!(first.url, "https://example.com/");
+        assert_eq!(manager.get_tab(ids[1]).unwrap().state, TabState::Background);
+    }
+
+    #[test]
+    fn test_crashed_tab_is_errored_until_reloaded() {
+        let mut engine = MockEngine::new();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 1);
+
+        manager.handle_engine_event(&EngineEvent::ViewCrashed(ids[0], "oom".to_string()));
+        let tab = manager.get_tab(ids[0]).unwrap();
+        assert_eq!(tab.state, TabState::Error);
+        assert_eq!(tab.url, "https://example.com/");
+
+        engine.load_url(ids[0], "https://example.com/").unwrap();
+        for event in engine.poll_events() {
+            manager.handle_engine_event(&event);
+        }
+        assert_eq!(manager.get_tab(ids[0]).unwrap().state, TabState::Active);
+    }
+
+    #[test]
+    fn test_suspend_and_resume_round_trip() {
+        let mut engine = MockEngine::new();
+        let handle = engine.handle();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 2);
+
+        // The active tab is never suspended
+        manager.suspend_tab(ids[0], &mut engine).unwrap();
+        assert_eq!(manager.suspended_count(), 0);
+
+        manager.suspend_tab(ids[1], &mut engine).unwrap();
+        assert_eq!(manager.get_tab(ids[1]).unwrap().state, TabState::Suspended);
+        let data = manager.get_tab(ids[1]).unwrap().suspended_data.clone().unwrap();
+        assert_eq!(data.url, "https://example.com/");
+        assert!(data.snapshot.is_some());
+
+        handle.clear_calls();
+        manager.switch_to_tab(ids[1], &mut engine).unwrap();
+        let methods: Vec<&str> = handle.calls().iter().map(|c| c.method).collect();
+        assert_eq!(methods, vec!["resume_view", "load_url"]);
+        assert_eq!(manager.active_tab_id(), Some(ids[1]));
+        assert!(manager.get_tab(ids[1]).unwrap().suspended_data.is_none());
+    }
+
+    #[test]
+    fn test_close_tab_skips_engine_for_suspended() {
+        let mut engine = MockEngine::new();
+        let handle = engine.handle();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 3);
+
+        manager.suspend_tab(ids[2], &mut engine).unwrap();
+        manager.close_tab(ids[2], &mut engine).unwrap();
+        assert!(handle.calls_to("destroy_view").is_empty());
+
+        manager.close_tab(ids[0], &mut engine).unwrap();
+        assert_eq!(handle.calls_to("destroy_view").len(), 1);
+        assert_eq!(manager.active_tab_id(), Some(ids[1]));
+        assert_eq!(manager.tab_count(), 1);
+    }
+
+    #[test]
+    fn test_tabs_playing_audio_are_not_suspended() {
+        let mut engine = MockEngine::new();
+        let mut manager = TabManager::new(SuspensionConfig {
+            inactive_threshold: Duration::ZERO,
+            ..SuspensionConfig::default()
+        });
+        let ids = open_tabs(&mut manager, &mut engine, 3);
+        let media = |playing, audible, muted| MediaState {
+            playing,
+            audible,
+            muted,
+        };
+        // Muted audio still counts; a silent video does not
+        for (id, state) in [(ids[1], media(true, true, true)), (ids[2], media(true, false, false))] {
+            manager.handle_engine_event(&EngineEvent::MediaStateChanged(id, state));
+        }
+
+        manager.suspend_tab(ids[1], &mut engine).unwrap();
+        manager.suspend_oldest_inactive(2, &mut engine);
+        manager.check_suspensions(&mut engine);
+        assert_eq!(manager.get_tab(ids[1]).unwrap().state, TabState::Background);
+        assert_eq!(manager.get_tab(ids[2]).unwrap().state, TabState::Suspended);
+
+        manager.update_tab_media(ids[1], media(false, true, true));
+        manager.check_suspensions(&mut engine);
+        assert_eq!(manager.suspended_count(), 2);
+    }
+
+    #[test]
+    fn test_suspend_oldest_inactive() {
+        let mut engine = MockEngine::new();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 4);
+        for (age, id) in ids.iter().rev().enumerate() {
+            manager.get_tab_mut(*id).unwrap().last_active =
+                Instant::now() - Duration::from_secs(age as u64 * 60);
+        }
+
+        manager.suspend_oldest_inactive(2, &mut engine);
+        assert_eq!(manager.suspended_count(), 2);
+        assert_eq!(manager.get_tab(ids[1]).unwrap().state, TabState::Suspended);
+        assert_eq!(manager.get_tab(ids[2]).unwrap().state, TabState::Suspended);
+        assert_eq!(manager.get_tab(ids[3]).unwrap().state, TabState::Background);
+    }
+
+    #[test]
+    fn test_update_thumbnail() {
+        let mut engine = MockEngine::new();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 1);
+        assert!(manager.get_tab(ids[0]).unwrap().thumbnail.is_none());
+
+        manager.update_thumbnail(ids[0], &engine);
+        let png = manager.get_tab(ids[0]).unwrap().thumbnail.clone().unwrap();
+        assert_eq!(
+            crate::core::capture::png_dimensions(&png),
+            Some((320, 200))
+        );
+    }
+
+    #[test]
+    fn test_open_from_opener() {
+        let mut engine = MockEngine::new();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+        let ids = open_tabs(&mut manager, &mut engine, 2);
+        manager.switch_to_tab(ids[0], &mut engine).unwrap();
+
+        let request = |url: &str, disposition| NewViewRequest {
+            url: url.to_string(),
+            user_gesture: true,
+            disposition,
+        };
+        let background = manager
+            .open_from(
+                ids[0],
+                &request("https://example.com/a", WindowDisposition::BackgroundTab),
+                &mut engine,
+            )
+            .unwrap();
+        assert_eq!(manager.active_tab_id(), Some(ids[0]));
+        let foreground = manager
+            .open_from(
+                ids[0],
+                &request("https://example.com/b", WindowDisposition::ForegroundTab),
+                &mut engine,
+            )
+            .unwrap();
+        assert_eq!(manager.active_tab_id(), Some(foreground));
+        assert_eq!(manager.get_tab(foreground).unwrap().opener, Some(ids[0]));
+
+        // Opened tabs follow their opener, in the order they were opened
+        let order: Vec<ViewId> = manager.tabs_in_order().iter().map(|t| t.view_id).collect();
+        assert_eq!(order, vec![ids[0], background, foreground, ids[1]]);
+
+        // Closing an opened tab goes back to the opener
+        manager.close_tab(foreground, &mut engine).unwrap();
+        assert_eq!(manager.active_tab_id(), Some(ids[0]));
+        manager.close_tab(ids[0], &mut engine).unwrap();
+        assert_eq!(manager.get_tab(background).unwrap().opener, None);
+    }
+
+    #[test]
+    fn test_reopen_in_engine() {
+        let engine = MockEngine::new();
+        let handle = engine.handle();
+        let mut engines = engine.into_engine_set();
+        let mut manager = TabManager::new(SuspensionConfig::default());
+
+        let first = manager.create_tab(&mut engines).unwrap();
+        let second = manager.create_tab_in("other", &mut engines).unwrap();
+        assert_eq!(manager.get_tab(second).unwrap().engine.as_deref(), Some("other"));
+        for id in [first, second] {
+            engines.load_url(id, "https://example.com/").unwrap();
+        }
+        for event in engines.poll_events() {
+            manager.handle_engine_event(&event);
+        }
+
+        // A background tab keeps its state and stays suspended
+        manager.suspend_tab(second, &mut engines).unwrap();
+        manager.reopen_in_engine(second, "mock", &mut engines).unwrap();
+        let tab = manager.get_tab(second).unwrap();
+        assert_eq!(tab.engine, None);
+        assert_eq!(tab.state, TabState::Suspended);
+        assert_eq!(tab.suspended_data.as_ref().unwrap().url, "https://example.com/");
+        assert_eq!(handle.calls_to("suspend_view").len(), 1);
+
+        manager.switch_to_tab(second, &mut engines).unwrap();
+        let loads = handle.calls_to("load_url");
+        assert_eq!(loads.last().unwrap().view_id, Some(second));
+        assert_eq!(loads.last().unwrap().args, vec!["https://example.com/"]);
+
+        // A live tab is loaded again in its new engine
+        manager.reopen_in_engine(first, "other", &mut engines).unwrap();
+        assert_eq!(handle.calls_to("destroy_view").len(), 1);
+        let tab = manager.get_tab(first).unwrap();
+        assert_eq!(tab.state, TabState::Loading);
+        assert_eq!(tab.title, "Example Domain");
+        assert_eq!(
+            engines.get_navigation_state(first).unwrap().url,
+            "https://example.com/"
+        );
+        assert!(manager.reopen_in_engine(first, "servo", &mut engines).is_err());
+        assert_eq!(engines.engine_of(first), "other");
+    }
 }
@@ -3,6 +3,8 @@
 //! Checks GitHub releases for new versions and notifies the user.
 //! Does not auto-install; requires user confirmation.
 
+use crate::core::config::NetworkConfig;
+use crate::core::proxy;
 use serde::Deserialize;
 
 /// Represents a GitHub release.
@@ -41,6 +43,8 @@ pub struct UpdateChecker {
     current_version: String,
     repo: String,
     check_prerelease: bool,
+    /// Proxy settings for the release queries
+    network: NetworkConfig,
 }
 
 impl UpdateChecker {
@@ -53,6 +57,7 @@ impl UpdateChecker {
             current_version: current_version.to_string(),
             repo: repo.to_string(),
             check_prerelease: false,
+            network: NetworkConfig::default(),
         }
     }
 
@@ -69,6 +74,11 @@ impl UpdateChecker {
         self.check_prerelease = check;
     }
 
+    /// Send the release queries through the configured proxy.
+    pub fn set_network(&mut self, network: NetworkConfig) {
+        self.network = network;
+    }
+
     /// Check for available updates by querying the GitHub releases API.
     pub async fn check_for_updates(&self) -> Result<Option<UpdateInfo>, Box<dyn std::error::Error + Send + Sync>> {
         let url = format!(
@@ -76,7 +86,7 @@ impl UpdateChecker {
             self.repo
         );
 
-        let client = reqwest::Client::builder()
+        let client = proxy::client_builder(&self.network)?
             .user_agent("asteroid-browser")
             .timeout(std::time::Duration::from_secs(15))
             .build()?;
@@ -137,7 +147,7 @@ impl UpdateChecker {
             self.repo, tag
         );
 
-        let client = reqwest::Client::builder()
+        let client = proxy::client_builder(&self.network)?
             .user_agent("asteroid-browser")
             .timeout(std::time::Duration::from_secs(15))
             .build()?;
@@ -190,9 +200,11 @@ impl UpdateChecker {
 /// Start the background update checker (runs every 24 hours).
 pub fn start_update_checker(
     update_tx: tokio::sync::mpsc::Sender<UpdateInfo>,
+    network: NetworkConfig,
 ) {
     tokio::spawn(async move {
-        let checker = UpdateChecker::with_defaults();
+        let mut checker = UpdateChecker::with_defaults();
+        checker.set_network(network);
 
         loop {
             match checker.check_for_updates().await {
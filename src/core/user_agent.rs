@@ -0,0 +1,389 @@
+//! User agent overrides for Asteroid Browser.
+//!
+//! The agent a view sends is, most specific first: the one picked for its
+//! tab, the `[[engine.user_agent_rules]]` entry for the site, or the
+//! `engine.user_agent` setting. Each can name a preset (`desktop`,
+//! `mobile`, `minimal`) or give a full agent string; an empty value keeps
+//! the engine's own. Some sites serve much lighter pages to mobile agents,
+//! which helps on machines with little memory.
+//!
+//! `UserAgents` is the engine's navigation interceptor, so every document
+//! (links, redirects and history steps included) is requested with the
+//! agent for its own host. Engines without one get agents through
+//! `BrowserEngine::set_user_agent` before the browser navigates a view and
+//! whenever a view changes site, from the view's next navigation on.
+
+use crate::core::engine::{BrowserEngine, EngineResult, Navigation, NavigationInterceptor, ViewId};
+use crate::core::site_data::{host_in_site, host_of};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Agent strings shipped with the browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAgentPreset {
+    /// Firefox on Linux
+    Desktop,
+    /// Firefox on Android
+    Mobile,
+    /// Bare agent that reveals nothing; many sites fall back to basic pages
+    Minimal,
+}
+
+impl UserAgentPreset {
+    pub const ALL: [UserAgentPreset; 3] = [Self::Desktop, Self::Mobile, Self::Minimal];
+
+    /// Name used in the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Desktop => "desktop",
+            Self::Mobile => "mobile",
+            Self::Minimal => "minimal",
+        }
+    }
+
+    /// Name shown in the settings.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Desktop => "Desktop",
+            Self::Mobile => "Mobile",
+            Self::Minimal => "Minimal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn user_agent(&self) -> &'static str {
+        match self {
+            Self::Desktop => {
+                "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+            }
+            Self::Mobile => "Mozilla/5.0 (Android 14; Mobile; rv:128.0) Gecko/128.0 Firefox/128.0",
+            Self::Minimal => "Mozilla/5.0",
+        }
+    }
+}
+
+/// Agent string for a setting value: a preset name or a full agent.
+/// `None` for an empty value, which keeps the engine's own agent.
+pub fn resolve_user_agent(value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if let Some(preset) = UserAgentPreset::from_name(value) {
+        return Ok(Some(preset.user_agent().to_string()));
+    }
+    // Sent as a header, so it must stay on one line
+    if value.chars().any(char::is_control) {
+        return Err("A user agent must not contain control characters".to_string());
+    }
+    Ok(Some(value.to_string()))
+}
+
+/// User agent for one site, from `[[engine.user_agent_rules]]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserAgentRule {
+    /// Host the rule applies to, along with its subdomains
+    pub site: String,
+    /// Preset name or agent string; empty for the engine's own
+    pub user_agent: String,
+}
+
+/// Agent chosen for each view, shared by the engine thread and the
+/// engine's navigation interceptor.
+#[derive(Debug, Default)]
+pub struct UserAgents {
+    choices: RwLock<Choices>,
+}
+
+#[derive(Debug, Default)]
+struct Choices {
+    /// `engine.user_agent`, resolved
+    default: Option<String>,
+    /// Site and resolved agent of each rule
+    rules: Vec<(String, Option<String>)>,
+    /// Agent picked for a tab, overriding the rules
+    tabs: HashMap<ViewId, Option<String>>,
+    /// Agent last handed to the engine for each view
+    applied: HashMap<ViewId, Option<String>>,
+}
+
+impl Choices {
+    fn user_agent_for(&self, view_id: ViewId, url: &str) -> Option<String> {
+        if let Some(agent) = self.tabs.get(&view_id) {
+            return agent.clone();
+        }
+        let host = host_of(url);
+        self.rules
+            .iter()
+            .filter(|(site, _)| host_in_site(host, site))
+            // The most specific site wins
+            .max_by_key(|(site, _)| site.len())
+            .map(|(_, agent)| agent)
+            .unwrap_or(&self.default)
+            .clone()
+    }
+}
+
+impl UserAgents {
+    pub fn new(default: &str, rules: &[UserAgentRule]) -> Self {
+        let agents = Self::default();
+        agents.configure(default, rules);
+        agents
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Choices> {
+        self.choices.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Choices> {
+        self.choices.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the setting and the site rules. Invalid values are logged
+    /// and left out. Views pick the change up on their next navigation.
+    pub fn configure(&self, default: &str, rules: &[UserAgentRule]) {
+        let default = resolve_user_agent(default).unwrap_or_else(|e| {
+            log::warn!("Ignoring engine.user_agent: {}", e);
+            None
+        });
+        let rules = rules
+            .iter()
+            .filter_map(|rule| {
+                let site = rule
+                    .site
+                    .trim()
+                    .trim_start_matches('.')
+                    .to_ascii_lowercase();
+                match resolve_user_agent(&rule.user_agent) {
+                    Ok(agent) if !site.is_empty() => Some((site, agent)),
+                    Ok(_) => None,
+                    Err(e) => {
+                        log::warn!("Ignoring user agent rule for {}: {}", rule.site, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        let mut choices = self.write();
+        choices.default = default;
+        choices.rules = rules;
+    }
+
+    /// Pick an agent for a tab (a preset name or agent string), or go back
+    /// to the rules with `None`.
+    pub fn set_tab(&self, view_id: ViewId, value: Option<&str>) -> Result<(), String> {
+        match value {
+            Some(value) => {
+                let agent = resolve_user_agent(value)?;
+                self.write().tabs.insert(view_id, agent);
+            }
+            None => {
+                self.write().tabs.remove(&view_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Agent a view should send for `url`; `None` for the engine's own.
+    pub fn user_agent_for(&self, view_id: ViewId, url: &str) -> Option<String> {
+        self.read().user_agent_for(view_id, url)
+    }
+
+    /// Hand the agent for `url` to the engine, unless the view already has
+    /// it. Returns whether the agent changed.
+    pub fn apply(
+        &self,
+        engine: &mut dyn BrowserEngine,
+        view_id: ViewId,
+        url: &str,
+    ) -> EngineResult<bool> {
+        let (agent, current) = {
+            let choices = self.read();
+            let current = choices.applied.get(&view_id).cloned().unwrap_or(None);
+            (choices.user_agent_for(view_id, url), current)
+        };
+        if agent == current {
+            return Ok(false);
+        }
+        engine.set_user_agent(view_id, agent.as_deref())?;
+        self.write().applied.insert(view_id, agent);
+        Ok(true)
+    }
+
+    /// Forget a closed view.
+    pub fn forget(&self, view_id: ViewId) {
+        let mut choices = self.write();
+        choices.tabs.remove(&view_id);
+        choices.applied.remove(&view_id);
+    }
+}
+
+impl NavigationInterceptor for UserAgents {
+    fn intercept_navigation(&self, navigation: &mut Navigation) {
+        let mut choices = self.write();
+        let agent = choices.user_agent_for(navigation.view_id, &navigation.url);
+        choices.applied.insert(navigation.view_id, agent.clone());
+        navigation.user_agent = agent;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::mock::MockEngine;
+
+    fn rule(site: &str, user_agent: &str) -> UserAgentRule {
+        UserAgentRule {
+            site: site.to_string(),
+            user_agent: user_agent.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_user_agent() {
+        assert_eq!(resolve_user_agent(""), Ok(None));
+        assert_eq!(
+            resolve_user_agent("Minimal"),
+            Ok(Some("Mozilla/5.0".to_string()))
+        );
+        assert_eq!(
+            resolve_user_agent(" Lynx/2.9 "),
+            Ok(Some("Lynx/2.9".to_string()))
+        );
+        assert!(resolve_user_agent("Evil\r\nCookie: x").is_err());
+    }
+
+    #[test]
+    fn test_most_specific_choice_wins() {
+        let agents = UserAgents::new(
+            "desktop",
+            &[
+                rule("example.com", "mobile"),
+                rule("www.example.com", ""),
+                rule("bad.test", "a\nb"),
+            ],
+        );
+        let view = ViewId(1);
+        let mobile = UserAgentPreset::Mobile.user_agent();
+        let desktop = UserAgentPreset::Desktop.user_agent();
+
+        assert_eq!(
+            agents
+                .user_agent_for(view, "https://m.Example.com/")
+                .as_deref(),
+            Some(mobile)
+        );
+        assert_eq!(
+            agents
+                .user_agent_for(view, "https://www.example.com/")
+                .as_deref(),
+            None
+        );
+        assert_eq!(
+            agents
+                .user_agent_for(view, "https://notexample.com/")
+                .as_deref(),
+            Some(desktop)
+        );
+        assert_eq!(
+            agents.user_agent_for(view, "https://bad.test/").as_deref(),
+            Some(desktop)
+        );
+
+        agents.set_tab(view, Some("minimal")).unwrap();
+        assert_eq!(
+            agents
+                .user_agent_for(view, "https://example.com/")
+                .as_deref(),
+            Some("Mozilla/5.0")
+        );
+        assert_eq!(
+            agents
+                .user_agent_for(ViewId(2), "https://example.com/")
+                .as_deref(),
+            Some(mobile)
+        );
+        assert!(agents.set_tab(view, Some("\u{7}")).is_err());
+        agents.set_tab(view, None).unwrap();
+        assert_eq!(
+            agents
+                .user_agent_for(view, "https://example.com/")
+                .as_deref(),
+            Some(mobile)
+        );
+    }
+
+    #[test]
+    fn test_apply_only_on_change() {
+        let mut engine = MockEngine::new();
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let handle = engine.handle();
+        let agents = UserAgents::new("", &[rule("light.test", "mobile")]);
+
+        assert!(!agents
+            .apply(&mut engine, view, "https://example.com/")
+            .unwrap());
+        assert!(agents
+            .apply(&mut engine, view, "https://light.test/a")
+            .unwrap());
+        assert!(!agents
+            .apply(&mut engine, view, "https://light.test/b")
+            .unwrap());
+        assert!(agents
+            .apply(&mut engine, view, "https://example.com/")
+            .unwrap());
+
+        let calls = handle.calls_to("set_user_agent");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].args, vec![UserAgentPreset::Mobile.user_agent()]);
+        assert_eq!(calls[1].args, vec![""]);
+    }
+
+    #[test]
+    fn test_links_into_ruled_site_use_its_agent() {
+        use crate::engines::mock::{MockFixture, MockPage};
+        use std::sync::Arc;
+
+        let fixture = MockFixture::test_pages()
+            .page(
+                "https://start.test/",
+                MockPage::new("Start").link("https://m.light.test/"),
+            )
+            .page("https://m.light.test/", MockPage::new("Light"));
+        let mut engine = MockEngine::with_fixture(fixture);
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let agents = Arc::new(UserAgents::new("", &[rule("light.test", "mobile")]));
+        engine
+            .set_navigation_interceptor(Some(agents.clone()))
+            .unwrap();
+
+        agents
+            .apply(&mut engine, view, "https://start.test/")
+            .unwrap();
+        engine.load_url(view, "https://start.test/").unwrap();
+        assert_eq!(engine.document_user_agent(view).unwrap(), None);
+
+        // The first document of the ruled site already gets its agent
+        engine.follow_link(view, 0).unwrap();
+        let mobile = UserAgentPreset::Mobile.user_agent();
+        assert_eq!(
+            engine.document_user_agent(view).unwrap().as_deref(),
+            Some(mobile)
+        );
+        // and the browser does not hand it over again when the URL changes
+        assert!(!agents
+            .apply(&mut engine, view, "https://m.light.test/")
+            .unwrap());
+
+        engine.go_back(view).unwrap();
+        assert_eq!(engine.document_user_agent(view).unwrap(), None);
+    }
+}
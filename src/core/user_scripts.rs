@@ -0,0 +1,157 @@
+//! User scripts for Asteroid Browser.
+//!
+//! Engines keep the scripts registered with
+//! `BrowserEngine::add_user_script` in a `UserScripts` set and ask it which
+//! ones to inject when a document starts loading. Browser features (link
+//! hints, cosmetic filtering, ...) run in isolated worlds, where page
+//! scripts cannot reach their state and where `asteroid.postMessage`
+//! sends messages back as `EngineEvent::UserScriptMessage`.
+
+use crate::core::engine::{InjectionTime, ScriptWorld, UserScript, UserScriptId};
+use std::collections::BTreeMap;
+
+/// Whether `url` matches a user script pattern; `*` matches any run of
+/// characters, everything else is compared literally.
+pub fn url_matches(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = url.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Registered user scripts, in registration order.
+#[derive(Debug, Default)]
+pub struct UserScripts {
+    next_id: u64,
+    scripts: BTreeMap<UserScriptId, UserScript>,
+}
+
+impl UserScripts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(
+        &mut self,
+        source: &str,
+        injection_time: InjectionTime,
+        world: ScriptWorld,
+        url_matches: &[&str],
+    ) -> UserScriptId {
+        self.next_id += 1;
+        let id = UserScriptId(self.next_id);
+        self.scripts.insert(
+            id,
+            UserScript {
+                source: source.to_string(),
+                injection_time,
+                world,
+                url_matches: url_matches.iter().map(|p| p.to_string()).collect(),
+            },
+        );
+        id
+    }
+
+    /// Remove a script; false if it was not registered.
+    pub fn remove(&mut self, id: UserScriptId) -> bool {
+        self.scripts.remove(&id).is_some()
+    }
+
+    pub fn get(&self, id: UserScriptId) -> Option<&UserScript> {
+        self.scripts.get(&id)
+    }
+
+    /// Scripts to inject into a document at `url`, in registration order.
+    pub fn for_url(&self, url: &str) -> Vec<(UserScriptId, &UserScript)> {
+        self.scripts
+            .iter()
+            .filter(|(_, script)| {
+                script.url_matches.is_empty()
+                    || script.url_matches.iter().any(|p| url_matches(p, url))
+            })
+            .map(|(id, script)| (*id, script))
+            .collect()
+    }
+
+    /// Whether any script of the named isolated world runs at `url`.
+    pub fn has_world(&self, world: &str, url: &str) -> bool {
+        self.for_url(url)
+            .iter()
+            .any(|(_, script)| matches!(&script.world, ScriptWorld::Isolated(name) if name == world))
+    }
+
+    /// Every script, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = (UserScriptId, &UserScript)> {
+        self.scripts.iter().map(|(id, script)| (*id, script))
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_matches() {
+        assert!(url_matches("https://*", "https://example.com/"));
+        assert!(url_matches("*://example.com/*", "http://example.com/a"));
+        assert!(url_matches("https://*.test/*/x", "https://a.test/b/c/x"));
+        assert!(url_matches("https://a.test/", "https://a.test/"));
+        assert!(!url_matches("https://a.test/", "https://a.test/b"));
+        assert!(!url_matches("https://*", "http://example.com/"));
+        assert!(!url_matches("*.test/x*", "https://a.com/x"));
+        assert!(!url_matches("https://ab*ba", "https://aba"));
+    }
+
+    #[test]
+    fn test_scripts_for_url() {
+        let mut scripts = UserScripts::new();
+        let all = scripts.add(
+            "hints()",
+            InjectionTime::End,
+            ScriptWorld::isolated("hints"),
+            &[],
+        );
+        let web = scripts.add(
+            "hide()",
+            InjectionTime::Start,
+            ScriptWorld::Page,
+            &["https://*"],
+        );
+
+        let ids: Vec<_> = scripts
+            .for_url("https://a.test/")
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(ids, vec![all, web]);
+        assert_eq!(scripts.for_url("asteroid://settings").len(), 1);
+        assert!(scripts.has_world("hints", "https://a.test/"));
+        assert!(!scripts.has_world("hints-other", "https://a.test/"));
+
+        assert!(scripts.remove(all));
+        assert!(!scripts.remove(all));
+        assert!(!scripts.has_world("hints", "https://a.test/"));
+        assert_eq!(scripts.len(), 1);
+    }
+}
@@ -0,0 +1,130 @@
+//! Hang watchdog for Asteroid Browser.
+//!
+//! The engine host pings every live view with `BrowserEngine::ping_view`
+//! at a fixed interval. A view whose answer (`EngineEvent::ViewResponsive`)
+//! does not arrive within the timeout is reported once as unresponsive,
+//! and is not pinged again until it answers.
+
+use crate::core::engine::ViewId;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How often live views are pinged.
+pub const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a view may take to answer before it counts as hung.
+pub const UNRESPONSIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outstanding pings and the views they found hung.
+#[derive(Debug)]
+pub struct Watchdog {
+    interval: Duration,
+    timeout: Duration,
+    last_round: Option<Instant>,
+    /// When each view was pinged, until it answers
+    pings: HashMap<ViewId, Instant>,
+    /// Views already reported as unresponsive
+    unresponsive: HashSet<ViewId>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(PING_INTERVAL, UNRESPONSIVE_TIMEOUT)
+    }
+}
+
+impl Watchdog {
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Self {
+            interval,
+            timeout,
+            last_round: None,
+            pings: HashMap::new(),
+            unresponsive: HashSet::new(),
+        }
+    }
+
+    /// Start a round of pings if one is due: returns which of the live
+    /// `views` to ping, i.e. those not still owing an answer. Views that
+    /// are no longer live are forgotten.
+    pub fn round(&mut self, views: &[ViewId], now: Instant) -> Vec<ViewId> {
+        if self
+            .last_round
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Vec::new();
+        }
+        self.last_round = Some(now);
+        self.pings.retain(|id, _| views.contains(id));
+        self.unresponsive.retain(|id| views.contains(id));
+
+        let mut pinged = Vec::new();
+        for id in views {
+            if !self.pings.contains_key(id) {
+                self.pings.insert(*id, now);
+                pinged.push(*id);
+            }
+        }
+        pinged
+    }
+
+    /// Views whose ping went unanswered for longer than the timeout and
+    /// that were not reported yet.
+    pub fn overdue(&mut self, now: Instant) -> Vec<ViewId> {
+        let mut overdue: Vec<ViewId> = self
+            .pings
+            .iter()
+            .filter(|(id, sent)| {
+                now.duration_since(**sent) > self.timeout && !self.unresponsive.contains(id)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        overdue.sort_by_key(|id| id.0);
+        self.unresponsive.extend(overdue.iter().copied());
+        overdue
+    }
+
+    /// Record an answer; true if the view had been reported unresponsive.
+    pub fn answered(&mut self, view_id: ViewId) -> bool {
+        self.pings.remove(&view_id);
+        self.unresponsive.remove(&view_id)
+    }
+
+    /// Stop watching a view, e.g. one that crashed or could not be pinged.
+    pub fn forget(&mut self, view_id: ViewId) {
+        self.pings.remove(&view_id);
+        self.unresponsive.remove(&view_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unanswered_pings_are_reported_once() {
+        let mut watchdog = Watchdog::new(Duration::from_secs(5), Duration::from_secs(10));
+        let (a, b) = (ViewId(1), ViewId(2));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(watchdog.round(&[a, b], start), vec![a, b]);
+        // Not due yet
+        assert!(watchdog.round(&[a, b], at(2)).is_empty());
+        assert!(!watchdog.answered(a));
+
+        // `b` still owes an answer and is not pinged again
+        assert_eq!(watchdog.round(&[a, b], at(5)), vec![a]);
+        assert!(watchdog.overdue(at(10)).is_empty());
+        assert_eq!(watchdog.overdue(at(11)), vec![b]);
+        assert!(watchdog.overdue(at(12)).is_empty());
+        watchdog.answered(a);
+
+        assert!(watchdog.answered(b));
+        assert_eq!(watchdog.round(&[a, b], at(15)), vec![a, b]);
+
+        // Closed views are dropped
+        assert!(watchdog.round(&[a], at(20)).is_empty());
+        assert_eq!(watchdog.overdue(at(60)), vec![a]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Per-site zoom for Asteroid Browser.
+//!
+//! Zoom steps used by the zoom shortcuts, and a store that remembers the
+//! zoom chosen for each host so it is restored when a view navigates back
+//! to that site. Levels are persisted to zoom.json next to the config file.
+
+use crate::core::engine::{BrowserEngine, EngineResult, ViewId, Zoom};
+use crate::core::site_data::host_of;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Levels visited by zoom in/out, as in other browsers.
+pub const ZOOM_STEPS: [f64; 16] = [
+    0.3, 0.5, 0.67, 0.8, 0.9, 1.0, 1.1, 1.2, 1.33, 1.5, 1.7, 2.0, 2.4, 3.0, 4.0, 5.0,
+];
+
+/// Next zoom step above `level`.
+pub fn zoom_in(level: f64) -> f64 {
+    ZOOM_STEPS
+        .iter()
+        .copied()
+        .find(|step| *step > level + 0.001)
+        .unwrap_or(Zoom::MAX_LEVEL)
+}
+
+/// Next zoom step below `level`.
+pub fn zoom_out(level: f64) -> f64 {
+    ZOOM_STEPS
+        .iter()
+        .rev()
+        .copied()
+        .find(|step| *step < level - 0.001)
+        .unwrap_or(Zoom::MIN_LEVEL)
+}
+
+/// Host a zoom level is stored under, `None` for URLs without one
+/// (about:blank, data: URLs, ...).
+pub fn zoom_host(url: &str) -> Option<String> {
+    if !url.contains("://") {
+        return None;
+    }
+    let host = host_of(url);
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_ascii_lowercase())
+    }
+}
+
+/// Zoom chosen per host. Hosts at the default zoom are not stored.
+#[derive(Debug, Default)]
+pub struct ZoomStore {
+    /// Where levels are saved, if anywhere
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, Zoom>,
+}
+
+impl ZoomStore {
+    /// Create a store, loading previous levels from `path` if given.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let entries = path.as_deref().and_then(load_entries).unwrap_or_default();
+        Self { path, entries }
+    }
+
+    /// Zoom to use for a URL.
+    pub fn zoom_for(&self, url: &str) -> Zoom {
+        zoom_host(url)
+            .and_then(|host| self.entries.get(&host).copied())
+            .unwrap_or_default()
+    }
+
+    /// Remember the zoom of the site a URL belongs to.
+    pub fn set(&mut self, url: &str, zoom: Zoom) {
+        let Some(host) = zoom_host(url) else {
+            return;
+        };
+        let changed = if zoom.is_default() {
+            self.entries.remove(&host).is_some()
+        } else {
+            self.entries.insert(host, zoom) != Some(zoom)
+        };
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Apply the stored zoom after a view navigated from `previous` to
+    /// `url`. Navigating within a site keeps the current zoom.
+    pub fn restore(
+        &self,
+        engine: &mut dyn BrowserEngine,
+        view_id: ViewId,
+        previous: Option<&str>,
+        url: &str,
+    ) -> EngineResult<()> {
+        if previous.and_then(zoom_host) == zoom_host(url) {
+            return Ok(());
+        }
+        engine.set_zoom(view_id, self.zoom_for(url))
+    }
+
+    fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(&self.entries)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(path, content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::error!("Failed to save zoom levels to {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn load_entries(path: &Path) -> Option<BTreeMap<String, Zoom>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+            log::error!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::ZoomMode;
+    use crate::engines::mock::MockEngine;
+
+    #[test]
+    fn test_zoom_steps() {
+        assert_eq!(zoom_in(1.0), 1.1);
+        assert_eq!(zoom_out(1.0), 0.9);
+        assert_eq!(zoom_in(1.25), 1.33);
+        assert_eq!(zoom_in(5.0), Zoom::MAX_LEVEL);
+        assert_eq!(zoom_out(0.3), Zoom::MIN_LEVEL);
+    }
+
+    #[test]
+    fn test_store_per_host() {
+        let mut store = ZoomStore::new(None);
+        let zoom = Zoom::new(1.5, ZoomMode::FullPage);
+        store.set("https://Docs.example.com:8443/a", zoom);
+        store.set("about:blank", zoom);
+
+        assert_eq!(store.zoom_for("http://docs.example.com/b?q"), zoom);
+        assert_eq!(store.zoom_for("https://example.com/"), Zoom::default());
+        assert_eq!(store.entries.len(), 1);
+
+        // Going back to 100% forgets the site
+        store.set("https://docs.example.com/", Zoom::default());
+        assert!(store.entries.is_empty());
+    }
+
+    #[test]
+    fn test_restore_on_navigation() {
+        let mut engine = MockEngine::new();
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let handle = engine.handle();
+
+        let mut store = ZoomStore::new(None);
+        let zoom = Zoom::new(2.0, ZoomMode::TextOnly);
+        store.set("https://big.test/", zoom);
+
+        store
+            .restore(
+                &mut engine,
+                view,
+                Some("about:blank"),
+                "https://big.test/page",
+            )
+            .unwrap();
+        assert_eq!(engine.get_zoom(view).unwrap(), zoom);
+
+        // Same site: left alone
+        store
+            .restore(
+                &mut engine,
+                view,
+                Some("https://big.test/page"),
+                "https://big.test/other",
+            )
+            .unwrap();
+        assert_eq!(handle.calls_to("set_zoom").len(), 1);
+
+        store
+            .restore(
+                &mut engine,
+                view,
+                Some("https://big.test/other"),
+                "https://small.test/",
+            )
+            .unwrap();
+        assert_eq!(engine.get_zoom(view).unwrap(), Zoom::default());
+    }
+
+    #[test]
+    fn test_zoom_persists() {
+        let dir = std::env::temp_dir().join(format!("asteroid-zoom-{}", std::process::id()));
+        let path = dir.join("zoom.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let zoom = Zoom::new(0.8, ZoomMode::FullPage);
+        let mut store = ZoomStore::new(Some(path.clone()));
+        store.set("https://small.test/", zoom);
+
+        let store = ZoomStore::new(Some(path));
+        assert_eq!(store.zoom_for("https://small.test/x"), zoom);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,697 @@
+//! BrowserEngine conformance suite.
+//!
+//! Generic checks over `Box<dyn BrowserEngine>` that every backend has to
+//! pass for the engine swap promised by `core::engine` to hold. Each check
+//! runs against every engine in `engines::create_registry()` (plus the mock
+//! engine, alone and as the default of an `EngineSet`), on a fresh instance. Engines that report
+//! `EngineError::InitializationFailed` are not available in this build and
+//! are skipped.
+
+use crate::core::engine::{
+    BrowserEngine, CaptureArea, CaptureOptions, EngineError, EngineEvent, EngineResult,
+    FindOptions, InjectionTime, PdfOptions, PermissionKind, ScriptWorld, SiteDataKind, TrimLevel, ViewId, Zoom,
+    ZoomMode,
+};
+use crate::core::media_policy::MediaPolicy;
+
+const TEST_URL: &str = "https://example.com/";
+
+/// Fresh, initialized instances of every engine under test.
+fn engines_under_test() -> Vec<(String, Box<dyn BrowserEngine>)> {
+    let mut registry = super::create_registry();
+    registry.register("mock", super::mock::create_mock_engine);
+
+    let mut names: Vec<String> = registry
+        .available_engines()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    names.sort();
+
+    let mut engines = Vec::new();
+    for name in names {
+        let mut engine = registry.create(&name).unwrap();
+        match engine.initialize() {
+            Ok(()) => engines.push((name, engine)),
+            Err(EngineError::InitializationFailed(msg)) => {
+                eprintln!(
+                    "conformance: skipping unavailable engine '{}': {}",
+                    name, msg
+                );
+            }
+            Err(e) => panic!("{}: initialize failed: {}", name, e),
+        }
+    }
+
+    // Routing views between engines must not be visible through the trait
+    let mut set = super::mock::MockEngine::new().into_engine_set();
+    set.initialize().unwrap();
+    engines.push(("engine-set".to_string(), Box::new(set)));
+    engines
+}
+
+fn assert_view_not_found<T: std::fmt::Debug>(
+    name: &str,
+    what: &str,
+    result: EngineResult<T>,
+    id: ViewId,
+) {
+    match result {
+        Err(EngineError::ViewNotFound(v)) if v == id => {}
+        other => panic!("{}: {} on missing view returned {:?}", name, what, other),
+    }
+}
+
+/// Lifecycle-relevant events for one view, in order.
+fn load_events(events: Vec<EngineEvent>, id: ViewId) -> Vec<EngineEvent> {
+    events
+        .into_iter()
+        .filter(|e| match e {
+            EngineEvent::LoadStarted(v)
+            | EngineEvent::LoadFinished(v)
+            | EngineEvent::UrlChanged(v, _)
+            | EngineEvent::LoadProgress(v, _) => *v == id,
+            _ => false,
+        })
+        .collect()
+}
+
+fn check_view_lifecycle(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(1);
+    engine.create_view(id).unwrap();
+    assert!(
+        engine.create_view(id).is_err(),
+        "{}: duplicate create_view succeeded",
+        name
+    );
+    assert!(
+        engine.get_navigation_state(id).is_ok(),
+        "{}: new view has no state",
+        name
+    );
+
+    engine.destroy_view(id).unwrap();
+    assert_view_not_found(name, "destroy_view", engine.destroy_view(id), id);
+    assert_view_not_found(
+        name,
+        "get_navigation_state",
+        engine.get_navigation_state(id),
+        id,
+    );
+
+    // Views are gone after shutdown
+    engine.create_view(ViewId(2)).unwrap();
+    engine.shutdown().unwrap();
+    assert_view_not_found(
+        name,
+        "get_navigation_state",
+        engine.get_navigation_state(ViewId(2)),
+        ViewId(2),
+    );
+}
+
+fn check_load_event_order(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(1);
+    engine.create_view(id).unwrap();
+    engine.poll_events();
+    engine.load_url(id, TEST_URL).unwrap();
+
+    let events = load_events(engine.poll_events(), id);
+    assert!(
+        matches!(events.first(), Some(EngineEvent::LoadStarted(_))),
+        "{}: load did not start with LoadStarted: {:?}",
+        name,
+        events
+    );
+    assert!(
+        matches!(events.last(), Some(EngineEvent::LoadFinished(_))),
+        "{}: load did not end with LoadFinished: {:?}",
+        name,
+        events
+    );
+    let url_pos = events
+        .iter()
+        .position(|e| matches!(e, EngineEvent::UrlChanged(_, u) if u == TEST_URL))
+        .unwrap_or_else(|| panic!("{}: no UrlChanged for {}", name, TEST_URL));
+
+    let mut last_progress = 0.0;
+    for (i, event) in events.iter().enumerate() {
+        if let EngineEvent::LoadProgress(_, p) = event {
+            assert!(i > url_pos, "{}: LoadProgress before UrlChanged", name);
+            assert!(
+                (0.0..=1.0).contains(p),
+                "{}: progress {} out of range",
+                name,
+                p
+            );
+            assert!(*p >= last_progress, "{}: progress went backwards", name);
+            last_progress = *p;
+        }
+    }
+    assert_eq!(last_progress, 1.0, "{}: load finished below 100%", name);
+
+    let state = engine.get_navigation_state(id).unwrap();
+    assert_eq!(state.url, TEST_URL, "{}: navigation state URL", name);
+    assert!(
+        !state.is_loading,
+        "{}: still loading after LoadFinished",
+        name
+    );
+}
+
+fn check_view_not_found(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(404);
+    assert_view_not_found(name, "load_url", engine.load_url(id, TEST_URL), id);
+    assert_view_not_found(
+        name,
+        "load_html",
+        engine.load_html(id, "<p></p>", TEST_URL),
+        id,
+    );
+    assert_view_not_found(name, "go_back", engine.go_back(id), id);
+    assert_view_not_found(name, "go_forward", engine.go_forward(id), id);
+    assert_view_not_found(name, "reload", engine.reload(id), id);
+    assert_view_not_found(name, "stop", engine.stop(id), id);
+    assert_view_not_found(name, "execute_script", engine.execute_script(id, "1"), id);
+    assert_view_not_found(name, "ping_view", engine.ping_view(id), id);
+    assert_view_not_found(
+        name,
+        "set_user_agent",
+        engine.set_user_agent(id, Some("Mozilla/5.0")),
+        id,
+    );
+    assert_view_not_found(name, "set_muted", engine.set_muted(id, true), id);
+    assert_view_not_found(name, "media_play_pause", engine.media_play_pause(id), id);
+    assert_view_not_found(
+        name,
+        "set_media_policy",
+        engine.set_media_policy(id, MediaPolicy::default()),
+        id,
+    );
+    assert_view_not_found(name, "suspend_view", engine.suspend_view(id), id);
+    assert_view_not_found(name, "resume_view", engine.resume_view(id), id);
+    assert_view_not_found(name, "destroy_view", engine.destroy_view(id), id);
+    assert_view_not_found(
+        name,
+        "get_navigation_state",
+        engine.get_navigation_state(id),
+        id,
+    );
+    assert_view_not_found(
+        name,
+        "find_in_page",
+        engine.find_in_page(id, "x", &FindOptions::default(), true),
+        id,
+    );
+    assert_view_not_found(name, "clear_find", engine.clear_find(id), id);
+    assert_view_not_found(
+        name,
+        "respond_permission",
+        engine.respond_permission(id, "https://example.com", PermissionKind::Camera, true),
+        id,
+    );
+    assert_view_not_found(
+        name,
+        "accept_certificate_exception",
+        engine.accept_certificate_exception(id, "example.com", "ab:cd"),
+        id,
+    );
+}
+
+fn check_history_bounds(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(1);
+    engine.create_view(id).unwrap();
+    let state = engine.get_navigation_state(id).unwrap();
+    assert!(
+        !state.can_go_back && !state.can_go_forward,
+        "{}: fresh view has history",
+        name
+    );
+    assert!(
+        matches!(engine.go_back(id), Err(EngineError::NavigationError(_))),
+        "{}: go_back on fresh view",
+        name
+    );
+    assert!(
+        matches!(engine.go_forward(id), Err(EngineError::NavigationError(_))),
+        "{}: go_forward on fresh view",
+        name
+    );
+}
+
+fn check_suspend_resume(name: &str, engine: &mut dyn BrowserEngine) {
+    let (active, other) = (ViewId(1), ViewId(2));
+    for id in [active, other] {
+        engine.create_view(id).unwrap();
+        engine.load_url(id, TEST_URL).unwrap();
+    }
+    engine.poll_events();
+
+    let before = engine.get_memory_usage().total_bytes;
+    engine.suspend_view(other).unwrap();
+    assert!(
+        engine.get_memory_usage().total_bytes < before,
+        "{}: suspending a view did not release memory",
+        name
+    );
+    assert_eq!(
+        engine.get_navigation_state(other).unwrap().url,
+        TEST_URL,
+        "{}: suspended view lost its URL",
+        name
+    );
+
+    // Suspending one view leaves the others usable
+    engine.load_url(active, TEST_URL).unwrap();
+
+    engine.resume_view(other).unwrap();
+    engine.load_url(other, TEST_URL).unwrap();
+    assert_eq!(engine.get_navigation_state(other).unwrap().url, TEST_URL);
+    engine.destroy_view(other).unwrap();
+}
+
+fn check_memory_monotonicity(name: &str, engine: &mut dyn BrowserEngine) {
+    let mut last = engine.get_memory_usage().total_bytes;
+    for n in 1..=3 {
+        engine.create_view(ViewId(n)).unwrap();
+        engine.load_url(ViewId(n), TEST_URL).unwrap();
+        let now = engine.get_memory_usage().total_bytes;
+        assert!(now > last, "{}: memory did not grow with view {}", name, n);
+        last = now;
+    }
+
+    engine.suspend_view(ViewId(3)).unwrap();
+    let now = engine.get_memory_usage().total_bytes;
+    assert!(now < last, "{}: memory did not shrink on suspend", name);
+    last = now;
+
+    engine.destroy_view(ViewId(2)).unwrap();
+    let now = engine.get_memory_usage().total_bytes;
+    assert!(now < last, "{}: memory did not shrink on destroy", name);
+}
+
+fn check_trim_memory(name: &str, engine: &mut dyn BrowserEngine) {
+    for level in [TrimLevel::Light, TrimLevel::Moderate, TrimLevel::Aggressive] {
+        engine.trim_memory(level).unwrap();
+    }
+
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_url(ViewId(1), TEST_URL).unwrap();
+    let mut last = engine.get_memory_usage().total_bytes;
+    for level in [TrimLevel::Light, TrimLevel::Moderate, TrimLevel::Aggressive] {
+        engine.trim_memory(level).unwrap();
+        let now = engine.get_memory_usage().total_bytes;
+        assert!(
+            now <= last,
+            "{}: trim_memory({:?}) increased memory",
+            name,
+            level
+        );
+        last = now;
+    }
+    assert!(
+        engine.get_navigation_state(ViewId(1)).is_ok(),
+        "{}: trim destroyed a view",
+        name
+    );
+}
+
+fn check_initialize_idempotent(name: &str, engine: &mut dyn BrowserEngine) {
+    engine.create_view(ViewId(1)).unwrap();
+    engine.initialize().unwrap();
+    assert!(
+        engine.get_navigation_state(ViewId(1)).is_ok(),
+        "{}: re-initializing dropped views",
+        name
+    );
+}
+
+fn check_request_interceptor(name: &str, engine: &mut dyn BrowserEngine) {
+    use crate::core::blocker::ContentBlocker;
+    use std::sync::{Arc, Mutex};
+
+    let blocker = Arc::new(Mutex::new(ContentBlocker::new()));
+    engine.set_request_interceptor(Some(blocker)).unwrap();
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_url(ViewId(1), TEST_URL).unwrap();
+    engine
+        .set_request_interceptor(None)
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+}
+
+fn check_site_data(name: &str, engine: &mut dyn BrowserEngine) {
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_url(ViewId(1), TEST_URL).unwrap();
+    let usage = engine.site_data_usage().unwrap();
+    assert!(
+        usage.windows(2).all(|w| w[0].origin < w[1].origin),
+        "{}: site data usage not ordered by origin",
+        name
+    );
+
+    engine
+        .clear_site_data(None, &SiteDataKind::ALL)
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    assert!(
+        engine.list_cookies(None).unwrap().is_empty(),
+        "{}: cookies survived clearing all site data",
+        name
+    );
+    assert!(
+        engine.site_data_usage().unwrap().is_empty(),
+        "{}: storage survived clearing all site data",
+        name
+    );
+    engine.clear_cookies().unwrap();
+}
+
+fn check_capture_view(name: &str, engine: &mut dyn BrowserEngine) {
+    use crate::core::capture::png_dimensions;
+
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_url(ViewId(1), TEST_URL).unwrap();
+
+    let viewport = engine
+        .capture_view(ViewId(1), CaptureOptions::default())
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let (width, height) =
+        png_dimensions(&viewport).unwrap_or_else(|| panic!("{}: capture is not a PNG", name));
+
+    let full_page = CaptureOptions {
+        area: CaptureArea::FullPage,
+        max_width: None,
+    };
+    let (_, page_height) = png_dimensions(&engine.capture_view(ViewId(1), full_page).unwrap())
+        .unwrap_or_else(|| panic!("{}: full-page capture is not a PNG", name));
+    assert!(
+        page_height >= height,
+        "{}: full page is shorter than the viewport",
+        name
+    );
+
+    let (thumb_width, _) =
+        png_dimensions(&engine.capture_view(ViewId(1), CaptureOptions::thumbnail()).unwrap())
+            .unwrap();
+    assert!(
+        thumb_width <= 320 && thumb_width <= width,
+        "{}: thumbnail not scaled down",
+        name
+    );
+
+    assert!(matches!(
+        engine.capture_view(ViewId(99), CaptureOptions::default()),
+        Err(EngineError::ViewNotFound(_))
+    ));
+}
+
+fn check_print_to_pdf(name: &str, engine: &mut dyn BrowserEngine) {
+    let path = std::env::temp_dir().join(format!(
+        "asteroid-conformance-{}-{}.pdf",
+        name,
+        std::process::id()
+    ));
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_url(ViewId(1), TEST_URL).unwrap();
+    engine
+        .print_to_pdf(ViewId(1), &PdfOptions::default(), &path)
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let pdf = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: no PDF written: {}", name, e));
+    let _ = std::fs::remove_file(&path);
+    assert!(pdf.starts_with(b"%PDF"), "{}: output is not a PDF", name);
+
+    assert!(matches!(
+        engine.print_to_pdf(ViewId(99), &PdfOptions::default(), &path),
+        Err(EngineError::ViewNotFound(_))
+    ));
+}
+
+fn check_page_source(name: &str, engine: &mut dyn BrowserEngine) {
+    let html = "<html><body><p>source</p></body></html>";
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_html(ViewId(1), html, TEST_URL).unwrap();
+    assert_eq!(
+        engine.page_source(ViewId(1)).unwrap().as_deref(),
+        Some(html),
+        "{}: load_html document not returned as source",
+        name
+    );
+    assert!(matches!(
+        engine.page_source(ViewId(99)),
+        Err(EngineError::ViewNotFound(_))
+    ));
+}
+
+fn check_zoom(name: &str, engine: &mut dyn BrowserEngine) {
+    engine.create_view(ViewId(1)).unwrap();
+    assert_eq!(engine.get_zoom(ViewId(1)).unwrap(), Zoom::default());
+    engine.poll_events();
+
+    let zoom = Zoom::new(1.5, ZoomMode::TextOnly);
+    engine.set_zoom(ViewId(1), zoom).unwrap();
+    assert_eq!(engine.get_zoom(ViewId(1)).unwrap(), zoom, "{}: zoom not kept", name);
+    let changes: Vec<Zoom> = engine
+        .poll_events()
+        .into_iter()
+        .filter_map(|e| match e {
+            EngineEvent::ZoomChanged(ViewId(1), zoom) => Some(zoom),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(changes, vec![zoom], "{}: expected one ZoomChanged", name);
+
+    // Same zoom again is not a change; out of range levels are clamped
+    engine.set_zoom(ViewId(1), zoom).unwrap();
+    assert!(
+        !engine
+            .poll_events()
+            .iter()
+            .any(|e| matches!(e, EngineEvent::ZoomChanged(..))),
+        "{}: unchanged zoom reported",
+        name
+    );
+    engine
+        .set_zoom(ViewId(1), Zoom { level: 40.0, mode: ZoomMode::FullPage })
+        .unwrap();
+    assert_eq!(engine.get_zoom(ViewId(1)).unwrap().level, Zoom::MAX_LEVEL);
+
+    assert!(matches!(
+        engine.set_zoom(ViewId(99), zoom),
+        Err(EngineError::ViewNotFound(_))
+    ));
+}
+
+fn check_certificate_exception(name: &str, engine: &mut dyn BrowserEngine) {
+    engine.create_view(ViewId(1)).unwrap();
+    engine.load_url(ViewId(1), TEST_URL).unwrap();
+    engine.poll_events();
+
+    // Nothing was blocked, so there is nothing to retry
+    engine
+        .accept_certificate_exception(ViewId(1), "example.com", "ab:cd")
+        .unwrap();
+    assert!(
+        engine.poll_events().is_empty(),
+        "{}: accepting an exception without an error navigated",
+        name
+    );
+}
+
+fn check_internal_scheme(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(1);
+    engine.create_view(id).unwrap();
+    engine.load_url(id, TEST_URL).unwrap();
+    engine.poll_events();
+
+    // asteroid:// is left to the browser's router
+    engine.load_url(id, "asteroid://about").unwrap();
+    let events = engine.poll_events();
+    assert!(
+        events.iter().any(|e| matches!(
+            e,
+            EngineEvent::InternalPageRequested(v, url) if *v == id && url == "asteroid://about"
+        )),
+        "{}: internal navigation not reported: {:?}",
+        name,
+        events
+    );
+    assert!(
+        load_events(events, id).is_empty(),
+        "{}: engine loaded an internal URL itself",
+        name
+    );
+    assert_eq!(engine.get_navigation_state(id).unwrap().url, TEST_URL);
+}
+
+fn check_user_scripts(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(1);
+    let script = engine
+        .add_user_script(
+            "void 0",
+            InjectionTime::Start,
+            ScriptWorld::isolated("conformance"),
+            &["https://example.com/*"],
+        )
+        .unwrap();
+    engine.create_view(id).unwrap();
+    engine.load_url(id, TEST_URL).unwrap();
+    engine.poll_events();
+
+    assert!(
+        engine
+            .execute_script_in_world(id, "conformance", "void 0")
+            .is_ok(),
+        "{}: script world missing from a matching document",
+        name
+    );
+    assert!(
+        engine
+            .execute_script_in_world(id, "missing", "void 0")
+            .is_err(),
+        "{}: script ran in a world that was never created",
+        name
+    );
+    assert_view_not_found(
+        name,
+        "execute_script_in_world",
+        engine.execute_script_in_world(ViewId(99), "conformance", "void 0"),
+        ViewId(99),
+    );
+
+    engine.remove_user_script(script).unwrap();
+    assert!(
+        engine.remove_user_script(script).is_err(),
+        "{}: removed a user script twice",
+        name
+    );
+}
+
+fn check_find_in_page(name: &str, engine: &mut dyn BrowserEngine) {
+    let id = ViewId(1);
+    engine.create_view(id).unwrap();
+    engine.load_url(id, TEST_URL).unwrap();
+    engine.poll_events();
+
+    engine
+        .find_in_page(id, "example", &FindOptions::default(), true)
+        .unwrap();
+    let results: Vec<(usize, usize)> = engine
+        .poll_events()
+        .into_iter()
+        .filter_map(|e| match e {
+            EngineEvent::FindResult(v, current, total) if v == id => Some((current, total)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(results.len(), 1, "{}: expected one FindResult", name);
+    let (current, total) = results[0];
+    assert!(
+        current <= total && (current == 0) == (total == 0),
+        "{}: inconsistent find result {} of {}",
+        name,
+        current,
+        total
+    );
+
+    let regex = FindOptions {
+        regex: true,
+        ..Default::default()
+    };
+    assert!(
+        engine.find_in_page(id, "(", &regex, true).is_err(),
+        "{}: invalid pattern accepted",
+        name
+    );
+    engine.clear_find(id).unwrap();
+}
+
+fn check_capabilities(name: &str, engine: &mut dyn BrowserEngine) {
+    // Whatever an engine claims has to work
+    let caps = engine.capabilities();
+    let id = ViewId(1);
+    engine.create_view(id).unwrap();
+    engine.load_url(id, TEST_URL).unwrap();
+    engine.poll_events();
+
+    if caps.find {
+        engine
+            .find_in_page(id, "example", &FindOptions::default(), true)
+            .unwrap_or_else(|e| panic!("{}: find claimed but failed: {}", name, e));
+    }
+    if caps.script_execution {
+        engine
+            .execute_script(id, "1")
+            .unwrap_or_else(|e| panic!("{}: scripts claimed but failed: {}", name, e));
+    }
+    if caps.capture {
+        engine
+            .capture_view(id, CaptureOptions::default())
+            .unwrap_or_else(|e| panic!("{}: capture claimed but failed: {}", name, e));
+    }
+    if caps.request_interception {
+        engine
+            .set_request_interceptor(None)
+            .unwrap_or_else(|e| panic!("{}: interception claimed but failed: {}", name, e));
+        engine
+            .set_navigation_interceptor(None)
+            .unwrap_or_else(|e| panic!("{}: interception claimed but failed: {}", name, e));
+    }
+    for decoder in caps.video_decoders {
+        engine
+            .set_video_decoder(decoder.clone())
+            .unwrap_or_else(|e| panic!("{}: {:?} claimed but failed: {}", name, decoder, e));
+    }
+    if caps.suspend {
+        engine
+            .suspend_view(id)
+            .and_then(|_| engine.resume_view(id))
+            .unwrap_or_else(|e| panic!("{}: suspend claimed but failed: {}", name, e));
+    }
+}
+
+/// Generate one `#[test]` per check, each run against every engine.
+macro_rules! conformance_tests {
+    ($($check:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $check() {
+                for (name, mut engine) in engines_under_test() {
+                    super::$check(&name, engine.as_mut());
+                }
+            }
+        )*
+    };
+}
+
+mod tests {
+    use super::engines_under_test;
+
+    conformance_tests!(
+        check_view_lifecycle,
+        check_load_event_order,
+        check_view_not_found,
+        check_history_bounds,
+        check_suspend_resume,
+        check_memory_monotonicity,
+        check_trim_memory,
+        check_initialize_idempotent,
+        check_request_interceptor,
+        check_site_data,
+        check_capture_view,
+        check_print_to_pdf,
+        check_page_source,
+        check_zoom,
+        check_certificate_exception,
+        check_internal_scheme,
+        check_user_scripts,
+        check_find_in_page,
+        check_capabilities,
+    );
+
+    #[test]
+    fn test_suite_covers_default_engine() {
+        let names: Vec<String> = engines_under_test().into_iter().map(|(n, _)| n).collect();
+        assert!(names.contains(&"mock".to_string()));
+        #[cfg(feature = "gecko-engine")]
+        assert!(names.contains(&"gecko".to_string()));
+    }
+}
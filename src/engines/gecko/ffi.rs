@@ -4,6 +4,11 @@
 //! Gecko rendering engine via C/C++ bindings. In a full implementation,
 //! these would link to the actual Gecko/SpiderMonkey libraries.
 
+use crate::core::capture::RgbaImage;
+use crate::core::engine::{Cookie, PdfOptions, SiteDataUsage};
+use crate::core::print::blank_pdf;
+use std::path::Path;
+
 /// Gecko runtime handle (opaque pointer in full implementation).
 #[derive(Debug)]
 pub struct GeckoRuntime {
@@ -112,6 +117,372 @@ pub fn gecko_configure_vaapi(enabled: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Set a Gecko preference at runtime.
+pub fn gecko_set_pref(name: &str, value: &str) -> Result<(), String> {
+    log::debug!("Gecko FFI: Setting {} = {}", name, value);
+
+    // Placeholder: actual implementation would call Preferences::SetBool,
+    // SetInt or SetCString depending on the value, as prefs.js does.
+
+    Ok(())
+}
+
+/// Register (or unregister) the network request observer.
+///
+/// When registered, every subresource request is handed to
+/// `GeckoEngine::on_subresource_request` before it hits the network.
+pub fn gecko_set_request_observer(enabled: bool) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Request observer {}",
+        if enabled { "registered" } else { "unregistered" }
+    );
+
+    // Placeholder: actual implementation would add/remove an nsIObserver
+    // for "http-on-modify-request" and cancel or redirect the channel
+    // according to the interceptor's decision.
+
+    Ok(())
+}
+
+/// Register (or unregister) the top-level load observer.
+///
+/// When registered, every document load of a view, including links,
+/// redirects and history steps, is handed to `GeckoEngine::on_navigation`
+/// before its request is sent.
+pub fn gecko_set_navigation_observer(enabled: bool) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Navigation observer {}",
+        if enabled { "registered" } else { "unregistered" }
+    );
+
+    // Placeholder: actual implementation would observe
+    // "document-on-modify-request" in the parent process and redirect the
+    // top-level channel to the URL `on_navigation` returns.
+
+    Ok(())
+}
+
+/// Resolve a pending content permission prompt for a web view.
+pub fn gecko_permission_response(view_id: u64, permission: &str, granted: bool) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: {} {} for view {}",
+        if granted { "Granting" } else { "Denying" },
+        permission,
+        view_id
+    );
+
+    // Placeholder: actual implementation would call allow() or cancel()
+    // on the nsIContentPermissionRequest held for this view.
+
+    Ok(())
+}
+
+/// Set the zoom of a web view.
+///
+/// Text-only zoom scales fonts and leaves images and layout alone.
+pub fn gecko_set_zoom(view_id: u64, level: f64, text_only: bool) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Setting {} zoom of view {} to {:.2}",
+        if text_only { "text" } else { "full" },
+        view_id,
+        level
+    );
+
+    // Placeholder: actual implementation would set fullZoom or textZoom on
+    // the view's BrowsingContext and reset the other one to 1.0.
+
+    Ok(())
+}
+
+/// Set the user agent a web view sends; `None` restores Gecko's own.
+pub fn gecko_set_user_agent(view_id: u64, user_agent: Option<&str>) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Setting user agent of view {} to {:?}",
+        view_id,
+        user_agent
+    );
+
+    // Placeholder: actual implementation would set customUserAgent on the
+    // view's top BrowsingContext (an empty string restores the default),
+    // which the next navigation and its subresources pick up.
+
+    Ok(())
+}
+
+/// Mute or unmute the sound of a web view.
+pub fn gecko_set_muted(view_id: u64, muted: bool) -> Result<(), String> {
+    log::debug!("Gecko FFI: Setting muted of view {} to {}", view_id, muted);
+
+    // Placeholder: actual implementation would call Mute()/Unmute() on the
+    // view's BrowserParent, which silences every media element and audio
+    // context of the page.
+
+    Ok(())
+}
+
+/// Pause or resume the media session of a web view. The new state comes
+/// back through `GeckoEngine::on_media_state_changed`.
+pub fn gecko_media_play_pause(view_id: u64) -> Result<(), String> {
+    log::debug!("Gecko FFI: Toggling media playback of view {}", view_id);
+
+    // Placeholder: actual implementation would send a play or pause
+    // MediaControlKey to the view's MediaController, as hardware media
+    // keys do.
+
+    Ok(())
+}
+
+/// Give a web view its own autoplay and preload policy, with the values
+/// of `media.autoplay.default` and `media.preload.default`/`auto`.
+pub fn gecko_set_media_policy(
+    view_id: u64,
+    autoplay: u32,
+    preload: (u32, u32),
+) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Setting media policy of view {} to autoplay {}, preload {:?}",
+        view_id,
+        autoplay,
+        preload
+    );
+
+    // Placeholder: actual implementation would store the autoplay default
+    // on the view's top BrowsingContext, where AutoplayPolicy looks before
+    // the global pref, and the preload caps HTMLMediaElement reads in
+    // place of the media.preload prefs.
+
+    Ok(())
+}
+
+/// Trust a certificate for a host until the browser exits.
+pub fn gecko_add_certificate_override(host: &str, fingerprint: &str) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Adding certificate override for {} ({})",
+        host,
+        fingerprint
+    );
+
+    // Placeholder: actual implementation would call
+    // nsICertOverrideService::rememberValidityOverride() with
+    // aTemporary = true for the host and certificate.
+
+    Ok(())
+}
+
+/// Expose or hide the `window.asteroid` bridge for a view's document.
+pub fn gecko_set_privileged(view_id: u64, privileged: bool) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: {} internal page bridge for view {}",
+        if privileged { "Enabling" } else { "Disabling" },
+        view_id
+    );
+
+    // Placeholder: actual implementation would register the AsteroidBridge
+    // JSWindowActor for the view's BrowsingContext only, matching
+    // asteroid:// documents.
+
+    Ok(())
+}
+
+/// Register a user script with the runtime.
+///
+/// `world` is the name of the isolated world, or `None` for the page's
+/// own globals.
+pub fn gecko_register_user_script(
+    id: u64,
+    source: &str,
+    run_at: &str,
+    world: Option<&str>,
+    matches: &[String],
+) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Registering user script {} ({} chars, {}, {}, {} patterns)",
+        id,
+        source.len(),
+        run_at,
+        world.unwrap_or("page world"),
+        matches.len()
+    );
+
+    // Placeholder: actual implementation would hand the script to the
+    // AsteroidUserScripts JSWindowActor, which evaluates it on
+    // document-element-inserted / DOMContentLoaded / load, either in the
+    // content window or in a Cu.Sandbox with the window as prototype and
+    // wantXrays, one sandbox per world and document.
+
+    Ok(())
+}
+
+/// Stop injecting a user script.
+pub fn gecko_unregister_user_script(id: u64) -> Result<(), String> {
+    log::debug!("Gecko FFI: Unregistering user script {}", id);
+    Ok(())
+}
+
+/// Execute JavaScript in an isolated world of a web view's document.
+pub fn gecko_execute_in_world(view_id: u64, world: &str, script: &str) -> Result<String, String> {
+    log::debug!(
+        "Gecko FFI: Executing {} chars in world {} of view {}",
+        script.len(),
+        world,
+        view_id
+    );
+
+    // Placeholder: actual implementation would evaluate the script in the
+    // document's sandbox for the world and return the JSON result.
+
+    Ok("null".to_string())
+}
+
+/// Search a web view's document for a pattern, selecting the next (or
+/// previous) match. Returns the 1-based current match and the total.
+pub fn gecko_find_in_page(
+    view_id: u64,
+    pattern: &str,
+    case_sensitive: bool,
+    links_only: bool,
+    highlight_all: bool,
+    forward: bool,
+) -> Result<(usize, usize), String> {
+    log::debug!(
+        "Gecko FFI: Finding /{}/ in view {} (case: {}, links: {}, all: {}, forward: {})",
+        pattern,
+        view_id,
+        case_sensitive,
+        links_only,
+        highlight_all,
+        forward
+    );
+
+    // Placeholder: actual implementation would hand the pattern to the
+    // Finder actor of the browsing context, which runs it over the text
+    // nodes (or the link text with links_only), selects the current match
+    // and highlights the others if highlight_all is set.
+
+    Ok((0, 0))
+}
+
+/// Remove find-in-page selection and highlights from a web view.
+pub fn gecko_clear_find(view_id: u64) -> Result<(), String> {
+    log::debug!("Gecko FFI: Clearing find in view {}", view_id);
+    Ok(())
+}
+
+/// Post a ping to the content process of a web view. Its answer comes
+/// back through `GeckoEngine::on_ping_answered`.
+pub fn gecko_ping_view(view_id: u64) -> Result<(), String> {
+    log::trace!("Gecko FFI: Pinging view {}", view_id);
+
+    // Placeholder: actual implementation would send a message to the
+    // view's BrowserParent, which the content process's main thread
+    // answers once it is free to run it.
+
+    Ok(())
+}
+
+/// Every cookie in the Gecko cookie service, including those kept from
+/// earlier sessions.
+pub fn gecko_list_cookies() -> Result<Vec<Cookie>, String> {
+    log::debug!("Gecko FFI: Listing cookies");
+
+    // Placeholder: actual implementation would walk
+    // nsICookieManager::cookies and convert each nsICookie.
+
+    Ok(Vec::new())
+}
+
+/// Storage each origin keeps in its profile: local storage, IndexedDB and
+/// the HTTP cache.
+pub fn gecko_storage_usage() -> Result<Vec<SiteDataUsage>, String> {
+    log::debug!("Gecko FFI: Reading storage usage");
+
+    // Placeholder: actual implementation would call
+    // nsIQuotaManagerService::GetUsage for local storage and IndexedDB and
+    // visit the cache storage service for per-origin cache sizes.
+
+    Ok(Vec::new())
+}
+
+/// Remove a cookie from the Gecko cookie service.
+pub fn gecko_remove_cookie(domain: &str, name: &str, path: &str) -> Result<(), String> {
+    log::debug!("Gecko FFI: Removing cookie {} for {}{}", name, domain, path);
+
+    // Placeholder: actual implementation would call nsICookieManager::remove()
+
+    Ok(())
+}
+
+/// Clear stored site data, for one origin or for everything.
+///
+/// `kinds` are the data kinds to clear ("cookies", "local-storage",
+/// "indexed-db", "cache").
+pub fn gecko_clear_data(origin: Option<&str>, kinds: &[&str]) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Clearing {:?} for {}",
+        kinds,
+        origin.unwrap_or("all origins")
+    );
+
+    // Placeholder: actual implementation would call
+    // nsIClearDataService::DeleteDataFromPrincipal (or DeleteData when no
+    // origin is given) with the matching CLEAR_* flags.
+
+    Ok(())
+}
+
+/// Render a web view into an RGBA buffer.
+///
+/// With `full_page` the whole scrollable document is drawn instead of
+/// just the visible viewport.
+pub fn gecko_capture_view(view_id: u64, full_page: bool) -> Result<RgbaImage, String> {
+    log::debug!(
+        "Gecko FFI: Capturing {} of view {}",
+        if full_page { "full page" } else { "viewport" },
+        view_id
+    );
+
+    // Placeholder: actual implementation would call
+    // nsIDOMWindowUtils / DrawSnapshot on the view's browsing context
+    // (with the document's scroll size for full-page captures) and read
+    // back the pixels.
+
+    Ok(RgbaImage::filled(1280, 800, [255, 255, 255, 255]))
+}
+
+/// Print a web view to a PDF file.
+pub fn gecko_print_to_pdf(
+    view_id: u64,
+    options: &PdfOptions,
+    title: &str,
+    path: &Path,
+) -> Result<(), String> {
+    log::debug!(
+        "Gecko FFI: Printing view {} to {} ({:?})",
+        view_id,
+        path.display(),
+        options.page_size
+    );
+
+    // Placeholder: actual implementation would create nsIPrintSettings
+    // with kOutputFormatPDF, toFileName = path, paper size, orientation,
+    // margins, printBGColors/printBGImages and header/footer strings from
+    // `options`, then call nsIWebBrowserPrint::Print on the view's window.
+
+    std::fs::write(path, blank_pdf(options, title, 1)).map_err(|e| e.to_string())
+}
+
+/// Read the response body of a web view's current document from the
+/// network cache, if it is still there.
+pub fn gecko_get_document_source(view_id: u64) -> Option<String> {
+    log::debug!("Gecko FFI: Reading document source of view {}", view_id);
+
+    // Placeholder: actual implementation would open the document channel's
+    // cache entry (nsICacheEntry via the view's cache key) and read the
+    // stored body, as view-source: does.
+
+    None
+}
+
 /// Get memory usage for a web view.
 pub fn gecko_get_view_memory(view: &GeckoWebView) -> u64 {
     if !view.active {
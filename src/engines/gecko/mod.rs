@@ -7,11 +7,23 @@
 pub mod prefs;
 pub mod ffi;
 
+use crate::core::blocker::ResourceType;
 use crate::core::engine::{
-    BrowserEngine, EngineError, EngineEvent, EngineResult, MemoryStats,
-    NavigationState, TrimLevel, VideoDecoder, ViewId,
+    BrowserEngine, CaptureArea, CaptureOptions, CertificateError, Cookie, EngineCapabilities, EngineError, EngineEvent, EngineResult, EngineSettings, FindOptions, InjectionTime, LoadError, MediaState, MemoryStats,
+    Navigation, NavigationInterceptor, NavigationState, NewViewRequest, PdfOptions, PermissionKind, RequestDecision, RequestInfo, RequestInterceptor,
+    ScriptWorld, SiteDataKind, SiteDataUsage, TrimLevel, UserScriptId, VideoDecoder, ViewId, Zoom,
+    ZoomMode,
 };
-use std::collections::HashMap;
+use crate::core::certificates::CertificateExceptions;
+use crate::core::find::find_pattern;
+use crate::core::internal_pages::is_internal_url;
+use crate::core::media_policy::MediaPolicy;
+use crate::core::user_scripts::UserScripts;
+use crate::core::permissions::origin_of;
+use crate::core::site_data::SiteDataJar;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
 
 /// State of a Gecko view.
 #[derive(Debug)]
@@ -23,6 +35,19 @@ struct GeckoView {
     can_go_forward: bool,
     progress: f64,
     suspended: bool,
+    /// Document passed to `load_html`, kept for view-source
+    html: Option<String>,
+    zoom: Zoom,
+    /// Navigation stopped by a certificate error, retried once accepted
+    blocked_navigation: Option<CertificateError>,
+    /// Document is an internal page with the `window.asteroid` bridge
+    privileged: bool,
+    /// Agent set with `set_user_agent`, restored on resume
+    user_agent: Option<String>,
+    /// Media playback, as last reported by the media controller
+    media: MediaState,
+    /// Policy set with `set_media_policy`, restored on resume
+    media_policy: Option<MediaPolicy>,
 }
 
 impl GeckoView {
@@ -35,6 +60,13 @@ impl GeckoView {
             can_go_forward: false,
             progress: 0.0,
             suspended: false,
+            html: None,
+            zoom: Zoom::default(),
+            blocked_navigation: None,
+            privileged: false,
+            user_agent: None,
+            media: MediaState::default(),
+            media_policy: None,
         }
     }
 }
@@ -57,6 +89,21 @@ pub struct GeckoEngine {
     pending_events: Vec<EngineEvent>,
     /// Total memory estimate
     memory_usage: u64,
+    /// Hook consulted for every subresource load
+    interceptor: Option<Arc<dyn RequestInterceptor>>,
+    /// Hook consulted before every top-level navigation
+    navigation_interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    /// Permission prompts waiting for an answer
+    pending_permissions: HashSet<(ViewId, String, PermissionKind)>,
+    /// Cookies and storage seen this session, ahead of what Gecko's
+    /// stores report
+    site_data: SiteDataJar,
+    /// Certificates trusted by the user for this session
+    certificate_exceptions: CertificateExceptions,
+    /// Settings last applied through `apply_settings`
+    settings: EngineSettings,
+    /// Scripts injected into matching documents
+    user_scripts: UserScripts,
 }
 
 impl GeckoEngine {
@@ -68,9 +115,343 @@ impl GeckoEngine {
             initialized: false,
             pending_events: Vec::new(),
             memory_usage: 0,
+            interceptor: None,
+            navigation_interceptor: None,
+            pending_permissions: HashSet::new(),
+            site_data: SiteDataJar::new(),
+            certificate_exceptions: CertificateExceptions::new(),
+            settings: EngineSettings::default(),
+            user_scripts: UserScripts::new(),
         }
     }
 
+    /// Origin of the page loaded in a view.
+    fn view_origin(&self, view_id: ViewId) -> EngineResult<String> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        origin_of(&view.url).ok_or_else(|| {
+            EngineError::Other(format!("No origin for {}", view.url))
+        })
+    }
+
+    /// Record a cookie set by the page loaded in a view.
+    ///
+    /// Called from the "cookie-changed" observer.
+    pub fn on_cookie_set(&mut self, view_id: ViewId, cookie: Cookie) -> EngineResult<()> {
+        let origin = self.view_origin(view_id)?;
+        self.site_data.set_cookie(&origin, cookie);
+        Ok(())
+    }
+
+    /// Site data Gecko holds, from this session and earlier ones.
+    fn stored_site_data(&self) -> EngineResult<SiteDataJar> {
+        let cookies = ffi::gecko_list_cookies().map_err(EngineError::Other)?;
+        let usage = ffi::gecko_storage_usage().map_err(EngineError::Other)?;
+        let mut jar = self.site_data.clone();
+        jar.merge_stored(cookies, usage);
+        Ok(jar)
+    }
+
+    /// Record storage written by the page loaded in a view.
+    ///
+    /// Called from the quota manager's usage callbacks.
+    pub fn on_storage_written(
+        &mut self,
+        view_id: ViewId,
+        kind: SiteDataKind,
+        bytes: u64,
+    ) -> EngineResult<()> {
+        let origin = self.view_origin(view_id)?;
+        self.site_data.add_usage(&origin, kind, bytes);
+        Ok(())
+    }
+
+    /// Report a certificate rejected while navigating a view.
+    ///
+    /// Called from the TLS error handler of the document channel. Returns
+    /// `true` when the user already trusted this certificate and the load
+    /// may go on; otherwise the navigation stops and `CertificateError` is
+    /// emitted.
+    pub fn on_certificate_error(
+        &mut self,
+        view_id: ViewId,
+        error: CertificateError,
+    ) -> EngineResult<bool> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if let Some(fingerprint) = error.fingerprint() {
+            if self.certificate_exceptions.allows(&error.host, fingerprint) {
+                return Ok(true);
+            }
+        }
+
+        view.is_loading = false;
+        view.blocked_navigation = Some(error.clone());
+        self.pending_events
+            .push(EngineEvent::CertificateError(view_id, error));
+        Ok(false)
+    }
+
+    /// Report a navigation that failed with a network error.
+    ///
+    /// Called from the docshell's `nsIWebProgressListener::onStateChange`
+    /// when the document channel fails before any content arrives.
+    pub fn on_load_error(&mut self, view_id: ViewId, error: LoadError) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.is_loading = false;
+        view.progress = 0.0;
+        self.pending_events
+            .push(EngineEvent::LoadFailed(view_id, error));
+        Ok(())
+    }
+
+    /// Report that the content process of a view died.
+    ///
+    /// Called from the `ipc:content-shutdown` observer when the process
+    /// went away abnormally (crash, OOM kill). The view stays, and loads
+    /// again in a new process on its next navigation.
+    pub fn on_content_crashed(&mut self, view_id: ViewId, reason: &str) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.is_loading = false;
+        view.progress = 0.0;
+        self.pending_events
+            .push(EngineEvent::ViewCrashed(view_id, reason.to_string()));
+        Ok(())
+    }
+
+    /// Report the answer to a `ping_view`.
+    ///
+    /// Called from the BrowserParent once the content process replied.
+    pub fn on_ping_answered(&mut self, view_id: ViewId) -> EngineResult<()> {
+        if !self.views.contains_key(&view_id) {
+            return Err(EngineError::ViewNotFound(view_id));
+        }
+        self.pending_events.push(EngineEvent::ViewResponsive(view_id));
+        Ok(())
+    }
+
+    /// Report a change in the media playing in a view.
+    ///
+    /// Called from the view's MediaController when playback starts or
+    /// stops and when the page becomes audible or silent.
+    pub fn on_media_state_changed(
+        &mut self,
+        view_id: ViewId,
+        playing: bool,
+        audible: bool,
+    ) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        let state = MediaState {
+            playing,
+            audible,
+            muted: view.media.muted,
+        };
+        if state != view.media {
+            view.media = state;
+            self.pending_events
+                .push(EngineEvent::MediaStateChanged(view_id, state));
+        }
+        Ok(())
+    }
+
+    /// Hand a navigation to an `asteroid://` URL to the browser.
+    ///
+    /// Called from the `asteroid` protocol handler, so links on any page
+    /// end up here instead of being loaded by Gecko.
+    pub fn on_internal_navigation(&mut self, view_id: ViewId, url: &str) -> EngineResult<()> {
+        if !self.views.contains_key(&view_id) {
+            return Err(EngineError::ViewNotFound(view_id));
+        }
+        self.pending_events
+            .push(EngineEvent::InternalPageRequested(view_id, url.to_string()));
+        Ok(())
+    }
+
+    /// Report a message posted through the `window.asteroid` bridge.
+    ///
+    /// Called from the bridge's JSWindowActor with the URL of the sending
+    /// document. Returns `false` and drops the message unless the document
+    /// is the internal page the browser loaded into the view.
+    pub fn on_internal_message(
+        &mut self,
+        view_id: ViewId,
+        document_url: &str,
+        message: serde_json::Value,
+    ) -> EngineResult<bool> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if !view.privileged || view.url != document_url {
+            log::warn!("Dropped bridge message from {} in {}", document_url, view_id);
+            return Ok(false);
+        }
+        self.pending_events
+            .push(EngineEvent::InternalMessage(view_id, message));
+        Ok(true)
+    }
+
+    /// Report a message a user script posted with `asteroid.postMessage`.
+    ///
+    /// Called from the user script actor with the URL of the document and
+    /// the isolated world the script runs in. Returns `false` and drops the
+    /// message unless a script of that world was injected there.
+    pub fn on_user_script_message(
+        &mut self,
+        view_id: ViewId,
+        document_url: &str,
+        world: &str,
+        message: serde_json::Value,
+    ) -> EngineResult<bool> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.url != document_url || !self.user_scripts.has_world(world, document_url) {
+            log::warn!("Dropped message of world {} from {}", world, document_url);
+            return Ok(false);
+        }
+        self.pending_events.push(EngineEvent::UserScriptMessage(
+            view_id,
+            world.to_string(),
+            message,
+        ));
+        Ok(true)
+    }
+
+    /// Report a page asking for a new view.
+    ///
+    /// Called from the `nsIBrowserDOMWindow::createContentWindow`
+    /// implementation for `window.open` and `target=_blank` links. The
+    /// browser decides whether to open it; nothing is created here.
+    pub fn on_new_window(&mut self, opener: ViewId, request: NewViewRequest) -> EngineResult<()> {
+        if !self.views.contains_key(&opener) {
+            return Err(EngineError::ViewNotFound(opener));
+        }
+        self.pending_events
+            .push(EngineEvent::NewViewRequested(opener, request));
+        Ok(())
+    }
+
+    /// Queue a permission prompt for the page loaded in a view.
+    ///
+    /// Called from the `nsIContentPermissionPrompt` implementation; the
+    /// request stays pending until `respond_permission` answers it.
+    pub fn on_permission_request(
+        &mut self,
+        view_id: ViewId,
+        kind: PermissionKind,
+    ) -> EngineResult<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        let origin = origin_of(&view.url).ok_or_else(|| {
+            EngineError::Other(format!("No origin to grant permissions to: {}", view.url))
+        })?;
+
+        if self.pending_permissions.insert((view_id, origin.clone(), kind)) {
+            self.pending_events
+                .push(EngineEvent::PermissionRequested(view_id, origin, kind));
+        }
+        Ok(())
+    }
+
+    /// Route a subresource load through the request interceptor.
+    ///
+    /// Called from the `http-on-modify-request` observer for every request
+    /// that is not a top-level navigation. Returns the URL that should
+    /// actually be fetched, or `None` if the request was cancelled.
+    pub fn on_subresource_request(
+        &mut self,
+        view_id: ViewId,
+        url: &str,
+        resource_type: ResourceType,
+    ) -> EngineResult<Option<String>> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+
+        let interceptor = match self.interceptor {
+            Some(ref interceptor) => interceptor,
+            None => return Ok(Some(url.to_string())),
+        };
+
+        let request = RequestInfo {
+            view_id,
+            url: url.to_string(),
+            initiator: view.url.clone(),
+            resource_type,
+        };
+
+        match interceptor.intercept(&request) {
+            RequestDecision::Allow => Ok(Some(request.url)),
+            RequestDecision::Block => {
+                self.pending_events
+                    .push(EngineEvent::RequestBlocked(view_id, request.url));
+                Ok(None)
+            }
+            RequestDecision::Redirect(target) => {
+                log::debug!("Redirecting {} -> {}", request.url, target);
+                Ok(Some(target))
+            }
+        }
+    }
+
+    /// Route a top-level navigation through the navigation interceptor.
+    ///
+    /// Called from the top-level load observer for links, forms, redirects
+    /// and history steps, and by `load_url` for the browser's own loads.
+    /// Sets the agent the interceptor picks before the request goes out.
+    /// Returns the URL that should actually be loaded.
+    pub fn on_navigation(&mut self, view_id: ViewId, url: &str) -> EngineResult<String> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        // Prompts of the page being left can no longer be answered
+        self.pending_permissions.retain(|(id, _, _)| *id != view_id);
+        let interceptor = match self.navigation_interceptor {
+            Some(ref interceptor) => interceptor,
+            None => return Ok(url.to_string()),
+        };
+
+        let mut navigation = Navigation {
+            view_id,
+            url: url.to_string(),
+            user_agent: view.user_agent.clone(),
+        };
+        interceptor.intercept_navigation(&mut navigation);
+        if navigation.user_agent != view.user_agent {
+            ffi::gecko_set_user_agent(view_id.0, navigation.user_agent.as_deref())
+                .map_err(EngineError::Other)?;
+            view.user_agent = navigation.user_agent.clone();
+        }
+        if navigation.url != url {
+            log::debug!("Redirecting navigation {} -> {}", url, navigation.url);
+            self.pending_events.push(EngineEvent::NavigationRedirected(
+                view_id,
+                url.to_string(),
+                navigation.url.clone(),
+            ));
+        }
+        Ok(navigation.url)
+    }
+
     /// Probe for VA-API hardware acceleration support.
     fn probe_vaapi(&self) -> bool {
         // Check for VA-API libraries on the system
@@ -178,14 +559,21 @@ impl BrowserEngine for GeckoEngine {
     }
 
     fn load_url(&mut self, view_id: ViewId, url: &str) -> EngineResult<()> {
+        if is_internal_url(url) {
+            return self.on_internal_navigation(view_id, url);
+        }
+        let url = &self.on_navigation(view_id, url)?;
         let view = self
             .views
             .get_mut(&view_id)
             .ok_or(EngineError::ViewNotFound(view_id))?;
 
         view.url = url.to_string();
+        view.privileged = false;
         view.is_loading = true;
         view.progress = 0.0;
+        view.html = None;
+        view.blocked_navigation = None;
 
         self.pending_events
             .push(EngineEvent::LoadStarted(view_id));
@@ -218,12 +606,23 @@ impl BrowserEngine for GeckoEngine {
         view.url = base_url.to_string();
         view.is_loading = false;
         view.progress = 1.0;
+        view.html = Some(html.to_string());
+        self.pending_permissions.retain(|(id, _, _)| *id != view_id);
+        // Only documents the browser itself loads as internal pages get
+        // the bridge
+        view.privileged = is_internal_url(base_url);
+        ffi::gecko_set_privileged(view_id.0, view.privileged).map_err(EngineError::Other)?;
 
         log::debug!(
             "Loaded {} bytes of HTML into {}",
             html.len(),
             view_id
         );
+
+        // Issue the document's subresource loads
+        for (url, resource_type) in extract_subresources(html) {
+            self.on_subresource_request(view_id, &url, resource_type)?;
+        }
         Ok(())
     }
 
@@ -280,6 +679,56 @@ impl BrowserEngine for GeckoEngine {
         Ok(())
     }
 
+    fn set_user_agent(&mut self, view_id: ViewId, user_agent: Option<&str>) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        ffi::gecko_set_user_agent(view_id.0, user_agent).map_err(EngineError::Other)?;
+        view.user_agent = user_agent.map(str::to_string);
+        Ok(())
+    }
+
+    fn set_muted(&mut self, view_id: ViewId, muted: bool) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        ffi::gecko_set_muted(view_id.0, muted).map_err(EngineError::Other)?;
+        if view.media.muted != muted {
+            view.media.muted = muted;
+            self.pending_events
+                .push(EngineEvent::MediaStateChanged(view_id, view.media));
+        }
+        Ok(())
+    }
+
+    fn media_play_pause(&mut self, view_id: ViewId) -> EngineResult<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+        ffi::gecko_media_play_pause(view_id.0).map_err(EngineError::Other)
+    }
+
+    fn set_media_policy(&mut self, view_id: ViewId, policy: MediaPolicy) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        ffi::gecko_set_media_policy(
+            view_id.0,
+            prefs::autoplay_pref(policy.autoplay),
+            prefs::preload_prefs(policy.preload),
+        )
+        .map_err(EngineError::Other)?;
+        view.media_policy = Some(policy);
+        Ok(())
+    }
+
     fn execute_script(
         &mut self,
         view_id: ViewId,
@@ -299,6 +748,75 @@ impl BrowserEngine for GeckoEngine {
         Ok(serde_json::Value::Null)
     }
 
+    fn add_user_script(
+        &mut self,
+        script: &str,
+        time: InjectionTime,
+        world: ScriptWorld,
+        url_matches: &[&str],
+    ) -> EngineResult<UserScriptId> {
+        let id = self.user_scripts.add(script, time, world, url_matches);
+        let Some(registered) = self.user_scripts.get(id) else {
+            return Err(EngineError::Other(format!("{} was not registered", id)));
+        };
+        let run_at = match time {
+            InjectionTime::Start => "document_start",
+            InjectionTime::End => "document_end",
+            InjectionTime::Idle => "document_idle",
+        };
+        let world_name = match registered.world {
+            ScriptWorld::Page => None,
+            ScriptWorld::Isolated(ref name) => Some(name.as_str()),
+        };
+        let result =
+            ffi::gecko_register_user_script(id.0, script, run_at, world_name, &registered.url_matches);
+        if let Err(e) = result {
+            self.user_scripts.remove(id);
+            return Err(EngineError::Other(e));
+        }
+        Ok(id)
+    }
+
+    fn remove_user_script(&mut self, id: UserScriptId) -> EngineResult<()> {
+        if !self.user_scripts.remove(id) {
+            return Err(EngineError::Other(format!("Unknown user script: {}", id)));
+        }
+        ffi::gecko_unregister_user_script(id.0).map_err(EngineError::Other)
+    }
+
+    fn execute_script_in_world(
+        &mut self,
+        view_id: ViewId,
+        world: &str,
+        script: &str,
+    ) -> EngineResult<serde_json::Value> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if !self.user_scripts.has_world(world, &view.url) {
+            return Err(EngineError::Other(format!(
+                "No {} world in {}",
+                world, view_id
+            )));
+        }
+        let result =
+            ffi::gecko_execute_in_world(view_id.0, world, script).map_err(EngineError::Other)?;
+        serde_json::from_str(&result)
+            .map_err(|e| EngineError::Other(format!("Invalid script result: {}", e)))
+    }
+
+    fn ping_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+        ffi::gecko_ping_view(view_id.0).map_err(EngineError::Other)
+    }
+
     fn suspend_view(&mut self, view_id: ViewId) -> EngineResult<()> {
         let view = self
             .views
@@ -324,6 +842,34 @@ impl BrowserEngine for GeckoEngine {
             .ok_or(EngineError::ViewNotFound(view_id))?;
 
         view.suspended = false;
+        if !view.zoom.is_default() {
+            let zoom = view.zoom;
+            if let Err(e) =
+                ffi::gecko_set_zoom(view_id.0, zoom.level, zoom.mode == ZoomMode::TextOnly)
+            {
+                log::warn!("Failed to restore zoom of {}: {}", view_id, e);
+            }
+        }
+        if view.user_agent.is_some() {
+            if let Err(e) = ffi::gecko_set_user_agent(view_id.0, view.user_agent.as_deref()) {
+                log::warn!("Failed to restore user agent of {}: {}", view_id, e);
+            }
+        }
+        if view.media.muted {
+            if let Err(e) = ffi::gecko_set_muted(view_id.0, true) {
+                log::warn!("Failed to keep {} muted: {}", view_id, e);
+            }
+        }
+        if let Some(policy) = view.media_policy {
+            let result = ffi::gecko_set_media_policy(
+                view_id.0,
+                prefs::autoplay_pref(policy.autoplay),
+                prefs::preload_prefs(policy.preload),
+            );
+            if let Err(e) = result {
+                log::warn!("Failed to restore media policy of {}: {}", view_id, e);
+            }
+        }
         self.memory_usage = self
             .views
             .values()
@@ -338,6 +884,7 @@ impl BrowserEngine for GeckoEngine {
         self.views
             .remove(&view_id)
             .ok_or(EngineError::ViewNotFound(view_id))?;
+        self.pending_permissions.retain(|(id, _, _)| *id != view_id);
 
         self.memory_usage = self
             .views
@@ -412,16 +959,29 @@ impl BrowserEngine for GeckoEngine {
         })
     }
 
-    fn find_in_page(&mut self, view_id: ViewId, query: &str, forward: bool) -> EngineResult<()> {
+    fn find_in_page(
+        &mut self,
+        view_id: ViewId,
+        query: &str,
+        options: &FindOptions,
+        forward: bool,
+    ) -> EngineResult<()> {
         if !self.views.contains_key(&view_id) {
             return Err(EngineError::ViewNotFound(view_id));
         }
-        log::debug!(
-            "Find in page {}: '{}' (forward: {})",
-            view_id,
-            query,
-            forward
-        );
+        // Compiled here so every engine agrees on what matches
+        let pattern = find_pattern(query, options).map_err(EngineError::Other)?;
+        let (current, total) = ffi::gecko_find_in_page(
+            view_id.0,
+            pattern.as_str(),
+            options.case_sensitive,
+            options.links_only,
+            options.highlight_all,
+            forward,
+        )
+        .map_err(EngineError::Other)?;
+        self.pending_events
+            .push(EngineEvent::FindResult(view_id, current, total));
         Ok(())
     }
 
@@ -429,21 +989,244 @@ impl BrowserEngine for GeckoEngine {
         if !self.views.contains_key(&view_id) {
             return Err(EngineError::ViewNotFound(view_id));
         }
+        ffi::gecko_clear_find(view_id.0).map_err(EngineError::Other)
+    }
+
+    fn apply_settings(&mut self, settings: &EngineSettings) -> EngineResult<()> {
+        let prefs = prefs::get_settings_prefs(settings);
+        log::info!("Applying {} Gecko settings preferences", prefs.len());
+        for (key, value) in &prefs {
+            ffi::gecko_set_pref(key, value).map_err(EngineError::Other)?;
+        }
+        self.settings = settings.clone();
+        Ok(())
+    }
+
+    fn set_request_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn RequestInterceptor>>,
+    ) -> EngineResult<()> {
+        ffi::gecko_set_request_observer(interceptor.is_some())
+            .map_err(EngineError::Other)?;
+        self.interceptor = interceptor;
+        Ok(())
+    }
+
+    fn set_navigation_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    ) -> EngineResult<()> {
+        ffi::gecko_set_navigation_observer(interceptor.is_some())
+            .map_err(EngineError::Other)?;
+        self.navigation_interceptor = interceptor;
+        Ok(())
+    }
+
+    fn respond_permission(
+        &mut self,
+        view_id: ViewId,
+        origin: &str,
+        kind: PermissionKind,
+        granted: bool,
+    ) -> EngineResult<()> {
+        if !self.views.contains_key(&view_id) {
+            return Err(EngineError::ViewNotFound(view_id));
+        }
+        if !self
+            .pending_permissions
+            .remove(&(view_id, origin.to_string(), kind))
+        {
+            return Err(EngineError::Other(format!(
+                "No pending {} request from {}",
+                kind.as_str(),
+                origin
+            )));
+        }
+        ffi::gecko_permission_response(view_id.0, kind.as_str(), granted)
+            .map_err(EngineError::Other)
+    }
+
+    fn list_cookies(&self, origin: Option<&str>) -> EngineResult<Vec<Cookie>> {
+        Ok(self.stored_site_data()?.cookies(origin))
+    }
+
+    fn delete_cookie(&mut self, cookie: &Cookie) -> EngineResult<()> {
+        ffi::gecko_remove_cookie(&cookie.domain, &cookie.name, &cookie.path)
+            .map_err(EngineError::Other)?;
+        self.site_data.delete_cookie(cookie);
+        Ok(())
+    }
+
+    fn clear_cookies(&mut self) -> EngineResult<()> {
+        self.clear_site_data(None, &[SiteDataKind::Cookies])
+    }
+
+    fn clear_site_data(&mut self, origin: Option<&str>, kinds: &[SiteDataKind]) -> EngineResult<()> {
+        let flags: Vec<&str> = kinds
+            .iter()
+            .map(|kind| match kind {
+                SiteDataKind::Cookies => "cookies",
+                SiteDataKind::LocalStorage => "local-storage",
+                SiteDataKind::IndexedDb => "indexed-db",
+                SiteDataKind::Cache => "cache",
+            })
+            .collect();
+        ffi::gecko_clear_data(origin, &flags).map_err(EngineError::Other)?;
+        self.site_data.clear(origin, kinds);
         Ok(())
     }
 
+    fn site_data_usage(&self) -> EngineResult<Vec<SiteDataUsage>> {
+        Ok(self.stored_site_data()?.usage())
+    }
+
+    fn capture_view(&self, view_id: ViewId, options: CaptureOptions) -> EngineResult<Vec<u8>> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+        let full_page = options.area == CaptureArea::FullPage;
+        ffi::gecko_capture_view(view_id.0, full_page)
+            .and_then(|image| image.into_png(&options))
+            .map_err(EngineError::Other)
+    }
+
+    fn set_zoom(&mut self, view_id: ViewId, zoom: Zoom) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        let zoom = Zoom::new(zoom.level, zoom.mode);
+        if zoom == view.zoom {
+            return Ok(());
+        }
+
+        // Suspended views get their zoom back on resume
+        if !view.suspended {
+            ffi::gecko_set_zoom(view_id.0, zoom.level, zoom.mode == ZoomMode::TextOnly)
+                .map_err(EngineError::Other)?;
+        }
+        view.zoom = zoom;
+        self.pending_events
+            .push(EngineEvent::ZoomChanged(view_id, zoom));
+        Ok(())
+    }
+
+    fn get_zoom(&self, view_id: ViewId) -> EngineResult<Zoom> {
+        self.views
+            .get(&view_id)
+            .map(|view| view.zoom)
+            .ok_or(EngineError::ViewNotFound(view_id))
+    }
+
+    fn accept_certificate_exception(
+        &mut self,
+        view_id: ViewId,
+        host: &str,
+        fingerprint: &str,
+    ) -> EngineResult<()> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+
+        ffi::gecko_add_certificate_override(host, fingerprint).map_err(EngineError::Other)?;
+        self.certificate_exceptions.add(host, fingerprint);
+
+        let retry = view
+            .blocked_navigation
+            .take_if(|error| error.host.eq_ignore_ascii_case(host));
+        match retry {
+            Some(error) => self.load_url(view_id, &error.url),
+            None => Ok(()),
+        }
+    }
+
+    fn page_source(&self, view_id: ViewId) -> EngineResult<Option<String>> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        match view.html {
+            Some(ref html) => Ok(Some(html.clone())),
+            None => Ok(ffi::gecko_get_document_source(view_id.0)),
+        }
+    }
+
+    fn print_to_pdf(&mut self, view_id: ViewId, options: &PdfOptions, path: &Path) -> EngineResult<()> {
+        let view = self
+            .views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+        ffi::gecko_print_to_pdf(view_id.0, options, &view.title, path).map_err(EngineError::Other)
+    }
+
     fn engine_info(&self) -> (String, String) {
         ("Gecko".to_string(), "124.0".to_string())
     }
 
+    fn capabilities(&self) -> EngineCapabilities {
+        EngineCapabilities::all()
+    }
+
     fn poll_events(&mut self) -> Vec<EngineEvent> {
         std::mem::take(&mut self.pending_events)
     }
 }
 
+/// Find absolute subresource URLs referenced by an HTML document.
+fn extract_subresources(html: &str) -> Vec<(String, ResourceType)> {
+    let mut resources = Vec::new();
+    let lower = html.to_ascii_lowercase();
+
+    for (start, _) in lower.match_indices('<') {
+        let tag_end = match lower[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = &lower[start + 1..tag_end];
+        let name = tag.split_whitespace().next().unwrap_or("");
+
+        let (attr, resource_type) = match name {
+            "script" => ("src=", ResourceType::Script),
+            "img" => ("src=", ResourceType::Image),
+            "iframe" => ("src=", ResourceType::SubDocument),
+            "video" | "audio" | "source" => ("src=", ResourceType::Media),
+            "link" if tag.contains("stylesheet") => ("href=", ResourceType::Stylesheet),
+            _ => continue,
+        };
+
+        if let Some(pos) = tag.find(attr) {
+            // Read the value from the original (case-preserving) document
+            let value_start = start + 1 + pos + attr.len();
+            let rest = &html[value_start..tag_end];
+            let quote = rest.chars().next().unwrap_or(' ');
+            let value = if quote == '"' || quote == '\'' {
+                rest[1..].split(quote).next().unwrap_or("")
+            } else {
+                rest.split_whitespace().next().unwrap_or("")
+            };
+
+            if value.starts_with("http://") || value.starts_with("https://") {
+                resources.push((value.to_string(), resource_type));
+            }
+        }
+    }
+
+    resources
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::blocker::ContentBlocker;
+    use std::sync::Mutex;
 
     #[test]
     fn test_gecko_engine_creation() {
@@ -501,4 +1284,428 @@ mod tests {
         let after = engine.get_memory_usage().total_bytes;
         assert!(after < before);
     }
+
+    #[test]
+    fn test_extract_subresources() {
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="https://cdn.example.com/site.css">
+            <SCRIPT src='https://ads.example.net/Ad.js'></SCRIPT>
+        </head><body>
+            <img src="https://example.com/logo.png" alt="logo">
+            <img src="data:image/gif;base64,R0lGOD">
+            <img src="relative.png">
+        </body></html>"#;
+
+        let resources = extract_subresources(html);
+        assert_eq!(resources.len(), 3);
+        assert_eq!(resources[0].1, ResourceType::Stylesheet);
+        assert_eq!(resources[1], ("https://ads.example.net/Ad.js".to_string(), ResourceType::Script));
+        assert_eq!(resources[2].1, ResourceType::Image);
+    }
+
+    #[test]
+    fn test_gecko_routes_subresources_through_interceptor() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let blocker = Arc::new(Mutex::new(ContentBlocker::new()));
+        engine.set_request_interceptor(Some(blocker.clone())).unwrap();
+
+        let view_id = ViewId(1);
+        engine.create_view(view_id).unwrap();
+        let html = include_str!("../../../test-pages/ads.html");
+        engine.load_html(view_id, html, "https://example.com/ads.html").unwrap();
+
+        let blocked: Vec<String> = engine
+            .poll_events()
+            .into_iter()
+            .filter_map(|e| match e {
+                EngineEvent::RequestBlocked(_, url) => Some(url),
+                _ => None,
+            })
+            .collect();
+        assert!(blocked.iter().any(|url| url.contains("doubleclick.net")));
+        assert!(blocker.lock().unwrap().stats().total_blocked > 0);
+
+        // Removing the interceptor lets everything through again
+        engine.set_request_interceptor(None).unwrap();
+        let url = engine
+            .on_subresource_request(view_id, "https://doubleclick.net/ad.js", ResourceType::Script)
+            .unwrap();
+        assert!(url.is_some());
+    }
+
+    #[test]
+    fn test_gecko_routes_navigations_through_interceptor() {
+        use crate::core::https_only::HttpsOnlyPolicy;
+
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let view_id = ViewId(1);
+        engine.create_view(view_id).unwrap();
+        let policy = Arc::new(HttpsOnlyPolicy::new(true, None));
+        engine.set_navigation_interceptor(Some(policy)).unwrap();
+
+        let url = engine.on_navigation(view_id, "http://example.com/next").unwrap();
+        assert_eq!(url, "https://example.com/next");
+        assert!(engine.poll_events().iter().any(|e| matches!(
+            e,
+            EngineEvent::NavigationRedirected(_, original, _) if original == "http://example.com/next"
+        )));
+        assert_eq!(
+            engine.on_navigation(view_id, "http://localhost/").unwrap(),
+            "http://localhost/"
+        );
+        assert!(engine.on_navigation(ViewId(9), "http://example.com/").is_err());
+
+        // The browser's own loads take the same route
+        engine.load_url(view_id, "http://example.com/").unwrap();
+        assert_eq!(
+            engine.get_navigation_state(view_id).unwrap().url,
+            "https://example.com/"
+        );
+        assert!(engine.poll_events().iter().any(|e| matches!(
+            e,
+            EngineEvent::UrlChanged(_, url) if url == "https://example.com/"
+        )));
+    }
+
+    #[test]
+    fn test_gecko_load_url_uses_site_user_agent() {
+        use crate::core::user_agent::{UserAgentPreset, UserAgentRule, UserAgents};
+
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let view_id = ViewId(1);
+        engine.create_view(view_id).unwrap();
+        let rules = [UserAgentRule {
+            site: "light.test".to_string(),
+            user_agent: "mobile".to_string(),
+        }];
+        let agents = Arc::new(UserAgents::new("", &rules));
+        engine.set_navigation_interceptor(Some(agents)).unwrap();
+
+        engine.load_url(view_id, "https://example.com/").unwrap();
+        assert_eq!(engine.views[&view_id].user_agent, None);
+        // Typed into the address bar, with no earlier page on the site
+        engine.load_url(view_id, "https://light.test/").unwrap();
+        assert_eq!(
+            engine.views[&view_id].user_agent.as_deref(),
+            Some(UserAgentPreset::Mobile.user_agent())
+        );
+        engine.load_url(view_id, "https://example.com/").unwrap();
+        assert_eq!(engine.views[&view_id].user_agent, None);
+    }
+
+    #[test]
+    fn test_gecko_permission_prompt() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        assert!(engine.on_permission_request(id, PermissionKind::Camera).is_err());
+
+        engine.load_url(id, "https://meet.test/room").unwrap();
+        engine.poll_events();
+        engine.on_permission_request(id, PermissionKind::Camera).unwrap();
+        let events = engine.poll_events();
+        assert!(matches!(
+            events.as_slice(),
+            [EngineEvent::PermissionRequested(_, origin, PermissionKind::Camera)] if origin == "https://meet.test"
+        ));
+
+        engine
+            .respond_permission(id, "https://meet.test", PermissionKind::Camera, true)
+            .unwrap();
+        // Answered requests are no longer pending
+        assert!(engine
+            .respond_permission(id, "https://meet.test", PermissionKind::Camera, true)
+            .is_err());
+
+        // A request left unanswered does not hide the same one from the
+        // next page
+        engine.on_permission_request(id, PermissionKind::Camera).unwrap();
+        engine.load_url(id, "https://meet.test/other").unwrap();
+        engine.poll_events();
+        engine.on_permission_request(id, PermissionKind::Camera).unwrap();
+        assert!(engine.poll_events().iter().any(|e| matches!(
+            e,
+            EngineEvent::PermissionRequested(_, _, PermissionKind::Camera)
+        )));
+    }
+
+    #[test]
+    fn test_gecko_new_window_request() {
+        use crate::core::engine::WindowDisposition;
+
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.poll_events();
+
+        let request = NewViewRequest {
+            url: "https://example.com/popup".to_string(),
+            user_gesture: false,
+            disposition: WindowDisposition::Popup,
+        };
+        assert!(engine.on_new_window(ViewId(9), request.clone()).is_err());
+        engine.on_new_window(id, request.clone()).unwrap();
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::NewViewRequested(opener, r)] if *opener == id && *r == request
+        ));
+    }
+
+    #[test]
+    fn test_gecko_load_error() {
+        use crate::core::engine::LoadErrorKind;
+
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://no-tls.test/").unwrap();
+        engine.poll_events();
+
+        let error = LoadError {
+            url: "https://no-tls.test/".to_string(),
+            kind: LoadErrorKind::SecureConnectionFailed,
+        };
+        engine.on_load_error(id, error.clone()).unwrap();
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::LoadFailed(_, e)] if *e == error
+        ));
+        assert!(!engine.get_navigation_state(id).unwrap().is_loading);
+        assert!(engine.on_load_error(ViewId(9), error).is_err());
+    }
+
+    #[test]
+    fn test_gecko_crash_and_ping() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://heavy.test/").unwrap();
+        engine.poll_events();
+
+        engine.ping_view(id).unwrap();
+        engine.on_ping_answered(id).unwrap();
+        engine.on_content_crashed(id, "oom").unwrap();
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::ViewResponsive(_), EngineEvent::ViewCrashed(_, reason)] if reason == "oom"
+        ));
+        assert!(!engine.get_navigation_state(id).unwrap().is_loading);
+
+        engine.suspend_view(id).unwrap();
+        assert!(engine.ping_view(id).is_err());
+        assert!(engine.on_content_crashed(ViewId(9), "oom").is_err());
+    }
+
+    #[test]
+    fn test_gecko_media_state() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.poll_events();
+
+        engine.on_media_state_changed(id, true, true).unwrap();
+        engine.on_media_state_changed(id, true, true).unwrap();
+        engine.set_muted(id, true).unwrap();
+        engine.media_play_pause(id).unwrap();
+        engine.on_media_state_changed(id, false, true).unwrap();
+        let states: Vec<MediaState> = engine
+            .poll_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                EngineEvent::MediaStateChanged(_, state) => Some(state),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(states.len(), 3);
+        assert!(states[1].muted && states[1].is_playing_audio());
+        assert!(states[2].muted && !states[2].playing);
+
+        engine.set_media_policy(id, MediaPolicy::default()).unwrap();
+        engine.suspend_view(id).unwrap();
+        engine.resume_view(id).unwrap();
+        assert!(engine.set_muted(ViewId(9), true).is_err());
+        assert!(engine.set_media_policy(ViewId(9), MediaPolicy::default()).is_err());
+        engine.suspend_view(id).unwrap();
+        assert!(engine.media_play_pause(id).is_err());
+    }
+
+    #[test]
+    fn test_gecko_internal_pages() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://example.com/").unwrap();
+        engine.poll_events();
+
+        // Internal URLs are left to the browser
+        engine.load_url(id, "asteroid://settings").unwrap();
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::InternalPageRequested(_, url)] if url == "asteroid://settings"
+        ));
+        assert_eq!(engine.get_navigation_state(id).unwrap().url, "https://example.com/");
+
+        // Web content cannot use the bridge
+        let message = serde_json::json!({ "type": "save" });
+        assert!(!engine
+            .on_internal_message(id, "https://example.com/", message.clone())
+            .unwrap());
+
+        engine
+            .load_html(id, "<h1>Settings</h1>", "asteroid://settings")
+            .unwrap();
+        engine.poll_events();
+        assert!(!engine
+            .on_internal_message(id, "https://example.com/", message.clone())
+            .unwrap());
+        assert!(engine
+            .on_internal_message(id, "asteroid://settings", message)
+            .unwrap());
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::InternalMessage(_, _)]
+        ));
+    }
+
+    #[test]
+    fn test_gecko_user_scripts() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://example.com/").unwrap();
+        engine.poll_events();
+
+        let script = engine
+            .add_user_script(
+                "hide()",
+                InjectionTime::Start,
+                ScriptWorld::isolated("cosmetic"),
+                &["https://*"],
+            )
+            .unwrap();
+        assert!(engine
+            .execute_script_in_world(id, "cosmetic", "count()")
+            .is_ok());
+        assert!(engine.execute_script_in_world(id, "other", "count()").is_err());
+
+        // Only the world the script was injected in can post
+        let message = serde_json::json!({ "type": "hidden", "count": 3 });
+        assert!(!engine
+            .on_user_script_message(id, "https://example.com/", "other", message.clone())
+            .unwrap());
+        assert!(engine
+            .on_user_script_message(id, "https://example.com/", "cosmetic", message.clone())
+            .unwrap());
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::UserScriptMessage(_, world, _)] if world == "cosmetic"
+        ));
+
+        engine.remove_user_script(script).unwrap();
+        assert!(engine.remove_user_script(script).is_err());
+        assert!(!engine
+            .on_user_script_message(id, "https://example.com/", "cosmetic", message)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_gecko_certificate_exception() {
+        use crate::core::engine::{CertificateErrorKind, CertificateInfo};
+
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://self-signed.test/").unwrap();
+        engine.poll_events();
+
+        let error = CertificateError {
+            url: "https://self-signed.test/".to_string(),
+            host: "self-signed.test".to_string(),
+            kind: CertificateErrorKind::SelfSigned,
+            chain: vec![CertificateInfo {
+                subject: "CN=self-signed.test".to_string(),
+                issuer: "CN=self-signed.test".to_string(),
+                not_before: 0,
+                not_after: 0,
+                fingerprint: "aa:bb".to_string(),
+            }],
+        };
+        assert!(!engine.on_certificate_error(id, error.clone()).unwrap());
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::CertificateError(_, e)] if *e == error
+        ));
+
+        engine
+            .accept_certificate_exception(id, "self-signed.test", "AA:BB")
+            .unwrap();
+        assert!(matches!(
+            engine.poll_events().first(),
+            Some(EngineEvent::LoadStarted(_))
+        ));
+        // The accepted certificate no longer stops loads
+        assert!(engine.on_certificate_error(id, error).unwrap());
+        assert!(engine.poll_events().is_empty());
+    }
+
+    #[test]
+    fn test_gecko_site_data() {
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://shop.test/cart").unwrap();
+
+        let cookie = Cookie {
+            name: "cart".to_string(),
+            value: "42".to_string(),
+            domain: "shop.test".to_string(),
+            path: "/".to_string(),
+            secure: true,
+            http_only: true,
+            expires: None,
+        };
+        engine.on_cookie_set(id, cookie.clone()).unwrap();
+        engine
+            .on_storage_written(id, SiteDataKind::IndexedDb, 8192)
+            .unwrap();
+
+        let usage = engine.site_data_usage().unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].origin, "https://shop.test");
+        assert_eq!(usage[0].cookies, 1);
+        assert_eq!(usage[0].indexed_db_bytes, 8192);
+
+        engine.delete_cookie(&cookie).unwrap();
+        assert!(engine.list_cookies(Some("https://shop.test")).unwrap().is_empty());
+        engine.clear_site_data(Some("https://shop.test"), &SiteDataKind::ALL).unwrap();
+        assert!(engine.site_data_usage().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gecko_capture_view() {
+        use crate::core::capture::png_dimensions;
+
+        let mut engine = GeckoEngine::new();
+        engine.initialize().unwrap();
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+
+        let png = engine.capture_view(id, CaptureOptions::thumbnail()).unwrap();
+        assert_eq!(png_dimensions(&png), Some((320, 200)));
+
+        engine.suspend_view(id).unwrap();
+        assert!(engine.capture_view(id, CaptureOptions::default()).is_err());
+    }
 }
@@ -3,6 +3,10 @@
 //! Defines optimized preference values for memory efficiency,
 //! performance, and privacy.
 
+use crate::core::config::NetworkConfig;
+use crate::core::engine::EngineSettings;
+use crate::core::media_policy::{AutoplayPolicy, MediaPolicy, PreloadPolicy};
+use crate::core::proxy::{ProxyMode, ProxyServers};
 use std::collections::HashMap;
 
 /// Get memory optimization preferences.
@@ -116,6 +120,124 @@ pub fn get_privacy_prefs(send_dnt: bool) -> HashMap<String, String> {
     prefs
 }
 
+/// Get the preferences controlled by user settings.
+pub fn get_settings_prefs(settings: &EngineSettings) -> HashMap<String, String> {
+    let mut prefs = get_privacy_prefs(settings.send_dnt);
+
+    for key in [
+        "privacy.trackingprotection.enabled",
+        "privacy.trackingprotection.socialtracking.enabled",
+        "privacy.trackingprotection.cryptomining.enabled",
+        "privacy.trackingprotection.fingerprinting.enabled",
+    ] {
+        prefs.insert(key.into(), settings.tracking_protection.to_string());
+    }
+
+    // Cache capacities are in KB
+    prefs.insert(
+        "browser.cache.disk.capacity".into(),
+        (settings.cache_size_mb * 1024).to_string(),
+    );
+    prefs.insert(
+        "browser.cache.memory.capacity".into(),
+        (settings.memory_cache_mb * 1024).to_string(),
+    );
+    prefs.extend(get_proxy_prefs(&settings.network));
+    prefs.extend(get_media_prefs(&settings.media));
+
+    prefs
+}
+
+/// Get the proxy preferences for the `[network]` settings. Every proxy
+/// preference is set, so nothing is left over from another mode.
+pub fn get_proxy_prefs(network: &NetworkConfig) -> HashMap<String, String> {
+    let mut prefs = HashMap::new();
+
+    let servers = ProxyServers::from_config(network).unwrap_or_else(|e| {
+        log::warn!("Ignoring proxy servers: {}", e);
+        ProxyServers::default()
+    });
+    let proxy_type = match network.proxy_mode {
+        ProxyMode::Direct => 0,
+        ProxyMode::Manual => 1,
+        ProxyMode::Pac => 2,
+        ProxyMode::System => 5,
+    };
+    prefs.insert("network.proxy.type".into(), proxy_type.to_string());
+
+    for (pref, server) in [
+        ("network.proxy.http", &servers.http),
+        ("network.proxy.ssl", &servers.https),
+        ("network.proxy.socks", &servers.socks),
+    ] {
+        let (host, port) = server
+            .as_ref()
+            .map_or((String::new(), 0), |server| (server.host.clone(), server.port));
+        prefs.insert(pref.into(), host);
+        prefs.insert(format!("{}_port", pref), port.to_string());
+    }
+    prefs.insert("network.proxy.socks_version".into(), "5".into());
+    prefs.insert(
+        "network.proxy.no_proxies_on".into(),
+        network.bypass.join(", "),
+    );
+    prefs.insert(
+        "network.proxy.autoconfig_url".into(),
+        network.pac_url.trim().to_string(),
+    );
+
+    // DNS through the proxy; prefetching would still look names up locally
+    for key in ["network.proxy.socks_remote_dns", "network.proxy.socks5_remote_dns"] {
+        prefs.insert(key.into(), network.proxy_dns.to_string());
+    }
+    let proxied = network.proxy_mode != ProxyMode::Direct;
+    prefs.insert(
+        "network.dns.disablePrefetch".into(),
+        (proxied && network.proxy_dns).to_string(),
+    );
+
+    prefs
+}
+
+/// Value of `media.autoplay.default` for an autoplay policy.
+pub fn autoplay_pref(policy: AutoplayPolicy) -> u32 {
+    match policy {
+        AutoplayPolicy::Allow => 0,
+        AutoplayPolicy::BlockAudible => 1,
+        AutoplayPolicy::BlockAll => 5,
+    }
+}
+
+/// Values of `media.preload.default` (media without a preload attribute)
+/// and `media.preload.auto` (`preload="auto"`) for a preload policy:
+/// 1 fetches nothing, 2 metadata, 3 enough to play through.
+pub fn preload_prefs(policy: PreloadPolicy) -> (u32, u32) {
+    match policy {
+        PreloadPolicy::None => (1, 1),
+        PreloadPolicy::Metadata => (2, 2),
+        PreloadPolicy::Auto => (2, 3),
+    }
+}
+
+/// Get the autoplay and preload preferences for a media policy.
+pub fn get_media_prefs(policy: &MediaPolicy) -> HashMap<String, String> {
+    let mut prefs = HashMap::new();
+
+    prefs.insert(
+        "media.autoplay.default".into(),
+        autoplay_pref(policy.autoplay).to_string(),
+    );
+    prefs.insert(
+        "media.autoplay.block-webaudio".into(),
+        (policy.autoplay != AutoplayPolicy::Allow).to_string(),
+    );
+    let (default, auto) = preload_prefs(policy.preload);
+    prefs.insert("media.preload.default".into(), default.to_string());
+    prefs.insert("media.preload.auto".into(), auto.to_string());
+
+    prefs
+}
+
 /// Generate a prefs.js file content from a map of preferences.
 pub fn generate_prefs_js(prefs: &HashMap<String, String>) -> String {
     let mut output = String::new();
@@ -164,6 +286,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_settings_prefs() {
+        let settings = EngineSettings {
+            send_dnt: true,
+            tracking_protection: false,
+            cache_size_mb: 200,
+            memory_cache_mb: 32,
+            ..EngineSettings::default()
+        };
+        let prefs = get_settings_prefs(&settings);
+        assert_eq!(prefs["privacy.donottrackheader.enabled"], "true");
+        assert_eq!(prefs["privacy.trackingprotection.enabled"], "false");
+        assert_eq!(prefs["browser.cache.disk.capacity"], "204800");
+        assert_eq!(prefs["browser.cache.memory.capacity"], "32768");
+    }
+
+    #[test]
+    fn test_media_prefs() {
+        let prefs = get_settings_prefs(&EngineSettings::default());
+        assert_eq!(prefs["media.autoplay.default"], "5");
+        assert_eq!(prefs["media.preload.default"], "2");
+
+        let prefs = get_media_prefs(&MediaPolicy {
+            autoplay: AutoplayPolicy::Allow,
+            preload: PreloadPolicy::None,
+        });
+        assert_eq!(prefs["media.autoplay.default"], "0");
+        assert_eq!(prefs["media.autoplay.block-webaudio"], "false");
+        assert_eq!(prefs["media.preload.auto"], "1");
+    }
+
+    #[test]
+    fn test_proxy_prefs() {
+        let mut network = NetworkConfig {
+            proxy_mode: ProxyMode::Manual,
+            http_proxy: "proxy.corp:3128".to_string(),
+            socks_proxy: "socks5://10.0.0.2:1080".to_string(),
+            ..NetworkConfig::default()
+        };
+        let prefs = get_proxy_prefs(&network);
+        assert_eq!(prefs["network.proxy.type"], "1");
+        assert_eq!(prefs["network.proxy.http"], "proxy.corp");
+        assert_eq!(prefs["network.proxy.ssl_port"], "3128");
+        assert_eq!(prefs["network.proxy.socks"], "10.0.0.2");
+        assert_eq!(prefs["network.proxy.socks5_remote_dns"], "true");
+        assert_eq!(prefs["network.proxy.no_proxies_on"], "localhost, 127.0.0.1, ::1");
+        assert_eq!(prefs["network.dns.disablePrefetch"], "true");
+
+        network.proxy_mode = ProxyMode::Direct;
+        network.http_proxy = "no-port.corp".to_string();
+        let prefs = get_proxy_prefs(&network);
+        assert_eq!(prefs["network.proxy.type"], "0");
+        assert_eq!(prefs["network.proxy.http"], "");
+        assert_eq!(prefs["network.proxy.http_port"], "0");
+        assert_eq!(prefs["network.dns.disablePrefetch"], "false");
+    }
+
     #[test]
     fn test_prefs_js_generation() {
         let mut prefs = HashMap::new();
@@ -0,0 +1,1849 @@
+//! Scriptable in-process engine for headless tests.
+//!
+//! `MockEngine` implements the BrowserEngine trait entirely in memory.
+//! Pages are described by a declarative `MockFixture` (URL -> title, links,
+//! size and scripted events), each view keeps a real back/forward history,
+//! `poll_events` output is deterministic, and every call made to the engine
+//! is recorded so tests can assert on what the browser actually asked for.
+
+use crate::core::blocker::ResourceType;
+use crate::core::capture::RgbaImage;
+use crate::core::certificates::CertificateExceptions;
+use crate::core::find::{find_matches, find_pattern, FindSession};
+use crate::core::internal_pages::is_internal_url;
+use crate::core::engine_set::EngineSet;
+use crate::core::engine::{
+    BrowserEngine, CaptureArea, CaptureOptions, CertificateError, CertificateErrorKind,
+    CertificateInfo, Cookie, DownloadRequest, EngineCapabilities, EngineError, EngineEvent, EngineRegistry, EngineResult, EngineSettings,
+    FindOptions, InjectionTime, LoadError, LoadErrorKind, MediaState, MemoryStats, Navigation, NavigationInterceptor, NavigationState, NewViewRequest, PdfOptions, PermissionKind, RequestDecision, RequestInfo, RequestInterceptor,
+    ScriptWorld, SiteDataKind, SiteDataUsage, TrimLevel, UserScriptId, VideoDecoder, ViewId,
+    WindowDisposition, Zoom,
+};
+use crate::core::media_policy::{AutoplayPolicy, MediaPolicy, PreloadPolicy};
+use crate::core::permissions::origin_of;
+use crate::core::print::blank_pdf;
+use crate::core::site_data::{host_of, SiteDataJar};
+use crate::core::user_scripts::UserScripts;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Base memory charged for every active mock view.
+const VIEW_BASE_BYTES: u64 = 10 * 1024 * 1024;
+/// Memory charged for a suspended mock view.
+const SUSPENDED_VIEW_BYTES: u64 = 10 * 1024;
+/// Size of the viewport rendered by `capture_view`.
+const VIEWPORT_SIZE: (u32, u32) = (1280, 800);
+/// Document height that fits on one printed page.
+const PRINTED_PAGE_HEIGHT: u32 = 1100;
+
+/// Event emitted by a fixture page once it has finished loading.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MockEvent {
+    /// Page logs a console message
+    Console { message: String },
+    /// Page changes its title after load (e.g. from script)
+    Title { title: String },
+    /// Page provides a favicon
+    Favicon { data: Vec<u8> },
+    /// Navigation to the page is stopped by a certificate problem, until
+    /// the certificate is accepted
+    CertificateError {
+        kind: CertificateErrorKind,
+        #[serde(default = "default_fingerprint")]
+        fingerprint: String,
+    },
+    /// Navigation to the page fails with a network error
+    LoadError { kind: LoadErrorKind },
+    /// Page triggers a file download
+    Download {
+        url: String,
+        #[serde(default)]
+        filename: Option<String>,
+    },
+    /// Page asks for a permission
+    Permission { kind: PermissionKind },
+    /// Page opens a new view (`window.open`, `target=_blank`)
+    NewView {
+        url: String,
+        #[serde(default)]
+        user_gesture: bool,
+        #[serde(default)]
+        disposition: WindowDisposition,
+    },
+    /// The page's content process dies
+    Crash { reason: String },
+    /// The page stops answering `ping_view`
+    Hang,
+    /// The page starts playing media, with or without sound
+    Media { audible: bool },
+}
+
+fn default_fingerprint() -> String {
+    "AB:CD:EF:01:23:45:67:89".to_string()
+}
+
+/// A subresource referenced by a fixture page.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MockSubresource {
+    pub url: String,
+    /// Filter-list resource type name ("script", "image", ...)
+    #[serde(default)]
+    pub resource_type: String,
+}
+
+/// Declarative description of a page served by the mock engine.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct MockPage {
+    /// Document title
+    #[serde(default)]
+    pub title: String,
+    /// Links on the page, in document order
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// Memory charged while the page is loaded
+    #[serde(default)]
+    pub size_bytes: u64,
+    /// Subresources requested while loading
+    #[serde(default)]
+    pub subresources: Vec<MockSubresource>,
+    /// Events emitted after the load finishes
+    #[serde(default)]
+    pub events: Vec<MockEvent>,
+    /// Canned results for `execute_script`, keyed by script source
+    #[serde(default)]
+    pub script_results: HashMap<String, serde_json::Value>,
+    /// Cookies set by the page, name -> value
+    #[serde(default)]
+    pub cookies: BTreeMap<String, String>,
+    /// Bytes the page writes to localStorage
+    #[serde(default)]
+    pub local_storage_bytes: u64,
+    /// Document height in pixels, for full-page captures
+    #[serde(default)]
+    pub height: u32,
+    /// Response body returned by `page_source`
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Visible text searched by `find_in_page`; links are searched by
+    /// their URL, which stands in for the link text
+    #[serde(default)]
+    pub text: String,
+}
+
+impl MockPage {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a link to the page.
+    pub fn link(mut self, url: &str) -> Self {
+        self.links.push(url.to_string());
+        self
+    }
+
+    /// Set the memory charged for the page.
+    pub fn size(mut self, bytes: u64) -> Self {
+        self.size_bytes = bytes;
+        self
+    }
+
+    /// Add a subresource request to the page.
+    pub fn subresource(mut self, url: &str, resource_type: ResourceType) -> Self {
+        self.subresources.push(MockSubresource {
+            url: url.to_string(),
+            resource_type: resource_type.as_str().to_string(),
+        });
+        self
+    }
+
+    /// Add a scripted event emitted after load.
+    pub fn event(mut self, event: MockEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Register a canned result for a script.
+    pub fn script_result(mut self, script: &str, value: serde_json::Value) -> Self {
+        self.script_results.insert(script.to_string(), value);
+        self
+    }
+
+    /// Set a cookie when the page loads.
+    pub fn cookie(mut self, name: &str, value: &str) -> Self {
+        self.cookies.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set the response body returned by `page_source`.
+    pub fn source(mut self, html: &str) -> Self {
+        self.source = Some(html.to_string());
+        self
+    }
+
+    /// Set the visible text of the page.
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+
+    /// Set the document height.
+    pub fn height(mut self, pixels: u32) -> Self {
+        self.height = pixels;
+        self
+    }
+
+    /// Write to localStorage when the page loads.
+    pub fn local_storage(mut self, bytes: u64) -> Self {
+        self.local_storage_bytes = bytes;
+        self
+    }
+}
+
+/// Set of pages known to the mock engine.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockFixture {
+    #[serde(default)]
+    pub pages: HashMap<String, MockPage>,
+}
+
+impl MockFixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a fixture from TOML (`[pages."https://..."]` tables).
+    pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Add a page to the fixture.
+    pub fn page(mut self, url: &str, page: MockPage) -> Self {
+        self.pages.insert(url.to_string(), page);
+        self
+    }
+
+    /// Look up a page, falling back to an empty page titled with its URL.
+    fn resolve(&self, url: &str) -> MockPage {
+        self.pages.get(url).cloned().unwrap_or_else(|| MockPage {
+            title: url.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Small fixture mirroring the pages in `test-pages/`.
+    pub fn test_pages() -> Self {
+        Self::new()
+            .page("about:blank", MockPage::new("New Tab"))
+            .page(
+                "https://example.com/",
+                MockPage::new("Example Domain")
+                    .link("https://example.com/video.html")
+                    .link("https://example.com/ads.html")
+                    .size(2 * 1024 * 1024),
+            )
+            .page(
+                "https://example.com/video.html",
+                MockPage::new("Video Test")
+                    .subresource("https://example.com/clip.mp4", ResourceType::Media)
+                    .size(40 * 1024 * 1024),
+            )
+            .page(
+                "https://example.com/ads.html",
+                MockPage::new("Ad Blocker Test")
+                    .subresource("https://ad.doubleclick.net/test-pixel.gif", ResourceType::Image)
+                    .subresource("https://static.hotjar.com/tracker.js", ResourceType::Script)
+                    .subresource("https://example.com/style.css", ResourceType::Stylesheet)
+                    .size(8 * 1024 * 1024),
+            )
+    }
+}
+
+/// A single recorded call into the mock engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    /// Trait method name
+    pub method: &'static str,
+    /// View the call targeted, if any
+    pub view_id: Option<ViewId>,
+    /// Remaining arguments, formatted
+    pub args: Vec<String>,
+}
+
+/// Shared handle for inspecting and steering a boxed `MockEngine`.
+#[derive(Clone, Default)]
+pub struct MockHandle {
+    calls: Arc<Mutex<Vec<MockCall>>>,
+    injected: Arc<Mutex<Vec<EngineEvent>>>,
+}
+
+impl MockHandle {
+    /// All calls made so far, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Calls made to a single method.
+    pub fn calls_to(&self, method: &str) -> Vec<MockCall> {
+        self.calls()
+            .into_iter()
+            .filter(|c| c.method == method)
+            .collect()
+    }
+
+    /// Forget recorded calls.
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// Queue an arbitrary event for the next `poll_events`.
+    pub fn inject(&self, event: EngineEvent) {
+        self.injected.lock().unwrap().push(event);
+    }
+}
+
+/// Per-view state of the mock engine.
+#[derive(Debug)]
+struct MockView {
+    /// Visited URLs, oldest first
+    history: Vec<String>,
+    /// Index of the current entry in `history`
+    index: usize,
+    title: String,
+    suspended: bool,
+    size_bytes: u64,
+    /// Documents passed to `load_html`, by URL
+    documents: HashMap<String, String>,
+    zoom: Zoom,
+    /// Navigation stopped by a certificate error
+    blocked_navigation: Option<CertificateError>,
+    /// Current document is an internal page loaded with `load_html`
+    privileged: bool,
+    /// User scripts injected into the current document
+    injected: Vec<UserScriptId>,
+    /// Ongoing find in page
+    find: FindSession,
+    /// Page stopped answering pings
+    hung: bool,
+    /// A ping waits for the hung page to answer
+    pending_ping: bool,
+    /// Content process died
+    crashed: bool,
+    /// The page has media, playing or paused
+    has_media: bool,
+    media: MediaState,
+    /// Policy set with `set_media_policy`; pages play freely without one
+    media_policy: Option<MediaPolicy>,
+    /// Agent sent from the next navigation on; `None` for the engine's own
+    user_agent: Option<String>,
+    /// Agent the current document was requested with
+    document_user_agent: Option<String>,
+}
+
+impl MockView {
+    fn new() -> Self {
+        Self {
+            history: vec![String::from("about:blank")],
+            index: 0,
+            title: String::from("New Tab"),
+            suspended: false,
+            size_bytes: 0,
+            documents: HashMap::new(),
+            zoom: Zoom::default(),
+            blocked_navigation: None,
+            privileged: false,
+            injected: Vec::new(),
+            find: FindSession::default(),
+            hung: false,
+            pending_ping: false,
+            crashed: false,
+            has_media: false,
+            media: MediaState::default(),
+            media_policy: None,
+            user_agent: None,
+            document_user_agent: None,
+        }
+    }
+
+    fn url(&self) -> &str {
+        &self.history[self.index]
+    }
+
+    fn memory(&self) -> u64 {
+        if self.suspended {
+            SUSPENDED_VIEW_BYTES
+        } else {
+            VIEW_BASE_BYTES + self.size_bytes
+        }
+    }
+}
+
+/// In-memory BrowserEngine driven by a `MockFixture`.
+pub struct MockEngine {
+    fixture: MockFixture,
+    views: HashMap<ViewId, MockView>,
+    initialized: bool,
+    pending_events: Vec<EngineEvent>,
+    /// Shared cache charged on every load and released by `trim_memory`
+    cache_bytes: u64,
+    video_decoder: VideoDecoder,
+    hw_accel: bool,
+    interceptor: Option<Arc<dyn RequestInterceptor>>,
+    navigation_interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    /// Permission prompts waiting for `respond_permission`
+    pending_permissions: HashSet<(ViewId, String, PermissionKind)>,
+    /// Cookies and storage written by loaded pages
+    site_data: SiteDataJar,
+    certificate_exceptions: CertificateExceptions,
+    user_scripts: UserScripts,
+    /// Features the engine claims; the others fail like an engine
+    /// without them would
+    capabilities: EngineCapabilities,
+    handle: MockHandle,
+}
+
+impl MockEngine {
+    /// Create a mock engine serving the `test-pages` fixture.
+    pub fn new() -> Self {
+        Self::with_fixture(MockFixture::test_pages())
+    }
+
+    /// Create a mock engine serving the given fixture.
+    pub fn with_fixture(fixture: MockFixture) -> Self {
+        Self {
+            fixture,
+            views: HashMap::new(),
+            initialized: false,
+            pending_events: Vec::new(),
+            cache_bytes: 0,
+            video_decoder: VideoDecoder::Software,
+            hw_accel: false,
+            interceptor: None,
+            navigation_interceptor: None,
+            pending_permissions: HashSet::new(),
+            site_data: SiteDataJar::new(),
+            certificate_exceptions: CertificateExceptions::new(),
+            user_scripts: UserScripts::new(),
+            capabilities: EngineCapabilities::all(),
+            handle: MockHandle::default(),
+        }
+    }
+
+    /// Handle that stays usable after the engine is boxed.
+    pub fn handle(&self) -> MockHandle {
+        self.handle.clone()
+    }
+
+    /// The engine as the default, named "mock", of an `EngineSet` in which
+    /// a fresh mock engine is registered as "other".
+    pub fn into_engine_set(self) -> EngineSet {
+        let mut registry = EngineRegistry::new();
+        registry.register("other", create_mock_engine);
+        EngineSet::new("mock", Box::new(self), registry)
+    }
+
+    /// Follow the `index`-th link of the current page, like a user click.
+    pub fn follow_link(&mut self, view_id: ViewId, index: usize) -> EngineResult<()> {
+        let url = {
+            let view = self.view(view_id)?;
+            self.fixture
+                .resolve(view.url())
+                .links
+                .get(index)
+                .cloned()
+                .ok_or_else(|| EngineError::NavigationError(format!("No link #{}", index)))?
+        };
+        self.load_url(view_id, &url)
+    }
+
+    /// Post a message through the internal page bridge from the view's
+    /// current document, like page script would. Returns whether the
+    /// engine accepted it.
+    pub fn post_message(&mut self, view_id: ViewId, message: serde_json::Value) -> EngineResult<bool> {
+        if !self.view(view_id)?.privileged {
+            return Ok(false);
+        }
+        self.pending_events
+            .push(EngineEvent::InternalMessage(view_id, message));
+        Ok(true)
+    }
+
+    /// Post a message from a user script of the named isolated world, like
+    /// `asteroid.postMessage` would. Returns whether the engine accepted it,
+    /// which it only does if a script of that world was injected into the
+    /// view's current document.
+    pub fn post_script_message(
+        &mut self,
+        view_id: ViewId,
+        world: &str,
+        message: serde_json::Value,
+    ) -> EngineResult<bool> {
+        if !self.has_world(view_id, world)? {
+            return Ok(false);
+        }
+        self.pending_events.push(EngineEvent::UserScriptMessage(
+            view_id,
+            world.to_string(),
+            message,
+        ));
+        Ok(true)
+    }
+
+    /// Pretend to be an engine with fewer features.
+    pub fn set_capabilities(&mut self, capabilities: EngineCapabilities) {
+        self.capabilities = capabilities;
+    }
+
+    fn require(&self, supported: bool, feature: &str) -> EngineResult<()> {
+        if supported {
+            Ok(())
+        } else {
+            Err(EngineError::Other(format!("{} is not supported", feature)))
+        }
+    }
+
+    /// Agent the view's current document was requested with; `None` for
+    /// the engine's own.
+    pub fn document_user_agent(&self, view_id: ViewId) -> EngineResult<Option<String>> {
+        Ok(self.view(view_id)?.document_user_agent.clone())
+    }
+
+    /// User scripts injected into the view's current document.
+    pub fn injected_scripts(&self, view_id: ViewId) -> EngineResult<Vec<UserScriptId>> {
+        Ok(self.view(view_id)?.injected.clone())
+    }
+
+    fn has_world(&self, view_id: ViewId, world: &str) -> EngineResult<bool> {
+        Ok(self.view(view_id)?.injected.iter().any(|id| {
+            self.user_scripts
+                .get(*id)
+                .is_some_and(|s| matches!(&s.world, ScriptWorld::Isolated(name) if name == world))
+        }))
+    }
+
+    /// Inject the user scripts matching the view's new document.
+    fn inject_user_scripts(&mut self, view_id: ViewId, url: &str) {
+        let injected = self
+            .user_scripts
+            .for_url(url)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        if let Some(view) = self.views.get_mut(&view_id) {
+            view.injected = injected;
+        }
+    }
+
+    fn record(&self, method: &'static str, view_id: Option<ViewId>, args: &[&str]) {
+        self.handle.calls.lock().unwrap().push(MockCall {
+            method,
+            view_id,
+            args: args.iter().map(|a| a.to_string()).collect(),
+        });
+    }
+
+    fn view(&self, view_id: ViewId) -> EngineResult<&MockView> {
+        self.views
+            .get(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))
+    }
+
+    /// Look up a view that is able to navigate (exists and is not suspended).
+    fn live_view_mut(&mut self, view_id: ViewId) -> EngineResult<&mut MockView> {
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+        Ok(view)
+    }
+
+    /// Emit the load sequence for the view's current history entry.
+    fn commit_load(&mut self, view_id: ViewId) {
+        let url = match self.views.get(&view_id) {
+            Some(view) => view.url().to_string(),
+            None => return,
+        };
+        let page = self.fixture.resolve(&url);
+        if let Some(view) = self.views.get_mut(&view_id) {
+            view.document_user_agent = view.user_agent.clone();
+            view.privileged = false;
+            view.injected.clear();
+            view.hung = false;
+            view.crashed = false;
+            // Navigating away frees the page's event loop
+            if std::mem::take(&mut view.pending_ping) {
+                self.pending_events.push(EngineEvent::ViewResponsive(view_id));
+            }
+            // and stops its media; muting belongs to the view
+            if std::mem::take(&mut view.has_media) {
+                view.media.playing = false;
+                view.media.audible = false;
+                self.pending_events
+                    .push(EngineEvent::MediaStateChanged(view_id, view.media));
+            }
+        }
+        // Prompts of the page left behind can no longer be answered
+        self.pending_permissions.retain(|(id, _, _)| *id != view_id);
+
+        if let Some(error) = self.certificate_error(&url, &page) {
+            self.pending_events.push(EngineEvent::LoadStarted(view_id));
+            self.drop_failed_navigation(view_id);
+            if let Some(view) = self.views.get_mut(&view_id) {
+                view.blocked_navigation = Some(error.clone());
+            }
+            self.pending_events
+                .push(EngineEvent::CertificateError(view_id, error));
+            return;
+        }
+        let load_error = page.events.iter().find_map(|event| match event {
+            MockEvent::LoadError { kind } => Some(*kind),
+            _ => None,
+        });
+        if let Some(kind) = load_error {
+            self.pending_events.push(EngineEvent::LoadStarted(view_id));
+            self.drop_failed_navigation(view_id);
+            self.pending_events
+                .push(EngineEvent::LoadFailed(view_id, LoadError { url, kind }));
+            return;
+        }
+
+        self.pending_events.push(EngineEvent::LoadStarted(view_id));
+        self.pending_events
+            .push(EngineEvent::UrlChanged(view_id, url.clone()));
+        self.inject_user_scripts(view_id, &url);
+        self.pending_events
+            .push(EngineEvent::LoadProgress(view_id, 0.5));
+
+        for resource in &page.subresources {
+            self.route_subresource(view_id, &url, resource);
+        }
+
+        self.pending_events
+            .push(EngineEvent::LoadProgress(view_id, 1.0));
+        self.pending_events
+            .push(EngineEvent::TitleChanged(view_id, page.title.clone()));
+        self.pending_events.push(EngineEvent::LoadFinished(view_id));
+
+        if let Some(view) = self.views.get_mut(&view_id) {
+            view.title = page.title.clone();
+            view.size_bytes = page.size_bytes;
+        }
+        self.cache_bytes += page.size_bytes / 4;
+        self.store_site_data(&url, &page);
+
+        if let Ok(state) = self.get_navigation_state(view_id) {
+            self.pending_events
+                .push(EngineEvent::NavigationStateChanged(view_id, state));
+        }
+
+        for event in page.events {
+            let event = match event {
+                MockEvent::Console { message } => EngineEvent::ConsoleMessage(view_id, message),
+                MockEvent::Title { title } => {
+                    if let Some(view) = self.views.get_mut(&view_id) {
+                        view.title = title.clone();
+                    }
+                    EngineEvent::TitleChanged(view_id, title)
+                }
+                MockEvent::Favicon { data } => EngineEvent::FaviconReady(view_id, data),
+                // Handled before the page loads
+                MockEvent::CertificateError { .. } | MockEvent::LoadError { .. } => continue,
+                MockEvent::Permission { kind } => match origin_of(&url) {
+                    // A request already waiting is not asked again
+                    Some(origin)
+                        if self.pending_permissions.insert((view_id, origin.clone(), kind)) =>
+                    {
+                        EngineEvent::PermissionRequested(view_id, origin, kind)
+                    }
+                    _ => continue,
+                },
+                MockEvent::Download { url, filename } => EngineEvent::DownloadRequested(
+                    view_id,
+                    DownloadRequest {
+                        url,
+                        suggested_filename: filename,
+                        mime_type: None,
+                    },
+                ),
+                MockEvent::NewView {
+                    url,
+                    user_gesture,
+                    disposition,
+                } => EngineEvent::NewViewRequested(
+                    view_id,
+                    NewViewRequest {
+                        url,
+                        user_gesture,
+                        disposition,
+                    },
+                ),
+                MockEvent::Crash { reason } => {
+                    if let Some(view) = self.views.get_mut(&view_id) {
+                        view.crashed = true;
+                    }
+                    EngineEvent::ViewCrashed(view_id, reason)
+                }
+                MockEvent::Hang => {
+                    if let Some(view) = self.views.get_mut(&view_id) {
+                        view.hung = true;
+                    }
+                    continue;
+                }
+                MockEvent::Media { audible } => match self.views.get_mut(&view_id) {
+                    Some(view) => {
+                        view.has_media = true;
+                        view.media.audible = audible;
+                        // Blocked media waits for media_play_pause
+                        let policy = view.media_policy.unwrap_or(MediaPolicy {
+                            autoplay: AutoplayPolicy::Allow,
+                            preload: PreloadPolicy::Auto,
+                        });
+                        if !policy.autoplay.allows(audible) {
+                            continue;
+                        }
+                        view.media.playing = true;
+                        EngineEvent::MediaStateChanged(view_id, view.media)
+                    }
+                    None => continue,
+                },
+            };
+            self.pending_events.push(event);
+        }
+    }
+
+    /// Certificate error stopping a load of the page, unless its
+    /// certificate was accepted.
+    /// Forget the history entry of a navigation that never committed.
+    fn drop_failed_navigation(&mut self, view_id: ViewId) {
+        if let Some(view) = self.views.get_mut(&view_id) {
+            if view.history.len() > 1 {
+                view.history.remove(view.index);
+                view.index = view.index.saturating_sub(1);
+            }
+        }
+    }
+
+    fn certificate_error(&self, url: &str, page: &MockPage) -> Option<CertificateError> {
+        let host = host_of(url).to_string();
+        page.events.iter().find_map(|event| match event {
+            MockEvent::CertificateError { kind, fingerprint }
+                if !self.certificate_exceptions.allows(&host, fingerprint) =>
+            {
+                let issuer = if *kind == CertificateErrorKind::SelfSigned {
+                    format!("CN={}", host)
+                } else {
+                    "CN=Mock Test CA".to_string()
+                };
+                Some(CertificateError {
+                    url: url.to_string(),
+                    host: host.clone(),
+                    kind: *kind,
+                    chain: vec![CertificateInfo {
+                        subject: format!("CN={}", host),
+                        issuer,
+                        not_before: 1_600_000_000,
+                        not_after: 1_700_000_000,
+                        fingerprint: fingerprint.clone(),
+                    }],
+                })
+            }
+            _ => None,
+        })
+    }
+
+    /// Write the page's cookies and storage into the site data jar.
+    fn store_site_data(&mut self, url: &str, page: &MockPage) {
+        let Some(origin) = origin_of(url) else {
+            return;
+        };
+        for (name, value) in &page.cookies {
+            let cookie = Cookie {
+                name: name.clone(),
+                value: value.clone(),
+                domain: host_of(&origin).to_string(),
+                path: "/".to_string(),
+                secure: url.starts_with("https://"),
+                http_only: false,
+                expires: None,
+            };
+            self.site_data.set_cookie(&origin, cookie);
+        }
+        if page.local_storage_bytes > 0 {
+            self.site_data
+                .add_usage(&origin, SiteDataKind::LocalStorage, page.local_storage_bytes);
+        }
+        if page.size_bytes > 0 {
+            self.site_data
+                .add_usage(&origin, SiteDataKind::Cache, page.size_bytes / 4);
+        }
+    }
+
+    /// URL a navigation of the view to `url` ends up loading, after the
+    /// navigation interceptor. The view takes the agent it picked.
+    fn intercept_navigation(&mut self, view_id: ViewId, url: &str) -> String {
+        let interceptor = match self.navigation_interceptor {
+            Some(ref interceptor) => interceptor,
+            None => return url.to_string(),
+        };
+        let Some(view) = self.views.get_mut(&view_id) else {
+            return url.to_string();
+        };
+        let mut navigation = Navigation {
+            view_id,
+            url: url.to_string(),
+            user_agent: view.user_agent.clone(),
+        };
+        interceptor.intercept_navigation(&mut navigation);
+        view.user_agent = navigation.user_agent;
+        if navigation.url != url {
+            self.pending_events.push(EngineEvent::NavigationRedirected(
+                view_id,
+                url.to_string(),
+                navigation.url.clone(),
+            ));
+        }
+        navigation.url
+    }
+
+    /// Run the navigation interceptor on the view's current history entry
+    /// before it is loaded again.
+    fn intercept_history_entry(&mut self, view_id: ViewId) {
+        let Some(url) = self.views.get(&view_id).map(|view| view.url().to_string()) else {
+            return;
+        };
+        let url = self.intercept_navigation(view_id, &url);
+        if let Some(view) = self.views.get_mut(&view_id) {
+            let index = view.index;
+            view.history[index] = url;
+        }
+    }
+
+    fn route_subresource(&mut self, view_id: ViewId, initiator: &str, resource: &MockSubresource) {
+        let interceptor = match self.interceptor {
+            Some(ref interceptor) => interceptor,
+            None => return,
+        };
+
+        let request = RequestInfo {
+            view_id,
+            url: resource.url.clone(),
+            initiator: initiator.to_string(),
+            resource_type: ResourceType::from_str(&resource.resource_type),
+        };
+
+        if interceptor.intercept(&request) == RequestDecision::Block {
+            self.pending_events
+                .push(EngineEvent::RequestBlocked(view_id, request.url));
+        }
+    }
+}
+
+impl Default for MockEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BrowserEngine for MockEngine {
+    fn initialize(&mut self) -> EngineResult<()> {
+        self.record("initialize", None, &[]);
+        self.initialized = true;
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> EngineResult<()> {
+        self.record("shutdown", None, &[]);
+        self.views.clear();
+        self.initialized = false;
+        Ok(())
+    }
+
+    fn create_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("create_view", Some(view_id), &[]);
+        if self.views.contains_key(&view_id) {
+            return Err(EngineError::Other(format!(
+                "View {} already exists",
+                view_id
+            )));
+        }
+        self.views.insert(view_id, MockView::new());
+        Ok(())
+    }
+
+    fn load_url(&mut self, view_id: ViewId, url: &str) -> EngineResult<()> {
+        self.record("load_url", Some(view_id), &[url]);
+        let view = self.live_view_mut(view_id)?;
+        if is_internal_url(url) {
+            self.pending_events
+                .push(EngineEvent::InternalPageRequested(view_id, url.to_string()));
+            return Ok(());
+        }
+        view.blocked_navigation = None;
+        let url = self.intercept_navigation(view_id, url);
+
+        // A new navigation drops any forward entries
+        let view = self.live_view_mut(view_id)?;
+        view.history.truncate(view.index + 1);
+        view.history.push(url);
+        view.index += 1;
+
+        self.commit_load(view_id);
+        Ok(())
+    }
+
+    fn load_html(&mut self, view_id: ViewId, html: &str, base_url: &str) -> EngineResult<()> {
+        self.record("load_html", Some(view_id), &[base_url]);
+        let view = self.live_view_mut(view_id)?;
+
+        view.history.truncate(view.index + 1);
+        view.history.push(base_url.to_string());
+        view.index += 1;
+        view.size_bytes = html.len() as u64;
+        view.documents.insert(base_url.to_string(), html.to_string());
+        view.privileged = is_internal_url(base_url);
+        self.inject_user_scripts(view_id, base_url);
+
+        self.pending_events
+            .push(EngineEvent::UrlChanged(view_id, base_url.to_string()));
+        self.pending_events.push(EngineEvent::LoadFinished(view_id));
+        Ok(())
+    }
+
+    fn go_back(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("go_back", Some(view_id), &[]);
+        let view = self.live_view_mut(view_id)?;
+        if view.index == 0 {
+            return Err(EngineError::NavigationError(
+                "Cannot go back".to_string(),
+            ));
+        }
+        view.index -= 1;
+        self.intercept_history_entry(view_id);
+        self.commit_load(view_id);
+        Ok(())
+    }
+
+    fn go_forward(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("go_forward", Some(view_id), &[]);
+        let view = self.live_view_mut(view_id)?;
+        if view.index + 1 >= view.history.len() {
+            return Err(EngineError::NavigationError(
+                "Cannot go forward".to_string(),
+            ));
+        }
+        view.index += 1;
+        self.intercept_history_entry(view_id);
+        self.commit_load(view_id);
+        Ok(())
+    }
+
+    fn reload(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("reload", Some(view_id), &[]);
+        self.live_view_mut(view_id)?;
+        self.commit_load(view_id);
+        Ok(())
+    }
+
+    fn stop(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("stop", Some(view_id), &[]);
+        self.view(view_id)?;
+        Ok(())
+    }
+
+    fn set_user_agent(&mut self, view_id: ViewId, user_agent: Option<&str>) -> EngineResult<()> {
+        self.record("set_user_agent", Some(view_id), &[user_agent.unwrap_or_default()]);
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.user_agent = user_agent.map(str::to_string);
+        Ok(())
+    }
+
+    fn set_muted(&mut self, view_id: ViewId, muted: bool) -> EngineResult<()> {
+        self.record("set_muted", Some(view_id), &[&muted.to_string()]);
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.media.muted != muted {
+            view.media.muted = muted;
+            self.pending_events
+                .push(EngineEvent::MediaStateChanged(view_id, view.media));
+        }
+        Ok(())
+    }
+
+    fn set_media_policy(&mut self, view_id: ViewId, policy: MediaPolicy) -> EngineResult<()> {
+        self.record(
+            "set_media_policy",
+            Some(view_id),
+            &[policy.autoplay.name(), policy.preload.name()],
+        );
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.media_policy = Some(policy);
+        Ok(())
+    }
+
+    fn media_play_pause(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("media_play_pause", Some(view_id), &[]);
+        let view = self.live_view_mut(view_id)?;
+        if view.has_media {
+            view.media.playing = !view.media.playing;
+            let state = view.media;
+            self.pending_events
+                .push(EngineEvent::MediaStateChanged(view_id, state));
+        }
+        Ok(())
+    }
+
+    fn execute_script(&mut self, view_id: ViewId, script: &str) -> EngineResult<serde_json::Value> {
+        self.record("execute_script", Some(view_id), &[script]);
+        self.require(self.capabilities.script_execution, "Script execution")?;
+        let url = self.live_view_mut(view_id)?.url().to_string();
+        Ok(self
+            .fixture
+            .resolve(&url)
+            .script_results
+            .get(script)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+
+    fn add_user_script(
+        &mut self,
+        script: &str,
+        time: InjectionTime,
+        world: ScriptWorld,
+        url_matches: &[&str],
+    ) -> EngineResult<UserScriptId> {
+        let world_name = match world {
+            ScriptWorld::Page => String::from("page"),
+            ScriptWorld::Isolated(ref name) => name.clone(),
+        };
+        self.record("add_user_script", None, &[&world_name, script]);
+        self.require(self.capabilities.script_execution, "Script execution")?;
+        Ok(self.user_scripts.add(script, time, world, url_matches))
+    }
+
+    fn remove_user_script(&mut self, id: UserScriptId) -> EngineResult<()> {
+        self.record("remove_user_script", None, &[&id.0.to_string()]);
+        if !self.user_scripts.remove(id) {
+            return Err(EngineError::Other(format!("Unknown user script: {}", id)));
+        }
+        for view in self.views.values_mut() {
+            view.injected.retain(|injected| *injected != id);
+        }
+        Ok(())
+    }
+
+    fn execute_script_in_world(
+        &mut self,
+        view_id: ViewId,
+        world: &str,
+        script: &str,
+    ) -> EngineResult<serde_json::Value> {
+        self.record("execute_script_in_world", Some(view_id), &[world, script]);
+        self.require(self.capabilities.script_execution, "Script execution")?;
+        self.live_view_mut(view_id)?;
+        if !self.has_world(view_id, world)? {
+            return Err(EngineError::Other(format!(
+                "No {} world in {}",
+                world, view_id
+            )));
+        }
+        let url = self.view(view_id)?.url().to_string();
+        Ok(self
+            .fixture
+            .resolve(&url)
+            .script_results
+            .get(script)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+
+    fn ping_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("ping_view", Some(view_id), &[]);
+        let view = self.live_view_mut(view_id)?;
+        if view.hung {
+            view.pending_ping = true;
+        } else if !view.crashed {
+            self.pending_events.push(EngineEvent::ViewResponsive(view_id));
+        }
+        Ok(())
+    }
+
+    fn suspend_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("suspend_view", Some(view_id), &[]);
+        self.require(self.capabilities.suspend, "Suspending views")?;
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.suspended = true;
+        Ok(())
+    }
+
+    fn resume_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("resume_view", Some(view_id), &[]);
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.suspended = false;
+        Ok(())
+    }
+
+    fn destroy_view(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("destroy_view", Some(view_id), &[]);
+        self.views
+            .remove(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        self.pending_permissions.retain(|(id, _, _)| *id != view_id);
+        Ok(())
+    }
+
+    fn set_video_decoder(&mut self, decoder: VideoDecoder) -> EngineResult<()> {
+        self.record("set_video_decoder", None, &[&format!("{:?}", decoder)]);
+        self.require(
+            self.capabilities.video_decoders.contains(&decoder),
+            &format!("{:?} video decoding", decoder),
+        )?;
+        self.video_decoder = decoder;
+        Ok(())
+    }
+
+    fn enable_hardware_acceleration(&mut self, enabled: bool) -> EngineResult<()> {
+        self.record("enable_hardware_acceleration", None, &[&enabled.to_string()]);
+        self.hw_accel = enabled;
+        Ok(())
+    }
+
+    fn get_memory_usage(&self) -> MemoryStats {
+        let views: u64 = self.views.values().map(MockView::memory).sum();
+        let active = self.views.values().filter(|v| !v.suspended).count() as u64;
+
+        MemoryStats {
+            total_bytes: views + self.cache_bytes,
+            js_heap_bytes: active * 4 * 1024 * 1024,
+            image_cache_bytes: self.cache_bytes / 2,
+            dom_bytes: active * 2 * 1024 * 1024,
+            layout_bytes: active * 1024 * 1024,
+            network_cache_bytes: self.cache_bytes / 2,
+        }
+    }
+
+    fn trim_memory(&mut self, level: TrimLevel) -> EngineResult<()> {
+        self.record("trim_memory", None, &[&format!("{:?}", level)]);
+        self.cache_bytes = match level {
+            TrimLevel::Light => self.cache_bytes / 2,
+            TrimLevel::Moderate => self.cache_bytes / 4,
+            TrimLevel::Aggressive => 0,
+        };
+        Ok(())
+    }
+
+    fn get_navigation_state(&self, view_id: ViewId) -> EngineResult<NavigationState> {
+        let view = self.view(view_id)?;
+        Ok(NavigationState {
+            can_go_back: view.index > 0,
+            can_go_forward: view.index + 1 < view.history.len(),
+            is_loading: false,
+            url: view.url().to_string(),
+            title: view.title.clone(),
+            progress: 1.0,
+        })
+    }
+
+    fn find_in_page(
+        &mut self,
+        view_id: ViewId,
+        query: &str,
+        options: &FindOptions,
+        forward: bool,
+    ) -> EngineResult<()> {
+        self.record("find_in_page", Some(view_id), &[query, &forward.to_string()]);
+        self.require(self.capabilities.find, "Find in page")?;
+        let page = self.fixture.resolve(self.view(view_id)?.url());
+        let pattern = find_pattern(query, options).map_err(EngineError::Other)?;
+        let total = if options.links_only {
+            page.links
+                .iter()
+                .map(|link| find_matches(&pattern, link).len())
+                .sum()
+        } else {
+            find_matches(&pattern, &page.text).len()
+        };
+
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        let (current, total) = view.find.step(query, options, total, forward);
+        self.pending_events
+            .push(EngineEvent::FindResult(view_id, current, total));
+        Ok(())
+    }
+
+    fn clear_find(&mut self, view_id: ViewId) -> EngineResult<()> {
+        self.record("clear_find", Some(view_id), &[]);
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        view.find = FindSession::default();
+        Ok(())
+    }
+
+    fn apply_settings(&mut self, settings: &EngineSettings) -> EngineResult<()> {
+        self.record(
+            "apply_settings",
+            None,
+            &[
+                &format!("dnt={}", settings.send_dnt),
+                &format!("cache={}", settings.cache_size_mb),
+            ],
+        );
+        Ok(())
+    }
+
+    fn set_request_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn RequestInterceptor>>,
+    ) -> EngineResult<()> {
+        self.record(
+            "set_request_interceptor",
+            None,
+            &[if interceptor.is_some() { "some" } else { "none" }],
+        );
+        self.require(self.capabilities.request_interception, "Request interception")?;
+        self.interceptor = interceptor;
+        Ok(())
+    }
+
+    fn set_navigation_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    ) -> EngineResult<()> {
+        self.record(
+            "set_navigation_interceptor",
+            None,
+            &[if interceptor.is_some() { "some" } else { "none" }],
+        );
+        self.require(self.capabilities.request_interception, "Navigation interception")?;
+        self.navigation_interceptor = interceptor;
+        Ok(())
+    }
+
+    fn respond_permission(
+        &mut self,
+        view_id: ViewId,
+        origin: &str,
+        kind: PermissionKind,
+        granted: bool,
+    ) -> EngineResult<()> {
+        self.record(
+            "respond_permission",
+            Some(view_id),
+            &[origin, kind.as_str(), if granted { "granted" } else { "denied" }],
+        );
+        self.view(view_id)?;
+        if self
+            .pending_permissions
+            .remove(&(view_id, origin.to_string(), kind))
+        {
+            Ok(())
+        } else {
+            Err(EngineError::Other(format!(
+                "No pending {} request from {}",
+                kind.as_str(),
+                origin
+            )))
+        }
+    }
+
+    fn list_cookies(&self, origin: Option<&str>) -> EngineResult<Vec<Cookie>> {
+        self.record("list_cookies", None, &[origin.unwrap_or("*")]);
+        Ok(self.site_data.cookies(origin))
+    }
+
+    fn delete_cookie(&mut self, cookie: &Cookie) -> EngineResult<()> {
+        self.record("delete_cookie", None, &[&cookie.domain, &cookie.name]);
+        self.site_data.delete_cookie(cookie);
+        Ok(())
+    }
+
+    fn clear_cookies(&mut self) -> EngineResult<()> {
+        self.record("clear_cookies", None, &[]);
+        self.site_data.clear(None, &[SiteDataKind::Cookies]);
+        Ok(())
+    }
+
+    fn clear_site_data(&mut self, origin: Option<&str>, kinds: &[SiteDataKind]) -> EngineResult<()> {
+        let kinds_arg = format!("{:?}", kinds);
+        self.record("clear_site_data", None, &[origin.unwrap_or("*"), &kinds_arg]);
+        self.site_data.clear(origin, kinds);
+        Ok(())
+    }
+
+    fn site_data_usage(&self) -> EngineResult<Vec<SiteDataUsage>> {
+        self.record("site_data_usage", None, &[]);
+        Ok(self.site_data.usage())
+    }
+
+    fn capture_view(&self, view_id: ViewId, options: CaptureOptions) -> EngineResult<Vec<u8>> {
+        self.record("capture_view", Some(view_id), &[&format!("{:?}", options.area)]);
+        self.require(self.capabilities.capture, "Capture")?;
+        let view = self.view(view_id)?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+
+        // Fill with a colour derived from the title so different pages
+        // produce different images
+        let page = self.fixture.resolve(view.url());
+        let shade = view
+            .title
+            .bytes()
+            .fold(0u8, |acc, b| acc.wrapping_mul(31).wrapping_add(b));
+        let (width, viewport_height) = VIEWPORT_SIZE;
+        let height = match options.area {
+            CaptureArea::Viewport => viewport_height,
+            CaptureArea::FullPage => page.height.max(viewport_height),
+        };
+        RgbaImage::filled(width, height, [shade, 255 - shade, 128, 255])
+            .into_png(&options)
+            .map_err(EngineError::Other)
+    }
+
+    fn set_zoom(&mut self, view_id: ViewId, zoom: Zoom) -> EngineResult<()> {
+        self.record(
+            "set_zoom",
+            Some(view_id),
+            &[&zoom.level.to_string(), &format!("{:?}", zoom.mode)],
+        );
+        let zoom = Zoom::new(zoom.level, zoom.mode);
+        let view = self
+            .views
+            .get_mut(&view_id)
+            .ok_or(EngineError::ViewNotFound(view_id))?;
+        if view.zoom != zoom {
+            view.zoom = zoom;
+            self.pending_events
+                .push(EngineEvent::ZoomChanged(view_id, zoom));
+        }
+        Ok(())
+    }
+
+    fn get_zoom(&self, view_id: ViewId) -> EngineResult<Zoom> {
+        self.record("get_zoom", Some(view_id), &[]);
+        Ok(self.view(view_id)?.zoom)
+    }
+
+    fn accept_certificate_exception(
+        &mut self,
+        view_id: ViewId,
+        host: &str,
+        fingerprint: &str,
+    ) -> EngineResult<()> {
+        self.record(
+            "accept_certificate_exception",
+            Some(view_id),
+            &[host, fingerprint],
+        );
+        let view = self.live_view_mut(view_id)?;
+        let retry = view
+            .blocked_navigation
+            .take_if(|error| error.host.eq_ignore_ascii_case(host));
+        self.certificate_exceptions.add(host, fingerprint);
+        match retry {
+            Some(error) => self.load_url(view_id, &error.url),
+            None => Ok(()),
+        }
+    }
+
+    fn page_source(&self, view_id: ViewId) -> EngineResult<Option<String>> {
+        self.record("page_source", Some(view_id), &[]);
+        let view = self.view(view_id)?;
+        match view.documents.get(view.url()) {
+            Some(html) => Ok(Some(html.clone())),
+            None => Ok(self.fixture.resolve(view.url()).source),
+        }
+    }
+
+    fn print_to_pdf(&mut self, view_id: ViewId, options: &PdfOptions, path: &Path) -> EngineResult<()> {
+        self.record(
+            "print_to_pdf",
+            Some(view_id),
+            &[&path.display().to_string()],
+        );
+        self.require(self.capabilities.printing, "Printing")?;
+        let view = self.view(view_id)?;
+        if view.suspended {
+            return Err(EngineError::Other(format!("{} is suspended", view_id)));
+        }
+        let page = self.fixture.resolve(view.url());
+        let pages = page.height.div_ceil(PRINTED_PAGE_HEIGHT).max(1) as usize;
+        std::fs::write(path, blank_pdf(options, &view.title, pages))
+            .map_err(|e| EngineError::Other(format!("Failed to write PDF: {}", e)))
+    }
+
+    fn engine_info(&self) -> (String, String) {
+        ("Mock".to_string(), "1.0".to_string())
+    }
+
+    fn capabilities(&self) -> EngineCapabilities {
+        self.capabilities.clone()
+    }
+
+    fn poll_events(&mut self) -> Vec<EngineEvent> {
+        let mut events = std::mem::take(&mut self.pending_events);
+        events.append(&mut self.handle.injected.lock().unwrap());
+        events
+    }
+}
+
+/// Factory suitable for `EngineRegistry::register`.
+pub fn create_mock_engine() -> Box<dyn BrowserEngine> {
+    Box::new(MockEngine::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::blocker::ContentBlocker;
+
+    fn engine_with_view() -> MockEngine {
+        let mut engine = MockEngine::new();
+        engine.initialize().unwrap();
+        engine.create_view(ViewId(1)).unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_mock_load_event_sequence() {
+        let mut engine = engine_with_view();
+        engine.load_url(ViewId(1), "https://example.com/").unwrap();
+
+        let events = engine.poll_events();
+        assert!(matches!(events[0], EngineEvent::LoadStarted(ViewId(1))));
+        assert!(matches!(events[1], EngineEvent::UrlChanged(_, ref u) if u == "https://example.com/"));
+        assert!(events.iter().any(|e| matches!(e, EngineEvent::TitleChanged(_, t) if t == "Example Domain")));
+        assert!(engine.poll_events().is_empty());
+    }
+
+    #[test]
+    fn test_mock_history() {
+        let mut engine = engine_with_view();
+        let id = ViewId(1);
+        engine.load_url(id, "https://example.com/").unwrap();
+        engine.follow_link(id, 0).unwrap();
+        assert_eq!(engine.get_navigation_state(id).unwrap().title, "Video Test");
+
+        engine.go_back(id).unwrap();
+        let state = engine.get_navigation_state(id).unwrap();
+        assert_eq!(state.url, "https://example.com/");
+        assert!(state.can_go_back && state.can_go_forward);
+
+        // Navigating from the middle of history drops forward entries
+        engine.follow_link(id, 1).unwrap();
+        assert!(!engine.get_navigation_state(id).unwrap().can_go_forward);
+        assert!(engine.go_forward(id).is_err());
+    }
+
+    #[test]
+    fn test_mock_fixture_from_toml() {
+        let fixture = MockFixture::from_toml(
+            r#"
+            [pages."https://news.test/"]
+            title = "News"
+            links = ["https://news.test/a"]
+            size_bytes = 1024
+
+            [[pages."https://news.test/".events]]
+            type = "download"
+            url = "https://news.test/paper.pdf"
+
+            [[pages."https://news.test/".events]]
+            type = "new_view"
+            url = "https://ads.test/"
+            disposition = "popup"
+
+            [[pages."https://news.test/".events]]
+            type = "console"
+            message = "hello"
+            "#,
+        )
+        .unwrap();
+
+        let mut engine = MockEngine::with_fixture(fixture);
+        engine.create_view(ViewId(1)).unwrap();
+        engine.load_url(ViewId(1), "https://news.test/").unwrap();
+        let events = engine.poll_events();
+        assert!(matches!(events.last(), Some(EngineEvent::ConsoleMessage(_, m)) if m == "hello"));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            EngineEvent::NewViewRequested(ViewId(1), r)
+                if r.url == "https://ads.test/"
+                    && !r.user_gesture
+                    && r.disposition == WindowDisposition::Popup
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            EngineEvent::DownloadRequested(_, r) if r.url == "https://news.test/paper.pdf"
+        )));
+    }
+
+    #[test]
+    fn test_mock_records_calls() {
+        let mut engine = engine_with_view();
+        let handle = engine.handle();
+        engine.load_url(ViewId(1), "https://example.com/").unwrap();
+        assert!(engine.load_url(ViewId(9), "https://example.com/").is_err());
+
+        let loads = handle.calls_to("load_url");
+        assert_eq!(loads.len(), 2);
+        assert_eq!(loads[0].view_id, Some(ViewId(1)));
+        assert_eq!(loads[0].args, vec!["https://example.com/".to_string()]);
+    }
+
+    #[test]
+    fn test_mock_suspended_view_cannot_navigate() {
+        let mut engine = engine_with_view();
+        engine.suspend_view(ViewId(1)).unwrap();
+        assert!(engine.load_url(ViewId(1), "https://example.com/").is_err());
+        engine.resume_view(ViewId(1)).unwrap();
+        assert!(engine.load_url(ViewId(1), "https://example.com/").is_ok());
+    }
+
+    #[test]
+    fn test_mock_interception_and_injection() {
+        let mut engine = engine_with_view();
+        let blocker = Arc::new(Mutex::new(ContentBlocker::new()));
+        engine.set_request_interceptor(Some(blocker)).unwrap();
+        engine.handle().inject(EngineEvent::ConsoleMessage(ViewId(1), "injected".into()));
+
+        engine.load_url(ViewId(1), "https://example.com/ads.html").unwrap();
+        let events = engine.poll_events();
+        let blocked: Vec<&EngineEvent> = events
+            .iter()
+            .filter(|e| matches!(e, EngineEvent::RequestBlocked(..)))
+            .collect();
+        assert_eq!(blocked.len(), 1);
+        assert!(matches!(blocked[0], EngineEvent::RequestBlocked(_, u) if u.contains("doubleclick")));
+        assert!(matches!(events.last(), Some(EngineEvent::ConsoleMessage(_, m)) if m == "injected"));
+    }
+
+    #[test]
+    fn test_mock_in_registry() {
+        let mut registry = EngineRegistry::new();
+        registry.register("mock", create_mock_engine);
+        let engine = registry.create("mock").unwrap();
+        assert_eq!(engine.engine_info().0, "Mock");
+        let infos = registry.engine_infos();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].name, "mock");
+        assert_eq!(infos[0].display_name, "Mock");
+        assert_eq!(registry.capabilities("mock"), Some(EngineCapabilities::all()));
+        assert_eq!(registry.capabilities("servo"), None);
+    }
+
+    #[test]
+    fn test_mock_limited_capabilities() {
+        let mut engine = MockEngine::new();
+        engine.set_capabilities(EngineCapabilities {
+            find: true,
+            video_decoders: vec![VideoDecoder::Software],
+            ..Default::default()
+        });
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://example.com/").unwrap();
+
+        assert!(engine.find_in_page(id, "example", &FindOptions::default(), true).is_ok());
+        assert!(engine.suspend_view(id).is_err());
+        assert!(engine.execute_script(id, "1").is_err());
+        assert!(engine.set_video_decoder(VideoDecoder::VAAPI).is_err());
+        assert!(engine.set_video_decoder(VideoDecoder::Software).is_ok());
+        assert!(engine.set_request_interceptor(None).is_err());
+        assert!(!engine.capabilities().hardware_video());
+    }
+
+    #[test]
+    fn test_mock_permission_request() {
+        let fixture = MockFixture::test_pages().page(
+            "https://maps.test/",
+            MockPage::new("Maps").event(MockEvent::Permission {
+                kind: PermissionKind::Geolocation,
+            }),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        engine.create_view(ViewId(1)).unwrap();
+        engine.load_url(ViewId(1), "https://maps.test/").unwrap();
+        assert!(engine.poll_events().iter().any(|e| matches!(
+            e,
+            EngineEvent::PermissionRequested(_, o, PermissionKind::Geolocation) if o == "https://maps.test"
+        )));
+
+        let origin = "https://maps.test";
+        assert!(engine
+            .respond_permission(ViewId(1), origin, PermissionKind::Camera, true)
+            .is_err());
+        engine
+            .respond_permission(ViewId(1), origin, PermissionKind::Geolocation, false)
+            .unwrap();
+        assert_eq!(engine.handle().calls_to("respond_permission").len(), 2);
+    }
+
+    #[test]
+    fn test_mock_unanswered_permission_asked_again_after_navigation() {
+        let fixture = MockFixture::test_pages().page(
+            "https://maps.test/",
+            MockPage::new("Maps").event(MockEvent::Permission {
+                kind: PermissionKind::Geolocation,
+            }),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let requested = |engine: &mut MockEngine| {
+            engine
+                .poll_events()
+                .iter()
+                .any(|e| matches!(e, EngineEvent::PermissionRequested(..)))
+        };
+
+        engine.load_url(view, "https://maps.test/").unwrap();
+        assert!(requested(&mut engine));
+        // Left unanswered while the user navigates away and back
+        engine.load_url(view, "https://example.com/").unwrap();
+        engine.load_url(view, "https://maps.test/").unwrap();
+        assert!(requested(&mut engine));
+        engine.reload(view).unwrap();
+        assert!(requested(&mut engine));
+    }
+
+    #[test]
+    fn test_mock_certificate_error() {
+        let fixture = MockFixture::test_pages().page(
+            "https://expired.test/",
+            MockPage::new("Expired").event(MockEvent::CertificateError {
+                kind: CertificateErrorKind::Expired,
+                fingerprint: "11:22".to_string(),
+            }),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://example.com/").unwrap();
+        engine.poll_events();
+
+        engine.load_url(id, "https://expired.test/").unwrap();
+        let error = match engine.poll_events().as_slice() {
+            [EngineEvent::LoadStarted(_), EngineEvent::CertificateError(_, error)] => error.clone(),
+            other => panic!("unexpected events: {:?}", other),
+        };
+        assert_eq!(error.host, "expired.test");
+        assert_eq!(error.kind, CertificateErrorKind::Expired);
+        assert_eq!(error.fingerprint(), Some("11:22"));
+        // The stopped navigation is not in history
+        let state = engine.get_navigation_state(id).unwrap();
+        assert_eq!(state.url, "https://example.com/");
+        assert!(!state.can_go_forward);
+
+        engine
+            .accept_certificate_exception(id, "expired.test", "11:22")
+            .unwrap();
+        let events = engine.poll_events();
+        assert!(events.iter().any(|e| matches!(e, EngineEvent::LoadFinished(_))));
+        assert_eq!(engine.get_navigation_state(id).unwrap().url, "https://expired.test/");
+    }
+
+    #[test]
+    fn test_mock_internal_pages() {
+        let fixture = MockFixture::test_pages().page(
+            "https://evil.test/",
+            MockPage::new("Evil").link("asteroid://site-data/clear-all"),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://evil.test/").unwrap();
+        engine.poll_events();
+
+        // Links to internal pages are handed to the browser
+        engine.follow_link(id, 0).unwrap();
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::InternalPageRequested(_, url)] if url == "asteroid://site-data/clear-all"
+        ));
+        assert_eq!(engine.get_navigation_state(id).unwrap().url, "https://evil.test/");
+
+        let message = serde_json::json!({ "type": "clear" });
+        assert!(!engine.post_message(id, message.clone()).unwrap());
+        engine.load_html(id, "<h1>Data</h1>", "asteroid://site-data").unwrap();
+        assert!(engine.post_message(id, message.clone()).unwrap());
+        engine.load_url(id, "https://example.com/").unwrap();
+        assert!(!engine.post_message(id, message).unwrap());
+    }
+
+    #[test]
+    fn test_mock_user_scripts() {
+        let fixture = MockFixture::test_pages().page(
+            "https://news.test/",
+            MockPage::new("News").script_result("asteroidHints.show()", serde_json::json!(4)),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        let hints = engine
+            .add_user_script(
+                "hints()",
+                InjectionTime::End,
+                ScriptWorld::isolated("hints"),
+                &["https://*"],
+            )
+            .unwrap();
+
+        // Scripts are injected into matching documents as they load
+        engine.load_url(id, "https://news.test/").unwrap();
+        assert_eq!(engine.injected_scripts(id).unwrap(), vec![hints]);
+        assert_eq!(
+            engine
+                .execute_script_in_world(id, "hints", "asteroidHints.show()")
+                .unwrap(),
+            serde_json::json!(4)
+        );
+        assert!(engine
+            .execute_script_in_world(id, "other", "asteroidHints.show()")
+            .is_err());
+        engine.poll_events();
+
+        // Only the world's own scripts can post to the browser
+        let message = serde_json::json!({ "type": "hidden", "count": 2 });
+        assert!(!engine
+            .post_script_message(id, "other", message.clone())
+            .unwrap());
+        assert!(engine
+            .post_script_message(id, "hints", message.clone())
+            .unwrap());
+        assert!(matches!(
+            engine.poll_events().as_slice(),
+            [EngineEvent::UserScriptMessage(_, world, m)] if world == "hints" && *m == message
+        ));
+
+        engine.load_html(id, "<h1>Settings</h1>", "asteroid://settings").unwrap();
+        assert!(engine.injected_scripts(id).unwrap().is_empty());
+        engine.load_url(id, "https://news.test/").unwrap();
+        engine.remove_user_script(hints).unwrap();
+        assert!(engine.remove_user_script(hints).is_err());
+        assert!(!engine.post_script_message(id, "hints", message).unwrap());
+    }
+
+    #[test]
+    fn test_mock_find_in_page() {
+        let fixture = MockFixture::test_pages().page(
+            "https://docs.test/",
+            MockPage::new("Docs")
+                .text("Install the crate, then configure the crate features.")
+                .link("https://docs.test/crate")
+                .link("https://docs.test/faq"),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://docs.test/").unwrap();
+        engine.poll_events();
+
+        let find = |engine: &mut MockEngine, query: &str, options: &FindOptions, forward| {
+            engine.find_in_page(id, query, options, forward).unwrap();
+            match engine.poll_events().as_slice() {
+                [EngineEvent::FindResult(v, current, total)] if *v == id => (*current, *total),
+                events => panic!("unexpected events {:?}", events),
+            }
+        };
+        let options = FindOptions::default();
+        assert_eq!(find(&mut engine, "crate", &options, true), (1, 2));
+        assert_eq!(find(&mut engine, "crate", &options, true), (2, 2));
+        assert_eq!(find(&mut engine, "crate", &options, true), (1, 2));
+
+        // Quick find only looks at links
+        let links = FindOptions {
+            links_only: true,
+            ..Default::default()
+        };
+        assert_eq!(find(&mut engine, "crate", &links, true), (1, 1));
+        assert_eq!(find(&mut engine, "install", &links, true), (0, 0));
+
+        let regex = FindOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(find(&mut engine, "con.*ure", &regex, true), (1, 1));
+        assert!(engine.find_in_page(id, "(", &regex, true).is_err());
+        engine.clear_find(id).unwrap();
+        assert_eq!(find(&mut engine, "crate", &options, false), (1, 2));
+    }
+
+    #[test]
+    fn test_mock_site_data() {
+        let fixture = MockFixture::new().page(
+            "https://mail.test/",
+            MockPage::new("Mail")
+                .cookie("session", "s1")
+                .local_storage(4096)
+                .size(4 * 1024),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        engine.create_view(ViewId(1)).unwrap();
+        engine.load_url(ViewId(1), "https://mail.test/").unwrap();
+
+        let cookies = engine.list_cookies(Some("https://mail.test")).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].domain, "mail.test");
+        assert!(cookies[0].secure);
+
+        let usage = engine.site_data_usage().unwrap();
+        assert_eq!(usage[0].local_storage_bytes, 4096);
+        assert_eq!(usage[0].cache_bytes, 1024);
+
+        engine
+            .clear_site_data(Some("https://mail.test"), &[SiteDataKind::LocalStorage])
+            .unwrap();
+        assert_eq!(engine.site_data_usage().unwrap()[0].local_storage_bytes, 0);
+        engine.clear_cookies().unwrap();
+        assert!(engine.list_cookies(None).unwrap().is_empty());
+        assert_eq!(engine.handle().calls_to("clear_site_data").len(), 1);
+    }
+
+    #[test]
+    fn test_mock_capture_view() {
+        use crate::core::capture::png_dimensions;
+
+        let fixture = MockFixture::new().page("https://long.test/", MockPage::new("Long").height(3000));
+        let mut engine = MockEngine::with_fixture(fixture);
+        engine.create_view(ViewId(1)).unwrap();
+        engine.load_url(ViewId(1), "https://long.test/").unwrap();
+
+        let viewport = engine.capture_view(ViewId(1), CaptureOptions::default()).unwrap();
+        assert_eq!(png_dimensions(&viewport), Some((1280, 800)));
+        let full = CaptureOptions {
+            area: CaptureArea::FullPage,
+            max_width: None,
+        };
+        let page = engine.capture_view(ViewId(1), full).unwrap();
+        assert_eq!(png_dimensions(&page), Some((1280, 3000)));
+        assert!(engine.capture_view(ViewId(2), full).is_err());
+    }
+}
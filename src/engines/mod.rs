@@ -5,9 +5,46 @@
 
 pub mod gecko;
 pub mod servo;
+#[cfg(any(test, feature = "mock-engine"))]
+pub mod mock;
+#[cfg(test)]
+mod conformance;
+
+use crate::core::engine::{BrowserEngine, EngineRegistry};
+use crate::core::engine_set::EngineSet;
+
+/// Build a registry containing every engine compiled into this binary.
+pub fn create_registry() -> EngineRegistry {
+    let mut registry = EngineRegistry::new();
+
+    #[cfg(feature = "gecko-engine")]
+    registry.register("gecko", || -> Box<dyn BrowserEngine> {
+        Box::new(gecko::GeckoEngine::new())
+    });
+
+    #[cfg(feature = "servo-engine")]
+    registry.register("servo", || -> Box<dyn BrowserEngine> {
+        Box::new(servo::ServoEngine::new())
+    });
+
+    #[cfg(feature = "mock-engine")]
+    registry.register("mock", mock::create_mock_engine);
+
+    registry
+}
+
+/// Every compiled-in engine, with `preferred` as the default when it is
+/// one of them. Only the default engine is created up front.
+pub fn create_engine_set(preferred: &str) -> EngineSet {
+    let mut registry = create_registry();
+    registry.set_default(preferred);
+    let name = registry.default_name().to_string();
+    let engine = registry.create_default().unwrap_or_else(create_default_engine);
+    EngineSet::new(&name, engine, registry)
+}
 
 /// Create the default engine based on compile-time feature flags.
-pub fn create_default_engine() -> Box<dyn crate::core::engine::BrowserEngine> {
+pub fn create_default_engine() -> Box<dyn BrowserEngine> {
     #[cfg(feature = "gecko-engine")]
     {
         Box::new(gecko::GeckoEngine::new())
@@ -13,9 +13,13 @@
 //! - [ ] Production-ready stability
 
 use crate::core::engine::{
-    BrowserEngine, EngineError, EngineEvent, EngineResult, MemoryStats,
-    NavigationState, TrimLevel, VideoDecoder, ViewId,
+    BrowserEngine, CaptureOptions, Cookie, EngineCapabilities, EngineError, EngineEvent, EngineResult, EngineSettings,
+    FindOptions, InjectionTime, MemoryStats, NavigationInterceptor, NavigationState, PdfOptions, PermissionKind, RequestInterceptor,
+    ScriptWorld, SiteDataKind, SiteDataUsage, TrimLevel, UserScriptId, VideoDecoder, ViewId, Zoom,
 };
+use crate::core::media_policy::MediaPolicy;
+use std::path::Path;
+use std::sync::Arc;
 
 /// Servo engine implementation (stub).
 ///
@@ -23,11 +27,19 @@ use crate::core::engine::{
 /// For now, all operations return appropriate error messages.
 pub struct ServoEngine {
     initialized: bool,
+    /// Hook to hand to Servo's net thread once it is wired up
+    interceptor: Option<Arc<dyn RequestInterceptor>>,
+    /// Hook to consult before top-level loads once they are wired up
+    navigation_interceptor: Option<Arc<dyn NavigationInterceptor>>,
 }
 
 impl ServoEngine {
     pub fn new() -> Self {
-        Self { initialized: false }
+        Self {
+            initialized: false,
+            interceptor: None,
+            navigation_interceptor: None,
+        }
     }
 }
 
@@ -93,6 +105,30 @@ impl BrowserEngine for ServoEngine {
         ))
     }
 
+    fn set_user_agent(&mut self, _view_id: ViewId, _user_agent: Option<&str>) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn set_muted(&mut self, _view_id: ViewId, _muted: bool) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn media_play_pause(&mut self, _view_id: ViewId) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn set_media_policy(&mut self, _view_id: ViewId, _policy: MediaPolicy) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
     fn execute_script(
         &mut self,
         _view_id: ViewId,
@@ -103,6 +139,41 @@ impl BrowserEngine for ServoEngine {
         ))
     }
 
+    fn add_user_script(
+        &mut self,
+        _script: &str,
+        _time: InjectionTime,
+        _world: ScriptWorld,
+        _url_matches: &[&str],
+    ) -> EngineResult<UserScriptId> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn remove_user_script(&mut self, _id: UserScriptId) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn execute_script_in_world(
+        &mut self,
+        _view_id: ViewId,
+        _world: &str,
+        _script: &str,
+    ) -> EngineResult<serde_json::Value> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn ping_view(&mut self, _view_id: ViewId) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
     fn suspend_view(&mut self, _view_id: ViewId) -> EngineResult<()> {
         Err(EngineError::Other(
             "Servo engine not available".to_string(),
@@ -149,7 +220,13 @@ impl BrowserEngine for ServoEngine {
         ))
     }
 
-    fn find_in_page(&mut self, _view_id: ViewId, _query: &str, _forward: bool) -> EngineResult<()> {
+    fn find_in_page(
+        &mut self,
+        _view_id: ViewId,
+        _query: &str,
+        _options: &FindOptions,
+        _forward: bool,
+    ) -> EngineResult<()> {
         Err(EngineError::Other(
             "Servo engine not available".to_string(),
         ))
@@ -161,10 +238,130 @@ impl BrowserEngine for ServoEngine {
         ))
     }
 
+    fn apply_settings(&mut self, _settings: &EngineSettings) -> EngineResult<()> {
+        Err(EngineError::Other("Servo engine not available".to_string()))
+    }
+
+    fn set_request_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn RequestInterceptor>>,
+    ) -> EngineResult<()> {
+        // Kept so that the interceptor is in place as soon as Servo's
+        // resource thread exists; every fetch will be routed through it.
+        self.interceptor = interceptor;
+        Ok(())
+    }
+
+    fn set_navigation_interceptor(
+        &mut self,
+        interceptor: Option<Arc<dyn NavigationInterceptor>>,
+    ) -> EngineResult<()> {
+        // Kept like the request interceptor, for Servo's navigation checks
+        self.navigation_interceptor = interceptor;
+        Ok(())
+    }
+
+    fn respond_permission(
+        &mut self,
+        _view_id: ViewId,
+        _origin: &str,
+        _kind: PermissionKind,
+        _granted: bool,
+    ) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn list_cookies(&self, _origin: Option<&str>) -> EngineResult<Vec<Cookie>> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn delete_cookie(&mut self, _cookie: &Cookie) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn clear_cookies(&mut self) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn clear_site_data(
+        &mut self,
+        _origin: Option<&str>,
+        _kinds: &[SiteDataKind],
+    ) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn site_data_usage(&self) -> EngineResult<Vec<SiteDataUsage>> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn capture_view(&self, _view_id: ViewId, _options: CaptureOptions) -> EngineResult<Vec<u8>> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn set_zoom(&mut self, _view_id: ViewId, _zoom: Zoom) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn get_zoom(&self, _view_id: ViewId) -> EngineResult<Zoom> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn accept_certificate_exception(
+        &mut self,
+        _view_id: ViewId,
+        _host: &str,
+        _fingerprint: &str,
+    ) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn page_source(&self, _view_id: ViewId) -> EngineResult<Option<String>> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
+    fn print_to_pdf(
+        &mut self,
+        _view_id: ViewId,
+        _options: &PdfOptions,
+        _path: &Path,
+    ) -> EngineResult<()> {
+        Err(EngineError::Other(
+            "Servo engine not available".to_string(),
+        ))
+    }
+
     fn engine_info(&self) -> (String, String) {
         ("Servo".to_string(), "0.0.0-stub".to_string())
     }
 
+    fn capabilities(&self) -> EngineCapabilities {
+        // Nothing works until Servo is integrated
+        EngineCapabilities::default()
+    }
+
     fn poll_events(&mut self) -> Vec<EngineEvent> {
         Vec::new()
     }
@@ -183,6 +380,7 @@ mod tests {
     #[test]
     fn test_servo_stub_returns_errors() {
         let mut engine = ServoEngine::new();
+        assert_eq!(engine.capabilities(), EngineCapabilities::default());
         assert!(engine.create_view(ViewId(1)).is_err());
         assert!(engine.load_url(ViewId(1), "https://example.com").is_err());
     }
@@ -10,17 +10,46 @@ mod core;
 mod engines;
 mod ui;
 
-use crate::core::blocker::{ContentBlocker, DEFAULT_FILTERS};
+use crate::core::blocker::{ContentBlocker, COSMETIC_WORLD, DEFAULT_FILTERS};
+use crate::core::capture::ScreenshotArgs;
 use crate::core::config::Config;
+use crate::core::downloads::DownloadManager;
+use crate::core::engine::{
+    BrowserEngine, EngineError, EngineEvent, EngineResult, InjectionTime, InterceptorChain,
+    NavigationChain, ScriptWorld, ViewId,
+};
+use crate::core::engine_host::EngineHost;
+use crate::core::https_only::{HttpsOnly, HttpsOnlyPolicy};
+use crate::core::media_policy::MediaPolicies;
 use crate::core::memory::{monitor_memory_pressure_loop, MemoryMonitorConfig, MemoryPressure};
+use crate::core::permissions::PermissionStore;
+use crate::core::popups::PopupBlocker;
+use crate::core::proxy;
+use crate::core::zoom::ZoomStore;
+use crate::core::site_data;
 use crate::core::tab::{SuspensionConfig, TabManager};
 use crate::core::updater;
+use crate::core::user_agent::UserAgents;
+use crate::ui::actions::{connect_shortcuts, ActionDispatcher};
+use crate::ui::find_bar::FindHandler;
+use crate::ui::pages::PageContext;
+use crate::ui::permission_bar::PermissionPrompter;
+use crate::ui::popup_bar::PopupHandler;
+use crate::ui::shortcuts::{default_shortcuts, vim_hints_js, HINTS_WORLD};
+use crate::ui::tab_bar::TabList;
 
+use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::Application;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
 
 const APP_ID: &str = "com.asteroid.browser";
+/// How long `--screenshot` waits for the page to finish loading.
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(30);
 
 fn main() {
     // Initialize logging
@@ -49,86 +78,415 @@ fn main() {
         blocker.set_enabled(false);
         log::info!("Content blocker disabled");
     }
+    let blocker = Arc::new(Mutex::new(blocker));
 
-    // Create the engine
-    let mut engine = engines::create_default_engine();
+    // Create the engine; others are loaded when a tab asks for them
+    let mut engine = engines::create_engine_set(&config.engine.current);
     if let Err(e) = engine.initialize() {
         log::error!("Failed to initialize engine: {}", e);
     }
+    if let Err(e) = proxy::validate(&config.network) {
+        log::warn!("Invalid [network] settings: {}", e);
+    }
+    if let Err(e) = engine.apply_settings(&config.engine_settings()) {
+        log::warn!("Failed to apply engine settings: {}", e);
+    }
+    // Features the engine lacks are left off rather than failing later
+    let caps = engine.capabilities();
+
+    // Upgrade insecure loads before they reach the content blocker
+    let https_only = Arc::new(HttpsOnlyPolicy::new(
+        config.privacy.https_only,
+        Some(Config::data_path("https_exemptions.json")),
+    ));
+    let interceptor = InterceptorChain::new(vec![https_only.clone(), blocker.clone()]);
+
+    // Route every subresource load through the interceptors
+    if !caps.request_interception {
+        log::info!("Engine cannot intercept requests; content blocking is off");
+    } else if let Err(e) = engine.set_request_interceptor(Some(Arc::new(interceptor))) {
+        log::warn!("Failed to install request interceptor: {}", e);
+    }
+    // Each document is requested with the agent for its own site
+    let user_agents = Arc::new(UserAgents::new(
+        &config.engine.user_agent,
+        &config.engine.user_agent_rules,
+    ));
+    // and every top-level navigation, including links and redirects
+    let navigation = NavigationChain::new(vec![https_only.clone(), user_agents.clone()]);
+    if caps.request_interception {
+        if let Err(e) = engine.set_navigation_interceptor(Some(Arc::new(navigation))) {
+            log::warn!("Failed to install navigation interceptor: {}", e);
+        }
+    }
+
+    // Browser features running inside pages, out of reach of page scripts
+    if caps.script_execution {
+        let hints = engine.add_user_script(
+            vim_hints_js(),
+            InjectionTime::End,
+            ScriptWorld::isolated(HINTS_WORLD),
+            &["http://*", "https://*"],
+        );
+        if let Err(e) = hints {
+            log::warn!("Failed to add link hints script: {}", e);
+        }
+    }
+    let cosmetic_js = {
+        let blocker = blocker.lock().unwrap_or_else(|e| e.into_inner());
+        (caps.script_execution && blocker.is_enabled()).then(|| blocker.cosmetic_filter_js())
+    };
+    if let Some(script) = cosmetic_js {
+        let cosmetic = engine.add_user_script(
+            &script,
+            InjectionTime::Start,
+            ScriptWorld::isolated(COSMETIC_WORLD),
+            &["http://*", "https://*"],
+        );
+        if let Err(e) = cosmetic {
+            log::warn!("Failed to add cosmetic filter script: {}", e);
+        }
+    }
 
     // Configure hardware acceleration
-    if config.performance.hardware_acceleration {
+    if config.performance.hardware_acceleration && caps.hardware_video() {
         if let Err(e) = engine.enable_hardware_acceleration(true) {
             log::warn!("Failed to enable hardware acceleration: {}", e);
         }
     }
 
+    // Headless capture for scripts: --screenshot <url> <output.png>
+    if let Some(args) = ScreenshotArgs::parse(std::env::args().skip(1)) {
+        let result = args
+            .map_err(EngineError::Other)
+            .and_then(|args| take_screenshot(&mut engine, &args));
+        if let Err(e) = result {
+            log::error!("Screenshot failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Set up tab manager
     let suspension_config = SuspensionConfig {
-        enabled: config.general.tab_suspension_enabled,
+        enabled: config.general.tab_suspension_enabled && caps.suspend,
         inactive_threshold: Duration::from_secs(config.general.tab_suspension_delay),
         max_active_tabs: config.performance.max_active_tabs,
         suspend_pinned: false,
     };
-    let mut tab_manager = TabManager::new(suspension_config);
+    let tab_manager = TabManager::new(suspension_config);
 
-    // Create initial tab
-    match tab_manager.create_tab(engine.as_mut()) {
-        Ok(view_id) => {
-            let home = &config.general.home_page;
-            if let Err(e) = engine.load_url(view_id, home) {
-                log::error!("Failed to load home page: {}", e);
-            }
+    // Hand the engine and tabs over to their own thread
+    let host = match EngineHost::spawn(engine, tab_manager) {
+        Ok(host) => host,
+        Err(e) => {
+            log::error!("Failed to start engine thread: {}", e);
+            return;
         }
+    };
+    let handle = host.handle();
+
+    // Zoom levels chosen per site are kept across sessions
+    let zoom_store = ZoomStore::new(Some(Config::data_path("zoom.json")));
+    if let Err(e) = handle.call_blocking(move |host| host.zoom = zoom_store) {
+        log::error!("Failed to load zoom levels: {}", e);
+    }
+    let policy = https_only.clone();
+    if let Err(e) = handle.call_blocking(move |host| host.https_only = HttpsOnly::new(policy)) {
+        log::error!("Failed to enable HTTPS-only mode: {}", e);
+    }
+    if let Err(e) = handle.call_blocking(move |host| host.user_agents = user_agents) {
+        log::error!("Failed to set up user agents: {}", e);
+    }
+    let media = config.media.clone();
+    if let Err(e) =
+        handle.call_blocking(move |host| host.media_policies = MediaPolicies::new(&media))
+    {
+        log::error!("Failed to set up media policies: {}", e);
+    }
+
+    // Set up async runtime for background tasks
+    let rt = match tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
         Err(e) => {
-            log::error!("Failed to create initial tab: {}", e);
+            log::error!("Failed to start async runtime: {}", e);
+            return;
         }
+    };
+
+    // Create initial tab
+    let home = config.general.home_page.clone();
+    let initial_tab = rt.block_on(async {
+        let view_id = handle.create_tab().await?;
+        handle.switch_to_tab(view_id).await?;
+        handle.load_url(view_id, &home).await
+    });
+    if let Err(e) = initial_tab {
+        log::error!("Failed to open home page: {}", e);
     }
 
+    let downloads = Arc::new(tokio::sync::Mutex::new(DownloadManager::new(
+        config.general.download_path(),
+        Some(Config::data_path("downloads.json")),
+        &config.network,
+    )));
+
     // Start the GTK4 application
     let app = Application::builder().application_id(APP_ID).build();
 
+    // Shared with the settings page, which changes it while running
+    let shared_config = Rc::new(RefCell::new(config.clone()));
+
+    let ui_handle = handle.clone();
+    let block_popups = config.privacy.block_popups;
+    let page_config = shared_config.clone();
+    let engines = engines::create_registry().engine_infos();
+    let page_runtime = rt.handle().clone();
+    let page_downloads = downloads.clone();
+    let page_blocker = blocker.clone();
+    let script_blocker = blocker.clone();
     app.connect_activate(move |app| {
-        let window = ui::window::build_window(app);
+        let browser = ui::window::build_window(app);
         ui::window::load_css();
-        window.present();
-    });
+        browser.window.present();
 
-    // Set up async runtime for background tasks
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(2)
-        .enable_all()
-        .build();
+        let handle = ui_handle.clone();
+        let permission_store = Rc::new(RefCell::new(PermissionStore::new(Some(
+            Config::data_path("permissions.json"),
+        ))));
+        let prompter = PermissionPrompter::new(
+            browser.permission_bar.clone(),
+            permission_store.clone(),
+            handle.clone(),
+        );
+        let popup_blocker = Rc::new(RefCell::new(PopupBlocker::new(block_popups)));
+        let popups = PopupHandler::new(
+            browser.popup_bar.clone(),
+            popup_blocker.clone(),
+            permission_store.clone(),
+            handle.clone(),
+        );
+        let find = FindHandler::new(browser.find_bar.clone(), handle.clone());
+        let tab_list = TabList::new(browser.tab_list.clone(), handle.clone(), page_runtime.clone());
+        tab_list.refresh();
+        let dispatcher = ActionDispatcher::new(
+            browser.window.clone(),
+            handle.clone(),
+            page_runtime.clone(),
+            page_config.clone(),
+            page_downloads.clone(),
+            find.clone(),
+        );
+        connect_shortcuts(&browser.window, default_shortcuts(), dispatcher);
+        let pages = ui::pages::builtin_pages();
+        let page_context = PageContext {
+            handle: handle.clone(),
+            runtime: page_runtime.clone(),
+            config: page_config.clone(),
+            config_path: Some(Config::config_path()),
+            engines: engines.clone(),
+            permissions: permission_store,
+            downloads: page_downloads.clone(),
+            blocker: page_blocker.clone(),
+            https_only: https_only.clone(),
+            popups: popup_blocker,
+        };
 
-    if let Ok(rt) = rt {
-        rt.spawn(async move {
-            // Start memory pressure monitor
-            let mem_config = MemoryMonitorConfig::default();
-            let (pressure_tx, mut pressure_rx) =
-                tokio::sync::mpsc::channel::<MemoryPressure>(10);
-
-            tokio::spawn(monitor_memory_pressure_loop(mem_config, pressure_tx));
-
-            // Start update checker
-            if config.general.auto_update_check {
-                let (update_tx, mut update_rx) =
-                    tokio::sync::mpsc::channel(1);
-                updater::start_update_checker(update_tx);
-
-                tokio::spawn(async move {
-                    while let Some(info) = update_rx.recv().await {
-                        log::info!(
-                            "Update available: v{} - {}",
-                            info.version,
-                            info.release_url
+        // Route engine events to the window
+        let blocker = script_blocker.clone();
+        let window = browser.window.clone();
+        let status = browser.status_label.clone();
+        let mut events = handle.subscribe();
+        glib::timeout_add_local(Duration::from_millis(50), move || {
+            loop {
+                match events.try_recv() {
+                    Ok(event @ EngineEvent::PermissionRequested(..)) => {
+                        prompter.handle_event(&event);
+                    }
+                    Ok(event @ EngineEvent::NewViewRequested(..)) => {
+                        popups.handle_event(&event);
+                    }
+                    Ok(event @ EngineEvent::UrlChanged(..)) => {
+                        prompter.handle_event(&event);
+                        popups.handle_event(&event);
+                        find.handle_event(&event);
+                    }
+                    Ok(event @ EngineEvent::ViewClosed(..)) => {
+                        prompter.handle_event(&event);
+                        tab_list.refresh();
+                    }
+                    Ok(event @ EngineEvent::FindResult(..)) => {
+                        find.handle_event(&event);
+                    }
+                    Ok(EngineEvent::TitleChanged(view_id, title)) => {
+                        // Keep the window title in sync with the active tab
+                        let active = handle
+                            .call_blocking(|host| host.tabs.active_tab_id())
+                            .ok()
+                            .flatten();
+                        if active == Some(view_id) {
+                            window.set_title(Some(&format!("{} - Asteroid Browser", title)));
+                        }
+                    }
+                    Ok(EngineEvent::CertificateError(view_id, error)) => {
+                        log::warn!("Certificate error for {}: {:?}", error.host, error.kind);
+                        let result = handle.call_blocking(move |host| {
+                            ui::cert_error::show_interstitial(&mut host.engine, view_id, &error)
+                        });
+                        if let Ok(Err(e)) | Err(e) = result {
+                            log::error!("Failed to show certificate warning: {}", e);
+                        }
+                    }
+                    Ok(EngineEvent::LoadFailed(view_id, error)) => {
+                        log::warn!("Failed to load {}: {:?}", error.url, error.kind);
+                        let result = handle.call_blocking(move |host| {
+                            match host.https_only.take_fallback(view_id) {
+                                Some(url) => ui::https_only::show_fallback(
+                                    &mut host.engine,
+                                    view_id,
+                                    &url,
+                                    &error,
+                                ),
+                                None => Ok(()),
+                            }
+                        });
+                        if let Ok(Err(e)) | Err(e) = result {
+                            log::error!("Failed to show HTTPS-only fallback: {}", e);
+                        }
+                    }
+                    Ok(EngineEvent::InternalPageRequested(view_id, url)) => {
+                        let result = ui::pages::open_internal_page(
+                            &pages,
+                            &page_context,
+                            view_id,
+                            &url,
                         );
+                        if let Err(e) = result {
+                            log::error!("Failed to open {}: {}", url, e);
+                        }
                     }
-                });
+                    Ok(EngineEvent::InternalMessage(view_id, message)) => {
+                        let result = ui::pages::handle_internal_message(
+                            &pages,
+                            &page_context,
+                            view_id,
+                            &message,
+                        );
+                        if let Err(e) = result {
+                            log::error!("Failed to handle internal message: {}", e);
+                        }
+                    }
+                    Ok(EngineEvent::UserScriptMessage(_, world, message)) => {
+                        if world == COSMETIC_WORLD && message["type"] == "hidden" {
+                            let count = message["count"].as_u64().unwrap_or(0);
+                            if let Ok(mut blocker) = blocker.lock() {
+                                blocker.record_hidden(count);
+                            }
+                        }
+                    }
+                    Ok(EngineEvent::ZoomChanged(view_id, zoom)) => {
+                        let active = handle
+                            .call_blocking(|host| host.tabs.active_tab_id())
+                            .ok()
+                            .flatten();
+                        if active == Some(view_id) {
+                            status.set_text(&ui::window::zoom_status(&zoom));
+                        }
+                    }
+                    Ok(EngineEvent::ViewCrashed(view_id, reason)) => {
+                        let result = handle.call_blocking(move |host| {
+                            ui::crash::show_crash_page(host, view_id, &reason)
+                        });
+                        if let Ok(Err(e)) | Err(e) = result {
+                            log::error!("Failed to show crash page: {}", e);
+                        }
+                    }
+                    Ok(EngineEvent::ViewUnresponsive(view_id)) => {
+                        let active = handle
+                            .call_blocking(|host| host.tabs.active_tab_id())
+                            .ok()
+                            .flatten();
+                        if active == Some(view_id) {
+                            status.set_text(ui::crash::UNRESPONSIVE_NOTICE);
+                        }
+                    }
+                    // Speakers of tabs starting or stopping to play
+                    Ok(EngineEvent::MediaStateChanged(..)) => {
+                        tab_list.refresh();
+                    }
+                    Ok(EngineEvent::ViewResponsive(view_id)) => {
+                        // Leave the status alone unless it is the notice of this tab
+                        let active = handle
+                            .call_blocking(|host| host.tabs.active_tab_id())
+                            .ok()
+                            .flatten();
+                        if active == Some(view_id)
+                            && status.text().as_str() == ui::crash::UNRESPONSIVE_NOTICE
+                        {
+                            status.set_text("");
+                        }
+                    }
+                    Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+                    Err(_) => break,
+                }
+            }
+            glib::ControlFlow::Continue
+        });
+    });
+
+    // Start memory pressure monitor
+    let mem_config = MemoryMonitorConfig::default();
+    let (pressure_tx, mut pressure_rx) = tokio::sync::mpsc::channel::<MemoryPressure>(10);
+    rt.spawn(monitor_memory_pressure_loop(mem_config, pressure_tx));
+
+    // Handle memory pressure events on the engine thread
+    let pressure_handle = handle.clone();
+    rt.spawn(async move {
+        while let Some(pressure) = pressure_rx.recv().await {
+            log::warn!("Memory pressure: {:?}", pressure);
+            if let Err(e) = pressure_handle.memory_pressure(pressure).await {
+                log::error!("Failed to handle memory pressure: {}", e);
             }
+        }
+    });
 
-            // Handle memory pressure events
-            while let Some(pressure) = pressure_rx.recv().await {
-                log::warn!("Memory pressure: {:?}", pressure);
+    // Hand engine download requests to the download manager
+    let mut download_events = handle.subscribe();
+    let download_manager = downloads.clone();
+    rt.spawn(async move {
+        loop {
+            match download_events.recv().await {
+                Ok(EngineEvent::DownloadRequested(_, request)) => {
+                    download_manager
+                        .lock()
+                        .await
+                        .start(&request.url, request.suggested_filename.as_deref());
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("Download listener missed {} engine events", n);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // Start update checker
+    if config.general.auto_update_check {
+        let (update_tx, mut update_rx) = tokio::sync::mpsc::channel(1);
+        let network = config.network.clone();
+        rt.spawn(async move {
+            updater::start_update_checker(update_tx, network);
+            while let Some(info) = update_rx.recv().await {
+                log::info!(
+                    "Update available: v{} - {}",
+                    info.version,
+                    info.release_url
+                );
             }
         });
     }
@@ -137,9 +495,51 @@ fn main() {
     let exit_code = app.run();
 
     // Cleanup
-    if let Err(e) = engine.shutdown() {
+    rt.shutdown_background();
+    let config = shared_config.borrow().clone();
+    if config.privacy.clear_cookies_on_close {
+        let allowlist = config.privacy.cookie_allowlist.clone();
+        let cleared = handle.call_blocking(move |host| {
+            site_data::clear_cookies_on_close(&mut host.engine, &allowlist)
+        });
+        match cleared {
+            Ok(Ok(count)) => log::info!("Cleared {} cookies on close", count),
+            Ok(Err(e)) | Err(e) => log::error!("Failed to clear cookies: {}", e),
+        }
+    }
+    if let Err(e) = host.shutdown() {
         log::error!("Engine shutdown error: {}", e);
     }
 
     log::info!("Asteroid Browser exited with code: {:?}", exit_code);
 }
+
+/// Load a page in a fresh view and write a capture of it to disk.
+fn take_screenshot(engine: &mut dyn BrowserEngine, args: &ScreenshotArgs) -> EngineResult<()> {
+    let view_id = ViewId(1);
+    engine.create_view(view_id)?;
+    engine.load_url(view_id, &args.url)?;
+
+    let deadline = std::time::Instant::now() + SCREENSHOT_TIMEOUT;
+    loop {
+        let events = engine.poll_events();
+        if events
+            .iter()
+            .any(|e| matches!(e, EngineEvent::LoadFinished(id) if *id == view_id))
+        {
+            break;
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(EngineError::Other(format!("Timed out loading {}", args.url)));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let png = engine.capture_view(view_id, args.options)?;
+    std::fs::write(&args.output, png).map_err(|e| {
+        EngineError::Other(format!("Failed to write {}: {}", args.output.display(), e))
+    })?;
+    log::info!("Saved screenshot of {} to {}", args.url, args.output.display());
+    engine.destroy_view(view_id)
+}
+
@@ -0,0 +1,564 @@
+//! Browser action dispatch for Asteroid Browser.
+//!
+//! Applies keyboard shortcut and menu actions to the tab manager and
+//! engine. Actions that only affect window chrome (address bar focus,
+//! fullscreen, sidebar, ...) are handed back to the window, as are the
+//! ones that need its dialogs or background work, such as printing and
+//! viewing the source. Actions the active
+//! engine has no capability for are refused. `ActionDispatcher` runs
+//! actions from the window and carries out what comes back.
+
+use crate::core::config::Config;
+use crate::core::downloads::{Download, DownloadManager};
+use crate::core::engine::{
+    BrowserEngine, EngineCapabilities, EngineResult, PdfOptions, ViewId, Zoom,
+};
+use crate::core::engine_host::EngineHandle;
+use crate::core::print::save_pdf;
+use crate::core::tab::{TabManager, TabState};
+use crate::core::zoom::{zoom_in, zoom_out};
+use crate::ui::downloads::{generate_downloads_html, DOWNLOADS_URL};
+use crate::ui::find_bar::FindHandler;
+use crate::ui::print::show_print_dialog;
+use crate::ui::site_data::{generate_site_data_html, SITE_DATA_URL};
+use crate::ui::source::view_source;
+use crate::ui::shortcuts::{find_shortcut, BrowserAction, Shortcut, HINTS_WORLD};
+use gtk4::gdk::ModifierType;
+use gtk4::prelude::*;
+use gtk4::{glib, ApplicationWindow, EventControllerKey};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Everything an action may touch.
+pub struct ActionContext<'a> {
+    pub tabs: &'a mut TabManager,
+    pub engine: &'a mut dyn BrowserEngine,
+    pub config: &'a Config,
+    /// Current downloads, for the downloads page
+    pub downloads: &'a [Download],
+}
+
+/// What happened to a dispatched action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActionOutcome {
+    /// The action was carried out by the core
+    Handled,
+    /// The action must be carried out by the window
+    Window,
+    /// The window must show the print dialog for a view, whose page
+    /// has the given title
+    Print(ViewId, String),
+    /// The window must open the source of a view's page in a new tab,
+    /// which may have to be fetched again
+    ViewSource(ViewId),
+    /// The window must open the find bar; quick find only matches link
+    /// text
+    Find { quick: bool },
+    /// The active engine does not support the action
+    Unsupported,
+}
+
+/// Whether an engine with `caps` can carry out an action; menus and
+/// shortcuts for the others are hidden or disabled.
+pub fn action_supported(action: &BrowserAction, caps: &EngineCapabilities) -> bool {
+    match action {
+        BrowserAction::FindInPage | BrowserAction::QuickFind => caps.find,
+        BrowserAction::VimHints => caps.script_execution,
+        BrowserAction::PrintPage | BrowserAction::SavePdf => caps.printing,
+        _ => true,
+    }
+}
+
+/// Apply a browser action.
+pub fn handle_action(
+    action: &BrowserAction,
+    ctx: &mut ActionContext,
+) -> EngineResult<ActionOutcome> {
+    if !action_supported(action, &ctx.engine.capabilities()) {
+        return Ok(ActionOutcome::Unsupported);
+    }
+    match action {
+        BrowserAction::NewTab => {
+            let view_id = ctx.tabs.create_tab(ctx.engine)?;
+            ctx.tabs.switch_to_tab(view_id, ctx.engine)?;
+            ctx.engine.load_url(view_id, &ctx.config.general.home_page)?;
+        }
+        BrowserAction::CloseTab => {
+            if let Some(view_id) = ctx.tabs.active_tab_id() {
+                ctx.tabs.close_tab(view_id, ctx.engine)?;
+                if let Some(next) = ctx.tabs.active_tab_id() {
+                    ctx.tabs.switch_to_tab(next, ctx.engine)?;
+                }
+            }
+        }
+        BrowserAction::Reload => {
+            if let Some(tab) = ctx.tabs.active_tab() {
+                // A crashed tab shows the crash page; load what crashed
+                if tab.state == TabState::Error {
+                    let (view_id, url) = (tab.view_id, tab.url.clone());
+                    ctx.engine.load_url(view_id, &url)?;
+                } else {
+                    ctx.engine.reload(tab.view_id)?;
+                }
+            }
+        }
+        BrowserAction::GoBack => {
+            if let Some(view_id) = ctx.tabs.active_tab_id() {
+                ctx.engine.go_back(view_id)?;
+            }
+        }
+        BrowserAction::GoForward => {
+            if let Some(view_id) = ctx.tabs.active_tab_id() {
+                ctx.engine.go_forward(view_id)?;
+            }
+        }
+        BrowserAction::SwitchToTab(number) => {
+            // Ctrl+1-8 pick a position, Ctrl+9 always picks the last tab
+            let order: Vec<_> = ctx.tabs.tabs_in_order().iter().map(|t| t.view_id).collect();
+            let target = if *number >= 9 {
+                order.last().copied()
+            } else {
+                order.get((*number as usize).saturating_sub(1)).copied()
+            };
+            if let Some(view_id) = target {
+                ctx.tabs.switch_to_tab(view_id, ctx.engine)?;
+            }
+        }
+        BrowserAction::ZoomIn | BrowserAction::ZoomOut | BrowserAction::ZoomReset => {
+            if let Some(view_id) = ctx.tabs.active_tab_id() {
+                let zoom = ctx.engine.get_zoom(view_id)?;
+                let level = match action {
+                    BrowserAction::ZoomIn => zoom_in(zoom.level),
+                    BrowserAction::ZoomOut => zoom_out(zoom.level),
+                    _ => 1.0,
+                };
+                ctx.engine.set_zoom(view_id, Zoom::new(level, zoom.mode))?;
+            }
+        }
+        BrowserAction::OpenDownloads => {
+            let html = generate_downloads_html(ctx.downloads);
+            open_page(ctx, DOWNLOADS_URL, &html)?;
+        }
+        BrowserAction::SavePdf => {
+            if let Some(tab) = ctx.tabs.active_tab() {
+                let (view_id, title) = (tab.view_id, tab.title.clone());
+                save_pdf(
+                    ctx.engine,
+                    view_id,
+                    &PdfOptions::default(),
+                    &ctx.config.general.download_path(),
+                    &title,
+                )?;
+            }
+        }
+        BrowserAction::PrintPage => {
+            if let Some(tab) = ctx.tabs.active_tab() {
+                return Ok(ActionOutcome::Print(tab.view_id, tab.title.clone()));
+            }
+        }
+        BrowserAction::FindInPage => return Ok(ActionOutcome::Find { quick: false }),
+        BrowserAction::QuickFind => return Ok(ActionOutcome::Find { quick: true }),
+        BrowserAction::ViewSource => {
+            if let Some(view_id) = ctx.tabs.active_tab_id() {
+                return Ok(ActionOutcome::ViewSource(view_id));
+            }
+        }
+        BrowserAction::VimHints => {
+            // Without hints the key belongs to the page
+            if !ctx.config.general.vim_hints {
+                return Ok(ActionOutcome::Window);
+            }
+            if let Some(view_id) = ctx.tabs.active_tab_id() {
+                ctx.engine
+                    .execute_script_in_world(view_id, HINTS_WORLD, "asteroidHints.show()")?;
+            }
+        }
+        BrowserAction::OpenSiteData => {
+            let html = generate_site_data_html(&ctx.engine.site_data_usage()?);
+            open_page(ctx, SITE_DATA_URL, &html)?;
+        }
+        _ => return Ok(ActionOutcome::Window),
+    }
+
+    Ok(ActionOutcome::Handled)
+}
+
+/// Show a generated page, reusing a tab already open at `url` rather
+/// than stacking new ones.
+fn open_page(ctx: &mut ActionContext, url: &str, html: &str) -> EngineResult<()> {
+    let existing = ctx
+        .tabs
+        .tabs_in_order()
+        .iter()
+        .find(|t| t.url == url)
+        .map(|t| t.view_id);
+    let view_id = match existing {
+        Some(view_id) => view_id,
+        None => ctx.tabs.create_tab(ctx.engine)?,
+    };
+    ctx.tabs.switch_to_tab(view_id, ctx.engine)?;
+    ctx.engine.load_html(view_id, html, url)
+}
+
+/// Runs actions for the window: `handle_action` on the engine thread,
+/// then what it hands back to the window.
+#[derive(Clone)]
+pub struct ActionDispatcher {
+    window: ApplicationWindow,
+    handle: EngineHandle,
+    runtime: tokio::runtime::Handle,
+    config: Rc<RefCell<Config>>,
+    downloads: Arc<tokio::sync::Mutex<DownloadManager>>,
+    find: FindHandler,
+}
+
+impl ActionDispatcher {
+    pub fn new(
+        window: ApplicationWindow,
+        handle: EngineHandle,
+        runtime: tokio::runtime::Handle,
+        config: Rc<RefCell<Config>>,
+        downloads: Arc<tokio::sync::Mutex<DownloadManager>>,
+        find: FindHandler,
+    ) -> Self {
+        Self {
+            window,
+            handle,
+            runtime,
+            config,
+            downloads,
+            find,
+        }
+    }
+
+    /// Carry out an action.
+    pub fn dispatch(&self, action: BrowserAction) {
+        let config = self.config.borrow().clone();
+        let downloads = self
+            .runtime
+            .block_on(async { self.downloads.lock().await.list() });
+        let name = format!("{:?}", action);
+        let outcome = self.handle.call_blocking(move |host| {
+            let mut ctx = ActionContext {
+                tabs: &mut host.tabs,
+                engine: &mut host.engine,
+                config: &config,
+                downloads: &downloads,
+            };
+            handle_action(&action, &mut ctx)
+        });
+        match outcome {
+            Ok(Ok(outcome)) => self.finish(outcome),
+            Ok(Err(e)) | Err(e) => log::warn!("Failed to carry out {}: {}", name, e),
+        }
+    }
+
+    /// Do the window's part of an action.
+    fn finish(&self, outcome: ActionOutcome) {
+        match outcome {
+            ActionOutcome::Print(view_id, title) => {
+                show_print_dialog(&self.window, self.handle.clone(), view_id, title);
+            }
+            ActionOutcome::ViewSource(view_id) => {
+                let handle = self.handle.clone();
+                let network = self.config.borrow().network.clone();
+                self.runtime.spawn(async move {
+                    if let Err(e) = view_source(&handle, view_id, &network).await {
+                        log::error!("Failed to show page source: {}", e);
+                    }
+                });
+            }
+            ActionOutcome::Find { quick } => self.find.open(quick),
+            ActionOutcome::Handled | ActionOutcome::Window | ActionOutcome::Unsupported => {}
+        }
+    }
+}
+
+/// Dispatch the actions of shortcuts pressed in the window.
+pub fn connect_shortcuts(
+    window: &ApplicationWindow,
+    shortcuts: Vec<Shortcut>,
+    dispatcher: ActionDispatcher,
+) {
+    let keys = EventControllerKey::new();
+    keys.connect_key_pressed(move |_, key, _, state| {
+        let name = key.name().map(|name| name.to_string()).unwrap_or_default();
+        let action = find_shortcut(
+            &shortcuts,
+            &name,
+            state.contains(ModifierType::CONTROL_MASK),
+            state.contains(ModifierType::ALT_MASK),
+            state.contains(ModifierType::SHIFT_MASK),
+        );
+        match action {
+            Some(action) => {
+                dispatcher.dispatch(action);
+                glib::Propagation::Stop
+            }
+            None => glib::Propagation::Proceed,
+        }
+    });
+    window.add_controller(keys);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::EngineEvent;
+    use crate::core::tab::SuspensionConfig;
+    use crate::engines::mock::MockEngine;
+
+    fn dispatch(
+        action: BrowserAction,
+        tabs: &mut TabManager,
+        engine: &mut MockEngine,
+        config: &Config,
+    ) -> ActionOutcome {
+        let mut ctx = ActionContext {
+            tabs,
+            engine,
+            config,
+            downloads: &[],
+        };
+        let outcome = handle_action(&action, &mut ctx).unwrap();
+        let events: Vec<EngineEvent> = ctx.engine.poll_events();
+        for event in &events {
+            ctx.tabs.handle_engine_event(event);
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_new_tab_and_switching() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let mut config = Config::default();
+        config.general.home_page = "https://example.com/".to_string();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.tab_count(), 3);
+        let order: Vec<_> = tabs.tabs_in_order().iter().map(|t| t.view_id).collect();
+        assert_eq!(tabs.active_tab_id(), Some(order[2]));
+        assert_eq!(tabs.active_tab().unwrap().title, "Example Domain");
+
+        dispatch(BrowserAction::SwitchToTab(1), &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.active_tab_id(), Some(order[0]));
+        assert_eq!(tabs.get_tab(order[2]).unwrap().state, TabState::Background);
+
+        dispatch(BrowserAction::SwitchToTab(9), &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.active_tab_id(), Some(order[2]));
+    }
+
+    #[test]
+    fn test_navigation_actions_use_history() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let config = Config::default();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        let id = tabs.active_tab_id().unwrap();
+        engine.load_url(id, "https://example.com/").unwrap();
+        engine.follow_link(id, 0).unwrap();
+
+        dispatch(BrowserAction::GoBack, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.active_tab().unwrap().url, "https://example.com/");
+        dispatch(BrowserAction::GoForward, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.active_tab().unwrap().title, "Video Test");
+
+        let handle = engine.handle();
+        dispatch(BrowserAction::Reload, &mut tabs, &mut engine, &config);
+        assert_eq!(handle.calls_to("reload").len(), 1);
+
+        // A crashed tab reloads the page that crashed, not the crash page
+        tabs.handle_engine_event(&EngineEvent::ViewCrashed(id, "oom".to_string()));
+        let url = tabs.active_tab().unwrap().url.clone();
+        engine.load_html(id, "<p>crashed</p>", "asteroid://crashed").unwrap();
+        dispatch(BrowserAction::Reload, &mut tabs, &mut engine, &config);
+        assert_eq!(handle.calls_to("reload").len(), 1);
+        assert_eq!(handle.calls_to("load_url").last().unwrap().args, vec![url]);
+        assert_eq!(tabs.active_tab().unwrap().state, TabState::Active);
+    }
+
+    #[test]
+    fn test_close_tab_activates_neighbour() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let config = Config::default();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::CloseTab, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.tab_count(), 1);
+        assert_eq!(tabs.active_tab().unwrap().state, TabState::Active);
+    }
+
+    #[test]
+    fn test_window_actions_are_passed_through() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let config = Config::default();
+
+        let outcome = dispatch(BrowserAction::ToggleFullscreen, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Window);
+        assert!(engine.handle().calls().is_empty());
+    }
+
+    #[test]
+    fn test_open_downloads_reuses_tab() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let config = Config::default();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::OpenDownloads, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.tab_count(), 2);
+        assert_eq!(tabs.active_tab().unwrap().url, DOWNLOADS_URL);
+
+        dispatch(BrowserAction::SwitchToTab(1), &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::OpenDownloads, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.tab_count(), 2);
+        assert_eq!(tabs.active_tab().unwrap().url, DOWNLOADS_URL);
+        assert_eq!(engine.handle().calls_to("load_html").len(), 2);
+    }
+
+    #[test]
+    fn test_open_site_data() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let config = Config::default();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::OpenSiteData, &mut tabs, &mut engine, &config);
+        assert_eq!(tabs.active_tab().unwrap().url, SITE_DATA_URL);
+        assert_eq!(engine.handle().calls_to("site_data_usage").len(), 1);
+    }
+
+    #[test]
+    fn test_save_pdf_to_download_dir() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let mut config = Config::default();
+        let dir = std::env::temp_dir().join(format!("asteroid-actions-pdf-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        config.general.download_dir = dir.display().to_string();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        let outcome = dispatch(BrowserAction::SavePdf, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Handled);
+        let calls = engine.handle().calls_to("print_to_pdf");
+        assert_eq!(calls.len(), 1);
+        assert!(std::path::Path::new(&calls[0].args[0]).starts_with(&dir));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_print_page_opens_dialog_for_active_tab() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let mut config = Config::default();
+        config.general.home_page = "https://example.com/".to_string();
+
+        let outcome = dispatch(BrowserAction::PrintPage, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Handled);
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        let view_id = tabs.active_tab_id().unwrap();
+        // Printing to a real printer goes through the window's dialog
+        let outcome = dispatch(BrowserAction::PrintPage, &mut tabs, &mut engine, &config);
+        assert_eq!(
+            outcome,
+            ActionOutcome::Print(view_id, "Example Domain".to_string())
+        );
+        assert!(engine.handle().calls_to("print_to_pdf").is_empty());
+    }
+
+    #[test]
+    fn test_view_source_of_active_tab() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let mut config = Config::default();
+        config.general.home_page = "https://example.com/".to_string();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        let view_id = tabs.active_tab_id().unwrap();
+        let outcome = dispatch(BrowserAction::ViewSource, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::ViewSource(view_id));
+    }
+
+    #[test]
+    fn test_vim_hints_run_in_isolated_world() {
+        use crate::core::engine::{InjectionTime, ScriptWorld};
+        use crate::ui::shortcuts::vim_hints_js;
+
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let mut config = Config::default();
+        config.general.home_page = "https://example.com/".to_string();
+        engine
+            .add_user_script(
+                vim_hints_js(),
+                InjectionTime::End,
+                ScriptWorld::isolated(HINTS_WORLD),
+                &[],
+            )
+            .unwrap();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        config.general.vim_hints = false;
+        let outcome = dispatch(BrowserAction::VimHints, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Window);
+
+        config.general.vim_hints = true;
+        let outcome = dispatch(BrowserAction::VimHints, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Handled);
+        let calls = engine.handle().calls_to("execute_script_in_world");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].args, vec![HINTS_WORLD, "asteroidHints.show()"]);
+        assert!(engine.handle().calls_to("execute_script").is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_actions_are_refused() {
+        let mut engine = MockEngine::new();
+        engine.set_capabilities(EngineCapabilities {
+            find: true,
+            ..Default::default()
+        });
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let mut config = Config::default();
+        config.general.vim_hints = true;
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        for action in [BrowserAction::SavePdf, BrowserAction::PrintPage, BrowserAction::VimHints] {
+            let outcome = dispatch(action.clone(), &mut tabs, &mut engine, &config);
+            assert_eq!(outcome, ActionOutcome::Unsupported, "{:?}", action);
+        }
+        assert!(engine.handle().calls_to("print_to_pdf").is_empty());
+        let outcome = dispatch(BrowserAction::FindInPage, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Find { quick: false });
+        let outcome = dispatch(BrowserAction::QuickFind, &mut tabs, &mut engine, &config);
+        assert_eq!(outcome, ActionOutcome::Find { quick: true });
+    }
+
+    #[test]
+    fn test_zoom_actions() {
+        let mut engine = MockEngine::new();
+        let mut tabs = TabManager::new(SuspensionConfig::default());
+        let config = Config::default();
+
+        dispatch(BrowserAction::NewTab, &mut tabs, &mut engine, &config);
+        let id = tabs.active_tab_id().unwrap();
+        dispatch(BrowserAction::ZoomIn, &mut tabs, &mut engine, &config);
+        dispatch(BrowserAction::ZoomIn, &mut tabs, &mut engine, &config);
+        assert_eq!(engine.get_zoom(id).unwrap().level, 1.2);
+        dispatch(BrowserAction::ZoomOut, &mut tabs, &mut engine, &config);
+        assert_eq!(engine.get_zoom(id).unwrap().level, 1.1);
+        dispatch(BrowserAction::ZoomReset, &mut tabs, &mut engine, &config);
+        assert_eq!(engine.get_zoom(id).unwrap(), Zoom::default());
+    }
+}
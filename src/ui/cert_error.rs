@@ -0,0 +1,316 @@
+//! Certificate error interstitial for Asteroid Browser.
+//!
+//! When an engine stops a navigation with `EngineEvent::CertificateError`,
+//! `show_interstitial` loads a warning page into the view at
+//! `asteroid://cert-error?url=...`. Its "Go back" and "Proceed anyway"
+//! links are turned into engine calls by `parse_cert_error_command` and
+//! `handle_cert_error_command`.
+
+use crate::core::engine::{BrowserEngine, CertificateError, EngineResult, ViewId};
+use crate::ui::{encode_query_value, escape_html, query_param};
+
+/// Address of the interstitial.
+pub const CERT_ERROR_URL: &str = "asteroid://cert-error";
+
+/// Action picked on the interstitial.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertErrorCommand {
+    /// Leave the site
+    GoBack,
+    /// Trust the certificate for this session and load the page
+    Proceed { host: String, fingerprint: String },
+}
+
+/// Interstitial address for a stopped navigation.
+pub fn cert_error_page_url(url: &str) -> String {
+    format!("{}?url={}", CERT_ERROR_URL, encode_query_value(url))
+}
+
+fn proceed_link(error: &CertificateError) -> String {
+    format!(
+        "{}/proceed?host={}&fingerprint={}",
+        CERT_ERROR_URL,
+        encode_query_value(&error.host),
+        encode_query_value(error.fingerprint().unwrap_or_default())
+    )
+}
+
+/// Parse a control link produced by the interstitial.
+pub fn parse_cert_error_command(url: &str) -> Option<CertErrorCommand> {
+    let rest = url.strip_prefix(CERT_ERROR_URL)?.strip_prefix('/')?;
+    let (action, _) = rest.split_once('?').unwrap_or((rest, ""));
+    match action {
+        "back" => Some(CertErrorCommand::GoBack),
+        "proceed" => {
+            let host = query_param(url, "host").filter(|h| !h.is_empty())?;
+            let fingerprint = query_param(url, "fingerprint").filter(|f| !f.is_empty())?;
+            Some(CertErrorCommand::Proceed { host, fingerprint })
+        }
+        _ => None,
+    }
+}
+
+/// `YYYY-MM-DD` for a Unix timestamp.
+fn format_date(timestamp: u64) -> String {
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn chain_rows(error: &CertificateError) -> String {
+    error
+        .chain
+        .iter()
+        .map(|cert| {
+            format!(
+                r#"        <div class="cert">
+            <div><span>Subject</span>{subject}</div>
+            <div><span>Issuer</span>{issuer}</div>
+            <div><span>Valid</span>{from} to {until}</div>
+            <div><span>SHA-256</span><code>{fingerprint}</code></div>
+        </div>
+"#,
+                subject = escape_html(&cert.subject),
+                issuer = escape_html(&cert.issuer),
+                from = format_date(cert.not_before),
+                until = format_date(cert.not_after),
+                fingerprint = escape_html(&cert.fingerprint),
+            )
+        })
+        .collect()
+}
+
+/// Generate the interstitial HTML for a certificate error.
+pub fn generate_cert_error_html(error: &CertificateError) -> String {
+    // Without a fingerprint there is nothing an exception could be tied to
+    let proceed = if error.fingerprint().is_some() {
+        format!(
+            r#"<a class="proceed" href="{}">Proceed to {} (unsafe)</a>"#,
+            escape_html(&proceed_link(error)),
+            escape_html(&error.host)
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Security warning</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 640px;
+            margin: 0 auto;
+            padding: 80px 20px 40px;
+        }}
+        h1 {{
+            color: #e57373;
+            margin-bottom: 20px;
+            font-size: 28px;
+        }}
+        p {{ line-height: 1.5; margin-bottom: 12px; }}
+        .host {{ color: #7DC6DA; }}
+        .actions {{
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            margin: 30px 0;
+        }}
+        .back {{
+            background-color: #7DC6DA;
+            color: #0a0e1a;
+            padding: 10px 20px;
+            border-radius: 4px;
+            text-decoration: none;
+        }}
+        .proceed {{ color: #888; font-size: 14px; }}
+        details {{ font-size: 13px; color: #888; }}
+        summary {{ cursor: pointer; margin-bottom: 10px; }}
+        .cert {{
+            border-top: 1px solid #16213e;
+            padding: 10px 0;
+        }}
+        .cert span {{ display: inline-block; width: 80px; color: #555; }}
+        code {{ word-break: break-all; }}
+    </style>
+</head>
+<body>
+    <h1>Your connection is not secure</h1>
+    <p>Asteroid stopped loading <span class="host">{host}</span> because its certificate could not be trusted. Someone could be trying to intercept your connection.</p>
+    <p>{reason}</p>
+    <div class="actions">
+        <a class="back" href="{url}/back">Go back</a>
+        {proceed}
+    </div>
+    <details>
+        <summary>Certificate details ({kind})</summary>
+{chain}    </details>
+</body>
+</html>"#,
+        host = escape_html(&error.host),
+        reason = error.kind.description(),
+        url = CERT_ERROR_URL,
+        proceed = proceed,
+        kind = escape_html(&format!("{:?}", error.kind)),
+        chain = chain_rows(error),
+    )
+}
+
+/// Show the interstitial for a stopped navigation in its view.
+pub fn show_interstitial(
+    engine: &mut dyn BrowserEngine,
+    view_id: ViewId,
+    error: &CertificateError,
+) -> EngineResult<()> {
+    engine.load_html(
+        view_id,
+        &generate_cert_error_html(error),
+        &cert_error_page_url(&error.url),
+    )
+}
+
+/// Carry out an action picked on the interstitial.
+pub fn handle_cert_error_command(
+    engine: &mut dyn BrowserEngine,
+    view_id: ViewId,
+    command: &CertErrorCommand,
+) -> EngineResult<()> {
+    match command {
+        CertErrorCommand::GoBack => {
+            if engine.get_navigation_state(view_id)?.can_go_back {
+                engine.go_back(view_id)
+            } else {
+                engine.load_url(view_id, "about:blank")
+            }
+        }
+        CertErrorCommand::Proceed { host, fingerprint } => {
+            engine.accept_certificate_exception(view_id, host, fingerprint)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::{CertificateErrorKind, CertificateInfo, EngineEvent};
+    use crate::engines::mock::{MockEngine, MockEvent, MockFixture, MockPage};
+
+    fn expired_error() -> CertificateError {
+        CertificateError {
+            url: "https://expired.test/login".to_string(),
+            host: "expired.test".to_string(),
+            kind: CertificateErrorKind::Expired,
+            chain: vec![CertificateInfo {
+                subject: "CN=expired.test".to_string(),
+                issuer: "CN=<Test CA>".to_string(),
+                not_before: 1_577_836_800,
+                not_after: 1_609_459_199,
+                fingerprint: "AB:CD".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1_577_836_800), "2020-01-01");
+        assert_eq!(format_date(1_709_164_800), "2024-02-29");
+    }
+
+    #[test]
+    fn test_cert_error_html_generation() {
+        let html = generate_cert_error_html(&expired_error());
+        assert!(html.contains("The certificate has expired."));
+        assert!(html.contains("CN=&lt;Test CA&gt;"));
+        assert!(html.contains("2020-01-01 to 2020-12-31"));
+        assert!(html
+            .contains("asteroid://cert-error/proceed?host=expired.test&amp;fingerprint=AB%3ACD"));
+
+        let mut error = expired_error();
+        error.chain.clear();
+        assert!(!generate_cert_error_html(&error).contains("Proceed"));
+    }
+
+    #[test]
+    fn test_parse_cert_error_command() {
+        assert_eq!(
+            parse_cert_error_command("asteroid://cert-error/back"),
+            Some(CertErrorCommand::GoBack)
+        );
+        assert_eq!(
+            parse_cert_error_command(&proceed_link(&expired_error())),
+            Some(CertErrorCommand::Proceed {
+                host: "expired.test".to_string(),
+                fingerprint: "AB:CD".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_cert_error_command("asteroid://cert-error/proceed?host=a.test"),
+            None
+        );
+        assert_eq!(
+            parse_cert_error_command(&cert_error_page_url("https://a.test/")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_interstitial_flow() {
+        let fixture = MockFixture::test_pages().page(
+            "https://expired.test/",
+            MockPage::new("Expired").event(MockEvent::CertificateError {
+                kind: CertificateErrorKind::Expired,
+                fingerprint: "11:22".to_string(),
+            }),
+        );
+        let mut engine = MockEngine::with_fixture(fixture);
+        let id = ViewId(1);
+        engine.create_view(id).unwrap();
+        engine.load_url(id, "https://example.com/").unwrap();
+        engine.load_url(id, "https://expired.test/").unwrap();
+        let error = engine
+            .poll_events()
+            .into_iter()
+            .find_map(|e| match e {
+                EngineEvent::CertificateError(_, error) => Some(error),
+                _ => None,
+            })
+            .unwrap();
+
+        show_interstitial(&mut engine, id, &error).unwrap();
+        let state = engine.get_navigation_state(id).unwrap();
+        assert_eq!(state.url, cert_error_page_url("https://expired.test/"));
+
+        // Going back leaves the interstitial for the previous page
+        handle_cert_error_command(&mut engine, id, &CertErrorCommand::GoBack).unwrap();
+        assert_eq!(
+            engine.get_navigation_state(id).unwrap().url,
+            "https://example.com/"
+        );
+
+        engine.load_url(id, "https://expired.test/").unwrap();
+        engine.poll_events();
+        let command = parse_cert_error_command(&proceed_link(&error)).unwrap();
+        handle_cert_error_command(&mut engine, id, &command).unwrap();
+        assert_eq!(
+            engine.get_navigation_state(id).unwrap().url,
+            "https://expired.test/"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Crashed page for Asteroid Browser.
+//!
+//! When the content process of a view dies (`EngineEvent::ViewCrashed`),
+//! `show_crash_page` loads a notice into the view at
+//! `asteroid://crashed?url=...`. The tab keeps the address of the page
+//! that crashed, which the engine host reloads on its own with backoff;
+//! the "Reload" link does it right away.
+
+use crate::core::engine::{BrowserEngine, EngineError, EngineResult, ViewId};
+use crate::core::engine_host::HostState;
+use crate::core::site_data::host_of;
+use crate::ui::{encode_query_value, escape_html};
+use std::time::{Duration, Instant};
+
+/// Address of the crash page.
+pub const CRASHED_URL: &str = "asteroid://crashed";
+
+/// Status bar notice while the active tab's page is hung.
+pub const UNRESPONSIVE_NOTICE: &str = "This page is not responding";
+
+/// Action picked on the crash page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrashCommand {
+    /// Load the page that crashed again
+    Reload,
+}
+
+/// Crash page address for a crash of `url`.
+pub fn crash_page_url(url: &str) -> String {
+    format!("{}?url={}", CRASHED_URL, encode_query_value(url))
+}
+
+/// Parse a control link produced by the crash page.
+pub fn parse_crash_command(url: &str) -> Option<CrashCommand> {
+    let rest = url.strip_prefix(CRASHED_URL)?.strip_prefix('/')?;
+    let (action, _) = rest.split_once('?').unwrap_or((rest, ""));
+    match action {
+        "reload" => Some(CrashCommand::Reload),
+        _ => None,
+    }
+}
+
+/// Generate the crash page for `url`; `retry` is the wait before the
+/// automatic reload, if there is one.
+pub fn generate_crash_html(url: &str, reason: &str, retry: Option<Duration>) -> String {
+    let retry = match retry {
+        Some(wait) => format!(
+            "Asteroid will reload it in {} seconds.",
+            wait.as_secs_f64().ceil() as u64
+        ),
+        None => "It kept crashing, so Asteroid stopped reloading it.".to_string(),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Page crashed</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 640px;
+            margin: 0 auto;
+            padding: 80px 20px 40px;
+        }}
+        h1 {{
+            color: #e57373;
+            margin-bottom: 20px;
+            font-size: 28px;
+        }}
+        p {{ line-height: 1.5; margin-bottom: 12px; }}
+        .host {{ color: #7DC6DA; }}
+        .reason {{ color: #888; font-size: 14px; }}
+        .actions {{ margin: 30px 0; }}
+        .reload {{
+            background-color: #7DC6DA;
+            color: #0a0e1a;
+            padding: 10px 20px;
+            border-radius: 4px;
+            text-decoration: none;
+        }}
+    </style>
+</head>
+<body>
+    <h1>This page crashed</h1>
+    <p>The page on <span class="host">{host}</span> stopped working. Other tabs are not affected.</p>
+    <p>{retry}</p>
+    <p class="reason">{reason}</p>
+    <div class="actions">
+        <a class="reload" href="{base}/reload">Reload</a>
+    </div>
+</body>
+</html>"#,
+        host = escape_html(host_of(url)),
+        retry = retry,
+        reason = escape_html(reason),
+        base = CRASHED_URL,
+    )
+}
+
+/// Page that crashed in a view: the one recorded by the crash recovery,
+/// or the tab's address.
+fn crashed_url(host: &HostState, view_id: ViewId) -> EngineResult<String> {
+    host.crashes
+        .get(view_id)
+        .map(|crash| crash.url.clone())
+        .or_else(|| host.tabs.get_tab(view_id).map(|tab| tab.url.clone()))
+        .ok_or(EngineError::ViewNotFound(view_id))
+}
+
+/// Show the crash page in a view whose content process died.
+pub fn show_crash_page(host: &mut HostState, view_id: ViewId, reason: &str) -> EngineResult<()> {
+    let url = crashed_url(host, view_id)?;
+    let retry = host
+        .crashes
+        .get(view_id)
+        .and_then(|crash| crash.reload_in(Instant::now()));
+    host.crashes.showing_page(view_id);
+    host.engine.load_html(
+        view_id,
+        &generate_crash_html(&url, reason, retry),
+        &crash_page_url(&url),
+    )
+}
+
+/// Carry out an action picked on the crash page.
+pub fn handle_crash_command(
+    host: &mut HostState,
+    view_id: ViewId,
+    command: &CrashCommand,
+) -> EngineResult<()> {
+    match command {
+        CrashCommand::Reload => {
+            let url = crashed_url(host, view_id)?;
+            host.crashes.navigated(view_id);
+            host.engine.load_url(view_id, &url)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_page() {
+        let html = generate_crash_html(
+            "https://heavy.test/app",
+            "<oom>",
+            Some(Duration::from_millis(1500)),
+        );
+        assert!(html.contains("heavy.test</span>"));
+        assert!(html.contains("reload it in 2 seconds"));
+        assert!(html.contains("&lt;oom&gt;"));
+        assert!(html.contains("asteroid://crashed/reload"));
+        assert!(generate_crash_html("https://heavy.test/", "oom", None)
+            .contains("stopped reloading"));
+
+        assert_eq!(
+            crash_page_url("https://heavy.test/app"),
+            "asteroid://crashed?url=https%3A%2F%2Fheavy.test%2Fapp"
+        );
+        assert_eq!(
+            parse_crash_command("asteroid://crashed/reload"),
+            Some(CrashCommand::Reload)
+        );
+        assert_eq!(parse_crash_command(&crash_page_url("https://a.test/")), None);
+    }
+}
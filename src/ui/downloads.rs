@@ -0,0 +1,229 @@
+//! Downloads page for Asteroid Browser.
+//!
+//! Generates the HTML list of downloads displayed at asteroid://downloads.
+//! Pause, resume and cancel controls are links back into the page that
+//! `parse_downloads_command` turns into manager calls.
+
+use crate::core::downloads::{Download, DownloadId, DownloadState};
+use crate::ui::{escape_html, format_bytes};
+
+/// Address of the downloads page.
+pub const DOWNLOADS_URL: &str = "asteroid://downloads";
+
+/// Action requested from the downloads page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadCommand {
+    Pause(DownloadId),
+    Resume(DownloadId),
+    Cancel(DownloadId),
+    ClearFinished,
+}
+
+/// Parse a control link such as `asteroid://downloads/pause/3`.
+pub fn parse_downloads_command(url: &str) -> Option<DownloadCommand> {
+    let rest = url.strip_prefix(DOWNLOADS_URL)?.strip_prefix('/')?;
+    if rest == "clear" {
+        return Some(DownloadCommand::ClearFinished);
+    }
+    let (action, id) = rest.split_once('/')?;
+    let id: DownloadId = id.parse().ok()?;
+    match action {
+        "pause" => Some(DownloadCommand::Pause(id)),
+        "resume" => Some(DownloadCommand::Resume(id)),
+        "cancel" => Some(DownloadCommand::Cancel(id)),
+        _ => None,
+    }
+}
+
+fn download_row(download: &Download) -> String {
+    let size = match download.total_bytes {
+        Some(total) => format!(
+            "{} of {}",
+            format_bytes(download.received_bytes),
+            format_bytes(total)
+        ),
+        None => format_bytes(download.received_bytes),
+    };
+    let percent = download.progress().map(|p| p * 100.0).unwrap_or(0.0);
+
+    let mut controls = String::new();
+    let mut control = |action: &str, label: &str| {
+        controls.push_str(&format!(
+            r#"<a href="{}/{}/{}">{}</a>"#,
+            DOWNLOADS_URL, action, download.id, label
+        ));
+    };
+    match download.state {
+        DownloadState::InProgress => {
+            control("pause", "Pause");
+            control("cancel", "Cancel");
+        }
+        DownloadState::Paused | DownloadState::Failed => {
+            control("resume", "Resume");
+            control("cancel", "Cancel");
+        }
+        DownloadState::Completed | DownloadState::Cancelled => {}
+    }
+
+    let status = match download.error {
+        Some(ref error) if download.state == DownloadState::Failed => {
+            format!("{}: {}", download.state.label(), escape_html(error))
+        }
+        _ => download.state.label().to_string(),
+    };
+
+    format!(
+        r#"    <div class="download {state}">
+        <div class="info">
+            <div class="name">{name}</div>
+            <div class="url">{url}</div>
+            <progress max="100" value="{percent:.0}"></progress>
+            <div class="status">{status} &middot; {size}</div>
+        </div>
+        <div class="controls">{controls}</div>
+    </div>
+"#,
+        state = format!("{:?}", download.state).to_lowercase(),
+        name = escape_html(&download.file_name()),
+        url = escape_html(&download.url),
+        percent = percent,
+        status = status,
+        size = size,
+        controls = controls,
+    )
+}
+
+/// Generate the downloads HTML page, newest download first.
+pub fn generate_downloads_html(downloads: &[Download]) -> String {
+    let rows: String = if downloads.is_empty() {
+        r#"    <div class="empty">No downloads yet</div>
+"#
+        .to_string()
+    } else {
+        downloads.iter().rev().map(download_row).collect()
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Downloads</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 800px;
+            margin: 0 auto;
+            padding: 40px 20px;
+        }}
+        h1 {{
+            color: #7DC6DA;
+            margin-bottom: 30px;
+            font-size: 28px;
+        }}
+        .download {{
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            padding: 12px 0;
+            border-bottom: 1px solid #16213e;
+        }}
+        .download .info {{ flex: 1; min-width: 0; }}
+        .download .name {{ font-size: 14px; }}
+        .download .url, .download .status {{
+            font-size: 12px;
+            color: #888;
+            margin-top: 4px;
+            overflow: hidden;
+            text-overflow: ellipsis;
+            white-space: nowrap;
+        }}
+        .download.cancelled .name, .download.failed .name {{
+            color: #888;
+        }}
+        progress {{
+            width: 100%;
+            height: 4px;
+            margin-top: 6px;
+            accent-color: #7DC6DA;
+        }}
+        .download.completed progress, .download.cancelled progress {{
+            display: none;
+        }}
+        .controls a, .toolbar a {{
+            color: #7DC6DA;
+            margin-left: 12px;
+            font-size: 14px;
+            text-decoration: none;
+        }}
+        .toolbar {{ text-align: right; margin-bottom: 10px; }}
+        .empty {{ color: #888; text-align: center; padding: 40px 0; }}
+    </style>
+</head>
+<body>
+    <h1>Downloads</h1>
+    <div class="toolbar"><a href="{url}/clear">Clear finished</a></div>
+{rows}</body>
+</html>"#,
+        url = DOWNLOADS_URL,
+        rows = rows,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn download(id: DownloadId, state: DownloadState) -> Download {
+        Download {
+            id,
+            url: "https://example.com/a.zip?x=<1>".to_string(),
+            path: PathBuf::from("/tmp/a.zip"),
+            state,
+            received_bytes: 512 * 1024,
+            total_bytes: Some(1024 * 1024),
+            error: None,
+            started_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_downloads_html_generation() {
+        let html = generate_downloads_html(&[
+            download(1, DownloadState::Completed),
+            download(2, DownloadState::InProgress),
+        ]);
+        assert!(html.contains("a.zip"));
+        assert!(html.contains("x=&lt;1&gt;"));
+        assert!(html.contains("512.0 KB of 1.0 MB"));
+        assert!(html.contains("asteroid://downloads/pause/2"));
+        assert!(!html.contains("asteroid://downloads/pause/1"));
+        // Newest first
+        assert!(html.find("pause/2").unwrap() < html.find("download completed").unwrap());
+
+        assert!(generate_downloads_html(&[]).contains("No downloads yet"));
+    }
+
+    #[test]
+    fn test_parse_downloads_command() {
+        assert_eq!(
+            parse_downloads_command("asteroid://downloads/pause/3"),
+            Some(DownloadCommand::Pause(3))
+        );
+        assert_eq!(
+            parse_downloads_command("asteroid://downloads/clear"),
+            Some(DownloadCommand::ClearFinished)
+        );
+        assert_eq!(parse_downloads_command("asteroid://downloads"), None);
+        assert_eq!(
+            parse_downloads_command("asteroid://downloads/pause/x"),
+            None
+        );
+        assert_eq!(parse_downloads_command("https://downloads/pause/3"), None);
+    }
+}
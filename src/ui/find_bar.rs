@@ -0,0 +1,252 @@
+//! Find bar for Asteroid Browser.
+//!
+//! Shown below the page for find in page (Ctrl+F) and quick find (`/`),
+//! which only matches link text. The page is searched as the user types;
+//! Enter and the arrow buttons step through the matches, and the
+//! `EngineEvent::FindResult` counts are shown as "3 of 17".
+
+use crate::core::engine::{BrowserEngine, EngineError, EngineEvent, FindOptions, ViewId};
+use crate::core::engine_host::EngineHandle;
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, CheckButton, Entry, Label, Orientation};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Match count shown next to the query.
+pub fn find_status(current: usize, total: usize) -> String {
+    if total == 0 {
+        "No matches".to_string()
+    } else {
+        format!("{} of {}", current, total)
+    }
+}
+
+/// The find bar widgets.
+#[derive(Clone)]
+pub struct FindBar {
+    pub container: GtkBox,
+    entry: Entry,
+    status: Label,
+    case_sensitive: CheckButton,
+    whole_word: CheckButton,
+    regex: CheckButton,
+    highlight_all: CheckButton,
+    previous: Button,
+    next: Button,
+    close: Button,
+}
+
+impl FindBar {
+    pub fn new() -> Self {
+        let container = GtkBox::new(Orientation::Horizontal, 8);
+        container.add_css_class("find-bar");
+        container.set_visible(false);
+
+        let entry = Entry::new();
+        entry.set_width_request(240);
+        container.append(&entry);
+
+        let previous = Button::with_label("\u{2191}"); // ↑
+        previous.set_tooltip_text(Some("Previous match"));
+        container.append(&previous);
+
+        let next = Button::with_label("\u{2193}"); // ↓
+        next.set_tooltip_text(Some("Next match (Enter)"));
+        container.append(&next);
+
+        let status = Label::new(None);
+        container.append(&status);
+
+        let case_sensitive = CheckButton::with_label("Match case");
+        container.append(&case_sensitive);
+        let whole_word = CheckButton::with_label("Whole words");
+        container.append(&whole_word);
+        let regex = CheckButton::with_label("Regex");
+        container.append(&regex);
+        let highlight_all = CheckButton::with_label("Highlight all");
+        highlight_all.set_active(true);
+        container.append(&highlight_all);
+
+        let spacer = Label::new(None);
+        spacer.set_hexpand(true);
+        container.append(&spacer);
+
+        let close = Button::with_label("\u{2715}"); // ✕
+        close.set_tooltip_text(Some("Close (Escape)"));
+        container.append(&close);
+
+        Self {
+            container,
+            entry,
+            status,
+            case_sensitive,
+            whole_word,
+            regex,
+            highlight_all,
+            previous,
+            next,
+            close,
+        }
+    }
+
+    /// Options selected in the bar.
+    fn options(&self, links_only: bool) -> FindOptions {
+        FindOptions {
+            case_sensitive: self.case_sensitive.is_active(),
+            whole_word: self.whole_word.is_active(),
+            regex: self.regex.is_active(),
+            highlight_all: self.highlight_all.is_active(),
+            links_only,
+        }
+    }
+}
+
+impl Default for FindBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs the searches typed into the find bar in the active tab.
+#[derive(Clone)]
+pub struct FindHandler {
+    bar: FindBar,
+    handle: EngineHandle,
+    /// Quick find: only link text matches
+    links_only: Rc<Cell<bool>>,
+}
+
+impl FindHandler {
+    pub fn new(bar: FindBar, handle: EngineHandle) -> Self {
+        let handler = Self {
+            bar,
+            handle,
+            links_only: Rc::new(Cell::new(false)),
+        };
+
+        let h = handler.clone();
+        handler.bar.entry.connect_changed(move |_| h.search(true));
+        let h = handler.clone();
+        handler.bar.entry.connect_activate(move |_| h.search(true));
+        let h = handler.clone();
+        handler.bar.next.connect_clicked(move |_| h.search(true));
+        let h = handler.clone();
+        handler.bar.previous.connect_clicked(move |_| h.search(false));
+        for option in [
+            &handler.bar.case_sensitive,
+            &handler.bar.whole_word,
+            &handler.bar.regex,
+            &handler.bar.highlight_all,
+        ] {
+            let h = handler.clone();
+            option.connect_toggled(move |_| h.search(true));
+        }
+        let h = handler.clone();
+        handler.bar.close.connect_clicked(move |_| h.close());
+        handler
+    }
+
+    /// Show the bar; quick find only matches link text. Nothing happens
+    /// when the engine showing the tab cannot search pages.
+    pub fn open(&self, quick: bool) {
+        let supported = self
+            .handle
+            .call_blocking(|host| {
+                host.tabs
+                    .active_tab_id()
+                    .is_some_and(|view_id| host.engine.view_capabilities(view_id).find)
+            })
+            .unwrap_or(false);
+        if !supported {
+            return;
+        }
+        self.links_only.set(quick);
+        self.bar.entry.set_placeholder_text(Some(if quick {
+            "Find in links..."
+        } else {
+            "Find in page..."
+        }));
+        self.bar.container.set_visible(true);
+        self.bar.entry.grab_focus();
+        self.search(true);
+    }
+
+    /// Hide the bar and clear the highlights.
+    pub fn close(&self) {
+        self.bar.container.set_visible(false);
+        self.bar.status.set_text("");
+        self.clear_find();
+    }
+
+    /// Feed an engine event; find results update the match count.
+    pub fn handle_event(&self, event: &EngineEvent) {
+        match event {
+            EngineEvent::FindResult(view_id, current, total)
+                if self.active_tab() == Some(*view_id) && self.bar.container.is_visible() =>
+            {
+                self.bar.status.set_text(&find_status(*current, *total));
+            }
+            // Matches are gone once the page changes
+            EngineEvent::UrlChanged(view_id, _) if self.active_tab() == Some(*view_id) => {
+                self.bar.status.set_text("");
+            }
+            _ => {}
+        }
+    }
+
+    fn search(&self, forward: bool) {
+        if !self.bar.container.is_visible() {
+            return;
+        }
+        let query = self.bar.entry.text().to_string();
+        if query.is_empty() {
+            self.bar.status.set_text("");
+            self.clear_find();
+            return;
+        }
+        let options = self.bar.options(self.links_only.get());
+        let regex = options.regex;
+        let result = self.handle.call_blocking(move |host| {
+            let view_id = host
+                .tabs
+                .active_tab_id()
+                .ok_or_else(|| EngineError::Other("No active tab".to_string()))?;
+            host.engine.find_in_page(view_id, &query, &options, forward)
+        });
+        if let Ok(Err(e)) | Err(e) = result {
+            // Mostly unfinished regular expressions while typing
+            log::debug!("Find failed: {}", e);
+            self.bar
+                .status
+                .set_text(if regex { "Invalid pattern" } else { "Search failed" });
+        }
+    }
+
+    fn clear_find(&self) {
+        let result = self.handle.call_blocking(|host| match host.tabs.active_tab_id() {
+            Some(view_id) => host.engine.clear_find(view_id),
+            None => Ok(()),
+        });
+        if let Ok(Err(e)) | Err(e) = result {
+            log::warn!("Failed to clear find: {}", e);
+        }
+    }
+
+    fn active_tab(&self) -> Option<ViewId> {
+        self.handle
+            .call_blocking(|host| host.tabs.active_tab_id())
+            .ok()
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_status() {
+        assert_eq!(find_status(3, 17), "3 of 17");
+        assert_eq!(find_status(0, 0), "No matches");
+    }
+}
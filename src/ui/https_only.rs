@@ -0,0 +1,202 @@
+//! HTTPS-only fallback page for Asteroid Browser.
+//!
+//! Shown at `asteroid://https-only?url=...` when a navigation upgraded by
+//! HTTPS-only mode fails. "Continue to HTTP site" exempts the site and
+//! loads the original http:// address; "Go back" leaves it.
+
+use crate::core::engine::{BrowserEngine, EngineResult, LoadError, ViewId};
+use crate::core::https_only::HttpsOnlyPolicy;
+use crate::core::site_data::host_of;
+use crate::ui::{encode_query_value, escape_html, query_param};
+
+/// Address of the fallback page.
+pub const HTTPS_ONLY_URL: &str = "asteroid://https-only";
+
+/// Action picked on the fallback page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpsOnlyCommand {
+    /// Leave the site
+    GoBack,
+    /// Exempt the site and load the http:// address
+    Continue(String),
+}
+
+/// Fallback page address for a failed upgrade of `url`.
+pub fn fallback_page_url(url: &str) -> String {
+    format!("{}?url={}", HTTPS_ONLY_URL, encode_query_value(url))
+}
+
+fn continue_link(url: &str) -> String {
+    format!(
+        "{}/continue?url={}",
+        HTTPS_ONLY_URL,
+        encode_query_value(url)
+    )
+}
+
+/// Parse a control link produced by the fallback page.
+pub fn parse_https_only_command(url: &str) -> Option<HttpsOnlyCommand> {
+    let rest = url.strip_prefix(HTTPS_ONLY_URL)?.strip_prefix('/')?;
+    let (action, _) = rest.split_once('?').unwrap_or((rest, ""));
+    match action {
+        "back" => Some(HttpsOnlyCommand::GoBack),
+        "continue" => query_param(url, "url")
+            .filter(|u| u.starts_with("http://"))
+            .map(HttpsOnlyCommand::Continue),
+        _ => None,
+    }
+}
+
+/// Generate the fallback page for the http:// `url` whose upgrade failed.
+pub fn generate_https_only_html(url: &str, error: &LoadError) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Secure site not available</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 640px;
+            margin: 0 auto;
+            padding: 80px 20px 40px;
+        }}
+        h1 {{
+            color: #ffb74d;
+            margin-bottom: 20px;
+            font-size: 28px;
+        }}
+        p {{ line-height: 1.5; margin-bottom: 12px; }}
+        .host {{ color: #7DC6DA; }}
+        .reason {{ color: #888; font-size: 14px; }}
+        .actions {{
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            margin: 30px 0;
+        }}
+        .back {{
+            background-color: #7DC6DA;
+            color: #0a0e1a;
+            padding: 10px 20px;
+            border-radius: 4px;
+            text-decoration: none;
+        }}
+        .continue {{ color: #888; font-size: 14px; }}
+    </style>
+</head>
+<body>
+    <h1>Secure site not available</h1>
+    <p>HTTPS-only mode is on, but <span class="host">{host}</span> could not be loaded over a secure connection. The site most likely does not support HTTPS.</p>
+    <p>If you continue, pages on this site are loaded without encryption and others on your network can see and change them.</p>
+    <p class="reason">{reason}</p>
+    <div class="actions">
+        <a class="back" href="{base}/back">Go back</a>
+        <a class="continue" href="{continue_link}">Continue to HTTP site</a>
+    </div>
+</body>
+</html>"#,
+        host = escape_html(host_of(url)),
+        reason = error.kind.description(),
+        base = HTTPS_ONLY_URL,
+        continue_link = escape_html(&continue_link(url)),
+    )
+}
+
+/// Show the fallback page for a failed upgrade of `url` in its view.
+pub fn show_fallback(
+    engine: &mut dyn BrowserEngine,
+    view_id: ViewId,
+    url: &str,
+    error: &LoadError,
+) -> EngineResult<()> {
+    engine.load_html(
+        view_id,
+        &generate_https_only_html(url, error),
+        &fallback_page_url(url),
+    )
+}
+
+/// Carry out an action picked on the fallback page.
+pub fn handle_https_only_command(
+    engine: &mut dyn BrowserEngine,
+    policy: &HttpsOnlyPolicy,
+    view_id: ViewId,
+    command: &HttpsOnlyCommand,
+) -> EngineResult<()> {
+    match command {
+        HttpsOnlyCommand::GoBack => {
+            if engine.get_navigation_state(view_id)?.can_go_back {
+                engine.go_back(view_id)
+            } else {
+                engine.load_url(view_id, "about:blank")
+            }
+        }
+        HttpsOnlyCommand::Continue(url) => {
+            policy.exempt(host_of(url));
+            engine.load_url(view_id, url)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::LoadErrorKind;
+    use crate::engines::mock::MockEngine;
+
+    #[test]
+    fn test_https_only_page() {
+        let error = LoadError {
+            url: "https://plain.test/a".to_string(),
+            kind: LoadErrorKind::ConnectionRefused,
+        };
+        let html = generate_https_only_html("http://plain.test/a?b=1", &error);
+        assert!(html.contains("plain.test</span>"));
+        assert!(html.contains("The server refused the connection."));
+        assert!(
+            html.contains("asteroid://https-only/continue?url=http%3A%2F%2Fplain.test%2Fa%3Fb%3D1")
+        );
+
+        assert_eq!(
+            parse_https_only_command(&continue_link("http://plain.test/a?b=1")),
+            Some(HttpsOnlyCommand::Continue(
+                "http://plain.test/a?b=1".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_https_only_command("asteroid://https-only/back"),
+            Some(HttpsOnlyCommand::GoBack)
+        );
+        // Only http:// targets are accepted
+        assert_eq!(
+            parse_https_only_command("asteroid://https-only/continue?url=file%3A%2F%2F%2Fetc"),
+            None
+        );
+        assert_eq!(
+            parse_https_only_command(&fallback_page_url("http://a.test/")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_continue_exempts_site() {
+        let mut engine = MockEngine::new();
+        let view = ViewId(1);
+        engine.create_view(view).unwrap();
+        let policy = HttpsOnlyPolicy::new(true, None);
+
+        let command = HttpsOnlyCommand::Continue("http://plain.test/".to_string());
+        handle_https_only_command(&mut engine, &policy, view, &command).unwrap();
+        assert_eq!(policy.exemptions(), vec!["plain.test"]);
+        assert_eq!(
+            engine.get_navigation_state(view).unwrap().url,
+            "http://plain.test/"
+        );
+    }
+}
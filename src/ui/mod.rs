@@ -6,7 +6,12 @@
 //! - Navigation buttons (back, forward, reload)
 //! - Tab management (horizontal/vertical)
 //! - Keyboard shortcuts
-//! - Settings page
+//! - Site permission prompts and blocked popup notices
+//! - Find bar
+//! - Certificate error and HTTPS-only warnings
+//! - Crashed page notice
+//! - Printing
+//! - Settings, downloads, site data, site information and page source pages
 //! - Status overlay
 
 pub mod window;
@@ -14,6 +19,19 @@ pub mod toolbar;
 pub mod tab_bar;
 pub mod settings;
 pub mod shortcuts;
+pub mod actions;
+pub mod cert_error;
+pub mod crash;
+pub mod downloads;
+pub mod find_bar;
+pub mod https_only;
+pub mod pages;
+pub mod permission_bar;
+pub mod popup_bar;
+pub mod print;
+pub mod site_data;
+pub mod site_info;
+pub mod source;
 
 /// Keyboard shortcuts configuration.
 pub struct KeyboardShortcuts {
@@ -49,3 +67,83 @@ impl Default for KeyboardShortcuts {
         }
     }
 }
+
+/// Escape text for inclusion in generated HTML pages.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Human readable byte count.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Percent-encode a value for use in an internal page query string.
+pub fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decode a percent-encoded query value (`+` is a space).
+pub fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push((high * 16 + low) as u8);
+                    i += 3;
+                    continue;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Decoded value of a query parameter of an internal page URL.
+pub fn query_param(url: &str, name: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+    let query = query.split('#').next().unwrap_or(query);
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| decode_query_value(value))
+    })
+}
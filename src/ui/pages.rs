@@ -0,0 +1,761 @@
+//! Built-in internal pages for Asteroid Browser.
+//!
+//! Registers the handlers behind `asteroid://settings`, `about`, `memory`,
+//! `blocker`, `downloads`, `history`, `site-info` and the other pages the
+//! browser serves itself with the `core::internal_pages` router. Handlers
+//! run on the GTK thread and reach the engine through the engine handle.
+
+use crate::core::blocker::{ContentBlocker, DEFAULT_FILTERS};
+use crate::core::config::Config;
+use crate::core::downloads::DownloadManager;
+use crate::core::engine::{
+    BrowserEngine, EngineError, EngineInfo, EngineResult, SiteDataKind, ViewId,
+};
+use crate::core::engine_host::EngineHandle;
+use crate::core::https_only::HttpsOnlyPolicy;
+use crate::core::internal_pages::{InternalPages, InternalResponse};
+use crate::core::memory::get_system_memory;
+use crate::core::permissions::{PermissionDecision, PermissionStore};
+use crate::core::popups::PopupBlocker;
+use crate::ui::cert_error::{handle_cert_error_command, parse_cert_error_command};
+use crate::ui::crash::{handle_crash_command, parse_crash_command};
+use crate::ui::downloads::{
+    generate_downloads_html, parse_downloads_command, DownloadCommand, DOWNLOADS_URL,
+};
+use crate::ui::https_only::{handle_https_only_command, parse_https_only_command};
+use crate::ui::settings::generate_settings_html;
+use crate::ui::site_data::{
+    generate_site_data_html, parse_site_data_command, SiteDataCommand, SITE_DATA_URL,
+};
+use crate::ui::site_info::{
+    generate_site_info_html, parse_site_info_url, parse_site_media_message,
+};
+use crate::ui::source::{fetch_source, generate_source_html, parse_source_url, source_page_url};
+use crate::ui::{escape_html, format_bytes};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const SETTINGS_URL: &str = "asteroid://settings";
+pub const ABOUT_URL: &str = "asteroid://about";
+pub const MEMORY_URL: &str = "asteroid://memory";
+pub const BLOCKER_URL: &str = "asteroid://blocker";
+pub const HISTORY_URL: &str = "asteroid://history";
+
+/// Browser state the internal pages read and change.
+#[derive(Clone)]
+pub struct PageContext {
+    pub handle: EngineHandle,
+    /// Runtime for download and source fetching work
+    pub runtime: tokio::runtime::Handle,
+    pub config: Rc<RefCell<Config>>,
+    /// Where settings changes are saved, if anywhere
+    pub config_path: Option<PathBuf>,
+    /// Engines compiled into this build
+    pub engines: Vec<EngineInfo>,
+    pub permissions: Rc<RefCell<PermissionStore>>,
+    pub downloads: Arc<tokio::sync::Mutex<DownloadManager>>,
+    pub blocker: Arc<Mutex<ContentBlocker>>,
+    pub https_only: Arc<HttpsOnlyPolicy>,
+    pub popups: Rc<RefCell<PopupBlocker>>,
+}
+
+/// The router with every built-in page registered.
+pub fn builtin_pages() -> InternalPages<PageContext> {
+    let mut pages = InternalPages::new();
+
+    pages.register("settings", |ctx: &PageContext, _| {
+        Ok(page(SETTINGS_URL, settings_html(ctx)))
+    });
+    pages.on_message("settings", |ctx: &PageContext, view_id, message| {
+        match message["type"].as_str() {
+            Some("set") => {
+                let key = message["key"].as_str().unwrap_or("");
+                let result = update_setting(ctx, key, &message["value"]);
+                if result.is_err() {
+                    // Put the control back to the value in effect
+                    let html = settings_html(ctx);
+                    ctx.handle.call_blocking(move |host| {
+                        host.engine.load_html(view_id, &html, SETTINGS_URL)
+                    })??;
+                }
+                result
+            }
+            Some("set_permission") => {
+                let origin = message["origin"].as_str().unwrap_or("");
+                let kind = serde_json::from_value(message["permission"].clone());
+                let decision =
+                    serde_json::from_value::<PermissionDecision>(message["decision"].clone());
+                match (kind, decision) {
+                    (Ok(kind), Ok(decision)) if !origin.is_empty() => {
+                        ctx.permissions.borrow_mut().set(origin, kind, decision);
+                        Ok(())
+                    }
+                    _ => Err(invalid_message(message)),
+                }
+            }
+            _ => Err(invalid_message(message)),
+        }
+    });
+
+    pages.register("about", |ctx: &PageContext, _| {
+        let html = generate_about_html(&ctx.config.borrow());
+        Ok(page(ABOUT_URL, html))
+    });
+
+    pages.register("memory", |ctx: &PageContext, _| {
+        let (engine, tabs, suspended) = ctx.handle.call_blocking(|host| {
+            (
+                host.engine.get_memory_usage(),
+                host.tabs.tab_count(),
+                host.tabs.suspended_count(),
+            )
+        })?;
+        let system = get_system_memory();
+        let rows = [
+            ("Engine total", format_bytes(engine.total_bytes)),
+            ("JavaScript heaps", format_bytes(engine.js_heap_bytes)),
+            ("DOM", format_bytes(engine.dom_bytes)),
+            ("Layout", format_bytes(engine.layout_bytes)),
+            ("Image cache", format_bytes(engine.image_cache_bytes)),
+            ("Network cache", format_bytes(engine.network_cache_bytes)),
+            ("Tabs", format!("{} ({} suspended)", tabs, suspended)),
+            (
+                "System memory used",
+                format!(
+                    "{} of {}",
+                    format_bytes(system.used_bytes),
+                    format_bytes(system.total_bytes)
+                ),
+            ),
+            (
+                "System memory available",
+                format_bytes(system.available_bytes),
+            ),
+        ];
+        Ok(page(MEMORY_URL, generate_table_html("Memory", &rows)))
+    });
+
+    pages.register("blocker", |ctx: &PageContext, _| {
+        let (enabled, stats) = {
+            let blocker = ctx
+                .blocker
+                .lock()
+                .map_err(|e| EngineError::Other(e.to_string()))?;
+            (blocker.is_enabled(), blocker.stats().clone())
+        };
+        let rows = [
+            (
+                "Status",
+                if enabled { "Enabled" } else { "Disabled" }.to_string(),
+            ),
+            ("Filter rules", stats.filter_count.to_string()),
+            ("Requests checked", stats.total_checked.to_string()),
+            (
+                "Requests blocked",
+                format!("{} ({:.1}%)", stats.total_blocked, stats.block_rate()),
+            ),
+            ("Data saved", format!("{:.1} MB", stats.bytes_saved_mb())),
+            ("Elements hidden", stats.elements_hidden.to_string()),
+        ];
+        Ok(page(
+            BLOCKER_URL,
+            generate_table_html("Content blocker", &rows),
+        ))
+    });
+
+    pages.register("downloads", |ctx: &PageContext, request| {
+        if !request.path.is_empty() {
+            let command =
+                parse_downloads_command(request.url).ok_or_else(|| unknown(request.url))?;
+            let downloads = ctx.downloads.clone();
+            ctx.runtime
+                .block_on(async move {
+                    let mut downloads = downloads.lock().await;
+                    match command {
+                        DownloadCommand::Pause(id) => downloads.pause(id).await,
+                        DownloadCommand::Resume(id) => downloads.resume(id),
+                        DownloadCommand::Cancel(id) => downloads.cancel(id).await,
+                        DownloadCommand::ClearFinished => {
+                            downloads.clear_finished();
+                            Ok(())
+                        }
+                    }
+                })
+                .map_err(|e| EngineError::Other(e.to_string()))?;
+        }
+        let list = ctx
+            .runtime
+            .block_on(async { ctx.downloads.lock().await.list() });
+        Ok(page(DOWNLOADS_URL, generate_downloads_html(&list)))
+    });
+
+    pages.register("history", |ctx: &PageContext, _| {
+        Ok(page(HISTORY_URL, history_html(ctx)?))
+    });
+    pages.on_message("history", |ctx: &PageContext, view_id, message| {
+        if message["type"].as_str() != Some("clear") {
+            return Err(invalid_message(message));
+        }
+        ctx.handle.call_blocking(|host| host.history.clear())?;
+        let html = history_html(ctx)?;
+        ctx.handle
+            .call_blocking(move |host| host.engine.load_html(view_id, &html, HISTORY_URL))?
+    });
+
+    pages.register("site-data", |ctx: &PageContext, request| {
+        let command = parse_site_data_command(request.url);
+        if !request.path.is_empty() && command.is_none() {
+            return Err(unknown(request.url));
+        }
+        let html = ctx.handle.call_blocking(move |host| {
+            match command {
+                Some(SiteDataCommand::Clear(origin)) => host
+                    .engine
+                    .clear_site_data(Some(&origin), &SiteDataKind::ALL)?,
+                Some(SiteDataCommand::ClearAll) => {
+                    host.engine.clear_site_data(None, &SiteDataKind::ALL)?
+                }
+                None => {}
+            }
+            Ok::<_, EngineError>(generate_site_data_html(&host.engine.site_data_usage()?))
+        })??;
+        Ok(page(SITE_DATA_URL, html))
+    });
+
+    pages.register("site-info", |ctx: &PageContext, request| {
+        let url = parse_site_info_url(request.url).ok_or_else(|| unknown(request.url))?;
+        let html = generate_site_info_html(&url, &ctx.config.borrow().media);
+        Ok(page(request.url, html))
+    });
+    pages.on_message("site-info", |ctx: &PageContext, _, message| {
+        if message["type"].as_str() != Some("set_media") {
+            return Err(invalid_message(message));
+        }
+        let change = parse_site_media_message(message).ok_or_else(|| invalid_message(message))?;
+        let config = {
+            let mut config = ctx.config.borrow_mut();
+            config
+                .media
+                .set_site_rule(&change.site, change.autoplay, change.preload);
+            config.clone()
+        };
+        if let Some(ref path) = ctx.config_path {
+            config
+                .save_to(path)
+                .map_err(|e| EngineError::Other(format!("Failed to save settings: {}", e)))?;
+        }
+        apply_config(ctx, &config)
+    });
+
+    // Fetching may take a while, so the source is shown once it arrives
+    pages.register("source", |ctx: &PageContext, request| {
+        let url = parse_source_url(request.url).ok_or_else(|| unknown(request.url))?;
+        let (handle, view_id) = (ctx.handle.clone(), request.view_id);
+        let network = ctx.config.borrow().network.clone();
+        ctx.runtime.spawn(async move {
+            let result = match fetch_source(&url, &network).await {
+                Ok(source) => {
+                    let html = generate_source_html(&url, &source);
+                    handle
+                        .call(move |host| {
+                            host.engine
+                                .load_html(view_id, &html, &source_page_url(&url))
+                        })
+                        .await
+                }
+                Err(e) => Err(EngineError::NavigationError(e)),
+            };
+            if let Ok(Err(e)) | Err(e) = result {
+                log::error!("Failed to show page source: {}", e);
+            }
+        });
+        Ok(InternalResponse::Handled)
+    });
+
+    // The warning pages are only shown for a failed load; what remains
+    // here are their buttons
+    pages.register("cert-error", |ctx: &PageContext, request| {
+        let command = parse_cert_error_command(request.url).ok_or_else(|| unknown(request.url))?;
+        let view_id = request.view_id;
+        ctx.handle.call_blocking(move |host| {
+            handle_cert_error_command(&mut host.engine, view_id, &command)
+        })??;
+        Ok(InternalResponse::Handled)
+    });
+    pages.register("crashed", |ctx: &PageContext, request| {
+        let command = parse_crash_command(request.url).ok_or_else(|| unknown(request.url))?;
+        let view_id = request.view_id;
+        ctx.handle
+            .call_blocking(move |host| handle_crash_command(host, view_id, &command))??;
+        Ok(InternalResponse::Handled)
+    });
+    pages.register("https-only", |ctx: &PageContext, request| {
+        let command = parse_https_only_command(request.url).ok_or_else(|| unknown(request.url))?;
+        let (policy, view_id) = (ctx.https_only.clone(), request.view_id);
+        ctx.handle.call_blocking(move |host| {
+            handle_https_only_command(&mut host.engine, &policy, view_id, &command)
+        })??;
+        Ok(InternalResponse::Handled)
+    });
+
+    pages
+}
+
+fn settings_html(ctx: &PageContext) -> String {
+    generate_settings_html(
+        &ctx.config.borrow(),
+        &ctx.permissions.borrow(),
+        &ctx.engines,
+    )
+}
+
+/// Validate, save and apply a change made on the settings page.
+fn update_setting(ctx: &PageContext, key: &str, value: &serde_json::Value) -> EngineResult<()> {
+    if key == "engine.current" && !ctx.engines.iter().any(|e| value == e.name.as_str()) {
+        return Err(EngineError::Other(format!(
+            "Engine not available: {}",
+            value
+        )));
+    }
+    let config = {
+        let mut config = ctx.config.borrow_mut();
+        config.set(key, value).map_err(EngineError::Other)?;
+        config.clone()
+    };
+    if let Some(ref path) = ctx.config_path {
+        config
+            .save_to(path)
+            .map_err(|e| EngineError::Other(format!("Failed to save settings: {}", e)))?;
+    }
+    apply_config(ctx, &config)
+}
+
+/// Apply the settings that take effect without a restart.
+pub fn apply_config(ctx: &PageContext, config: &Config) -> EngineResult<()> {
+    {
+        let mut blocker = ctx
+            .blocker
+            .lock()
+            .map_err(|e| EngineError::Other(e.to_string()))?;
+        let enabled = config.privacy.block_ads || config.privacy.block_trackers;
+        // Filters are only loaded at startup when blocking is on
+        if enabled && blocker.stats().filter_count == 0 {
+            blocker.add_filter_list(DEFAULT_FILTERS);
+        }
+        blocker.set_enabled(enabled);
+    }
+    ctx.https_only.set_enabled(config.privacy.https_only);
+    ctx.popups
+        .borrow_mut()
+        .set_enabled(config.privacy.block_popups);
+
+    let general = config.general.clone();
+    let max_active_tabs = config.performance.max_active_tabs;
+    let hardware_acceleration = config.performance.hardware_acceleration;
+    let settings = config.engine_settings();
+    let user_agent = config.engine.user_agent.clone();
+    let rules = config.engine.user_agent_rules.clone();
+    let media = config.media.clone();
+    ctx.handle.call_blocking(move |host| {
+        host.user_agents.configure(&user_agent, &rules);
+        host.media_policies.configure(&media);
+        host.apply_media_policies();
+        let caps = host.engine.capabilities();
+        let suspension = &mut host.tabs.suspension_config;
+        suspension.enabled = general.tab_suspension_enabled && caps.suspend;
+        suspension.inactive_threshold = Duration::from_secs(general.tab_suspension_delay);
+        suspension.max_active_tabs = max_active_tabs;
+        host.engine.apply_settings(&settings)?;
+        host.engine
+            .enable_hardware_acceleration(hardware_acceleration && caps.hardware_video())
+    })?
+}
+
+fn page(url: &str, html: String) -> InternalResponse {
+    InternalResponse::Page {
+        url: url.to_string(),
+        html,
+    }
+}
+
+fn unknown(url: &str) -> EngineError {
+    EngineError::NavigationError(format!("Unknown page: {}", url))
+}
+
+fn invalid_message(message: &serde_json::Value) -> EngineError {
+    EngineError::Other(format!("Invalid internal message: {}", message))
+}
+
+fn history_html(ctx: &PageContext) -> EngineResult<String> {
+    let entries: Vec<_> = ctx
+        .handle
+        .call_blocking(|host| host.history.entries().cloned().collect())?;
+    let rows: String =
+        if entries.is_empty() {
+            r#"    <div class="empty">No pages visited yet</div>
+"#
+            .to_string()
+        } else {
+            entries
+            .iter()
+            .map(|entry| {
+                let title = if entry.title.is_empty() { &entry.url } else { &entry.title };
+                format!(
+                    r#"    <div class="row"><a href="{url}">{title}</a><span>{url_text}</span></div>
+"#,
+                    url = escape_html(&entry.url),
+                    title = escape_html(title),
+                    url_text = escape_html(&entry.url),
+                )
+            })
+            .collect()
+        };
+    let body = format!(
+        r#"    <button id="clear">Clear history</button>
+{rows}    <script>
+        document.getElementById("clear").addEventListener("click", () =>
+            window.asteroid.postMessage({{ type: "clear" }}));
+    </script>
+"#,
+        rows = rows,
+    );
+    Ok(page_shell("History", &body))
+}
+
+/// Generate the about page.
+pub fn generate_about_html(config: &Config) -> String {
+    let rows = [
+        ("Version", env!("CARGO_PKG_VERSION").to_string()),
+        ("Engine", config.engine.current.clone()),
+        ("Configuration", Config::config_path().display().to_string()),
+    ];
+    generate_table_html("Asteroid Browser", &rows)
+}
+
+/// Generate a page listing label/value pairs.
+fn generate_table_html(title: &str, rows: &[(&str, String)]) -> String {
+    let rows: String = rows
+        .iter()
+        .map(|(label, value)| {
+            format!(
+                "    <div class=\"row\"><span>{}</span><span>{}</span></div>\n",
+                escape_html(label),
+                escape_html(value)
+            )
+        })
+        .collect();
+    page_shell(title, &rows)
+}
+
+/// Wrap page content in the look shared by the internal pages.
+fn page_shell(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title}</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 800px;
+            margin: 0 auto;
+            padding: 40px 20px;
+        }}
+        h1 {{
+            color: #7DC6DA;
+            margin-bottom: 30px;
+            font-size: 28px;
+        }}
+        .row {{
+            display: flex;
+            justify-content: space-between;
+            gap: 20px;
+            padding: 12px 0;
+            border-bottom: 1px solid #16213e;
+            font-size: 14px;
+        }}
+        .row span:last-child {{ color: #888; overflow-wrap: anywhere; }}
+        a {{ color: #7DC6DA; text-decoration: none; }}
+        .empty {{ color: #888; padding: 12px 0; }}
+        button {{
+            background-color: #16213e;
+            color: #e0e0e0;
+            border: 1px solid #0f3460;
+            border-radius: 4px;
+            padding: 6px 12px;
+            margin-bottom: 12px;
+        }}
+    </style>
+</head>
+<body>
+    <h1>{title}</h1>
+{body}</body>
+</html>"#,
+        title = escape_html(title),
+        body = body,
+    )
+}
+
+/// Serve a navigation to an internal page in `view_id`.
+pub fn open_internal_page(
+    pages: &InternalPages<PageContext>,
+    ctx: &PageContext,
+    view_id: ViewId,
+    url: &str,
+) -> EngineResult<()> {
+    let current_url = current_url(ctx, view_id)?;
+    match pages.route(ctx, view_id, url, &current_url)? {
+        InternalResponse::Page { url, html } => ctx
+            .handle
+            .call_blocking(move |host| host.engine.load_html(view_id, &html, &url))?,
+        InternalResponse::Handled => Ok(()),
+    }
+}
+
+/// Hand a bridge message from `view_id` to its page.
+pub fn handle_internal_message(
+    pages: &InternalPages<PageContext>,
+    ctx: &PageContext,
+    view_id: ViewId,
+    message: &serde_json::Value,
+) -> EngineResult<()> {
+    let current_url = current_url(ctx, view_id)?;
+    pages.dispatch_message(ctx, view_id, &current_url, message)
+}
+
+/// Document the view shows. This is not always the tab's address: a
+/// crash page leaves the tab at the URL that crashed.
+fn current_url(ctx: &PageContext, view_id: ViewId) -> EngineResult<String> {
+    ctx.handle
+        .call_blocking(move |host| host.engine.get_navigation_state(view_id))?
+        .map(|state| state.url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::NetworkConfig;
+    use crate::core::engine::PermissionKind;
+    use crate::core::engine_host::EngineHost;
+    use crate::core::media_policy::AutoplayPolicy;
+    use crate::core::tab::{SuspensionConfig, TabManager};
+    use crate::engines::mock::MockEngine;
+    use crate::ui::site_info::site_info_url;
+
+    fn context(host: &EngineHost, runtime: &tokio::runtime::Runtime) -> PageContext {
+        PageContext {
+            handle: host.handle(),
+            runtime: runtime.handle().clone(),
+            config: Rc::new(RefCell::new(Config::default())),
+            permissions: Rc::new(RefCell::new(PermissionStore::new(None))),
+            downloads: Arc::new(tokio::sync::Mutex::new(DownloadManager::new(
+                std::env::temp_dir(),
+                None,
+                &NetworkConfig::default(),
+            ))),
+            blocker: Arc::new(Mutex::new(ContentBlocker::new())),
+            https_only: Arc::new(HttpsOnlyPolicy::new(false, None)),
+            config_path: None,
+            engines: crate::engines::create_registry().engine_infos(),
+            popups: Rc::new(RefCell::new(PopupBlocker::new(true))),
+        }
+    }
+
+    #[test]
+    fn test_builtin_pages() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(MockEngine::new().into_engine_set(), tabs).unwrap();
+        let ctx = context(&host, &runtime);
+        let pages = builtin_pages();
+        for name in [
+            "about",
+            "blocker",
+            "downloads",
+            "history",
+            "memory",
+            "settings",
+        ] {
+            assert!(pages.pages().contains(&name), "{}", name);
+        }
+
+        let view = runtime.block_on(ctx.handle.create_tab()).unwrap();
+        open_internal_page(&pages, &ctx, view, SETTINGS_URL).unwrap();
+        let url = ctx
+            .handle
+            .call_blocking(move |host| host.engine.get_navigation_state(view).unwrap().url)
+            .unwrap();
+        assert_eq!(url, SETTINGS_URL);
+        assert!(open_internal_page(&pages, &ctx, view, "asteroid://nothing").is_err());
+    }
+
+    #[test]
+    fn test_settings_messages() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(MockEngine::new().into_engine_set(), tabs).unwrap();
+        let ctx = context(&host, &runtime);
+        let pages = builtin_pages();
+        let message = serde_json::json!({
+            "type": "set_permission",
+            "origin": "https://maps.test",
+            "permission": "geolocation",
+            "decision": "allow",
+        });
+
+        pages
+            .dispatch_message(&ctx, ViewId(1), SETTINGS_URL, &message)
+            .unwrap();
+        assert_eq!(
+            ctx.permissions
+                .borrow()
+                .decision("https://maps.test", PermissionKind::Geolocation),
+            PermissionDecision::Allow
+        );
+        assert!(pages
+            .dispatch_message(
+                &ctx,
+                ViewId(1),
+                SETTINGS_URL,
+                &serde_json::json!({ "type": "x" })
+            )
+            .is_err());
+        assert!(pages
+            .dispatch_message(&ctx, ViewId(1), "https://evil.test/", &message)
+            .is_err());
+    }
+
+    #[test]
+    fn test_settings_changes_apply_live() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let engine = MockEngine::new();
+        let calls = engine.handle();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(engine.into_engine_set(), tabs).unwrap();
+        let dir = std::env::temp_dir().join(format!("asteroid-settings-{}", std::process::id()));
+        let ctx = PageContext {
+            config_path: Some(dir.join("config.toml")),
+            ..context(&host, &runtime)
+        };
+        let pages = builtin_pages();
+        let view = runtime.block_on(ctx.handle.create_tab()).unwrap();
+        let set = |key: &str, value: serde_json::Value| {
+            let message = serde_json::json!({ "type": "set", "key": key, "value": value });
+            pages.dispatch_message(&ctx, view, SETTINGS_URL, &message)
+        };
+
+        set("general.tab_suspension_delay", serde_json::json!(60)).unwrap();
+        let threshold = ctx
+            .handle
+            .call_blocking(|host| host.tabs.suspension_config.inactive_threshold)
+            .unwrap();
+        assert_eq!(threshold, Duration::from_secs(60));
+
+        set("privacy.block_ads", serde_json::json!(false)).unwrap();
+        assert!(!ctx.blocker.lock().unwrap().is_enabled());
+        set("privacy.https_only", serde_json::json!(true)).unwrap();
+        assert!(ctx.https_only.is_enabled());
+        set("privacy.send_dnt", serde_json::json!(true)).unwrap();
+        assert!(calls
+            .calls_to("apply_settings")
+            .iter()
+            .any(|c| c.args[0] == "dnt=true"));
+
+        let saved = std::fs::read_to_string(dir.join("config.toml")).unwrap();
+        assert!(saved.contains("tab_suspension_delay = 60"));
+
+        // Rejected values leave the configuration alone
+        assert!(set("general.tab_suspension_delay", serde_json::json!(-1)).is_err());
+        assert!(set("engine.current", serde_json::json!("netscape")).is_err());
+        assert_eq!(ctx.config.borrow().general.tab_suspension_delay, 60);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_crash_page_reload_link() {
+        use crate::core::crash::CrashRecovery;
+        use crate::core::engine::EngineEvent;
+        use crate::engines::mock::{MockEvent, MockFixture, MockPage};
+        use crate::ui::crash::show_crash_page;
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let fixture = MockFixture::test_pages().page(
+            "https://crash.test/",
+            MockPage::new("Crash").event(MockEvent::Crash {
+                reason: "oom".to_string(),
+            }),
+        );
+        let engine = MockEngine::with_fixture(fixture);
+        let calls = engine.handle();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(engine.into_engine_set(), tabs).unwrap();
+        let ctx = context(&host, &runtime);
+        let pages = builtin_pages();
+        let mut events = ctx.handle.subscribe();
+        let view = runtime.block_on(async {
+            ctx.handle
+                .call(|host| host.crashes = CrashRecovery::new(Duration::from_secs(600)))
+                .await
+                .unwrap();
+            let view = ctx.handle.create_tab().await.unwrap();
+            ctx.handle.load_url(view, "https://crash.test/").await.unwrap();
+            while !matches!(events.recv().await, Ok(EngineEvent::ViewCrashed(..))) {}
+            view
+        });
+        ctx.handle
+            .call_blocking(move |host| show_crash_page(host, view, "oom"))
+            .unwrap()
+            .unwrap();
+
+        open_internal_page(&pages, &ctx, view, "asteroid://crashed/reload").unwrap();
+        let loads = calls.calls_to("load_url");
+        assert_eq!(loads.len(), 2);
+        assert_eq!(loads[1].args[0], "https://crash.test/");
+    }
+
+    #[test]
+    fn test_site_info_media_rules() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let engine = MockEngine::new();
+        let calls = engine.handle();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(engine.into_engine_set(), tabs).unwrap();
+        let ctx = context(&host, &runtime);
+        let pages = builtin_pages();
+        let view = runtime.block_on(async {
+            let view = ctx.handle.create_tab().await.unwrap();
+            ctx.handle.load_url(view, "https://video.test/").await.unwrap();
+            view
+        });
+
+        let url = site_info_url("https://video.test/watch");
+        let info = runtime.block_on(ctx.handle.create_tab()).unwrap();
+        open_internal_page(&pages, &ctx, info, &url).unwrap();
+        let message = serde_json::json!({
+            "type": "set_media",
+            "site": "video.test",
+            "autoplay": "allow",
+            "preload": "",
+        });
+        pages.dispatch_message(&ctx, info, &url, &message).unwrap();
+
+        let rule = ctx.config.borrow().media.site_rule("video.test").cloned();
+        assert_eq!(rule.and_then(|r| r.autoplay), Some(AutoplayPolicy::Allow));
+        // Open tabs on the site pick the rule up right away
+        let applied = calls.calls_to("set_media_policy");
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].view_id, Some(view));
+        assert_eq!(applied[0].args, vec!["allow", "metadata"]);
+
+        let message = serde_json::json!({ "type": "set_media", "site": "video.test" });
+        assert!(pages.dispatch_message(&ctx, info, &url, &message).is_err());
+    }
+}
@@ -0,0 +1,353 @@
+//! Permission prompt bar for Asteroid Browser.
+//!
+//! Shown between the toolbar and the web content when a page asks for a
+//! permission that has no stored decision. Prompts are answered one at a
+//! time, in the order they arrived.
+
+use crate::core::engine::{BrowserEngine, EngineEvent, PermissionKind, ViewId};
+use crate::core::engine_host::EngineHandle;
+use crate::core::permissions::{PermissionDecision, PermissionStore};
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, CheckButton, Label, Orientation};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// A permission request waiting for the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingPermission {
+    pub view_id: ViewId,
+    pub origin: String,
+    pub kind: PermissionKind,
+}
+
+/// Ordered queue of permission prompts.
+#[derive(Debug, Default)]
+pub struct PermissionQueue {
+    pending: VecDeque<PendingPermission>,
+}
+
+impl PermissionQueue {
+    /// Handle a new request: returns the stored answer if there is one,
+    /// otherwise queues the request for the user.
+    pub fn request(
+        &mut self,
+        store: &PermissionStore,
+        view_id: ViewId,
+        origin: &str,
+        kind: PermissionKind,
+    ) -> Option<bool> {
+        match store.decision(origin, kind) {
+            PermissionDecision::Allow => Some(true),
+            PermissionDecision::Deny => Some(false),
+            PermissionDecision::Ask => {
+                self.pending.push_back(PendingPermission {
+                    view_id,
+                    origin: origin.to_string(),
+                    kind,
+                });
+                None
+            }
+        }
+    }
+
+    /// Drop the prompts of a view, whose page can no longer be answered
+    /// once it navigates away or closes. Returns whether any were queued.
+    pub fn remove_view(&mut self, view_id: ViewId) -> bool {
+        let len = self.pending.len();
+        self.pending.retain(|request| request.view_id != view_id);
+        self.pending.len() != len
+    }
+
+    /// Prompt currently shown to the user.
+    pub fn current(&self) -> Option<&PendingPermission> {
+        self.pending.front()
+    }
+
+    /// Answer the current prompt, storing the decision if `remember` is set.
+    pub fn answer(
+        &mut self,
+        store: &mut PermissionStore,
+        granted: bool,
+        remember: bool,
+    ) -> Option<PendingPermission> {
+        let request = self.pending.pop_front()?;
+        if remember {
+            let decision = if granted {
+                PermissionDecision::Allow
+            } else {
+                PermissionDecision::Deny
+            };
+            store.set(&request.origin, request.kind, decision);
+        }
+        Some(request)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Text shown in the bar for a request.
+pub fn prompt_message(origin: &str, kind: PermissionKind) -> String {
+    let host = origin.split_once("://").map(|(_, h)| h).unwrap_or(origin);
+    let what = match kind {
+        PermissionKind::Geolocation => "know your location",
+        PermissionKind::Notifications => "show notifications",
+        PermissionKind::Camera => "use your camera",
+        PermissionKind::Microphone => "use your microphone",
+        PermissionKind::Clipboard => "read your clipboard",
+        PermissionKind::PersistentStorage => "store data persistently on this device",
+        PermissionKind::Popups => "open pop-up windows",
+    };
+    format!("{} wants to {}", host, what)
+}
+
+/// The prompt bar widgets.
+#[derive(Clone)]
+pub struct PermissionBar {
+    pub container: GtkBox,
+    message: Label,
+    allow: Button,
+    deny: Button,
+    remember: CheckButton,
+}
+
+impl PermissionBar {
+    pub fn new() -> Self {
+        let container = GtkBox::new(Orientation::Horizontal, 8);
+        container.set_margin_start(8);
+        container.set_margin_end(8);
+        container.add_css_class("permission-bar");
+        container.set_visible(false);
+
+        let message = Label::new(None);
+        message.set_hexpand(true);
+        message.set_halign(gtk4::Align::Start);
+        container.append(&message);
+
+        let remember = CheckButton::with_label("Remember");
+        container.append(&remember);
+
+        let deny = Button::with_label("Block");
+        deny.add_css_class("permission-deny");
+        container.append(&deny);
+
+        let allow = Button::with_label("Allow");
+        allow.add_css_class("permission-allow");
+        container.append(&allow);
+
+        Self {
+            container,
+            message,
+            allow,
+            deny,
+            remember,
+        }
+    }
+
+    pub fn show(&self, request: &PendingPermission) {
+        self.message
+            .set_text(&prompt_message(&request.origin, request.kind));
+        self.remember.set_active(false);
+        self.container.set_visible(true);
+    }
+
+    pub fn hide(&self) {
+        self.container.set_visible(false);
+    }
+}
+
+impl Default for PermissionBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Connects the prompt bar to engine events and the permission store.
+#[derive(Clone)]
+pub struct PermissionPrompter {
+    bar: PermissionBar,
+    queue: Rc<RefCell<PermissionQueue>>,
+    store: Rc<RefCell<PermissionStore>>,
+    handle: EngineHandle,
+}
+
+impl PermissionPrompter {
+    pub fn new(
+        bar: PermissionBar,
+        store: Rc<RefCell<PermissionStore>>,
+        handle: EngineHandle,
+    ) -> Self {
+        let prompter = Self {
+            bar,
+            queue: Rc::new(RefCell::new(PermissionQueue::default())),
+            store,
+            handle,
+        };
+
+        for (button, granted) in [(&prompter.bar.allow, true), (&prompter.bar.deny, false)] {
+            let prompter = prompter.clone();
+            button.connect_clicked(move |_| prompter.answer(granted));
+        }
+        prompter
+    }
+
+    /// Feed an engine event: permission requests are queued, and the
+    /// prompts of a view that navigates or closes are dropped.
+    pub fn handle_event(&self, event: &EngineEvent) {
+        match event {
+            EngineEvent::PermissionRequested(view_id, origin, kind) => {
+                let stored =
+                    self.queue
+                        .borrow_mut()
+                        .request(&self.store.borrow(), *view_id, origin, *kind);
+                match stored {
+                    Some(granted) => self.respond(*view_id, origin.clone(), *kind, granted),
+                    // Later requests wait behind the prompt already on screen
+                    None if self.queue.borrow().len() == 1 => self.refresh(),
+                    None => {}
+                }
+            }
+            EngineEvent::UrlChanged(view_id, _) | EngineEvent::ViewClosed(view_id) => {
+                let removed = self.queue.borrow_mut().remove_view(*view_id);
+                if removed {
+                    self.refresh();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn answer(&self, granted: bool) {
+        let remember = self.bar.remember.is_active();
+        let answered =
+            self.queue
+                .borrow_mut()
+                .answer(&mut self.store.borrow_mut(), granted, remember);
+        if let Some(request) = answered {
+            self.respond(request.view_id, request.origin, request.kind, granted);
+        }
+        self.refresh();
+    }
+
+    fn respond(&self, view_id: ViewId, origin: String, kind: PermissionKind, granted: bool) {
+        let result = self.handle.call_blocking(move |host| {
+            host.engine
+                .respond_permission(view_id, &origin, kind, granted)
+        });
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) | Err(e) => log::warn!("Failed to answer permission request: {}", e),
+        }
+    }
+
+    /// Show the next prompt, or hide the bar when none are left.
+    fn refresh(&self) {
+        match self.queue.borrow().current() {
+            Some(request) => self.bar.show(request),
+            None => self.bar.hide(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue_uses_stored_decisions() {
+        let mut store = PermissionStore::new(None);
+        store.set(
+            "https://maps.test",
+            PermissionKind::Geolocation,
+            PermissionDecision::Allow,
+        );
+        let mut queue = PermissionQueue::default();
+
+        let stored = queue.request(
+            &store,
+            ViewId(1),
+            "https://maps.test",
+            PermissionKind::Geolocation,
+        );
+        assert_eq!(stored, Some(true));
+        assert!(queue.current().is_none());
+
+        assert_eq!(
+            queue.request(
+                &store,
+                ViewId(1),
+                "https://maps.test",
+                PermissionKind::Camera
+            ),
+            None
+        );
+        assert_eq!(queue.current().unwrap().kind, PermissionKind::Camera);
+    }
+
+    #[test]
+    fn test_queue_answers_in_order() {
+        let mut store = PermissionStore::new(None);
+        let mut queue = PermissionQueue::default();
+        queue.request(&store, ViewId(1), "https://a.test", PermissionKind::Camera);
+        queue.request(
+            &store,
+            ViewId(2),
+            "https://b.test",
+            PermissionKind::Notifications,
+        );
+        queue.request(
+            &store,
+            ViewId(3),
+            "https://c.test",
+            PermissionKind::Clipboard,
+        );
+
+        let first = queue.answer(&mut store, false, true).unwrap();
+        assert_eq!(first.origin, "https://a.test");
+        assert_eq!(
+            store.decision("https://a.test", PermissionKind::Camera),
+            PermissionDecision::Deny
+        );
+
+        let next = queue.answer(&mut store, true, false).unwrap();
+        assert_eq!(next.origin, "https://b.test");
+        assert_eq!(
+            store.decision("https://b.test", PermissionKind::Notifications),
+            PermissionDecision::Ask
+        );
+        assert_eq!(queue.len(), 1);
+        queue.answer(&mut store, true, false);
+        assert!(queue.answer(&mut store, true, false).is_none());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_drops_prompts_of_view() {
+        let store = PermissionStore::new(None);
+        let mut queue = PermissionQueue::default();
+        queue.request(&store, ViewId(1), "https://a.test", PermissionKind::Camera);
+        queue.request(&store, ViewId(2), "https://b.test", PermissionKind::Camera);
+        queue.request(&store, ViewId(1), "https://a.test", PermissionKind::Microphone);
+
+        assert!(queue.remove_view(ViewId(1)));
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.current().unwrap().origin, "https://b.test");
+        assert!(!queue.remove_view(ViewId(1)));
+        assert!(queue.remove_view(ViewId(2)));
+        assert!(queue.current().is_none());
+    }
+
+    #[test]
+    fn test_prompt_message() {
+        assert_eq!(
+            prompt_message("https://meet.test", PermissionKind::Microphone),
+            "meet.test wants to use your microphone"
+        );
+    }
+}
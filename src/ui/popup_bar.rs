@@ -0,0 +1,191 @@
+//! Blocked popup notice for Asteroid Browser.
+//!
+//! Opens the tabs pages ask for through `EngineEvent::NewViewRequested`,
+//! and shows "N pop-ups blocked" below the toolbar when the popup blocker
+//! stops some. The notice can open the blocked popups once or always allow
+//! popups from the site.
+
+use crate::core::engine::{EngineEvent, NewViewRequest, PermissionKind, ViewId};
+use crate::core::engine_host::EngineHandle;
+use crate::core::permissions::{PermissionDecision, PermissionStore};
+use crate::core::popups::{blocked_notice, PopupBlocker};
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, Label, Orientation};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// The notice widgets.
+#[derive(Clone)]
+pub struct PopupBar {
+    pub container: GtkBox,
+    message: Label,
+    open: Button,
+    always_allow: Button,
+    dismiss: Button,
+}
+
+impl PopupBar {
+    pub fn new() -> Self {
+        let container = GtkBox::new(Orientation::Horizontal, 8);
+        container.set_margin_start(8);
+        container.set_margin_end(8);
+        container.add_css_class("permission-bar");
+        container.set_visible(false);
+
+        let message = Label::new(None);
+        message.set_hexpand(true);
+        message.set_halign(gtk4::Align::Start);
+        container.append(&message);
+
+        let open = Button::with_label("Open");
+        container.append(&open);
+
+        let always_allow = Button::with_label("Always allow");
+        always_allow.add_css_class("permission-allow");
+        container.append(&always_allow);
+
+        let dismiss = Button::with_label("\u{2715}"); // ✕
+        dismiss.set_tooltip_text(Some("Dismiss"));
+        container.append(&dismiss);
+
+        Self {
+            container,
+            message,
+            open,
+            always_allow,
+            dismiss,
+        }
+    }
+
+    pub fn show(&self, count: usize) {
+        self.message.set_text(&blocked_notice(count));
+        self.container.set_visible(true);
+    }
+
+    pub fn hide(&self) {
+        self.container.set_visible(false);
+    }
+}
+
+impl Default for PopupBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Connects the notice to engine events, the popup blocker and the
+/// permission store.
+#[derive(Clone)]
+pub struct PopupHandler {
+    bar: PopupBar,
+    blocker: Rc<RefCell<PopupBlocker>>,
+    store: Rc<RefCell<PermissionStore>>,
+    handle: EngineHandle,
+    /// Opener whose blocked popups the notice is showing
+    shown_for: Rc<Cell<Option<ViewId>>>,
+}
+
+impl PopupHandler {
+    pub fn new(
+        bar: PopupBar,
+        blocker: Rc<RefCell<PopupBlocker>>,
+        store: Rc<RefCell<PermissionStore>>,
+        handle: EngineHandle,
+    ) -> Self {
+        let handler = Self {
+            bar,
+            blocker,
+            store,
+            handle,
+            shown_for: Rc::new(Cell::new(None)),
+        };
+
+        let h = handler.clone();
+        handler
+            .bar
+            .open
+            .connect_clicked(move |_| h.open_blocked(false));
+        let h = handler.clone();
+        handler
+            .bar
+            .always_allow
+            .connect_clicked(move |_| h.open_blocked(true));
+        let h = handler.clone();
+        handler.bar.dismiss.connect_clicked(move |_| {
+            if let Some(opener) = h.shown_for.take() {
+                h.blocker.borrow_mut().clear(opener);
+            }
+            h.bar.hide();
+        });
+        handler
+    }
+
+    /// Feed an engine event; new view requests and navigations are acted on.
+    pub fn handle_event(&self, event: &EngineEvent) {
+        match event {
+            EngineEvent::NewViewRequested(opener, request) => {
+                let opener = *opener;
+                let opener_url = self
+                    .handle
+                    .call_blocking(move |host| host.tabs.get_tab(opener).map(|t| t.url.clone()))
+                    .ok()
+                    .flatten();
+                let Some(opener_url) = opener_url else {
+                    return;
+                };
+
+                let allowed = self.blocker.borrow_mut().allow(
+                    &self.store.borrow(),
+                    opener,
+                    &opener_url,
+                    request,
+                );
+                if allowed {
+                    self.open(opener, request.clone());
+                } else {
+                    self.shown_for.set(Some(opener));
+                    self.bar.show(self.blocker.borrow().blocked_count(opener));
+                }
+            }
+            // Popups blocked on a page are forgotten when it is left
+            EngineEvent::UrlChanged(view_id, _) => {
+                self.blocker.borrow_mut().clear(*view_id);
+                if self.shown_for.get() == Some(*view_id) {
+                    self.shown_for.set(None);
+                    self.bar.hide();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn open_blocked(&self, always: bool) {
+        let Some(opener) = self.shown_for.take() else {
+            return;
+        };
+        self.bar.hide();
+        let Some(blocked) = self.blocker.borrow_mut().take_blocked(opener) else {
+            return;
+        };
+        if always && !blocked.origin.is_empty() {
+            self.store.borrow_mut().set(
+                &blocked.origin,
+                PermissionKind::Popups,
+                PermissionDecision::Allow,
+            );
+        }
+        for request in blocked.requests {
+            self.open(opener, request);
+        }
+    }
+
+    fn open(&self, opener: ViewId, request: NewViewRequest) {
+        let result = self
+            .handle
+            .call_blocking(move |host| host.tabs.open_from(opener, &request, &mut host.engine));
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) | Err(e) => log::warn!("Failed to open new tab: {}", e),
+        }
+    }
+}
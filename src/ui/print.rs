@@ -0,0 +1,97 @@
+//! Print dialog for Asteroid Browser.
+//!
+//! Real printers go through the GTK print dialog: the page is rendered to
+//! a temporary PDF with `BrowserEngine::print_to_pdf` using the paper and
+//! margins picked in the dialog, and the PDF is sent to the chosen printer
+//! as a print job. Saving as PDF does not need this path; see
+//! `core::print::save_pdf`.
+
+use crate::core::engine::{BrowserEngine, PageMargins, PageSize, PdfOptions, ViewId};
+use crate::core::engine_host::EngineHandle;
+use gtk4::prelude::*;
+use gtk4::{
+    ApplicationWindow, PageOrientation, PageSetup, PrintJob, PrintUnixDialog, ResponseType, Unit,
+};
+
+/// PDF options matching the paper, orientation and margins of a GTK page setup.
+pub fn pdf_options_from_setup(setup: &PageSetup) -> PdfOptions {
+    let landscape = matches!(
+        setup.orientation(),
+        PageOrientation::Landscape | PageOrientation::ReverseLandscape
+    );
+    // Paper size is reported for the chosen orientation
+    let (width, height) = (setup.paper_width(Unit::Mm), setup.paper_height(Unit::Mm));
+    let (width_mm, height_mm) = if landscape {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    PdfOptions {
+        page_size: PageSize::Custom {
+            width_mm,
+            height_mm,
+        },
+        landscape,
+        margins: PageMargins {
+            top: setup.top_margin(Unit::Mm),
+            right: setup.right_margin(Unit::Mm),
+            bottom: setup.bottom_margin(Unit::Mm),
+            left: setup.left_margin(Unit::Mm),
+        },
+        ..Default::default()
+    }
+}
+
+/// Show the print dialog for a view and print it on the chosen printer.
+pub fn show_print_dialog(
+    parent: &ApplicationWindow,
+    handle: EngineHandle,
+    view_id: ViewId,
+    title: String,
+) {
+    let dialog = PrintUnixDialog::new(Some("Print"), Some(parent));
+
+    dialog.connect_response(move |dialog, response| {
+        if response == ResponseType::Ok {
+            match dialog.selected_printer() {
+                Some(printer) => {
+                    let settings = dialog.settings();
+                    let setup = dialog.page_setup();
+                    let options = pdf_options_from_setup(&setup);
+                    let path = std::env::temp_dir().join(format!(
+                        "asteroid-print-{}-{}.pdf",
+                        std::process::id(),
+                        view_id.0
+                    ));
+
+                    let target = path.clone();
+                    let printed = handle.call_blocking(move |host| {
+                        host.engine.print_to_pdf(view_id, &options, &target)
+                    });
+                    match printed {
+                        Ok(Ok(())) => {
+                            let job = PrintJob::new(&title, &printer, &settings, &setup);
+                            match job.set_source_file(&path) {
+                                Ok(()) => job.send(move |_, result| {
+                                    if let Err(e) = result {
+                                        log::error!("Print job failed: {}", e);
+                                    }
+                                    let _ = std::fs::remove_file(&path);
+                                }),
+                                Err(e) => log::error!("Failed to queue print job: {}", e),
+                            }
+                        }
+                        Ok(Err(e)) | Err(e) => {
+                            log::error!("Failed to render page for printing: {}", e)
+                        }
+                    }
+                }
+                None => log::warn!("No printer selected"),
+            }
+        }
+        dialog.destroy();
+    });
+
+    dialog.present();
+}
@@ -4,9 +4,127 @@
 //! within the browser itself (at asteroid://settings).
 
 use crate::core::config::Config;
+use crate::core::engine::{EngineCapabilities, EngineInfo};
+use crate::core::media_policy::{AutoplayPolicy, PreloadPolicy};
+use crate::core::permissions::{PermissionDecision, PermissionStore};
+use crate::core::user_agent::UserAgentPreset;
+use crate::ui::escape_html;
 
-/// Generate the settings HTML page.
-pub fn generate_settings_html(config: &Config) -> String {
+/// Generate the site permissions section rows.
+fn permission_rows(permissions: &PermissionStore) -> String {
+    let entries = permissions.entries();
+    if entries.is_empty() {
+        return r#"    <div class="setting">
+        <div class="description">No site permissions saved. Sites will ask when they need one.</div>
+    </div>
+"#
+        .to_string();
+    }
+
+    entries
+        .iter()
+        .map(|(origin, kind, decision)| {
+            let option = |value: PermissionDecision, label: &str| {
+                format!(
+                    r#"<option value="{}" {}>{}</option>"#,
+                    value.as_str(),
+                    if *decision == value { "selected" } else { "" },
+                    label
+                )
+            };
+            format!(
+                r#"    <div class="setting">
+        <div>
+            <label>{origin}</label>
+            <div class="description">{kind}</div>
+        </div>
+        <select class="site-permission" data-origin="{origin}" data-permission="{key}">
+            {allow}
+            {deny}
+            {ask}
+        </select>
+    </div>
+"#,
+                origin = escape_html(origin),
+                kind = kind.label(),
+                key = kind.as_str(),
+                allow = option(PermissionDecision::Allow, "Allow"),
+                deny = option(PermissionDecision::Deny, "Block"),
+                ask = option(PermissionDecision::Ask, "Ask"),
+            )
+        })
+        .collect()
+}
+
+/// Generate the rendering engine choices.
+fn engine_options(current: &str, engines: &[EngineInfo]) -> String {
+    engines
+        .iter()
+        .map(|engine| {
+            format!(
+                "            <option value=\"{}\" {}>{} v{}</option>\n",
+                escape_html(&engine.name),
+                if engine.name == current { "selected" } else { "" },
+                escape_html(&engine.display_name),
+                escape_html(&engine.version),
+            )
+        })
+        .collect()
+}
+
+/// Generate the user agent choices. A custom agent string from the config
+/// file is kept as its own choice.
+fn user_agent_options(current: &str) -> String {
+    let option = |value: &str, label: &str| {
+        format!(
+            "            <option value=\"{}\" {}>{}</option>\n",
+            escape_html(value),
+            if value.eq_ignore_ascii_case(current.trim()) { "selected" } else { "" },
+            label,
+        )
+    };
+    let mut options = option("", "Engine default");
+    for preset in UserAgentPreset::ALL {
+        options += &option(preset.name(), preset.label());
+    }
+    if !current.trim().is_empty() && UserAgentPreset::from_name(current.trim()).is_none() {
+        options += &option(current.trim(), "Custom");
+    }
+    options
+}
+
+/// Generate the options of a fixed set of `(name, label)` choices.
+fn choice_options<'a>(current: &str, choices: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    choices
+        .map(|(name, label)| {
+            format!(
+                "            <option value=\"{}\" {}>{}</option>\n",
+                name,
+                if name == current { "selected" } else { "" },
+                label,
+            )
+        })
+        .collect()
+}
+
+/// Attributes of an input for a feature the current engine may lack.
+fn supported(supported: bool) -> &'static str {
+    if supported {
+        ""
+    } else {
+        r#"disabled title="Not supported by the current engine""#
+    }
+}
+
+/// Generate the settings HTML page. `engines` are the engines compiled
+/// into this build; settings the current one cannot honour are disabled.
+pub fn generate_settings_html(
+    config: &Config,
+    permissions: &PermissionStore,
+    engines: &[EngineInfo],
+) -> String {
+    let current = engines.iter().find(|e| e.name == config.engine.current);
+    let caps = current.map_or_else(EngineCapabilities::all, |e| e.capabilities.clone());
     format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -85,30 +203,37 @@ pub fn generate_settings_html(config: &Config) -> String {
     <div class="setting">
         <div>
             <label>Enable tab suspension</label>
-            <div class="description">Suspend inactive tabs after {} seconds</div>
+            <div class="description">Unload tabs left in the background to save memory</div>
+        </div>
+        <input type="checkbox" id="tab-suspension" data-key="general.tab_suspension_enabled" {} {}>
+    </div>
+    <div class="setting">
+        <div>
+            <label>Suspension delay (seconds)</label>
+            <div class="description">How long a tab stays inactive before it is suspended</div>
         </div>
-        <input type="checkbox" id="tab-suspension" {}>
+        <input type="number" id="suspension-delay" data-key="general.tab_suspension_delay" value="{}" min="30" max="86400" {}>
     </div>
     <div class="setting">
         <div>
             <label>Show vertical tab sidebar</label>
             <div class="description">Display tabs in a sidebar instead of horizontal strip</div>
         </div>
-        <input type="checkbox" id="vertical-tabs" {}>
+        <input type="checkbox" id="vertical-tabs" data-key="general.vertical_tabs" {}>
     </div>
     <div class="setting">
         <div>
             <label>Vim-style link hints</label>
             <div class="description">Press 'f' to show numbered hints on clickable elements</div>
         </div>
-        <input type="checkbox" id="vim-hints" {}>
+        <input type="checkbox" id="vim-hints" data-key="general.vim_hints" {} {}>
     </div>
     <div class="setting">
         <div>
             <label>Check for updates automatically</label>
             <div class="description">Check GitHub releases for new versions</div>
         </div>
-        <input type="checkbox" id="auto-update" {}>
+        <input type="checkbox" id="auto-update" data-key="general.auto_update_check" {}>
     </div>
 
     <h2>Performance</h2>
@@ -117,14 +242,14 @@ pub fn generate_settings_html(config: &Config) -> String {
             <label>Hardware video acceleration</label>
             <div class="description">Use VA-API for hardware video decoding</div>
         </div>
-        <input type="checkbox" id="hw-accel" {}>
+        <input type="checkbox" id="hw-accel" data-key="performance.hardware_acceleration" {} {}>
     </div>
     <div class="setting">
         <div>
             <label>Memory management</label>
             <div class="description">Aggressiveness of memory trimming</div>
         </div>
-        <select id="memory-trim">
+        <select id="memory-trim" data-key="performance.memory_trim_level">
             <option value="off" {}>Off</option>
             <option value="moderate" {}>Moderate</option>
             <option value="aggressive" {}>Aggressive</option>
@@ -135,7 +260,25 @@ pub fn generate_settings_html(config: &Config) -> String {
             <label>Cache size (MB)</label>
             <div class="description">Maximum disk cache size</div>
         </div>
-        <input type="number" id="cache-size" value="{}" min="10" max="500">
+        <input type="number" id="cache-size" data-key="performance.cache_size_mb" value="{}" min="10" max="500">
+    </div>
+
+    <h2>Media</h2>
+    <div class="setting">
+        <div>
+            <label>Autoplay</label>
+            <div class="description">Media that may start without a click. Sites can be changed on their site info page</div>
+        </div>
+        <select id="autoplay" data-key="media.autoplay">
+{}        </select>
+    </div>
+    <div class="setting">
+        <div>
+            <label>Preload</label>
+            <div class="description">How much media is downloaded before it plays</div>
+        </div>
+        <select id="preload" data-key="media.preload">
+{}        </select>
     </div>
 
     <h2>Privacy</h2>
@@ -144,47 +287,62 @@ pub fn generate_settings_html(config: &Config) -> String {
             <label>Block ads and trackers</label>
             <div class="description">Built-in content blocking using filter lists</div>
         </div>
-        <input type="checkbox" id="block-ads" {}>
+        <input type="checkbox" id="block-ads" data-key="privacy.block_ads" {} {}>
     </div>
     <div class="setting">
         <div>
             <label>Send Do Not Track header</label>
             <div class="description">Request websites not to track you</div>
         </div>
-        <input type="checkbox" id="send-dnt" {}>
+        <input type="checkbox" id="send-dnt" data-key="privacy.send_dnt" {}>
     </div>
     <div class="setting">
         <div>
             <label>Delete cookies on close</label>
             <div class="description">Clear all cookies when the browser is closed</div>
         </div>
-        <input type="checkbox" id="clear-cookies" {}>
+        <input type="checkbox" id="clear-cookies" data-key="privacy.clear_cookies_on_close" {}>
     </div>
     <div class="setting">
         <div>
             <label>HTTPS-only mode</label>
-            <div class="description">Warn when navigating to non-HTTPS sites</div>
+            <div class="description">Load sites over HTTPS and warn when one does not support it</div>
         </div>
-        <input type="checkbox" id="https-only" {}>
+        <input type="checkbox" id="https-only" data-key="privacy.https_only" {}>
+    </div>
+    <div class="setting">
+        <div>
+            <label>Block pop-ups</label>
+            <div class="description">Block windows sites open without a click</div>
+        </div>
+        <input type="checkbox" id="block-popups" data-key="privacy.block_popups" {}>
     </div>
 
+    <h2>Site permissions</h2>
+{}
     <h2>Advanced</h2>
     <div class="setting">
         <div>
             <label>Rendering engine</label>
-            <div class="description">Current rendering engine</div>
+            <div class="description">Takes effect after a restart</div>
         </div>
-        <select id="engine" disabled>
-            <option value="gecko" selected>Gecko v124</option>
-            <option value="servo">Servo (when available)</option>
-        </select>
+        <select id="engine" data-key="engine.current">
+{}        </select>
+    </div>
+    <div class="setting">
+        <div>
+            <label>User agent</label>
+            <div class="description">Mobile or minimal agents get lighter pages from some sites</div>
+        </div>
+        <select id="user-agent" data-key="engine.user_agent">
+{}        </select>
     </div>
     <div class="setting">
         <div>
             <label>Enable developer tools</label>
             <div class="description">Show web inspector and console</div>
         </div>
-        <input type="checkbox" id="devtools" {}>
+        <input type="checkbox" id="devtools" data-key="ui.developer_tools" {}>
     </div>
 
     <div class="version-info">
@@ -192,26 +350,62 @@ pub fn generate_settings_html(config: &Config) -> String {
         <div>Version {}</div>
         <div>Engine: {} v{}</div>
     </div>
+    <script>
+        for (const input of document.querySelectorAll("[data-key]")) {{
+            input.addEventListener("change", () => window.asteroid.postMessage({{
+                type: "set",
+                key: input.dataset.key,
+                value: input.type === "checkbox" ? input.checked
+                    : input.type === "number" ? Number(input.value)
+                    : input.value,
+            }}));
+        }}
+        for (const select of document.querySelectorAll(".site-permission")) {{
+            select.addEventListener("change", () => window.asteroid.postMessage({{
+                type: "set_permission",
+                origin: select.dataset.origin,
+                permission: select.dataset.permission,
+                decision: select.value,
+            }}));
+        }}
+    </script>
 </body>
 </html>"#,
-        config.general.tab_suspension_delay,
         if config.general.tab_suspension_enabled { "checked" } else { "" },
+        supported(caps.suspend),
+        config.general.tab_suspension_delay,
+        supported(caps.suspend),
         if config.general.vertical_tabs { "checked" } else { "" },
         if config.general.vim_hints { "checked" } else { "" },
+        supported(caps.script_execution),
         if config.general.auto_update_check { "checked" } else { "" },
         if config.performance.hardware_acceleration { "checked" } else { "" },
+        supported(caps.hardware_video()),
         if config.performance.memory_trim_level == "off" { "selected" } else { "" },
         if config.performance.memory_trim_level == "moderate" { "selected" } else { "" },
         if config.performance.memory_trim_level == "aggressive" { "selected" } else { "" },
         config.performance.cache_size_mb,
-        if config.privacy.block_ads { "checked" } else { "" },
+        choice_options(
+            config.media.autoplay.name(),
+            AutoplayPolicy::ALL.iter().map(|p| (p.name(), p.label())),
+        ),
+        choice_options(
+            config.media.preload.name(),
+            PreloadPolicy::ALL.iter().map(|p| (p.name(), p.label())),
+        ),
+        if config.privacy.block_ads || config.privacy.block_trackers { "checked" } else { "" },
+        supported(caps.request_interception),
         if config.privacy.send_dnt { "checked" } else { "" },
         if config.privacy.clear_cookies_on_close { "checked" } else { "" },
         if config.privacy.https_only { "checked" } else { "" },
+        if config.privacy.block_popups { "checked" } else { "" },
+        permission_rows(permissions),
+        engine_options(&config.engine.current, engines),
+        user_agent_options(&config.engine.user_agent),
         if config.ui.developer_tools { "checked" } else { "" },
         env!("CARGO_PKG_VERSION"),
-        config.engine.current,
-        "124.0",
+        current.map_or(config.engine.current.as_str(), |e| e.display_name.as_str()),
+        current.map_or("unknown", |e| e.version.as_str()),
     )
 }
 
@@ -222,10 +416,109 @@ mod tests {
     #[test]
     fn test_settings_html_generation() {
         let config = Config::default();
-        let html = generate_settings_html(&config);
+        let html = generate_settings_html(&config, &PermissionStore::new(None), &[]);
         assert!(html.contains("Asteroid Browser Settings"));
         assert!(html.contains("tab suspension"));
         assert!(html.contains("Hardware video acceleration"));
         assert!(html.contains("Block ads"));
+        assert!(html.contains("No site permissions saved"));
+        assert!(html.contains(r#"data-key="general.tab_suspension_delay" value="300""#));
+    }
+
+    #[test]
+    fn test_settings_lists_registered_engines() {
+        let engines = [
+            EngineInfo {
+                name: "gecko".to_string(),
+                display_name: "Gecko".to_string(),
+                version: "124.0".to_string(),
+                capabilities: EngineCapabilities::all(),
+            },
+            EngineInfo {
+                name: "mock".to_string(),
+                display_name: "Mock".to_string(),
+                version: "1.0".to_string(),
+                capabilities: EngineCapabilities::all(),
+            },
+        ];
+        let html = generate_settings_html(&Config::default(), &PermissionStore::new(None), &engines);
+        assert!(html.contains(r#"<option value="gecko" selected>Gecko v124.0</option>"#));
+        assert!(html.contains(r#"<option value="mock" >Mock v1.0</option>"#));
+        assert!(!html.contains("Servo"));
+        assert!(html.contains("Engine: Gecko v124.0"));
+        assert!(!html.contains("disabled"));
+    }
+
+    #[test]
+    fn test_settings_disable_unsupported_features() {
+        let engines = [EngineInfo {
+            name: "gecko".to_string(),
+            display_name: "Gecko".to_string(),
+            version: "124.0".to_string(),
+            capabilities: EngineCapabilities {
+                find: true,
+                script_execution: true,
+                ..Default::default()
+            },
+        }];
+        let html = generate_settings_html(&Config::default(), &PermissionStore::new(None), &engines);
+        for key in [
+            "general.tab_suspension_enabled",
+            "performance.hardware_acceleration",
+            "privacy.block_ads",
+        ] {
+            let input = html.split(&format!(r#"data-key="{}""#, key)).nth(1).unwrap();
+            let input = &input[..input.find('>').unwrap()];
+            assert!(input.contains("disabled"), "{}", key);
+        }
+        let hints = html.split(r#"data-key="general.vim_hints""#).nth(1).unwrap();
+        assert!(!hints[..hints.find('>').unwrap()].contains("disabled"));
+    }
+
+    #[test]
+    fn test_settings_lists_site_permissions() {
+        use crate::core::engine::PermissionKind;
+
+        let mut permissions = PermissionStore::new(None);
+        permissions.set(
+            "https://maps.test",
+            PermissionKind::Geolocation,
+            PermissionDecision::Allow,
+        );
+        let html = generate_settings_html(&Config::default(), &permissions, &[]);
+        assert!(html.contains(r#"data-origin="https://maps.test" data-permission="geolocation""#));
+        assert!(html.contains(r#"<option value="allow" selected>Allow</option>"#));
+        assert!(html.contains(r#"<option value="deny" >Block</option>"#));
+    }
+
+    #[test]
+    fn test_settings_user_agent_choices() {
+        let mut config = Config::default();
+        let html = generate_settings_html(&config, &PermissionStore::new(None), &[]);
+        assert!(html.contains(r#"<option value="" selected>Engine default</option>"#));
+        assert!(!html.contains("Custom"));
+
+        config.engine.user_agent = "Mobile".to_string();
+        let html = generate_settings_html(&config, &PermissionStore::new(None), &[]);
+        assert!(html.contains(r#"<option value="mobile" selected>Mobile</option>"#));
+
+        config.engine.user_agent = "Lynx/2.9 <x>".to_string();
+        let html = generate_settings_html(&config, &PermissionStore::new(None), &[]);
+        assert!(html.contains(r#"<option value="Lynx/2.9 &lt;x&gt;" selected>Custom</option>"#));
+    }
+
+    #[test]
+    fn test_settings_media_choices() {
+        let mut config = Config::default();
+        let html = generate_settings_html(&config, &PermissionStore::new(None), &[]);
+        assert!(html.contains(r#"<option value="block_all" selected>Block all</option>"#));
+        assert!(html.contains(r#"<option value="metadata" selected>Metadata only</option>"#));
+
+        config.media.autoplay = AutoplayPolicy::BlockAudible;
+        config.media.preload = PreloadPolicy::None;
+        let html = generate_settings_html(&config, &PermissionStore::new(None), &[]);
+        assert!(html.contains(r#"<option value="block_audible" selected>Block audible</option>"#));
+        assert!(html.contains(r#"<option value="none" selected>None</option>"#));
+        assert!(html.contains(r#"<option value="block_all" >Block all</option>"#));
     }
 }
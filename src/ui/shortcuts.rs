@@ -48,8 +48,12 @@ pub enum BrowserAction {
     ZoomReset,
     /// Open downloads (Ctrl+J)
     OpenDownloads,
+    /// Open the site data page
+    OpenSiteData,
     /// Print page (Ctrl+P)
     PrintPage,
+    /// Save page as PDF (Ctrl+Shift+S)
+    SavePdf,
     /// View page source (Ctrl+U)
     ViewSource,
 }
@@ -87,6 +91,7 @@ pub fn default_shortcuts() -> Vec<Shortcut> {
         Shortcut { key: "0".into(), ctrl: true, alt: false, shift: false, action: BrowserAction::ZoomReset },
         Shortcut { key: "j".into(), ctrl: true, alt: false, shift: false, action: BrowserAction::OpenDownloads },
         Shortcut { key: "p".into(), ctrl: true, alt: false, shift: false, action: BrowserAction::PrintPage },
+        Shortcut { key: "s".into(), ctrl: true, alt: false, shift: true, action: BrowserAction::SavePdf },
         Shortcut { key: "u".into(), ctrl: true, alt: false, shift: false, action: BrowserAction::ViewSource },
         // Tab switching: Ctrl+1 through Ctrl+9
         Shortcut { key: "1".into(), ctrl: true, alt: false, shift: false, action: BrowserAction::SwitchToTab(1) },
@@ -101,10 +106,34 @@ pub fn default_shortcuts() -> Vec<Shortcut> {
     ]
 }
 
+/// Action of the shortcut for a key press, if any. `key` is the key's
+/// GDK name, in either case.
+pub fn find_shortcut(
+    shortcuts: &[Shortcut],
+    key: &str,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+) -> Option<BrowserAction> {
+    shortcuts
+        .iter()
+        .find(|s| {
+            s.key.eq_ignore_ascii_case(key) && s.ctrl == ctrl && s.alt == alt && s.shift == shift
+        })
+        .map(|s| s.action.clone())
+}
+
 /// Vim-style link hint characters.
 pub const HINT_CHARS: &str = "asdfghjklqwertyuiopzxcvbnm";
 
+/// Isolated world the link hints script runs in.
+pub const HINTS_WORLD: &str = "vim-hints";
+
 /// Generate JavaScript for vim-style link hints.
+///
+/// Meant to be added as a user script in `HINTS_WORLD`, where
+/// `asteroidHints.show()` toggles the hints. Its state and key handling
+/// stay out of reach of page scripts.
 pub fn vim_hints_js() -> &'static str {
     r#"
 (function() {
@@ -177,6 +206,19 @@ pub fn vim_hints_js() -> &'static str {
         input = '';
     }
 
+    function onKeyDown(event) {
+        if (!active || event.ctrlKey || event.altKey || event.metaKey) return;
+        if (event.key === 'Escape') {
+            removeHints();
+        } else if (event.key.length === 1 && HINT_CHARS.includes(event.key.toLowerCase())) {
+            handleInput(event.key.toLowerCase());
+        } else {
+            return;
+        }
+        event.preventDefault();
+        event.stopImmediatePropagation();
+    }
+
     function handleInput(char) {
         input += char;
         const match = hints.find(h => h.label === input);
@@ -196,7 +238,8 @@ pub fn vim_hints_js() -> &'static str {
         }
     }
 
-    window.__asteroidHints = { show: showHints, remove: removeHints, handle: handleInput };
+    window.addEventListener('keydown', onKeyDown, true);
+    globalThis.asteroidHints = Object.freeze({ show: showHints, remove: removeHints });
 })();
 "#
 }
@@ -219,10 +262,25 @@ mod tests {
         assert!(has_address);
     }
 
+    #[test]
+    fn test_find_shortcut() {
+        let shortcuts = default_shortcuts();
+        let find = |key, ctrl, alt, shift| find_shortcut(&shortcuts, key, ctrl, alt, shift);
+        assert_eq!(find("p", true, false, false), Some(BrowserAction::PrintPage));
+        assert_eq!(find("S", true, false, true), Some(BrowserAction::SavePdf));
+        assert_eq!(find("slash", false, false, false), Some(BrowserAction::QuickFind));
+        assert_eq!(find("f", false, false, false), Some(BrowserAction::VimHints));
+        assert_eq!(find("p", false, false, false), None);
+        assert_eq!(find("p", true, true, false), None);
+    }
+
     #[test]
     fn test_vim_hints_js() {
         let js = vim_hints_js();
         assert!(js.contains("asteroid-hint"));
         assert!(js.contains("showHints"));
+        // Nothing is published on the page's window
+        assert!(!js.contains("window.__asteroidHints"));
+        assert!(js.contains("globalThis.asteroidHints"));
     }
 }
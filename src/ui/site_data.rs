@@ -0,0 +1,208 @@
+//! Site data page for Asteroid Browser.
+//!
+//! Generates the HTML list of cookies and storage used per origin,
+//! displayed at asteroid://site-data. Clear links point back into the
+//! page and are turned into engine calls by `parse_site_data_command`;
+//! settings links open the site's `asteroid://site-info` page.
+
+use crate::core::engine::SiteDataUsage;
+use crate::ui::site_info::site_info_url;
+use crate::ui::{escape_html, format_bytes};
+
+/// Address of the site data page.
+pub const SITE_DATA_URL: &str = "asteroid://site-data";
+
+/// Action requested from the site data page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SiteDataCommand {
+    /// Clear everything stored by one origin
+    Clear(String),
+    /// Clear everything stored by every origin
+    ClearAll,
+}
+
+/// Link that clears an origin, e.g. `asteroid://site-data/clear/https/example.com`.
+fn clear_link(origin: &str) -> String {
+    let (scheme, host) = origin.split_once("://").unwrap_or(("https", origin));
+    format!("{}/clear/{}/{}", SITE_DATA_URL, scheme, host)
+}
+
+/// Parse a control link produced by the site data page.
+pub fn parse_site_data_command(url: &str) -> Option<SiteDataCommand> {
+    let rest = url.strip_prefix(SITE_DATA_URL)?.strip_prefix('/')?;
+    if rest == "clear-all" {
+        return Some(SiteDataCommand::ClearAll);
+    }
+    let (scheme, host) = rest.strip_prefix("clear/")?.split_once('/')?;
+    if scheme.is_empty() || host.is_empty() || host.contains('/') {
+        return None;
+    }
+    Some(SiteDataCommand::Clear(format!("{}://{}", scheme, host)))
+}
+
+fn usage_row(usage: &SiteDataUsage) -> String {
+    let mut details = Vec::new();
+    if usage.cookies > 0 {
+        details.push(format!(
+            "{} cookie{}",
+            usage.cookies,
+            if usage.cookies == 1 { "" } else { "s" }
+        ));
+    }
+    for (label, bytes) in [
+        ("local storage", usage.local_storage_bytes),
+        ("IndexedDB", usage.indexed_db_bytes),
+        ("cache", usage.cache_bytes),
+    ] {
+        if bytes > 0 {
+            details.push(format!("{} {}", format_bytes(bytes), label));
+        }
+    }
+
+    format!(
+        r#"    <div class="site">
+        <div class="info">
+            <div class="origin">{origin}</div>
+            <div class="details">{details}</div>
+        </div>
+        <div class="total">{total}</div>
+        <a href="{info}">Settings</a>
+        <a href="{clear}">Clear</a>
+    </div>
+"#,
+        origin = escape_html(&usage.origin),
+        details = escape_html(&details.join(" · ")),
+        total = format_bytes(usage.total_bytes()),
+        info = escape_html(&site_info_url(&usage.origin)),
+        clear = escape_html(&clear_link(&usage.origin)),
+    )
+}
+
+/// Generate the site data HTML page, largest origins first.
+pub fn generate_site_data_html(usage: &[SiteDataUsage]) -> String {
+    let mut sorted: Vec<&SiteDataUsage> = usage.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.total_bytes()
+            .cmp(&a.total_bytes())
+            .then_with(|| a.origin.cmp(&b.origin))
+    });
+
+    let total: u64 = usage.iter().map(|u| u.total_bytes()).sum();
+    let rows: String = if sorted.is_empty() {
+        r#"    <div class="empty">No site data stored</div>
+"#
+        .to_string()
+    } else {
+        sorted.into_iter().map(usage_row).collect()
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Site Data</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 800px;
+            margin: 0 auto;
+            padding: 40px 20px;
+        }}
+        h1 {{
+            color: #7DC6DA;
+            margin-bottom: 30px;
+            font-size: 28px;
+        }}
+        .site {{
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            padding: 12px 0;
+            border-bottom: 1px solid #16213e;
+        }}
+        .site .info {{ flex: 1; min-width: 0; }}
+        .site .origin {{ font-size: 14px; }}
+        .site .details {{ font-size: 12px; color: #888; margin-top: 4px; }}
+        .site .total {{ font-size: 14px; color: #888; margin-left: 12px; }}
+        a {{
+            color: #7DC6DA;
+            margin-left: 12px;
+            font-size: 14px;
+            text-decoration: none;
+        }}
+        .toolbar {{
+            display: flex;
+            justify-content: space-between;
+            margin-bottom: 10px;
+            font-size: 14px;
+            color: #888;
+        }}
+        .empty {{ color: #888; text-align: center; padding: 40px 0; }}
+    </style>
+</head>
+<body>
+    <h1>Site Data</h1>
+    <div class="toolbar"><span>{total} stored</span><a href="{url}/clear-all">Clear all data</a></div>
+{rows}</body>
+</html>"#,
+        total = format_bytes(total),
+        url = SITE_DATA_URL,
+        rows = rows,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(origin: &str, cookies: usize, cache_bytes: u64) -> SiteDataUsage {
+        SiteDataUsage {
+            origin: origin.to_string(),
+            cookies,
+            local_storage_bytes: 2048,
+            indexed_db_bytes: 0,
+            cache_bytes,
+        }
+    }
+
+    #[test]
+    fn test_site_data_html_generation() {
+        let html = generate_site_data_html(&[
+            usage("https://small.test", 1, 0),
+            usage("https://big.test:8443", 3, 1024 * 1024),
+        ]);
+        assert!(html.contains("3 cookies"));
+        assert!(html.contains("1 cookie "));
+        assert!(html.contains("2.0 KB local storage"));
+        assert!(html.contains("asteroid://site-data/clear/https/big.test:8443"));
+        assert!(html.contains("asteroid://site-info?url=https%3A%2F%2Fbig.test%3A8443"));
+        // Largest first
+        assert!(html.find("big.test").unwrap() < html.find("small.test").unwrap());
+
+        assert!(generate_site_data_html(&[]).contains("No site data stored"));
+    }
+
+    #[test]
+    fn test_parse_site_data_command() {
+        assert_eq!(
+            parse_site_data_command("asteroid://site-data/clear/https/example.com:8443"),
+            Some(SiteDataCommand::Clear(
+                "https://example.com:8443".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_site_data_command("asteroid://site-data/clear-all"),
+            Some(SiteDataCommand::ClearAll)
+        );
+        assert_eq!(parse_site_data_command("asteroid://site-data"), None);
+        assert_eq!(
+            parse_site_data_command("asteroid://site-data/clear/https/a.test/x"),
+            None
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Site information page for Asteroid Browser.
+//!
+//! `asteroid://site-info?url=...` shows the site of a page and how the
+//! browser treats it, and lets the user change its autoplay and preload
+//! policy. Changes are posted through the page bridge and kept as
+//! `[[media.site_rules]]` in the configuration.
+
+use crate::core::config::MediaConfig;
+use crate::core::media_policy::{media_site, AutoplayPolicy, PreloadPolicy};
+use crate::ui::{decode_query_value, encode_query_value, escape_html};
+
+/// Prefix of site information addresses; the page URL follows, encoded.
+pub const SITE_INFO_URL_PREFIX: &str = "asteroid://site-info?url=";
+
+/// Address of the site information page for the page at `url`.
+pub fn site_info_url(url: &str) -> String {
+    format!("{}{}", SITE_INFO_URL_PREFIX, encode_query_value(url))
+}
+
+/// Page URL shown by a site information address.
+pub fn parse_site_info_url(url: &str) -> Option<String> {
+    let encoded = url.strip_prefix(SITE_INFO_URL_PREFIX)?;
+    let decoded = decode_query_value(encoded.split('&').next().unwrap_or(""));
+    (!decoded.is_empty()).then_some(decoded)
+}
+
+/// Media policy change posted by the page: the site, and its autoplay and
+/// preload policy, `None` to follow the global one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteMediaChange {
+    pub site: String,
+    pub autoplay: Option<AutoplayPolicy>,
+    pub preload: Option<PreloadPolicy>,
+}
+
+/// Parse a `set_media` message from the page.
+pub fn parse_site_media_message(message: &serde_json::Value) -> Option<SiteMediaChange> {
+    let site = message["site"].as_str().filter(|site| !site.is_empty())?;
+    // An empty choice follows the global policy
+    let autoplay = match message["autoplay"].as_str()? {
+        "" => None,
+        name => Some(AutoplayPolicy::from_name(name)?),
+    };
+    let preload = match message["preload"].as_str()? {
+        "" => None,
+        name => Some(PreloadPolicy::from_name(name)?),
+    };
+    Some(SiteMediaChange {
+        site: site.to_string(),
+        autoplay,
+        preload,
+    })
+}
+
+/// Options of a policy select: the global policy first, then each policy.
+fn policy_options<'a>(
+    global: &str,
+    current: Option<&str>,
+    policies: impl Iterator<Item = (&'a str, &'a str)>,
+) -> String {
+    let option = |value: &str, label: &str, selected: bool| {
+        format!(
+            "            <option value=\"{}\" {}>{}</option>\n",
+            value,
+            if selected { "selected" } else { "" },
+            escape_html(label),
+        )
+    };
+    let mut options = option("", &format!("Default ({})", global), current.is_none());
+    for (name, label) in policies {
+        options += &option(name, label, current == Some(name));
+    }
+    options
+}
+
+/// Generate the site information page for the page at `url`.
+pub fn generate_site_info_html(url: &str, media: &MediaConfig) -> String {
+    let site = media_site(url);
+    let rule = media.site_rule(&site);
+    let autoplay = policy_options(
+        media.autoplay.label(),
+        rule.and_then(|r| r.autoplay).map(|p| p.name()),
+        AutoplayPolicy::ALL.iter().map(|p| (p.name(), p.label())),
+    );
+    let preload = policy_options(
+        media.preload.label(),
+        rule.and_then(|r| r.preload).map(|p| p.name()),
+        PreloadPolicy::ALL.iter().map(|p| (p.name(), p.label())),
+    );
+    let connection = if url.starts_with("https://") {
+        "Connection is secure"
+    } else {
+        "Connection is not secure"
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{site}</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+            max-width: 600px;
+            margin: 0 auto;
+            padding: 40px 20px;
+        }}
+        h1 {{ color: #7DC6DA; font-size: 28px; }}
+        .connection {{ color: #888; font-size: 14px; margin: 8px 0 30px; }}
+        h2 {{ color: #7DC6DA; font-size: 18px; margin-bottom: 10px; }}
+        .setting {{
+            display: flex;
+            align-items: center;
+            justify-content: space-between;
+            padding: 12px 0;
+            border-bottom: 1px solid #16213e;
+        }}
+        label {{ font-size: 14px; }}
+        .description {{ font-size: 12px; color: #888; margin-top: 4px; }}
+        select {{
+            background: #16213e;
+            color: #e0e0e0;
+            border: 1px solid #2a3a5e;
+            border-radius: 4px;
+            padding: 4px 8px;
+        }}
+    </style>
+</head>
+<body>
+    <h1>{site}</h1>
+    <div class="connection">{connection}</div>
+    <h2>Media</h2>
+    <div class="setting">
+        <div>
+            <label>Autoplay</label>
+            <div class="description">Media that starts without a click</div>
+        </div>
+        <select id="autoplay">
+{autoplay}        </select>
+    </div>
+    <div class="setting">
+        <div>
+            <label>Preload</label>
+            <div class="description">How much media is fetched before it plays</div>
+        </div>
+        <select id="preload">
+{preload}        </select>
+    </div>
+    <script>
+        const post = () => window.asteroid.postMessage({{
+            type: "set_media",
+            site: {site_json},
+            autoplay: document.getElementById("autoplay").value,
+            preload: document.getElementById("preload").value,
+        }});
+        for (const select of document.querySelectorAll("select")) {{
+            select.addEventListener("change", post);
+        }}
+    </script>
+</body>
+</html>"#,
+        site = escape_html(&site),
+        connection = connection,
+        autoplay = autoplay,
+        preload = preload,
+        // A JSON string is valid script; only `</script>` could end it early
+        site_json = serde_json::Value::from(site.as_str())
+            .to_string()
+            .replace('<', "\\u003c"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::media_policy::MediaSiteRule;
+    use serde_json::json;
+
+    #[test]
+    fn test_site_info_url_round_trip() {
+        let url = "https://video.test/watch?v=1&t=2";
+        assert_eq!(parse_site_info_url(&site_info_url(url)).as_deref(), Some(url));
+        assert_eq!(parse_site_info_url("asteroid://site-info?url="), None);
+        assert_eq!(parse_site_info_url("asteroid://settings"), None);
+    }
+
+    #[test]
+    fn test_site_info_shows_site_policy() {
+        let mut media = MediaConfig::default();
+        let html = generate_site_info_html("https://Video.test/watch", &media);
+        assert!(html.contains("<h1>video.test</h1>"));
+        assert!(html.contains(r#"<option value="" selected>Default (Block all)</option>"#));
+
+        media.site_rules.push(MediaSiteRule {
+            site: "video.test".to_string(),
+            autoplay: Some(AutoplayPolicy::Allow),
+            preload: None,
+        });
+        let html = generate_site_info_html("https://video.test/", &media);
+        assert!(html.contains(r#"<option value="allow" selected>Allow</option>"#));
+        assert!(html.contains(r#"<option value="" selected>Default (Metadata only)</option>"#));
+    }
+
+    #[test]
+    fn test_site_info_script_posts_site() {
+        let html = generate_site_info_html("https://video.test/", &MediaConfig::default());
+        let script = &html[html.find("<script>").unwrap()..];
+        assert!(script.contains(r#"site: "video.test","#));
+        assert!(!script.contains("&quot;"));
+
+        let html = generate_site_info_html("https://a<b.test/", &MediaConfig::default());
+        let script = &html[html.find("<script>").unwrap()..];
+        assert!(script.contains(r#"site: "a\u003cb.test","#));
+    }
+
+    #[test]
+    fn test_parse_site_media_message() {
+        let message = json!({"type": "set_media", "site": "video.test", "autoplay": "allow", "preload": ""});
+        assert_eq!(
+            parse_site_media_message(&message),
+            Some(SiteMediaChange {
+                site: "video.test".to_string(),
+                autoplay: Some(AutoplayPolicy::Allow),
+                preload: None,
+            })
+        );
+        let message = json!({"site": "video.test", "autoplay": "sometimes", "preload": ""});
+        assert_eq!(parse_site_media_message(&message), None);
+        assert_eq!(parse_site_media_message(&json!({"site": ""})), None);
+    }
+}
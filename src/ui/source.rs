@@ -0,0 +1,518 @@
+//! Page source viewer for Asteroid Browser.
+//!
+//! `view_source` shows the current document of a view at
+//! `asteroid://source?url=...`, using the body the engine still holds or
+//! refetching the URL. The page is syntax highlighted, numbered, can wrap
+//! long lines, and links `src`/`href` values to the source of the
+//! referenced resource.
+
+use crate::core::config::NetworkConfig;
+use crate::core::engine::{BrowserEngine, EngineError, EngineResult, ViewId};
+use crate::core::engine_host::EngineHandle;
+use crate::core::proxy;
+use crate::ui::{decode_query_value, encode_query_value, escape_html};
+
+/// Prefix of source viewer addresses; the viewed URL follows, encoded.
+pub const SOURCE_URL_PREFIX: &str = "asteroid://source?url=";
+
+/// Address of the source viewer for `url`.
+pub fn source_page_url(url: &str) -> String {
+    format!("{}{}", SOURCE_URL_PREFIX, encode_query_value(url))
+}
+
+/// URL shown by a source viewer address.
+pub fn parse_source_url(url: &str) -> Option<String> {
+    let encoded = url.strip_prefix(SOURCE_URL_PREFIX)?;
+    let decoded = decode_query_value(encoded.split('&').next().unwrap_or(""));
+    if decoded.is_empty() {
+        None
+    } else {
+        Some(decoded)
+    }
+}
+
+/// Resolve a reference found in a document against the document URL.
+/// Only http(s) and file targets are returned.
+pub fn resolve_url(base: &str, reference: &str) -> Option<String> {
+    let reference = reference.trim();
+    let lower = reference.to_ascii_lowercase();
+    if ["http://", "https://", "file://"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+    {
+        return Some(reference.to_string());
+    }
+    if reference.is_empty()
+        || reference.starts_with('#')
+        || lower.contains(':') && !lower.starts_with('/')
+    {
+        // Fragments and other schemes (data:, javascript:, mailto:, ...)
+        return None;
+    }
+
+    let (scheme, rest) = base.split_once("://")?;
+    if let Some(host_relative) = reference.strip_prefix("//") {
+        return Some(format!("{}://{}", scheme, host_relative));
+    }
+
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+    if reference.starts_with('/') {
+        return Some(format!("{}://{}{}", scheme, authority, reference));
+    }
+
+    // Relative to the directory of the base path
+    let path = rest[authority_end..].split(['?', '#']).next().unwrap_or("");
+    let dir = match path.rfind('/') {
+        Some(i) => &path[..=i],
+        None => "/",
+    };
+    Some(format!("{}://{}{}{}", scheme, authority, dir, reference))
+}
+
+/// Highlighting class of a piece of source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Text,
+    Tag,
+    Attribute,
+    Value,
+    Comment,
+    Doctype,
+}
+
+impl TokenKind {
+    fn class(&self) -> Option<&'static str> {
+        match self {
+            Self::Text => None,
+            Self::Tag => Some("tag"),
+            Self::Attribute => Some("attr"),
+            Self::Value => Some("value"),
+            Self::Comment => Some("comment"),
+            Self::Doctype => Some("doctype"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    /// Resource referenced by an attribute value
+    link: Option<String>,
+}
+
+/// Attributes whose values reference other resources.
+const LINK_ATTRIBUTES: [&str; 5] = ["src", "href", "action", "poster", "data"];
+
+/// Split HTML source into highlighted tokens. Every byte of `source`
+/// ends up in exactly one token.
+fn tokenize<'a>(source: &'a str, base: &str) -> Vec<Token<'a>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut push = |kind, start: usize, end: usize, link: Option<String>| {
+        if end > start {
+            tokens.push(Token {
+                kind,
+                text: &source[start..end],
+                link,
+            });
+        }
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if source[i..].starts_with("<!--") {
+            let end = source[i + 4..]
+                .find("-->")
+                .map(|e| i + 4 + e + 3)
+                .unwrap_or(bytes.len());
+            push(TokenKind::Comment, i, end, None);
+            i = end;
+            continue;
+        }
+
+        let starts_tag = bytes[i] == b'<'
+            && bytes
+                .get(i + 1)
+                .is_some_and(|b| b.is_ascii_alphabetic() || matches!(b, b'/' | b'!' | b'?'));
+        if !starts_tag {
+            let end = source[i + 1..]
+                .find('<')
+                .map(|e| i + 1 + e)
+                .unwrap_or(bytes.len());
+            push(TokenKind::Text, i, end, None);
+            i = end;
+            continue;
+        }
+
+        // Find the closing '>' outside quoted values
+        let mut end = i + 1;
+        let mut quote = None;
+        while end < bytes.len() {
+            match (quote, bytes[end]) {
+                (None, b'>') => break,
+                (None, q @ (b'"' | b'\'')) => quote = Some(q),
+                (Some(q), b) if b == q => quote = None,
+                _ => {}
+            }
+            end += 1;
+        }
+        let tag_end = (end + 1).min(bytes.len());
+
+        if matches!(bytes[i + 1], b'!' | b'?') {
+            push(TokenKind::Doctype, i, tag_end, None);
+            i = tag_end;
+            continue;
+        }
+
+        // "<name" or "</name"
+        let mut j = i + 1;
+        if bytes[j] == b'/' {
+            j += 1;
+        }
+        let name_start = j;
+        while j < end && !bytes[j].is_ascii_whitespace() && !matches!(bytes[j], b'/' | b'>') {
+            j += 1;
+        }
+        let name = source[name_start..j].to_ascii_lowercase();
+        let closing = bytes[i + 1] == b'/';
+        push(TokenKind::Tag, i, j, None);
+
+        // Attributes
+        let mut attribute = String::new();
+        while j < end {
+            let b = bytes[j];
+            if b.is_ascii_whitespace() || b == b'=' {
+                let start = j;
+                while j < end && (bytes[j].is_ascii_whitespace() || bytes[j] == b'=') {
+                    j += 1;
+                }
+                push(TokenKind::Text, start, j, None);
+            } else if b == b'/' {
+                push(TokenKind::Tag, j, j + 1, None);
+                j += 1;
+            } else if bytes[j - 1] == b'='
+                || (bytes[j - 1].is_ascii_whitespace() && source[..j].trim_end().ends_with('='))
+            {
+                let start = j;
+                let (value_start, value_end) = if b == b'"' || b == b'\'' {
+                    let close = source[j + 1..end].find(b as char).map(|e| j + 1 + e);
+                    j = close.map(|c| c + 1).unwrap_or(end);
+                    (start + 1, close.unwrap_or(end))
+                } else {
+                    while j < end && !bytes[j].is_ascii_whitespace() {
+                        j += 1;
+                    }
+                    (start, j)
+                };
+                let link = if LINK_ATTRIBUTES.contains(&attribute.as_str()) {
+                    resolve_url(base, &source[value_start..value_end])
+                } else {
+                    None
+                };
+                push(TokenKind::Value, start, j, link);
+            } else {
+                let start = j;
+                while j < end && !bytes[j].is_ascii_whitespace() && !matches!(bytes[j], b'=' | b'/')
+                {
+                    j += 1;
+                }
+                attribute = source[start..j].to_ascii_lowercase();
+                push(TokenKind::Attribute, start, j, None);
+            }
+        }
+        push(TokenKind::Tag, end, tag_end, None);
+        i = tag_end;
+
+        // Script and style bodies are raw text up to their end tag
+        if !closing && (name == "script" || name == "style") {
+            let close = format!("</{}", name);
+            let body_end = source[i..]
+                .to_ascii_lowercase()
+                .find(&close)
+                .map(|e| i + e)
+                .unwrap_or(bytes.len());
+            push(TokenKind::Text, i, body_end, None);
+            i = body_end;
+        }
+    }
+    tokens
+}
+
+/// Render tokens as numbered lines of highlighted HTML.
+fn render_lines(tokens: &[Token]) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for token in tokens {
+        for (n, part) in token.text.split('\n').enumerate() {
+            if n > 0 {
+                lines.push(String::new());
+            }
+            if part.is_empty() {
+                continue;
+            }
+            let mut html = escape_html(part);
+            if let Some(ref link) = token.link {
+                html = format!(
+                    r#"<a href="{}">{}</a>"#,
+                    escape_html(&source_page_url(link)),
+                    html
+                );
+            }
+            if let Some(class) = token.kind.class() {
+                html = format!(r#"<span class="{}">{}</span>"#, class, html);
+            }
+            lines.last_mut().unwrap().push_str(&html);
+        }
+    }
+    lines
+}
+
+/// Generate the source viewer page for a document.
+pub fn generate_source_html(url: &str, source: &str) -> String {
+    let rows: String = render_lines(&tokenize(source, url))
+        .iter()
+        .enumerate()
+        .map(|(n, line)| {
+            format!(
+                "<div class=\"line\"><span class=\"ln\">{}</span><span class=\"code\">{}</span></div>\n",
+                n + 1,
+                line
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Source of {title}</title>
+    <style>
+        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif;
+            background-color: #0a0e1a;
+            color: #e0e0e0;
+        }}
+        .toolbar {{
+            position: sticky;
+            top: 0;
+            display: flex;
+            justify-content: space-between;
+            padding: 10px 20px;
+            background-color: #0a0e1a;
+            border-bottom: 1px solid #16213e;
+            font-size: 13px;
+        }}
+        .toolbar .url {{ color: #7DC6DA; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }}
+        .toolbar label {{ color: #888; margin-left: 20px; white-space: nowrap; }}
+        .source {{ font-family: monospace; font-size: 13px; padding: 10px 0; }}
+        .line {{ display: flex; }}
+        .ln {{
+            flex: none;
+            width: 60px;
+            padding-right: 12px;
+            text-align: right;
+            color: #555;
+            user-select: none;
+        }}
+        .code {{ white-space: pre; min-height: 1.2em; }}
+        body.wrap .code {{ white-space: pre-wrap; word-break: break-all; }}
+        .tag {{ color: #7DC6DA; }}
+        .attr {{ color: #c39ac9; }}
+        .value {{ color: #a5d6a7; }}
+        .comment {{ color: #666; font-style: italic; }}
+        .doctype {{ color: #888; }}
+        a {{ color: inherit; text-decoration: underline; }}
+    </style>
+</head>
+<body>
+    <div class="toolbar">
+        <span class="url">{url}</span>
+        <label><input type="checkbox" onchange="document.body.classList.toggle('wrap', this.checked)"> Wrap long lines</label>
+    </div>
+    <div class="source">
+{rows}    </div>
+</body>
+</html>"#,
+        title = escape_html(url),
+        url = escape_html(url),
+        rows = rows,
+    )
+}
+
+/// Fetch a document again when the engine no longer has its body.
+pub async fn fetch_source(url: &str, network: &NetworkConfig) -> Result<String, String> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| e.to_string());
+    }
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Cannot fetch source of {}", url));
+    }
+    let client = proxy::client_builder(network)?
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let response = response.error_for_status().map_err(|e| e.to_string())?;
+    response.text().await.map_err(|e| e.to_string())
+}
+
+/// Open the source of a view's current document in a new tab.
+/// Returns the new tab.
+pub async fn view_source(
+    handle: &EngineHandle,
+    view_id: ViewId,
+    network: &NetworkConfig,
+) -> EngineResult<ViewId> {
+    let (url, cached) = handle
+        .call(move |host| {
+            let url = host
+                .tabs
+                .get_tab(view_id)
+                .map(|tab| tab.url.clone())
+                .ok_or(EngineError::ViewNotFound(view_id))?;
+            let source = host.engine.page_source(view_id)?;
+            Ok::<_, EngineError>((url, source))
+        })
+        .await??;
+
+    // The source viewer shows the source of the page it displays
+    let url = parse_source_url(&url).unwrap_or(url);
+    let source = match cached {
+        Some(source) => source,
+        None => fetch_source(&url, network)
+            .await
+            .map_err(EngineError::NavigationError)?,
+    };
+
+    let html = generate_source_html(&url, &source);
+    handle
+        .call(move |host| {
+            let tab = host.tabs.create_tab(&mut host.engine)?;
+            host.tabs.switch_to_tab(tab, &mut host.engine)?;
+            host.engine.load_html(tab, &html, &source_page_url(&url))?;
+            Ok(tab)
+        })
+        .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_value_round_trip() {
+        let url = "https://example.com/a b?q=1&r=ä#top";
+        let encoded = encode_query_value(url);
+        assert!(!encoded.contains(['&', '?', '#', ' ']));
+        assert_eq!(decode_query_value(&encoded), url);
+        assert_eq!(decode_query_value("100%+sure%2"), "100% sure%2");
+
+        let page = source_page_url(url);
+        assert!(page.starts_with(SOURCE_URL_PREFIX));
+        assert_eq!(parse_source_url(&page).as_deref(), Some(url));
+        assert_eq!(parse_source_url("asteroid://source?url="), None);
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://example.com/docs/page.html?x=1";
+        assert_eq!(
+            resolve_url(base, "style.css").as_deref(),
+            Some("https://example.com/docs/style.css")
+        );
+        assert_eq!(
+            resolve_url(base, "/app.js").as_deref(),
+            Some("https://example.com/app.js")
+        );
+        assert_eq!(
+            resolve_url(base, "//cdn.test/lib.js").as_deref(),
+            Some("https://cdn.test/lib.js")
+        );
+        assert_eq!(
+            resolve_url(base, "http://other.test/").as_deref(),
+            Some("http://other.test/")
+        );
+        assert_eq!(resolve_url(base, "#top"), None);
+        assert_eq!(resolve_url(base, "data:image/png;base64,AA"), None);
+        assert_eq!(resolve_url(base, "javascript:void(0)"), None);
+    }
+
+    #[test]
+    fn test_tokenize_covers_source() {
+        let source = "<!DOCTYPE html>\n<!-- note -->\n<a href=\"/x\" class=big>Go</a>\n\
+                      <script>if (a<b) {}</script><img src='i.png' alt=\"a > b\">";
+        let tokens = tokenize(source, "https://example.com/");
+        let joined: String = tokens.iter().map(|t| t.text).collect();
+        assert_eq!(joined, source);
+
+        let kinds: Vec<(TokenKind, &str)> = tokens.iter().map(|t| (t.kind, t.text)).collect();
+        assert!(kinds.contains(&(TokenKind::Doctype, "<!DOCTYPE html>")));
+        assert!(kinds.contains(&(TokenKind::Comment, "<!-- note -->")));
+        assert!(kinds.contains(&(TokenKind::Attribute, "class")));
+        assert!(kinds.contains(&(TokenKind::Value, "big")));
+        assert!(kinds.contains(&(TokenKind::Text, "if (a<b) {}")));
+        assert!(kinds.contains(&(TokenKind::Value, "\"a > b\"")));
+
+        let links: Vec<&str> = tokens.iter().filter_map(|t| t.link.as_deref()).collect();
+        assert_eq!(
+            links,
+            vec!["https://example.com/x", "https://example.com/i.png"]
+        );
+    }
+
+    #[test]
+    fn test_source_html_generation() {
+        let html = generate_source_html(
+            "https://example.com/",
+            "<html>\n<script src=\"app.js\"></script>\n</html>",
+        );
+        assert!(html.contains(r#"<span class="ln">3</span>"#));
+        assert!(!html.contains(r#"<span class="ln">4</span>"#));
+        assert!(html.contains(r#"<span class="tag">&lt;html</span>"#));
+        assert!(html.contains("asteroid://source?url=https%3A%2F%2Fexample.com%2Fapp.js"));
+        assert!(html.contains("Wrap long lines"));
+    }
+
+    #[test]
+    fn test_view_source_uses_engine_copy() {
+        use crate::core::engine_host::EngineHost;
+        use crate::core::tab::{SuspensionConfig, TabManager};
+        use crate::engines::mock::{MockEngine, MockFixture, MockPage};
+
+        let fixture = MockFixture::new().page(
+            "https://example.com/",
+            MockPage::new("Example").source("<p>hello</p>"),
+        );
+        let engine = MockEngine::with_fixture(fixture);
+        let handle_mock = engine.handle();
+        let host = EngineHost::spawn(
+            engine.into_engine_set(),
+            TabManager::new(SuspensionConfig::default()),
+        )
+        .unwrap();
+        let handle = host.handle();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let source_tab = rt.block_on(async {
+            let tab = handle.create_tab().await.unwrap();
+            handle.load_url(tab, "https://example.com/").await.unwrap();
+            // Let the host apply the load to the tab manager
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            view_source(&handle, tab, &NetworkConfig::default()).await.unwrap()
+        });
+
+        let loads = handle_mock.calls_to("load_html");
+        assert_eq!(loads.len(), 1);
+        assert_eq!(loads[0].view_id, Some(source_tab));
+        assert_eq!(loads[0].args[0], source_page_url("https://example.com/"));
+        host.shutdown().unwrap();
+    }
+}
@@ -2,10 +2,14 @@
 //!
 //! Provides both horizontal tab strip and vertical tab sidebar.
 //! Supports tab switching, closing, drag-to-reorder, and visual
-//! indicators for suspended/loading tabs.
+//! indicators for suspended/loading tabs. Tabs playing audio show a
+//! speaker that mutes them when clicked.
 
-use crate::core::engine::ViewId;
+use crate::core::engine::{EngineResult, ViewId};
+use crate::core::engine_host::EngineHandle;
 use crate::core::tab::{Tab, TabState};
+use gtk4::prelude::*;
+use gtk4::{Box as GtkBox, Button, Orientation};
 
 /// Visual representation of a tab in the sidebar/strip.
 #[derive(Debug, Clone)]
@@ -17,7 +21,14 @@ pub struct TabEntry {
     pub is_loading: bool,
     pub is_suspended: bool,
     pub is_pinned: bool,
+    /// Playing sound, or would be if not muted
+    pub is_playing_audio: bool,
+    pub is_muted: bool,
     pub favicon: Option<Vec<u8>>,
+    /// Page thumbnail (PNG) for the tab switcher
+    pub thumbnail: Option<Vec<u8>>,
+    /// Engine showing the tab, when it is not the default one
+    pub engine: Option<String>,
 }
 
 impl TabEntry {
@@ -34,7 +45,11 @@ impl TabEntry {
             is_loading: tab.state == TabState::Loading,
             is_suspended: tab.state == TabState::Suspended,
             is_pinned: tab.pinned,
+            is_playing_audio: tab.media.is_playing_audio(),
+            is_muted: tab.media.muted,
             favicon: tab.favicon.clone(),
+            thumbnail: tab.thumbnail.clone(),
+            engine: tab.engine.clone(),
         }
     }
 
@@ -47,6 +62,14 @@ impl TabEntry {
         }
     }
 
+    /// Tooltip for the tab: its URL, and the engine if not the default.
+    pub fn tooltip(&self) -> String {
+        match &self.engine {
+            Some(engine) => format!("{}\nShown by {}", self.url, engine),
+            None => self.url.clone(),
+        }
+    }
+
     /// Get a status indicator character.
     pub fn status_indicator(&self) -> &str {
         if self.is_loading {
@@ -59,6 +82,18 @@ impl TabEntry {
             ""
         }
     }
+
+    /// Speaker shown next to the title, if any, and the action of a click
+    /// on it. Muted tabs keep theirs so they can be unmuted.
+    pub fn audio_indicator(&self) -> Option<(&str, TabBarAction)> {
+        if self.is_muted {
+            Some(("\u{1F507}", TabBarAction::SetMuted(self.view_id, false))) // 🔇 muted
+        } else if self.is_playing_audio {
+            Some(("\u{1F50A}", TabBarAction::SetMuted(self.view_id, true))) // 🔊 playing
+        } else {
+            None
+        }
+    }
 }
 
 /// Tab bar action events.
@@ -70,6 +105,17 @@ pub enum TabBarAction {
     CloseTab(ViewId),
     /// Create a new tab
     NewTab,
+    /// Create a new tab shown by a named engine
+    NewTabInEngine(String),
+    /// Show a tab in another engine, keeping its URL
+    ReopenInEngine(ViewId, String),
+    /// Send a user agent (preset name or agent string) from a tab, or go
+    /// back to the configured one with `None`
+    SetUserAgent(ViewId, Option<String>),
+    /// Mute or unmute a tab
+    SetMuted(ViewId, bool),
+    /// Pause or resume the media of a tab
+    MediaPlayPause(ViewId),
     /// Pin/unpin a tab
     TogglePin(ViewId),
     /// Move tab to new position
@@ -78,6 +124,119 @@ pub enum TabBarAction {
     ToggleSidebar,
 }
 
+/// Carry out a tab bar action. The ones about the window itself (new
+/// tabs, the sidebar, pinning and moving tabs) are left to it.
+pub async fn apply_tab_bar_action(
+    handle: &EngineHandle,
+    action: &TabBarAction,
+) -> EngineResult<()> {
+    match action {
+        TabBarAction::SwitchTab(view_id) => handle.switch_to_tab(*view_id).await,
+        TabBarAction::CloseTab(view_id) => handle.close_tab(*view_id).await,
+        TabBarAction::ReopenInEngine(view_id, engine) => {
+            handle.reopen_in_engine(*view_id, engine).await
+        }
+        TabBarAction::SetUserAgent(view_id, user_agent) => {
+            handle.set_user_agent(*view_id, user_agent.as_deref()).await
+        }
+        TabBarAction::SetMuted(view_id, muted) => handle.set_muted(*view_id, *muted).await,
+        TabBarAction::MediaPlayPause(view_id) => handle.media_play_pause(*view_id).await,
+        TabBarAction::NewTab
+        | TabBarAction::NewTabInEngine(_)
+        | TabBarAction::TogglePin(_)
+        | TabBarAction::MoveTab(..)
+        | TabBarAction::ToggleSidebar => Ok(()),
+    }
+}
+
+/// Tab list of the sidebar, rebuilt from the tab manager.
+#[derive(Clone)]
+pub struct TabList {
+    container: GtkBox,
+    handle: EngineHandle,
+    runtime: tokio::runtime::Handle,
+}
+
+impl TabList {
+    pub fn new(container: GtkBox, handle: EngineHandle, runtime: tokio::runtime::Handle) -> Self {
+        Self {
+            container,
+            handle,
+            runtime,
+        }
+    }
+
+    /// Show the tabs as they are now.
+    pub fn refresh(&self) {
+        let entries = self.handle.call_blocking(|host| {
+            let active = host.tabs.active_tab_id();
+            host.tabs
+                .tabs_in_order()
+                .iter()
+                .map(|tab| TabEntry::from_tab(tab, active == Some(tab.view_id)))
+                .collect::<Vec<_>>()
+        });
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to list tabs: {}", e);
+                return;
+            }
+        };
+        while let Some(child) = self.container.first_child() {
+            self.container.remove(&child);
+        }
+        for entry in &entries {
+            self.container.append(&self.row(entry));
+        }
+    }
+
+    fn row(&self, entry: &TabEntry) -> GtkBox {
+        let row = GtkBox::new(Orientation::Horizontal, 4);
+        row.add_css_class("tab-entry");
+        if entry.is_active {
+            row.add_css_class("active");
+        }
+        if entry.is_suspended {
+            row.add_css_class("suspended");
+        }
+
+        let title = Button::with_label(&format!(
+            "{} {}",
+            entry.status_indicator(),
+            entry.display_title(24)
+        ));
+        title.set_tooltip_text(Some(&entry.tooltip()));
+        title.set_hexpand(true);
+        self.connect(&title, TabBarAction::SwitchTab(entry.view_id));
+        row.append(&title);
+
+        if let Some((icon, action)) = entry.audio_indicator() {
+            let speaker = Button::with_label(icon);
+            speaker.set_tooltip_text(Some(if entry.is_muted {
+                "Unmute tab"
+            } else {
+                "Mute tab"
+            }));
+            self.connect(&speaker, action);
+            row.append(&speaker);
+        }
+        row
+    }
+
+    fn connect(&self, button: &Button, action: TabBarAction) {
+        let (handle, runtime) = (self.handle.clone(), self.runtime.clone());
+        button.connect_clicked(move |_| {
+            let (handle, action) = (handle.clone(), action.clone());
+            runtime.spawn(async move {
+                if let Err(e) = apply_tab_bar_action(&handle, &action).await {
+                    log::warn!("Failed to apply {:?}: {}", action, e);
+                }
+            });
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,7 +251,11 @@ mod tests {
             is_loading: false,
             is_suspended: false,
             is_pinned: false,
+            is_playing_audio: false,
+            is_muted: false,
             favicon: None,
+            thumbnail: None,
+            engine: None,
         };
 
         let short = entry.display_title(20);
@@ -100,6 +263,27 @@ mod tests {
         assert!(short.ends_with("..."));
     }
 
+    #[test]
+    fn test_tooltip_names_engine() {
+        let mut entry = TabEntry {
+            view_id: ViewId(1),
+            title: "Test".to_string(),
+            url: "https://example.com".to_string(),
+            is_active: false,
+            is_loading: false,
+            is_suspended: false,
+            is_pinned: false,
+            is_playing_audio: false,
+            is_muted: false,
+            favicon: None,
+            thumbnail: None,
+            engine: None,
+        };
+        assert_eq!(entry.tooltip(), "https://example.com");
+        entry.engine = Some("servo".to_string());
+        assert_eq!(entry.tooltip(), "https://example.com\nShown by servo");
+    }
+
     #[test]
     fn test_status_indicator() {
         let mut entry = TabEntry {
@@ -110,7 +294,11 @@ mod tests {
             is_loading: true,
             is_suspended: false,
             is_pinned: false,
+            is_playing_audio: false,
+            is_muted: false,
             favicon: None,
+            thumbnail: None,
+            engine: None,
         };
 
         assert!(!entry.status_indicator().is_empty()); // loading indicator
@@ -118,4 +306,72 @@ mod tests {
         entry.is_suspended = true;
         assert!(!entry.status_indicator().is_empty()); // suspended indicator
     }
+
+    #[test]
+    fn test_audio_indicator_mutes_and_unmutes() {
+        let mut entry = TabEntry {
+            view_id: ViewId(1),
+            title: "Radio".to_string(),
+            url: "https://radio.test".to_string(),
+            is_active: false,
+            is_loading: false,
+            is_suspended: false,
+            is_pinned: false,
+            is_playing_audio: false,
+            is_muted: false,
+            favicon: None,
+            thumbnail: None,
+            engine: None,
+        };
+        assert!(entry.audio_indicator().is_none());
+
+        entry.is_playing_audio = true;
+        assert!(matches!(
+            entry.audio_indicator(),
+            Some((_, TabBarAction::SetMuted(ViewId(1), true)))
+        ));
+        // Stays visible while muted, even once the media stops
+        entry.is_muted = true;
+        entry.is_playing_audio = false;
+        assert!(matches!(
+            entry.audio_indicator(),
+            Some(("\u{1F507}", TabBarAction::SetMuted(ViewId(1), false)))
+        ));
+    }
+
+    #[test]
+    fn test_media_actions_reach_engine() {
+        use crate::core::engine_host::EngineHost;
+        use crate::core::tab::{SuspensionConfig, TabManager};
+        use crate::engines::mock::MockEngine;
+
+        let engine = MockEngine::new();
+        let mock = engine.handle();
+        let tabs = TabManager::new(SuspensionConfig::default());
+        let host = EngineHost::spawn(engine.into_engine_set(), tabs).unwrap();
+        let handle = host.handle();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let id = handle.create_tab().await.unwrap();
+            apply_tab_bar_action(&handle, &TabBarAction::SetMuted(id, true))
+                .await
+                .unwrap();
+            apply_tab_bar_action(&handle, &TabBarAction::MediaPlayPause(id))
+                .await
+                .unwrap();
+            assert!(
+                apply_tab_bar_action(&handle, &TabBarAction::SetMuted(ViewId(999), true))
+                    .await
+                    .is_err()
+            );
+        });
+        assert_eq!(mock.calls_to("set_muted")[0].args, vec!["true"]);
+        assert_eq!(mock.calls_to("media_play_pause").len(), 1);
+
+        host.shutdown().unwrap();
+    }
 }
@@ -5,6 +5,9 @@
 //! - Combined address/search bar (omnibox)
 //! - Menu button
 
+use crate::core::https_only::{is_local_host, HttpsOnlyPolicy};
+use crate::core::site_data::host_of;
+
 /// Toolbar action events.
 #[derive(Debug, Clone)]
 pub enum ToolbarAction {
@@ -31,6 +34,11 @@ pub fn parse_address_input(input: &str) -> String {
         return trimmed.to_string();
     }
 
+    // Local servers rarely have certificates
+    if !trimmed.contains(' ') && is_local_host(host_of(trimmed)) {
+        return format!("http://{}", trimmed);
+    }
+
     // Check for common URL patterns
     if trimmed.contains('.') && !trimmed.contains(' ') {
         // Looks like a domain name
@@ -44,6 +52,13 @@ pub fn parse_address_input(input: &str) -> String {
     )
 }
 
+/// Address bar input to navigate to, upgraded to HTTPS when HTTPS-only
+/// mode applies to it.
+pub fn resolve_address_input(input: &str, https_only: &HttpsOnlyPolicy) -> String {
+    let url = parse_address_input(input);
+    https_only.upgrade(&url).unwrap_or(url)
+}
+
 /// Simple URL encoding for search queries.
 fn urlencoding_encode(input: &str) -> String {
     input
@@ -86,6 +101,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_local_address() {
+        assert_eq!(parse_address_input("localhost:8080"), "http://localhost:8080");
+        assert_eq!(parse_address_input("192.168.1.1/admin"), "http://192.168.1.1/admin");
+    }
+
+    #[test]
+    fn test_resolve_https_only() {
+        let policy = HttpsOnlyPolicy::new(true, None);
+        assert_eq!(
+            resolve_address_input("http://example.com/a", &policy),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            resolve_address_input("localhost:3000", &policy),
+            "http://localhost:3000"
+        );
+        policy.set_enabled(false);
+        assert_eq!(
+            resolve_address_input("http://example.com/a", &policy),
+            "http://example.com/a"
+        );
+    }
+
     #[test]
     fn test_parse_search() {
         let result = parse_address_input("rust programming");
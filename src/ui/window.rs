@@ -3,17 +3,43 @@
 //! Creates the primary GTK4 application window with minimal chrome:
 //! - Navigation toolbar (back, forward, reload, address bar, menu)
 //! - Optional vertical tab sidebar
+//! - Permission prompt bar (hidden until a site asks)
+//! - Blocked popup notice
 //! - Web content area
+//! - Find bar (hidden until Ctrl+F or `/`)
 //! - Status overlay (bottom-left, appears on hover/activity)
 
+use crate::core::engine::{Zoom, ZoomMode};
+use crate::ui::find_bar::FindBar;
+use crate::ui::permission_bar::PermissionBar;
+use crate::ui::popup_bar::PopupBar;
 use gtk4::prelude::*;
 use gtk4::{
     Application, ApplicationWindow, Box as GtkBox, Button, Entry,
     Label, Orientation, Paned, ScrolledWindow, Separator,
 };
 
+/// The main window and the widgets updated after it is built.
+pub struct BrowserWindow {
+    pub window: ApplicationWindow,
+    pub permission_bar: PermissionBar,
+    pub popup_bar: PopupBar,
+    pub find_bar: FindBar,
+    pub status_label: Label,
+    /// Tab list of the sidebar, filled by `TabList`
+    pub tab_list: GtkBox,
+}
+
+/// Status overlay text for a zoom level.
+pub fn zoom_status(zoom: &Zoom) -> String {
+    match zoom.mode {
+        ZoomMode::FullPage => format!("Zoom {}%", zoom.percent()),
+        ZoomMode::TextOnly => format!("Text zoom {}%", zoom.percent()),
+    }
+}
+
 /// Build the main browser window.
-pub fn build_window(app: &Application) -> ApplicationWindow {
+pub fn build_window(app: &Application) -> BrowserWindow {
     let window = ApplicationWindow::builder()
         .application(app)
         .title("Asteroid Browser")
@@ -28,11 +54,17 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     let toolbar = build_toolbar();
     main_box.append(&toolbar);
 
+    // Permission prompts slide in below the toolbar
+    let permission_bar = PermissionBar::new();
+    main_box.append(&permission_bar.container);
+    let popup_bar = PopupBar::new();
+    main_box.append(&popup_bar.container);
+
     // Horizontal layout for sidebar + content
     let content_paned = Paned::new(Orientation::Horizontal);
 
     // Tab sidebar (hidden by default, toggleable with F1)
-    let sidebar = build_tab_sidebar();
+    let (sidebar, tab_list) = build_tab_sidebar();
     sidebar.set_visible(false);
     content_paned.set_start_child(Some(&sidebar));
     content_paned.set_position(200);
@@ -43,6 +75,10 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
 
     main_box.append(&content_paned);
 
+    // Find bar below the page
+    let find_bar = FindBar::new();
+    main_box.append(&find_bar.container);
+
     // Status bar overlay
     let status_label = Label::new(Some("Ready"));
     status_label.set_halign(gtk4::Align::Start);
@@ -52,7 +88,14 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     main_box.append(&status_label);
 
     window.set_child(Some(&main_box));
-    window
+    BrowserWindow {
+        window,
+        permission_bar,
+        popup_bar,
+        find_bar,
+        status_label,
+        tab_list,
+    }
 }
 
 /// Build the navigation toolbar.
@@ -98,8 +141,8 @@ fn build_toolbar() -> GtkBox {
     toolbar
 }
 
-/// Build the vertical tab sidebar.
-fn build_tab_sidebar() -> GtkBox {
+/// Build the vertical tab sidebar, and the list the tabs go in.
+fn build_tab_sidebar() -> (GtkBox, GtkBox) {
     let sidebar = GtkBox::new(Orientation::Vertical, 2);
     sidebar.set_width_request(200);
     sidebar.add_css_class("tab-sidebar");
@@ -128,7 +171,7 @@ fn build_tab_sidebar() -> GtkBox {
     sidebar.append(&scrolled);
     sidebar.append(&new_tab_btn);
 
-    sidebar
+    (sidebar, tab_list)
 }
 
 /// Build the main content area.
@@ -275,6 +318,18 @@ window {
     padding: 4px 8px;
 }
 
+/* Permission prompt */
+.permission-bar {
+    background-color: #16213e;
+    border-bottom: 1px solid #0f3460;
+    padding: 6px 8px;
+}
+
+.permission-allow {
+    background-color: #7DC6DA;
+    color: #0a0e1a;
+}
+
 /* Tab entry in sidebar */
 .tab-entry {
     padding: 8px;